@@ -1,7 +1,7 @@
 //! Benchmark for `BMS` file parsing and chart conversion.
 
 use bms_rs::bms::command::channel::mapper::KeyLayoutBeat;
-use bms_rs::bms::{default_config, parse_bms};
+use bms_rs::bms::{default_config, parse_bms, parse_bms_header};
 use bms_rs::chart::prelude::Process;
 use criterion::{Criterion, Throughput};
 use std::{collections::BTreeMap, sync::LazyLock};
@@ -74,6 +74,25 @@ fn bench_parse_bms(c: &mut Criterion) {
     group.finish();
 }
 
+fn bench_parse_bms_header(c: &mut Criterion) {
+    let files = scan_bms_files();
+    let mut group = c.benchmark_group("parse_bms_header");
+
+    for file in &files {
+        group.throughput(Throughput::Bytes(file.source.len() as u64));
+        group.bench_function(&file.name, |b| {
+            b.iter(|| {
+                parse_bms_header(
+                    std::hint::black_box(&file.source),
+                    std::hint::black_box(default_config()),
+                )
+            });
+        });
+    }
+
+    group.finish();
+}
+
 fn bench_bms_to_chart(c: &mut Criterion) {
     let mut group = c.benchmark_group("bms_to_chart");
 
@@ -91,5 +110,6 @@ static PARSED_CHARTS: LazyLock<ParsedBmsCharts> = LazyLock::new(load_bms_charts)
 fn main() {
     let mut criterion = Criterion::default().without_plots();
     bench_parse_bms(&mut criterion);
+    bench_parse_bms_header(&mut criterion);
     bench_bms_to_chart(&mut criterion);
 }
@@ -0,0 +1,23 @@
+//! Canonical BMS formatter example
+//!
+//! This example formats the BMS file given as the argument, or the standard input if omitted, and prints the result.
+//!
+//! ```sh
+//! cargo run --example bms_fmt -- path/to/chart.bms
+//! ```
+
+use std::io::{Read, Write};
+
+use bms_rs::bms::format::format_bms;
+
+fn main() -> std::io::Result<()> {
+    let bytes = if let Some(path) = std::env::args().nth(1) {
+        std::fs::read(path)?
+    } else {
+        let mut bytes = Vec::new();
+        std::io::stdin().read_to_end(&mut bytes)?;
+        bytes
+    };
+    let source = String::from_utf8_lossy(&bytes);
+    std::io::stdout().write_all(format_bms(&source).as_bytes())
+}
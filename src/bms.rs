@@ -19,12 +19,20 @@ use std::{cell::RefCell, marker::PhantomData, rc::Rc};
 
 pub mod command;
 
+pub mod cst;
+pub mod fix;
+pub mod format;
+pub mod incremental;
+pub mod key_mode;
 pub mod lex;
+pub mod lint;
+pub mod long_note;
 pub mod model;
 pub mod parse;
 pub mod prelude;
 pub mod process;
 pub mod rng;
+pub mod source_map;
 pub mod unparse;
 
 use thiserror::Error;
@@ -36,14 +44,16 @@ use ariadne::Report;
 use crate::diagnostics::{SimpleSource, ToAriadne};
 
 use self::{
-    lex::{LexOutput, LexWarningWithRange},
+    key_mode::{KeyModeDetection, KeyModeHints, detect_key_mode_from_tokens},
+    lex::{LexOutput, LexWarningWithRange, stream::LexLine},
     model::Bms,
     parse::{
         ParseErrorWithRange, ParseWarningWithRange,
         check_playing::{PlayingCheckOutput, PlayingError, PlayingWarning},
         token_processor::{
-            DefaultTokenRelaxer, NoopTokenModifier, SequentialTokenModifier, TokenModifier,
-            TokenProcessor, full_preset,
+            DefaultTokenRelaxer, NoopTokenModifier, ProcessContext, SequentialProcessor,
+            SequentialTokenModifier, TokenModifier, TokenProcessor, full_preset_with_extension,
+            header_preset,
         },
     },
     prelude::*,
@@ -69,12 +79,15 @@ pub enum BmsWarning {
 }
 
 /// A configuration builder for [`parse_bms`]. Its methods can be chained to set parameters you want.
+///
+/// The extension processor `X` parses the commands not supported by this crate, such as vendor-specific headers. Its output is returned as [`ExtendedBmsOutput::extensions`] by [`parse_bms_with_extension`]. It is `()` which does nothing by default.
 #[must_use]
-pub struct ParseConfig<T, P, R, M> {
+pub struct ParseConfig<T, P, R, M, X = ()> {
     key_mapper: PhantomData<fn() -> T>,
     prompter: P,
     rng: R,
     token_modifier: M,
+    token_processor: X,
 }
 
 /// Creates the default configuration builder with the basic key layout [`KeyLayoutBeat`], the prompter [`AlwaysWarnAndUseNewer`] and the standard RNG [`rand::rngs::StdRng`].
@@ -90,6 +103,7 @@ pub fn default_config() -> ParseConfig<
         prompter: AlwaysWarnAndUseNewer,
         rng: RandRng(rand::make_rng()),
         token_modifier: DefaultTokenRelaxer,
+        token_processor: (),
     }
 }
 
@@ -103,6 +117,7 @@ pub fn default_config()
         prompter: AlwaysWarnAndUseNewer,
         rng: rng::JavaRandom::default(),
         token_modifier: DefaultTokenRelaxer,
+        token_processor: (),
     }
 }
 
@@ -115,37 +130,41 @@ pub fn default_config_with_rng<R>(
         prompter: AlwaysWarnAndUseNewer,
         rng,
         token_modifier: DefaultTokenRelaxer,
+        token_processor: (),
     }
 }
 
-impl<T, P, R, M> ParseConfig<T, P, R, M> {
+impl<T, P, R, M, X> ParseConfig<T, P, R, M, X> {
     /// Sets the key mapper to the `T2` one.
-    pub fn key_mapper<T2: KeyLayoutMapper>(self) -> ParseConfig<T2, P, R, M> {
+    pub fn key_mapper<T2: KeyLayoutMapper>(self) -> ParseConfig<T2, P, R, M, X> {
         ParseConfig {
             key_mapper: PhantomData,
             prompter: self.prompter,
             rng: self.rng,
             token_modifier: self.token_modifier,
+            token_processor: self.token_processor,
         }
     }
 
     /// Sets the prompter to `prompter`.
-    pub fn prompter<P2: Prompter>(self, prompter: P2) -> ParseConfig<T, P2, R, M> {
+    pub fn prompter<P2: Prompter>(self, prompter: P2) -> ParseConfig<T, P2, R, M, X> {
         ParseConfig {
             key_mapper: PhantomData,
             prompter,
             rng: self.rng,
             token_modifier: self.token_modifier,
+            token_processor: self.token_processor,
         }
     }
 
     /// Sets the RNG to `rng`.
-    pub fn rng<R2: Rng>(self, rng: R2) -> ParseConfig<T, P, R2, M> {
+    pub fn rng<R2: Rng>(self, rng: R2) -> ParseConfig<T, P, R2, M, X> {
         ParseConfig {
             key_mapper: PhantomData,
             prompter: self.prompter,
             rng,
             token_modifier: self.token_modifier,
+            token_processor: self.token_processor,
         }
     }
 
@@ -156,7 +175,7 @@ impl<T, P, R, M> ParseConfig<T, P, R, M> {
     pub fn append_token_modifier<M2: TokenModifier>(
         self,
         token_modifier: M2,
-    ) -> ParseConfig<T, P, R, SequentialTokenModifier<M, M2>>
+    ) -> ParseConfig<T, P, R, SequentialTokenModifier<M, M2>, X>
     where
         M: TokenModifier,
     {
@@ -165,6 +184,7 @@ impl<T, P, R, M> ParseConfig<T, P, R, M> {
             prompter: self.prompter,
             rng: self.rng,
             token_modifier: self.token_modifier.then(token_modifier),
+            token_processor: self.token_processor,
         }
     }
 
@@ -172,12 +192,13 @@ impl<T, P, R, M> ParseConfig<T, P, R, M> {
     pub fn override_token_modifier<M2: TokenModifier>(
         self,
         token_modifier: M2,
-    ) -> ParseConfig<T, P, R, M2> {
+    ) -> ParseConfig<T, P, R, M2, X> {
         ParseConfig {
             key_mapper: PhantomData,
             prompter: self.prompter,
             rng: self.rng,
             token_modifier,
+            token_processor: self.token_processor,
         }
     }
 
@@ -186,7 +207,7 @@ impl<T, P, R, M> ParseConfig<T, P, R, M> {
     /// Accepts a closure `f` that takes the current modifier `M` and returns a new
     /// modifier `M2`. This is useful for wrapping, decorating, or transforming the
     /// modifier while keeping static dispatch.
-    pub fn map_token_modifier<F, M2>(self, f: F) -> ParseConfig<T, P, R, M2>
+    pub fn map_token_modifier<F, M2>(self, f: F) -> ParseConfig<T, P, R, M2, X>
     where
         F: FnOnce(M) -> M2,
     {
@@ -195,38 +216,83 @@ impl<T, P, R, M> ParseConfig<T, P, R, M> {
             prompter: self.prompter,
             rng: self.rng,
             token_modifier: f(self.token_modifier),
+            token_processor: self.token_processor,
         }
     }
 
     /// Clean all token modifiers by switching to a no-op modifier.
-    pub fn clean_token_modifier(self) -> ParseConfig<T, P, R, NoopTokenModifier> {
+    pub fn clean_token_modifier(self) -> ParseConfig<T, P, R, NoopTokenModifier, X> {
         self.override_token_modifier(NoopTokenModifier)
     }
 
-    pub(crate) fn build(self) -> (impl TokenProcessor<Output = Bms>, P)
+    /// Append an extension token processor by sequentially composing it after the current one.
+    ///
+    /// The output of the extension processors becomes a pair of the current one and the new one. Note that the default extension processor is `()`, so use [`Self::override_token_processor`] to register the first one.
+    pub fn append_token_processor<X2: TokenProcessor>(
+        self,
+        token_processor: X2,
+    ) -> ParseConfig<T, P, R, M, SequentialProcessor<X, X2>>
+    where
+        X: TokenProcessor,
+    {
+        ParseConfig {
+            key_mapper: PhantomData,
+            prompter: self.prompter,
+            rng: self.rng,
+            token_modifier: self.token_modifier,
+            token_processor: self.token_processor.then(token_processor),
+        }
+    }
+
+    /// Override and replace the current extension token processor with the provided one.
+    ///
+    /// The processor reads the same tokens as the processors this crate provided, and its output is returned as [`ExtendedBmsOutput::extensions`] by [`parse_bms_with_extension`]. The warnings reported to [`ProcessContext`] are returned as [`BmsWarning::Parse`].
+    pub fn override_token_processor<X2: TokenProcessor>(
+        self,
+        token_processor: X2,
+    ) -> ParseConfig<T, P, R, M, X2> {
+        ParseConfig {
+            key_mapper: PhantomData,
+            prompter: self.prompter,
+            rng: self.rng,
+            token_modifier: self.token_modifier,
+            token_processor,
+        }
+    }
+
+    pub(crate) fn build(self) -> (impl TokenProcessor<Output = (Bms, X::Output)>, P)
     where
         T: KeyLayoutMapper,
         P: Prompter,
         R: Rng,
+        X: TokenProcessor,
     {
-        struct AggregateTokenProcessor<T, R> {
+        struct AggregateTokenProcessor<T, R, X> {
             key_mapper: PhantomData<fn() -> T>,
             rng: Rc<RefCell<R>>,
+            extension: Rc<X>,
         }
-        impl<T: KeyLayoutMapper, R: Rng> TokenProcessor for AggregateTokenProcessor<T, R> {
-            type Output = Bms;
+        impl<T: KeyLayoutMapper, R: Rng, X: TokenProcessor> TokenProcessor
+            for AggregateTokenProcessor<T, R, X>
+        {
+            type Output = (Bms, X::Output);
 
             fn process<P: Prompter>(
                 &self,
                 ctx: &mut parse::token_processor::ProcessContext<'_, '_, P>,
             ) -> Result<Self::Output, ParseErrorWithRange> {
-                full_preset::<T, R>(Rc::clone(&self.rng)).process(ctx)
+                full_preset_with_extension::<T, R, _>(
+                    Rc::clone(&self.rng),
+                    Rc::clone(&self.extension),
+                )
+                .process(ctx)
             }
         }
         (
-            AggregateTokenProcessor::<T, R> {
+            AggregateTokenProcessor::<T, R, X> {
                 key_mapper: PhantomData,
                 rng: Rc::new(RefCell::new(self.rng)),
+                extension: Rc::new(self.token_processor),
             },
             self.prompter,
         )
@@ -238,6 +304,20 @@ pub fn parse_bms<T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
     source: &str,
     config: ParseConfig<T, P, R, M>,
 ) -> BmsOutput {
+    parse_bms_with_extension(source, config).into()
+}
+
+/// Parse a BMS file from source text with the specified command preset, and returns also the output of the extension token processor registered by [`ParseConfig::override_token_processor`] or [`ParseConfig::append_token_processor`].
+pub fn parse_bms_with_extension<
+    T: KeyLayoutMapper,
+    P: Prompter,
+    R: Rng,
+    M: TokenModifier,
+    X: TokenProcessor,
+>(
+    source: &str,
+    config: ParseConfig<T, P, R, M, X>,
+) -> ExtendedBmsOutput<X::Output> {
     // Parse tokens using default channel parser
     let LexOutput {
         mut tokens,
@@ -247,8 +327,125 @@ pub fn parse_bms<T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
     // Convert lex warnings to BmsWarning
     let mut warnings: Vec<BmsWarning> = lex_warnings.into_iter().map(BmsWarning::Lex).collect();
 
-    config.token_modifier.modify(&mut tokens);
-    let parse_output = Bms::from_token_stream::<'_, T, _, _, _>(&tokens, config);
+    warnings.extend(modify_tokens(
+        &config.token_modifier,
+        &mut tokens,
+        Some(source),
+    ));
+    let mut output = parse_modified_tokens(&tokens, warnings, config);
+    attach_fixes(source, &mut output.warnings);
+    output
+}
+
+/// Parses a BMS file from the lines read by [`StreamLexer`](lex::stream::StreamLexer), which can decode other encodings than UTF-8 by [`LineDecoder`](lex::stream::LineDecoder).
+///
+/// The `#RANDOM` and `#SWITCH` scopes need all the tokens at once, so the lines are lexed after all of them are read. The tokens borrow the lines, so every line is kept alive until the parse ends, and the memory is not bounded by the line length. To read only a part of the input, take the lines before passing them.
+pub fn parse_bms_lines<'a, T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
+    lines: impl IntoIterator<Item = &'a LexLine>,
+    config: ParseConfig<T, P, R, M>,
+) -> BmsOutput {
+    let mut warnings = Vec::new();
+    let mut tokens: lex::TokenStream<'a> = lines
+        .into_iter()
+        .flat_map(|line| {
+            let LexOutput {
+                tokens,
+                lex_warnings,
+            } = line.lex();
+            warnings.extend(lex_warnings.into_iter().map(BmsWarning::Lex));
+            tokens
+        })
+        .collect();
+    warnings.extend(modify_tokens(&config.token_modifier, &mut tokens, None));
+    parse_modified_tokens(&tokens, warnings, config).into()
+}
+
+/// Parses a BMS file from source text with the key mapper detected by [`detect_key_mode_from_tokens`], instead of the one of `config`.
+///
+/// The `#RANDOM` and `#SWITCH` branches are all inspected by the detection, so the mapper is the same for every branch to be activated.
+///
+/// The key mapper `T` of `config` is overridden by the detected one, so it does not matter which mapper `config` has, such as [`KeyLayoutBeat`] of [`default_config`]. The detected layout is in [`AutoBmsOutput::detection`].
+pub fn parse_bms_auto<T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
+    source: &str,
+    config: ParseConfig<T, P, R, M>,
+    hints: &KeyModeHints<'_>,
+) -> AutoBmsOutput {
+    let LexOutput {
+        mut tokens,
+        lex_warnings,
+    } = lex::TokenStream::parse_lex(source);
+    let mut warnings: Vec<BmsWarning> = lex_warnings.into_iter().map(BmsWarning::Lex).collect();
+
+    warnings.extend(modify_tokens(
+        &config.token_modifier,
+        &mut tokens,
+        Some(source),
+    ));
+    let detection = detect_key_mode_from_tokens(&tokens, hints);
+    let mut output = match detection.layout {
+        KeyLayoutKind::Beat => {
+            parse_modified_tokens(&tokens, warnings, config.key_mapper::<KeyLayoutBeat>())
+        }
+        KeyLayoutKind::PmsBmeType => parse_modified_tokens(
+            &tokens,
+            warnings,
+            config.key_mapper::<KeyLayoutPmsBmeType>(),
+        ),
+        KeyLayoutKind::Pms => {
+            parse_modified_tokens(&tokens, warnings, config.key_mapper::<KeyLayoutPms>())
+        }
+        KeyLayoutKind::BeatNanasi => parse_modified_tokens(
+            &tokens,
+            warnings,
+            config.key_mapper::<KeyLayoutBeatNanasi>(),
+        ),
+        KeyLayoutKind::DscOctFp => {
+            parse_modified_tokens(&tokens, warnings, config.key_mapper::<KeyLayoutDscOctFp>())
+        }
+    };
+    attach_fixes(source, &mut output.warnings);
+    AutoBmsOutput {
+        output: output.into(),
+        detection,
+    }
+}
+
+/// Applies `modifier` to `tokens`, and returns its warnings.
+fn modify_tokens(
+    modifier: &impl TokenModifier,
+    tokens: &mut lex::TokenStream<'_>,
+    source: Option<&str>,
+) -> impl Iterator<Item = BmsWarning> {
+    modifier
+        .modify_with_warnings(tokens, source)
+        .into_iter()
+        .map(BmsWarning::Parse)
+}
+
+/// Attaches the fixes which need `source` to the parse warnings.
+fn attach_fixes(source: &str, warnings: &mut [BmsWarning]) {
+    fix::attach_duplication_fixes(
+        source,
+        warnings.iter_mut().filter_map(|warning| match warning {
+            BmsWarning::Parse(warning) => Some(warning),
+            _ => None,
+        }),
+    );
+}
+
+/// Parses the tokens already modified by the token modifier of `config`, and runs the playing checks.
+fn parse_modified_tokens<
+    T: KeyLayoutMapper,
+    P: Prompter,
+    R: Rng,
+    M: TokenModifier,
+    X: TokenProcessor,
+>(
+    tokens: &lex::TokenStream<'_>,
+    mut warnings: Vec<BmsWarning>,
+    config: ParseConfig<T, P, R, M, X>,
+) -> ExtendedBmsOutput<X::Output> {
+    let parse_output = Bms::from_token_stream_with_extension::<'_, T, _, _, _, _>(tokens, config);
     let bms_result = parse_output.bms;
     // Convert parse warnings to BmsWarning
     warnings.extend(
@@ -271,12 +468,78 @@ pub fn parse_bms<T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
         warnings.extend(playing_errors.into_iter().map(BmsWarning::PlayingError));
     }
 
-    BmsOutput {
+    ExtendedBmsOutput {
         bms: bms_result,
+        extensions: parse_output.extensions,
+        warnings,
+    }
+}
+
+/// Parses only the header commands of a BMS file from source text, for listing songs.
+///
+/// It lexes the lines until the first message which places the objects, so the commands after it are ignored. The remaining lines are only checked whether they start with `#RANDOM` or `#SWITCH` for [`BmsHeaderOutput::has_random`]. The processors of [`header_preset`] run on the commands activated by `#RANDOM` and `#SWITCH` scopes. The key mapper does not affect the headers, so `config` has the default one [`KeyLayoutBeat`]. The extension token processor and the playing checks are not run.
+pub fn parse_bms_header<P: Prompter, R: Rng, M: TokenModifier>(
+    source: &str,
+    config: ParseConfig<KeyLayoutBeat, P, R, M>,
+) -> BmsHeaderOutput {
+    // The source is already split at `\n` as `StreamLexer` does, so each line is lexed only once.
+    let mut lines = source.split_inclusive('\n');
+    let mut offset = 0;
+    let mut warnings = Vec::new();
+    let mut header_tokens = Vec::new();
+    for line in lines.by_ref() {
+        let LexOutput {
+            tokens,
+            lex_warnings,
+        } = lex::stream::lex_at(line, offset);
+        offset += line.len();
+        if tokens
+            .iter()
+            .any(|token| matches!(token.content(), Token::Message { .. }))
+        {
+            break;
+        }
+        warnings.extend(lex_warnings.into_iter().map(BmsWarning::Lex));
+        header_tokens.extend(tokens);
+    }
+    let mut tokens: lex::TokenStream<'_> = header_tokens.into_iter().collect();
+
+    warnings.extend(modify_tokens(
+        &config.token_modifier,
+        &mut tokens,
+        Some(source),
+    ));
+    let has_random = tokens.iter().any(|token| {
+        ["RANDOM", "SWITCH"]
+            .iter()
+            .any(|command| token.content().is_header(command))
+    }) || lines.any(is_random_line);
+    let headers: Vec<_> = tokens.iter().collect();
+    let mut headers_slice = headers.as_slice();
+    let mut ctx = ProcessContext::new(&mut headers_slice, &config.prompter);
+    let bms = header_preset(Rc::new(RefCell::new(config.rng))).process(&mut ctx);
+    warnings.extend(ctx.into_warnings().into_iter().map(BmsWarning::Parse));
+    attach_fixes(source, &mut warnings);
+
+    BmsHeaderOutput {
+        bms,
+        has_random,
         warnings,
     }
 }
 
+/// Returns whether `line` starts with `#RANDOM` or `#SWITCH`, without lexing it.
+fn is_random_line(line: &str) -> bool {
+    let Some(command) = line.trim_start().strip_prefix('#') else {
+        return false;
+    };
+    ["RANDOM", "SWITCH"].iter().any(|name| {
+        command
+            .get(..name.len())
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case(name))
+    })
+}
+
 /// Output of parsing a BMS file.
 #[derive(Debug, Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize))]
@@ -288,6 +551,52 @@ pub struct BmsOutput {
     pub warnings: Vec<BmsWarning>,
 }
 
+/// Output of [`parse_bms_with_extension`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct ExtendedBmsOutput<E> {
+    /// The parsed BMS data.
+    pub bms: Result<Bms, ParseErrorWithRange>,
+    /// The output of the extension token processors registered to [`ParseConfig`], or `None` if parsing failed.
+    pub extensions: Option<E>,
+    /// Warnings that occurred during parsing.
+    pub warnings: Vec<BmsWarning>,
+}
+
+impl<E> From<ExtendedBmsOutput<E>> for BmsOutput {
+    fn from(output: ExtendedBmsOutput<E>) -> Self {
+        Self {
+            bms: output.bms,
+            warnings: output.warnings,
+        }
+    }
+}
+
+/// Output of [`parse_bms_auto`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct AutoBmsOutput {
+    /// The output of parsing with the detected key mapper.
+    pub output: BmsOutput,
+    /// The detected key mode and its mapper.
+    pub detection: KeyModeDetection,
+}
+
+/// Output of [`parse_bms_header`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct BmsHeaderOutput {
+    /// The parsed BMS data which has only the header fields. See [`header_preset`] for the available fields.
+    pub bms: Result<Bms, ParseErrorWithRange>,
+    /// Whether the source has `#RANDOM` or `#SWITCH` commands, that is, the chart may vary on each play.
+    pub has_random: bool,
+    /// Warnings that occurred during parsing.
+    pub warnings: Vec<BmsWarning>,
+}
+
 #[cfg(feature = "diagnostics")]
 impl ToAriadne for BmsWarning {
     fn to_report<'a>(
@@ -340,3 +340,20 @@ impl KeyLayoutMapper for KeyLayoutDscOctFp {
         Some(Self::new(side, kind, key))
     }
 }
+
+/// The [`KeyLayoutMapper`]s in this module, to choose one of them at runtime.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyLayoutKind {
+    /// [`KeyLayoutBeat`].
+    #[default]
+    Beat,
+    /// [`KeyLayoutPmsBmeType`].
+    PmsBmeType,
+    /// [`KeyLayoutPms`].
+    Pms,
+    /// [`KeyLayoutBeatNanasi`].
+    BeatNanasi,
+    /// [`KeyLayoutDscOctFp`].
+    DscOctFp,
+}
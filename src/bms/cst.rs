@@ -0,0 +1,645 @@
+//! Lossless concrete syntax tree of BMS format.
+//!
+//! Raw [String] == `Cst::parse` ==> [`Cst`] == `Cst::lex` ==> [`super::lex::TokenStream`] (in [`LexOutput`])
+//!
+//! [`Cst`] keeps every byte of the source text, such as comments, spaces, the order of commands, duplicated definitions and the line endings. So `Cst::parse(source).to_string()` always equals to `source`. Unlike [`Bms::unparse`](crate::bms::model::Bms::unparse), the edit methods of [`Cst`] rewrite only the lines relating to the edit, so the text diff is minimal. It is useful for editors and auto-fixers.
+//!
+//! The commands in `#IF`-`#ENDIF` and `#SWITCH`-`#ENDSW` scopes are not touched by the edit methods, because they may not be activated.
+
+use std::{fmt, ops::Range};
+
+use thiserror::Error;
+
+use crate::bms::{
+    command::{ObjId, channel::NoteChannelId, mixin::SourceRangeMixin, time::ObjTime},
+    lex::{LexOutput, TokenStream, token::Token},
+};
+
+/// A line ending of [`CstLine`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum LineEnding {
+    /// `\n`.
+    #[default]
+    Lf,
+    /// `\r\n`.
+    CrLf,
+    /// No line ending, only for the last line of the source.
+    None,
+}
+
+impl LineEnding {
+    /// Returns the text of the line ending.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Lf => "\n",
+            Self::CrLf => "\r\n",
+            Self::None => "",
+        }
+    }
+}
+
+/// A line of [`Cst`], which keeps the text as is.
+///
+/// The line is lexed once on creation and on each edit, and the byte ranges of its parts are kept.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CstLine {
+    text: String,
+    line_ending: LineEnding,
+    shape: LineShape,
+}
+
+/// The lexed structure of [`CstLine`] as the byte ranges in its text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LineShape {
+    Blank,
+    Header {
+        name: Range<usize>,
+        args: Range<usize>,
+    },
+    Message {
+        track: u64,
+        channel: NoteChannelId,
+        message: Range<usize>,
+    },
+    Other,
+}
+
+impl LineShape {
+    fn lex(text: &str) -> Self {
+        if text.trim().is_empty() {
+            return Self::Blank;
+        }
+        let token = TokenStream::parse_lex(text)
+            .tokens
+            .into_iter()
+            .next()
+            .map(SourceRangeMixin::into_content);
+        match token {
+            Some(Token::Header { name, args }) => {
+                let name_start = text.len() - text.trim_start().trim_start_matches('#').len();
+                let content_end = text.trim_end().len();
+                Self::Header {
+                    name: name_start..name_start + name.len(),
+                    args: content_end - args.len()..content_end,
+                }
+            }
+            Some(Token::Message {
+                track,
+                channel,
+                message,
+            }) => {
+                let message_start = text.find(':').map_or(text.len(), |colon| colon + 1);
+                Self::Message {
+                    track: track.0,
+                    channel: channel.into(),
+                    message: message_start..message_start + message.len(),
+                }
+            }
+            Some(Token::NotACommand(_)) | None => Self::Other,
+        }
+    }
+}
+
+/// An error on editing [`Cst`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+pub enum CstEditError {
+    /// The time needs a message line finer than [`MAX_MESSAGE_RESOLUTION`] objects.
+    #[error("resolution {resolution} of the message exceeds {MAX_MESSAGE_RESOLUTION}")]
+    ResolutionTooHigh {
+        /// The number of the objects in the message line needed for the time.
+        resolution: u64,
+    },
+}
+
+/// The maximum number of the objects in a message line written by [`Cst::add_object`].
+pub const MAX_MESSAGE_RESOLUTION: u64 = 3840;
+
+/// A syntactic kind of [`CstLine`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum CstLineKind<'a> {
+    /// The line has only whitespaces.
+    Blank,
+    /// The line is a [`Token::Header`].
+    Header {
+        /// The name of the command without `#`.
+        name: &'a str,
+        /// The arguments of the command without the surrounding whitespaces.
+        args: &'a str,
+    },
+    /// The line is a [`Token::Message`].
+    Message {
+        /// The track of the message.
+        track: u64,
+        /// The channel of the message.
+        channel: NoteChannelId,
+        /// The object id sequence of the message.
+        message: &'a str,
+    },
+    /// The line is not a command, or failed to be lexed.
+    Other,
+}
+
+impl CstLine {
+    /// Creates a new line from the text without the line ending.
+    #[must_use]
+    pub fn new(text: impl Into<String>, line_ending: LineEnding) -> Self {
+        let text = text.into();
+        Self {
+            shape: LineShape::lex(&text),
+            text,
+            line_ending,
+        }
+    }
+
+    /// Returns the text without the line ending.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the line ending.
+    #[must_use]
+    pub const fn line_ending(&self) -> LineEnding {
+        self.line_ending
+    }
+
+    /// Returns the length in bytes including the line ending.
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.text.len() + self.line_ending.as_str().len()
+    }
+
+    /// Returns whether the line has no bytes including the line ending.
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Lexes the line into a token, or `None` if it is blank or failed to be lexed.
+    #[must_use]
+    pub fn token(&self) -> Option<Token<'_>> {
+        TokenStream::parse_lex(&self.text)
+            .tokens
+            .into_iter()
+            .next()
+            .map(SourceRangeMixin::into_content)
+    }
+
+    /// Returns the syntactic kind of the line, without lexing it again.
+    #[must_use]
+    pub fn kind(&self) -> CstLineKind<'_> {
+        let slice = |range: &Range<usize>| self.text.get(range.clone()).unwrap_or_default();
+        match &self.shape {
+            LineShape::Blank => CstLineKind::Blank,
+            LineShape::Header { name, args } => CstLineKind::Header {
+                name: slice(name),
+                args: slice(args),
+            },
+            LineShape::Message {
+                track,
+                channel,
+                message,
+            } => CstLineKind::Message {
+                track: *track,
+                channel: *channel,
+                message: slice(message),
+            },
+            LineShape::Other => CstLineKind::Other,
+        }
+    }
+
+    /// The byte range of the arguments of the header, or the empty range at the end of the command.
+    fn args_range(&self) -> Range<usize> {
+        if let LineShape::Header { args, .. } = &self.shape {
+            return args.clone();
+        }
+        let content_end = self.text.trim_end().len();
+        content_end..content_end
+    }
+
+    /// Replaces the text in `range` with `with`, and lexes the line again.
+    fn replace_range(&mut self, range: Range<usize>, with: &str) {
+        self.text.replace_range(range, with);
+        self.shape = LineShape::lex(&self.text);
+    }
+
+    /// Returns `1` if the line opens a control flow scope, `-1` if it closes one, or `0` otherwise.
+    fn control_flow_depth_change(&self) -> isize {
+        let CstLineKind::Header { name, .. } = self.kind() else {
+            return 0;
+        };
+        if ["IF", "SWITCH", "SETSWITCH"]
+            .iter()
+            .any(|keyword| name.eq_ignore_ascii_case(keyword))
+        {
+            1
+        } else if ["ENDIF", "ENDSW"]
+            .iter()
+            .any(|keyword| name.eq_ignore_ascii_case(keyword))
+        {
+            -1
+        } else {
+            0
+        }
+    }
+}
+
+impl fmt::Display for CstLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.text, self.line_ending.as_str())
+    }
+}
+
+/// Lossless concrete syntax tree of BMS format, which is a list of the lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct Cst {
+    lines: Vec<CstLine>,
+}
+
+impl Cst {
+    /// Splits the source text into the lines, keeping every byte.
+    #[must_use]
+    pub fn parse(source: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut rest = source;
+        while !rest.is_empty() {
+            let (line, line_ending, next) = match rest.split_once('\n') {
+                Some((line, next)) => line
+                    .strip_suffix('\r')
+                    .map_or((line, LineEnding::Lf, next), |line_without_cr| {
+                        (line_without_cr, LineEnding::CrLf, next)
+                    }),
+                None => (rest, LineEnding::None, ""),
+            };
+            lines.push(CstLine::new(line, line_ending));
+            rest = next;
+        }
+        Self { lines }
+    }
+
+    /// Returns the lines.
+    #[must_use]
+    pub fn lines(&self) -> &[CstLine] {
+        &self.lines
+    }
+
+    /// Returns the byte range of the line at `index` in the rendered text, excluding the line ending.
+    #[must_use]
+    pub fn line_range(&self, index: usize) -> Option<Range<usize>> {
+        let line = self.lines.get(index)?;
+        let start: usize = self.lines.iter().take(index).map(CstLine::len).sum();
+        Some(start..start + line.text.len())
+    }
+
+    /// Lexes the lines into [`TokenStream`]. The ranges of the tokens point into the rendered text of this tree, so they can be used with [`Bms::from_token_stream`](crate::bms::model::Bms::from_token_stream) as usual.
+    pub fn lex(&self) -> LexOutput<'_> {
+        let mut tokens = Vec::new();
+        let mut lex_warnings = Vec::new();
+        let mut offset = 0;
+        for line in &self.lines {
+            let output = TokenStream::parse_lex(&line.text);
+            let shift = |range: &Range<usize>| range.start + offset..range.end + offset;
+            tokens.extend(output.tokens.into_iter().map(|token| {
+                let range = shift(token.range());
+                SourceRangeMixin::new(token.into_content(), range)
+            }));
+            lex_warnings.extend(output.lex_warnings.into_iter().map(|warning| {
+                let range = shift(warning.range());
+                SourceRangeMixin::new(warning.into_content(), range)
+            }));
+            offset += line.len();
+        }
+        LexOutput {
+            tokens: TokenStream { tokens },
+            lex_warnings,
+        }
+    }
+
+    /// Sets the arguments of the last header `#name` out of the control flow scopes, keeping the spaces around them. If there is no such header, inserts a new line after the last header except the control flow commands.
+    ///
+    /// Returns the index of the edited or inserted line.
+    pub fn set_header(&mut self, name: &str, args: &str) -> usize {
+        if let Some(index) = self.find_headers(name).last().copied()
+            && let Some(line) = self.lines.get_mut(index)
+        {
+            let range = line.args_range();
+            let separator = if range.is_empty() && !args.is_empty() {
+                " "
+            } else {
+                ""
+            };
+            line.replace_range(range, &format!("{separator}{args}"));
+            return index;
+        }
+        let text = if args.is_empty() {
+            format!("#{name}")
+        } else {
+            format!("#{name} {args}")
+        };
+        let index = self
+            .top_level_lines()
+            .filter(|&index| {
+                self.lines.get(index).is_some_and(|line| {
+                    matches!(line.kind(), CstLineKind::Header { name: line_name, .. } if !is_control_flow(line_name))
+                })
+            })
+            .last()
+            .map_or(0, |index| index + 1);
+        self.insert_line(index, text);
+        index
+    }
+
+    /// Removes all the headers `#name` out of the control flow scopes.
+    ///
+    /// Returns the number of the removed lines.
+    pub fn remove_header(&mut self, name: &str) -> usize {
+        let indexes = self.find_headers(name);
+        for &index in indexes.iter().rev() {
+            self.lines.remove(index);
+        }
+        indexes.len()
+    }
+
+    /// Adds an object `id` at `time` on `channel`, as a new message line next to the messages of the same track.
+    ///
+    /// Returns the index of the inserted line.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`CstEditError::ResolutionTooHigh`] if the reduced denominator of `time` exceeds [`MAX_MESSAGE_RESOLUTION`], because the line would be too long.
+    pub fn add_object(
+        &mut self,
+        time: ObjTime,
+        channel: NoteChannelId,
+        id: ObjId,
+    ) -> Result<usize, CstEditError> {
+        let resolution = time.denominator_u64();
+        if MAX_MESSAGE_RESOLUTION < resolution {
+            return Err(CstEditError::ResolutionTooHigh { resolution });
+        }
+        // The time at the end of a track is the start of the next one.
+        let target_track = time.track().0 + time.numerator() / resolution;
+        let position = (time.numerator() % resolution) as usize;
+        let message: String = (0..resolution as usize)
+            .map(|index| {
+                if index == position {
+                    id.to_string()
+                } else {
+                    "00".to_string()
+                }
+            })
+            .collect();
+        let text = format!("#{target_track:03}{channel}:{message}");
+
+        let key = (target_track, channel);
+        let top_level_messages: Vec<(usize, (u64, NoteChannelId))> = self
+            .top_level_lines()
+            .filter_map(|index| match self.lines.get(index)?.kind() {
+                CstLineKind::Message {
+                    track,
+                    channel: line_channel,
+                    ..
+                } => Some((index, (track, line_channel))),
+                _ => None,
+            })
+            .collect();
+        let index = match top_level_messages
+            .iter()
+            .rfind(|(_, line_key)| *line_key <= key)
+        {
+            Some(&(index, _)) => index + 1,
+            None => top_level_messages
+                .first()
+                .map_or(self.lines.len(), |&(index, _)| index),
+        };
+        self.insert_line(index, text);
+        Ok(index)
+    }
+
+    /// Removes the object at `time` on `channel` out of the control flow scopes, by replacing it with `00`. If the message line becomes empty, the line is removed.
+    ///
+    /// Returns whether the object was found.
+    pub fn remove_object(&mut self, time: ObjTime, channel: NoteChannelId) -> bool {
+        let target = self.top_level_lines().find_map(|index| {
+            let line = self.lines.get(index)?;
+            let CstLineKind::Message {
+                track,
+                channel: line_channel,
+                message,
+            } = line.kind()
+            else {
+                return None;
+            };
+            if track != time.track().0 || line_channel != channel {
+                return None;
+            }
+            let position = message_objects(track, message)
+                .position(|(object_time, id)| object_time == time && id != b"00")?;
+            let message_start = line.text.find(':')? + 1;
+            Some((index, message_start + position * 2))
+        });
+        let Some((index, start)) = target else {
+            return false;
+        };
+        let Some(line) = self.lines.get_mut(index) else {
+            return false;
+        };
+        line.replace_range(start..start + 2, "00");
+        let is_empty = matches!(
+            line.kind(),
+            CstLineKind::Message { message, .. } if message.chars().all(|c| c == '0')
+        );
+        if is_empty {
+            let line_ending = line.line_ending;
+            self.lines.remove(index);
+            // Keep the source without the line ending at the end as is.
+            if line_ending == LineEnding::None
+                && index == self.lines.len()
+                && let Some(last) = self.lines.last_mut()
+            {
+                last.line_ending = LineEnding::None;
+            }
+        }
+        true
+    }
+
+    /// Iterates the indexes of the lines out of the control flow scopes.
+    fn top_level_lines(&self) -> impl Iterator<Item = usize> + '_ {
+        self.lines
+            .iter()
+            .enumerate()
+            .scan(ScopeDepth::default(), |depth, (index, line)| {
+                Some(depth.step(line).then_some(index))
+            })
+            .flatten()
+    }
+
+    /// Returns the byte ranges of the lines out of the control flow scopes defining `id` by `command` such as `WAV`, including the line endings, in order.
+    pub(crate) fn top_level_definitions(&self, command: &str, id: ObjId) -> Vec<Range<usize>> {
+        let case_sensitive_obj_id = self.top_level_lines().any(|index| {
+            self.lines.get(index).is_some_and(|line| {
+                matches!(line.kind(), CstLineKind::Header { name, args } if is_base_62(name, args))
+            })
+        });
+        let mut depth = ScopeDepth::default();
+        let mut start = 0;
+        let mut ranges = Vec::new();
+        for line in &self.lines {
+            let end = start + line.len();
+            if depth.step(line)
+                && let CstLineKind::Header { name, .. } = line.kind()
+                && let Some((line_command, line_id)) = split_definition(name)
+                && line_command.eq_ignore_ascii_case(command)
+                && ObjId::try_from(line_id, case_sensitive_obj_id)
+                    .is_ok_and(|line_id| line_id == id)
+            {
+                ranges.push(start..end);
+            }
+            start = end;
+        }
+        ranges
+    }
+
+    fn find_headers(&self, name: &str) -> Vec<usize> {
+        self.top_level_lines()
+            .filter(|&index| {
+                self.lines.get(index).is_some_and(|line| {
+                    matches!(line.kind(), CstLineKind::Header { name: line_name, .. } if line_name.eq_ignore_ascii_case(name))
+                })
+            })
+            .collect()
+    }
+
+    /// Inserts a line at `index`, using the line ending of the neighbor.
+    fn insert_line(&mut self, index: usize, text: String) {
+        let line_ending = self
+            .lines
+            .get(index.saturating_sub(1))
+            .into_iter()
+            .chain(&self.lines)
+            .map(|line| line.line_ending)
+            .find(|&line_ending| line_ending != LineEnding::None)
+            .unwrap_or_default();
+        let mut line = CstLine::new(text, line_ending);
+        if index >= self.lines.len()
+            && let Some(last) = self.lines.last_mut()
+            && last.line_ending == LineEnding::None
+        {
+            // Move the missing line ending at the end to the new line.
+            last.line_ending = line_ending;
+            line.line_ending = LineEnding::None;
+        }
+        self.lines.insert(index.min(self.lines.len()), line);
+    }
+}
+
+/// Tracks the depth of the `#IF` and `#SWITCH` scopes over the lines.
+///
+/// The scopes left open in a `#RANDOM` block are closed at its `#ENDRANDOM`, so an unclosed `#IF`
+/// does not hide the rest of the file.
+#[derive(Debug, Default)]
+pub(crate) struct ScopeDepth {
+    depth: isize,
+    random_depths: Vec<isize>,
+}
+
+impl ScopeDepth {
+    /// Returns the current depth of the scopes.
+    pub(crate) const fn depth(&self) -> isize {
+        self.depth
+    }
+
+    /// Steps over `line`, and returns whether it is out of the scopes and does not open or close one.
+    pub(crate) fn step(&mut self, line: &CstLine) -> bool {
+        let change = line.control_flow_depth_change();
+        let is_top_level = self.depth == 0 && change == 0;
+        self.depth = (self.depth + change).max(0);
+        if let CstLineKind::Header { name, .. } = line.kind() {
+            if name.eq_ignore_ascii_case("RANDOM") || name.eq_ignore_ascii_case("SETRANDOM") {
+                self.random_depths.push(self.depth);
+            } else if name.eq_ignore_ascii_case("ENDRANDOM")
+                && let Some(depth) = self.random_depths.pop()
+            {
+                self.depth = depth;
+            }
+        }
+        is_top_level
+    }
+}
+
+/// Returns whether the header `name` is a control flow command such as `#RANDOM` or `#IF`.
+pub(crate) fn is_control_flow(name: &str) -> bool {
+    [
+        "RANDOM",
+        "SETRANDOM",
+        "IF",
+        "ELSEIF",
+        "ELSE",
+        "ENDIF",
+        "ENDRANDOM",
+        "SWITCH",
+        "SETSWITCH",
+        "CASE",
+        "SKIP",
+        "DEF",
+        "ENDSW",
+    ]
+    .iter()
+    .any(|keyword| name.eq_ignore_ascii_case(keyword))
+}
+
+/// The commands of the definitions followed by an object id, such as `#WAVxx`.
+pub(crate) const DEFINITION_COMMANDS: &[&str] = &[
+    "WAV",
+    "EXWAV",
+    "BMP",
+    "EXBMP",
+    "BGA",
+    "@BGA",
+    "SWBGA",
+    "ARGB",
+    "BPM",
+    "EXBPM",
+    "STOP",
+    "SCROLL",
+    "SPEED",
+    "EXRANK",
+    "TEXT",
+    "SEEK",
+    "CHANGEOPTION",
+];
+
+/// Splits the header `name` into the definition command in [`DEFINITION_COMMANDS`] and the id part, such as `WAV` and `1a` from `wav1a`.
+pub(crate) fn split_definition(name: &str) -> Option<(&'static str, &str)> {
+    DEFINITION_COMMANDS.iter().find_map(|&command| {
+        let id = name
+            .get(..command.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(command))
+            .and_then(|_| name.get(command.len()..))?;
+        (id.len() == 2 && id.bytes().all(|byte| byte.is_ascii_alphanumeric()))
+            .then_some((command, id))
+    })
+}
+
+/// Returns whether the header is `#BASE 62`, which makes the object ids case sensitive.
+pub(crate) fn is_base_62(name: &str, args: &str) -> bool {
+    name.eq_ignore_ascii_case("BASE") && args == "62"
+}
+
+/// Iterates the times of the `pairs` objects in a message on `track`, in order.
+pub(crate) fn object_times(track: u64, pairs: u64) -> impl Iterator<Item = ObjTime> {
+    (0..pairs).map_while(move |index| ObjTime::new(track, index, pairs))
+}
+
+/// Splits the message on `track` into the times and the 2-byte object ids, including `00`. The odd byte at the end is ignored.
+pub(crate) fn message_objects(track: u64, message: &str) -> impl Iterator<Item = (ObjTime, &[u8])> {
+    object_times(track, message.len() as u64 / 2).zip(message.as_bytes().chunks_exact(2))
+}
+
+impl fmt::Display for Cst {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.lines.iter().try_for_each(|line| write!(f, "{line}"))
+    }
+}
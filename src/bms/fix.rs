@@ -0,0 +1,190 @@
+//! Machine-applicable fixes for the diagnostics, like the suggestions of rustc.
+//!
+//! A [`Fix`] is a set of [`TextEdit`]s against the byte ranges of the source text, which are applied together by [`apply_fixes`]. The lints in [`crate::bms::lint`] attach them to [`LintDiagnostic::fix`](crate::bms::lint::LintDiagnostic::fix).
+//!
+//! Some [`ParseWarning`]s also carry a fix, returned by [`ParseWarning::fix`]: the misspellings normalized by [`DefaultTokenRelaxer`](crate::bms::parse::token_processor::DefaultTokenRelaxer) and the duplicated definitions. [`emit_bms_warnings`](crate::diagnostics::emit_bms_warnings) shows them as the help. Run the lints on the chart to get the fixes for the other problems.
+//!
+//! ```
+//! use bms_rs::bms::fix::{Fix, TextEdit, apply_fixes};
+//!
+//! let source = "#RONDAM 2\n#WAV01 kick.wav\n";
+//! let fixes = [
+//!     Fix::new(&"replace with `#RANDOM`", vec![TextEdit::replace(1..7, "RANDOM")]),
+//!     Fix::new(&"add `#TOTAL`", vec![TextEdit::insert(0, "#TOTAL 300\n")]),
+//! ];
+//! assert_eq!(
+//!     apply_fixes(source, &fixes),
+//!     "#TOTAL 300\n#RANDOM 2\n#WAV01 kick.wav\n"
+//! );
+//! ```
+
+use std::ops::Range;
+
+use crate::bms::{
+    cst::{Cst, split_definition},
+    parse::{ParseWarning, ParseWarningWithRange},
+};
+
+/// A replacement of the byte range in the source text.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TextEdit {
+    /// The byte range to be replaced. It is empty for an insertion.
+    pub range: Range<usize>,
+    /// The text to replace with. It is empty for a deletion.
+    pub replacement: String,
+}
+
+impl TextEdit {
+    /// Creates an edit replacing `range` with `replacement`.
+    pub fn replace(range: Range<usize>, replacement: impl Into<String>) -> Self {
+        Self {
+            range,
+            replacement: replacement.into(),
+        }
+    }
+
+    /// Creates an edit inserting `text` at the byte offset `at`.
+    pub fn insert(at: usize, text: impl Into<String>) -> Self {
+        Self::replace(at..at, text)
+    }
+
+    /// Creates an edit deleting `range`.
+    #[must_use]
+    pub const fn delete(range: Range<usize>) -> Self {
+        Self {
+            range,
+            replacement: String::new(),
+        }
+    }
+
+    /// Returns whether the ranges of the edits intersect, so they cannot be applied together. The insertions at the same offset do not conflict.
+    #[must_use]
+    pub const fn conflicts_with(&self, other: &Self) -> bool {
+        self.range.start < other.range.end && other.range.start < self.range.end
+    }
+}
+
+/// A set of edits fixing a problem, applied all or nothing.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Fix {
+    /// The description of the fix, such as ``replace with `#RANDOM` ``.
+    pub message: String,
+    /// The edits to apply.
+    pub edits: Vec<TextEdit>,
+}
+
+impl Fix {
+    /// Creates a new fix of `edits`.
+    #[must_use]
+    pub fn new(message: &impl ToString, edits: Vec<TextEdit>) -> Self {
+        Self {
+            message: message.to_string(),
+            edits,
+        }
+    }
+}
+
+/// Applies `fixes` to `source` and returns the corrected text.
+///
+/// The fixes are accepted in the order. A fix is skipped entirely if any of its edits is out of `source`, or conflicts with the edits accepted already, so run the checks again to find the remaining problems. The insertions at the same offset are applied in the order.
+#[must_use]
+pub fn apply_fixes<'a>(source: &str, fixes: impl IntoIterator<Item = &'a Fix>) -> String {
+    let mut accepted: Vec<&TextEdit> = vec![];
+    for fix in fixes {
+        let applicable = fix.edits.iter().enumerate().all(|(index, edit)| {
+            source.get(edit.range.clone()).is_some()
+                && !accepted.iter().any(|other| edit.conflicts_with(other))
+                && !fix
+                    .edits
+                    .iter()
+                    .take(index)
+                    .any(|other| edit.conflicts_with(other))
+        });
+        if applicable {
+            accepted.extend(&fix.edits);
+        }
+    }
+    // The insertions go before the replacement at the same offset, and the sort is stable to keep the order of the insertions.
+    accepted.sort_by_key(|edit| (edit.range.start, edit.range.end));
+
+    let mut fixed = String::with_capacity(source.len());
+    let mut copied = 0;
+    for edit in accepted {
+        fixed.push_str(source.get(copied..edit.range.start).unwrap_or_default());
+        fixed.push_str(&edit.replacement);
+        copied = edit.range.end;
+    }
+    fixed.push_str(source.get(copied..).unwrap_or_default());
+    fixed
+}
+
+/// Attaches the fixes to [`ParseWarning::DuplicatingDef`]s, removing the unused definition line of `source`.
+///
+/// The fix is attached only if both the definitions are out of the control flow scopes, because the other branches may use the one looking unused.
+pub(crate) fn attach_duplication_fixes<'a>(
+    source: &str,
+    warnings: impl IntoIterator<Item = &'a mut ParseWarningWithRange>,
+) {
+    let mut cst = None;
+    for warning in warnings {
+        let range = warning.range().clone();
+        let ParseWarning::DuplicatingDef {
+            id,
+            newer_used,
+            fix,
+        } = warning.content_mut()
+        else {
+            continue;
+        };
+        let Some((command, _)) = source
+            .get(range.clone())
+            .and_then(|name| name.trim_start().strip_prefix('#'))
+            .and_then(split_definition)
+        else {
+            continue;
+        };
+        let definitions = cst
+            .get_or_insert_with(|| Cst::parse(source))
+            .top_level_definitions(command, *id);
+        let Some(index) = definitions
+            .iter()
+            .position(|line| line.contains(&range.start))
+        else {
+            continue;
+        };
+        let unused = if *newer_used {
+            index
+                .checked_sub(1)
+                .and_then(|older| definitions.get(older))
+        } else {
+            definitions.get(index)
+        };
+        *fix = unused.map(|line| {
+            Fix::new(
+                &"remove the unused definition",
+                vec![TextEdit::delete(line.clone())],
+            )
+        });
+    }
+}
+
+/// Returns the range of the whole line containing `range`, including the line ending.
+pub(crate) fn line_range(source: &str, range: &Range<usize>) -> Range<usize> {
+    let start = source
+        .get(..range.start)
+        .and_then(|before| before.rfind('\n'))
+        .map_or(0, |newline| newline + 1);
+    let end = source
+        .get(range.end..)
+        .and_then(|after| after.find('\n'))
+        .map_or(source.len(), |newline| range.end + newline + 1);
+    start..end
+}
+
+/// Returns the length of the line ending at the end of `line`.
+pub(crate) fn line_ending(source: &str, line: &Range<usize>) -> usize {
+    let content = source.get(line.clone()).unwrap_or_default();
+    content.len() - content.trim_end_matches(['\r', '\n']).len()
+}
@@ -0,0 +1,272 @@
+//! Canonical formatter of BMS format, a.k.a. `bms-fmt`.
+//!
+//! [`format_bms`] splits the BMS source text into the `#RANDOM` and `#SWITCH` blocks, which are preserved verbatim, and the runs of the lines between them. Each run is printed in the canonical layout below, separating each section by a blank line:
+//!
+//! 1. The lines which are not commands, such as comments, in order.
+//! 2. The headers grouped into the song information (`#TITLE` and so on), the playing settings (`#BPM` and so on) and the resources (`#STAGEFILE` and so on), in the fixed order. `#BASE` comes first, because the ids in the definitions and `#LNOBJ` depend on it.
+//! 3. The definitions such as `#WAVxx` and `#BMPxx`, grouped by the command and sorted by the id.
+//! 4. The other headers, in order.
+//! 5. The messages ordered by the track and the channel. The message lines on the same track and channel are merged, and the resolution of each line is reduced. The objects at the same position are kept in separated lines, so the meaning of the score does not change.
+//!
+//! The lines are never moved across a block, because a header after `#ENDRANDOM` overrides the one in the activated branch and vice versa. The spaces around the commands are normalized, and all the lines end with the line ending first found in the source. The duplicated headers and definitions keep their relative order. Formatting the formatted text again returns the same text.
+
+use std::collections::BTreeMap;
+
+use num::Integer;
+
+use crate::bms::{
+    command::{
+        ObjId,
+        channel::{Channel, NoteChannelId},
+    },
+    cst::{
+        Cst, CstLine, CstLineKind, DEFINITION_COMMANDS, LineEnding, ScopeDepth, is_control_flow,
+        split_definition,
+    },
+    unparse::lcm_slice,
+};
+
+/// The header groups in order, printed in this order.
+const HEADER_GROUPS: &[&[&str]] = &[
+    &[
+        "BASE",
+        "PLAYER",
+        "GENRE",
+        "TITLE",
+        "SUBTITLE",
+        "ARTIST",
+        "SUBARTIST",
+        "MAKER",
+        "COMMENT",
+        "EMAIL",
+        "URL",
+    ],
+    &[
+        "BPM",
+        "BASEBPM",
+        "PLAYLEVEL",
+        "DIFFICULTY",
+        "RANK",
+        "DEFEXRANK",
+        "TOTAL",
+        "VOLWAV",
+        "LNTYPE",
+        "LNOBJ",
+        "LNMODE",
+    ],
+    &[
+        "STAGEFILE",
+        "BANNER",
+        "BACKBMP",
+        "PREVIEW",
+        "MOVIE",
+        "POORBGA",
+        "MIDIFILE",
+        "PATH_WAV",
+        "MATERIALS",
+    ],
+];
+
+/// Formats the BMS source text into the canonical layout. See [the module document](self) for the layout.
+#[must_use]
+pub fn format_bms(source: &str) -> String {
+    let cst = Cst::parse(source);
+    let line_ending = cst
+        .lines()
+        .iter()
+        .map(CstLine::line_ending)
+        .find(|&line_ending| line_ending != LineEnding::None)
+        .unwrap_or_default();
+
+    let mut output: Vec<Vec<String>> = Vec::new();
+    let mut sections = Sections::default();
+    let mut lines = cst.lines().iter().peekable();
+    while let Some(line) = lines.next() {
+        match line.kind() {
+            CstLineKind::Blank => {}
+            CstLineKind::Other => sections.not_commands.push(line.text().trim().to_string()),
+            CstLineKind::Header { name, .. } if is_control_flow(name) => {
+                // Collect the block until the scopes are closed and no control flow command follows.
+                let mut block = vec![line];
+                let mut depth = ScopeDepth::default();
+                depth.step(line);
+                let mut held = Vec::new();
+                while let Some(&next) = lines.peek() {
+                    if depth.depth() > 0 || is_control_flow_line(next) {
+                        block.append(&mut held);
+                        block.push(next);
+                        depth.step(next);
+                    } else if matches!(next.kind(), CstLineKind::Blank | CstLineKind::Other) {
+                        held.push(next);
+                    } else {
+                        break;
+                    }
+                    lines.next();
+                }
+                output.extend(std::mem::take(&mut sections).into_sections());
+                output.push(
+                    block
+                        .iter()
+                        .map(|block_line| block_line.text().to_string())
+                        .collect(),
+                );
+                for trivia in held {
+                    if trivia.kind() == CstLineKind::Other {
+                        sections.not_commands.push(trivia.text().trim().to_string());
+                    }
+                }
+            }
+            CstLineKind::Header { name, args } => sections.push_header(name, args),
+            CstLineKind::Message {
+                track,
+                channel,
+                message,
+            } => sections.push_message(track, channel, message),
+        }
+    }
+    output.extend(sections.into_sections());
+    render(&output, line_ending.as_str())
+}
+
+/// Joins the non-empty sections separated by a blank line.
+fn render(sections: &[Vec<String>], line_ending: &str) -> String {
+    let mut output = String::new();
+    for section in sections.iter().filter(|section| !section.is_empty()) {
+        if !output.is_empty() {
+            output.push_str(line_ending);
+        }
+        for line in section {
+            output.push_str(line);
+            output.push_str(line_ending);
+        }
+    }
+    output
+}
+
+fn is_control_flow_line(line: &CstLine) -> bool {
+    matches!(line.kind(), CstLineKind::Header { name, .. } if is_control_flow(name))
+}
+
+/// The lines of a run between the control flow blocks, grouped into the sections.
+#[derive(Debug, Default)]
+struct Sections {
+    not_commands: Vec<String>,
+    header_groups: [Vec<(usize, String)>; HEADER_GROUPS.len()],
+    definitions: [Vec<(ObjId, String)>; DEFINITION_COMMANDS.len()],
+    other_headers: Vec<String>,
+    messages: BTreeMap<(u64, NoteChannelId), MessageGroup>,
+}
+
+/// The messages on the same track and channel.
+#[derive(Debug, Default)]
+struct MessageGroup {
+    /// The messages kept as is, such as the section length.
+    verbatim: Vec<String>,
+    /// The objects as (numerator, denominator, id) in the layers, where no two objects share a position.
+    layers: Vec<Vec<(u64, u64, String)>>,
+}
+
+impl Sections {
+    fn push_header(&mut self, name: &str, args: &str) {
+        let line = if args.is_empty() {
+            format!("#{name}")
+        } else {
+            format!("#{name} {args}")
+        };
+        let upper_name = name.to_ascii_uppercase();
+        for (group, names) in self.header_groups.iter_mut().zip(HEADER_GROUPS) {
+            if let Some(order) = names.iter().position(|&header| header == upper_name) {
+                group.push((order, line));
+                return;
+            }
+        }
+        if let Some((command, id)) = split_definition(name)
+            && let Ok(id) = ObjId::try_from(id, true)
+            && let Some(definitions) = DEFINITION_COMMANDS
+                .iter()
+                .position(|&known| known == command)
+                .and_then(|index| self.definitions.get_mut(index))
+        {
+            definitions.push((id, line));
+            return;
+        }
+        self.other_headers.push(line);
+    }
+
+    fn push_message(&mut self, track: u64, channel: NoteChannelId, message: &str) {
+        let group = self.messages.entry((track, channel)).or_default();
+        let pairs = message.len() / 2;
+        let is_objects = Channel::try_from(channel) != Ok(Channel::SectionLen)
+            && message.len().is_multiple_of(2)
+            && message.is_ascii();
+        if !is_objects || pairs == 0 {
+            group
+                .verbatim
+                .push(format!("#{track:03}{channel}:{message}"));
+            return;
+        }
+        for index in 0..pairs {
+            let Some(id) = message.get(index * 2..index * 2 + 2) else {
+                continue;
+            };
+            if id == "00" {
+                continue;
+            }
+            let gcd = (index as u64).gcd(&(pairs as u64));
+            let position = (index as u64 / gcd, pairs as u64 / gcd);
+            let layer_index = group
+                .layers
+                .iter()
+                .position(|layer| {
+                    !layer
+                        .iter()
+                        .any(|&(numerator, denominator, _)| (numerator, denominator) == position)
+                })
+                .unwrap_or(group.layers.len());
+            if layer_index == group.layers.len() {
+                group.layers.push(Vec::new());
+            }
+            if let Some(layer) = group.layers.get_mut(layer_index) {
+                layer.push((position.0, position.1, id.to_string()));
+            }
+        }
+    }
+
+    fn into_sections(self) -> Vec<Vec<String>> {
+        let mut sections: Vec<Vec<String>> = Vec::new();
+        sections.push(self.not_commands);
+        for mut group in self.header_groups {
+            group.sort_by_key(|&(order, _)| order);
+            sections.push(group.into_iter().map(|(_, line)| line).collect());
+        }
+        for mut definitions in self.definitions {
+            definitions.sort_by_key(|&(id, _)| id);
+            sections.push(definitions.into_iter().map(|(_, line)| line).collect());
+        }
+        sections.push(self.other_headers);
+
+        let mut tracks: BTreeMap<u64, Vec<String>> = BTreeMap::new();
+        for ((track, channel), group) in self.messages {
+            let lines = tracks.entry(track).or_default();
+            lines.extend(group.verbatim);
+            for layer in group.layers {
+                let denominators: Vec<u64> = layer
+                    .iter()
+                    .map(|&(_, denominator, _)| denominator)
+                    .collect();
+                let resolution = lcm_slice(&denominators);
+                let mut ids = vec!["00"; resolution as usize];
+                for (numerator, denominator, id) in &layer {
+                    if let Some(slot) =
+                        ids.get_mut((numerator * (resolution / denominator)) as usize)
+                    {
+                        *slot = id;
+                    }
+                }
+                lines.push(format!("#{track:03}{channel}:{}", ids.concat()));
+            }
+        }
+        sections.extend(tracks.into_values());
+        sections
+    }
+}
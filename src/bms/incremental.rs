@@ -0,0 +1,819 @@
+//! Incremental re-parse of BMS source text after small edits, for editors.
+//!
+//! [`IncrementalBms`] keeps the source text, its tokens, an index of their positions and the parsed [`Bms`]. [`IncrementalBms::edit`] re-lexes only the lines touched by the edit, and updates only the model entries of the changed tokens in place:
+//!
+//! - The messages on the `Bgm` and `Note` channels replace the notes of their track and channel. The replaced notes are left as dangling objects until they become the majority.
+//! - The definitions such as `#WAVxx`, `#BMPxx`, `#BPMxx` and `#STOPxx` replace the entry of their id.
+//! - The lines which are not commands are ignored, except `%EMAIL` and `%URL`.
+//!
+//! It falls back to a full parse of the tokens if any other command is changed, a control flow command is changed, or the edit is inside a `#RANDOM` or `#SWITCH` scope. The notes fall back also if `#LNOBJ` is used, because it rewrites the notes in order. The definitions fall back also if they are duplicated or randomized, or if a message refers to the id and the objects hold the resolved value, as `#BPMxx` for the BPM changes.
+//!
+//! ```
+//! use bms_rs::bms::{incremental::{IncrementalBms, Reparse}, prelude::*};
+//!
+//! let source = "#WAV01 kick.wav\n#00111:01\n";
+//! let mut incremental = IncrementalBms::new(source, default_config());
+//! let reparse = incremental.edit(25..25, "01", default_config());
+//! assert_eq!(reparse, Reparse::Incremental);
+//! assert_eq!(incremental.source(), "#WAV01 kick.wav\n#00111:0101\n");
+//! let bms = incremental.bms().as_ref().unwrap();
+//! let notes = bms.notes().all_notes().filter(|note| !note.wav_id.is_null());
+//! assert_eq!(notes.count(), 2);
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    ops::Range,
+};
+
+use num::BigUint;
+
+use crate::bms::{
+    ParseConfig,
+    command::{
+        ObjId,
+        channel::{Channel, NoteChannelId, mapper::KeyLayoutMapper},
+        mixin::SourceRangeMixin,
+        time::{ObjTime, Track},
+    },
+    cst::{is_base_62, is_control_flow, split_definition},
+    default_config_with_rng,
+    lex::{
+        LexOutput, LexWarningWithRange, TokenStream,
+        token::{Token, TokenWithRange},
+    },
+    model::{Bms, control_flow::RandomizedObjects},
+    parse::{
+        ParseErrorWithRange, ParseOutput, ParseWarningWithRange,
+        prompt::{AlwaysUseNewer, Prompter},
+        token_processor::TokenModifier,
+    },
+    rng::{Rng, RngMock},
+};
+use crate::util::StrExtension;
+
+/// How [`IncrementalBms::edit`] updated the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Reparse {
+    /// Only the model entries of the changed tokens were updated.
+    Incremental,
+    /// The whole tokens were parsed again.
+    Full,
+}
+
+/// The definition command, merging the aliases such as `#EXBPMxx` into `#BPMxx`, and the id.
+type DefinitionKey = (&'static str, ObjId);
+
+/// The model entries affected by the changed tokens.
+#[derive(Debug, Default)]
+struct Affected {
+    /// The notes keyed by the track and the channel.
+    notes: HashSet<(Track, Channel)>,
+    /// The definitions keyed by the command and the id.
+    definitions: HashSet<DefinitionKey>,
+    /// Whether `%EMAIL` or `%URL` comments are changed.
+    metadata_comment: bool,
+}
+
+/// The positions of the tokens looked up by the incremental updates, kept in sync with the tokens on each edit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct TokenIndex {
+    /// The starts of the messages keyed by the track and the channel.
+    messages: HashMap<(Track, Channel), Vec<usize>>,
+    /// The starts of the definitions keyed by the command and the id.
+    definitions: HashMap<DefinitionKey, Vec<usize>>,
+    /// The starts of the comments which may be `%EMAIL` or `%URL`.
+    comments: Vec<usize>,
+    /// The starts of the commands opening or closing the `#RANDOM` and `#SWITCH` scopes, and whether they open.
+    scopes: Vec<(usize, bool)>,
+    /// The number of `#LNOBJ` commands.
+    lnobj: usize,
+    /// Whether `#BASE 62` is used. Editing `#BASE` needs a full parse, so it is fixed until then.
+    case_sensitive_obj_id: bool,
+}
+
+impl TokenIndex {
+    fn new(tokens: &[OwnedTokenWithRange]) -> Self {
+        let mut index = Self {
+            case_sensitive_obj_id: tokens.iter().any(|token| {
+                matches!(token.content(), OwnedToken::Header { name, args } if is_base_62(name, args))
+            }),
+            ..Self::default()
+        };
+        for token in tokens {
+            index.insert(&borrowed(token));
+        }
+        index
+    }
+
+    fn insert(&mut self, token: &TokenWithRange<'_>) {
+        let start = token.start();
+        if let Some(starts) = self.starts_mut(token.content()) {
+            let at = starts.partition_point(|&other| other < start);
+            starts.insert(at, start);
+        }
+        match scope_depth_change(token.content()) {
+            0 => {}
+            change => {
+                let at = self.scopes.partition_point(|&(other, _)| other < start);
+                self.scopes.insert(at, (start, change > 0));
+            }
+        }
+        if token.content().is_header("LNOBJ") {
+            self.lnobj += 1;
+        }
+    }
+
+    fn remove(&mut self, token: &TokenWithRange<'_>) {
+        let start = token.start();
+        if let Some(starts) = self.starts_mut(token.content()) {
+            starts.retain(|&other| other != start);
+        }
+        self.scopes.retain(|&(other, _)| other != start);
+        if token.content().is_header("LNOBJ") {
+            self.lnobj = self.lnobj.saturating_sub(1);
+        }
+        self.messages.retain(|_, starts| !starts.is_empty());
+        self.definitions.retain(|_, starts| !starts.is_empty());
+    }
+
+    /// Returns the list of the starts which `token` belongs to.
+    fn starts_mut(&mut self, token: &Token<'_>) -> Option<&mut Vec<usize>> {
+        match token {
+            Token::Header { name, .. } => {
+                let key = definition_key(name, self.case_sensitive_obj_id)?;
+                Some(self.definitions.entry(key).or_default())
+            }
+            Token::Message { track, channel, .. } => {
+                Some(self.messages.entry((*track, *channel)).or_default())
+            }
+            Token::NotACommand(line) => line
+                .trim_start()
+                .starts_with('%')
+                .then_some(&mut self.comments),
+        }
+    }
+
+    /// Shifts the starts from `old_line_end`.
+    fn shift(&mut self, old_line_end: usize, shift: impl Fn(usize) -> usize) {
+        let shift_start = |start: &mut usize| {
+            if *start >= old_line_end {
+                *start = shift(*start);
+            }
+        };
+        self.messages
+            .values_mut()
+            .chain(self.definitions.values_mut())
+            .flatten()
+            .chain(&mut self.comments)
+            .for_each(shift_start);
+        self.scopes
+            .iter_mut()
+            .for_each(|(start, _)| shift_start(start));
+    }
+
+    /// Returns the depth of the `#RANDOM` and `#SWITCH` scopes at `position`.
+    fn depth_at(&self, position: usize) -> usize {
+        self.scopes
+            .iter()
+            .take_while(|&&(start, _)| start < position)
+            .fold(0, |depth, &(_, opens)| {
+                if opens {
+                    depth + 1
+                } else {
+                    depth.saturating_sub(1)
+                }
+            })
+    }
+
+    /// Returns whether any message is on `channel`.
+    fn has_channel(&self, channel: Channel) -> bool {
+        self.messages.keys().any(|&(_, other)| other == channel)
+    }
+}
+
+/// A token which owns its text, because [`Token::NotACommand`] borrows the source text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OwnedToken {
+    Header {
+        name: String,
+        args: String,
+    },
+    Message {
+        track: Track,
+        channel: Channel,
+        message: String,
+    },
+    NotACommand(String),
+}
+
+impl OwnedToken {
+    fn new(token: &Token<'_>) -> Self {
+        match token {
+            Token::Header { name, args } => Self::Header {
+                name: name.to_string(),
+                args: args.to_string(),
+            },
+            Token::Message {
+                track,
+                channel,
+                message,
+            } => Self::Message {
+                track: *track,
+                channel: *channel,
+                message: message.to_string(),
+            },
+            Token::NotACommand(line) => Self::NotACommand((*line).to_string()),
+        }
+    }
+
+    fn as_token(&self) -> Token<'_> {
+        match self {
+            Self::Header { name, args } => Token::Header {
+                name: name.as_str().into(),
+                args: args.as_str().into(),
+            },
+            Self::Message {
+                track,
+                channel,
+                message,
+            } => Token::Message {
+                track: *track,
+                channel: *channel,
+                message: message.as_str().into(),
+            },
+            Self::NotACommand(line) => Token::NotACommand(line),
+        }
+    }
+}
+
+type OwnedTokenWithRange = SourceRangeMixin<OwnedToken>;
+
+fn owned(token: &TokenWithRange<'_>) -> OwnedTokenWithRange {
+    SourceRangeMixin::new(OwnedToken::new(token.content()), token.range().clone())
+}
+
+fn borrowed(token: &OwnedTokenWithRange) -> TokenWithRange<'_> {
+    SourceRangeMixin::new(token.content().as_token(), token.range().clone())
+}
+
+/// The source text and its parsed model, which can be updated by small edits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalBms<T> {
+    source: String,
+    tokens: Vec<OwnedTokenWithRange>,
+    index: TokenIndex,
+    lex_warnings: Vec<LexWarningWithRange>,
+    bms: Result<Bms, ParseErrorWithRange>,
+    parse_warnings: Vec<ParseWarningWithRange>,
+    /// The number of the dangling notes left by the incremental updates.
+    dangling_notes: usize,
+    _phantom: std::marker::PhantomData<fn() -> T>,
+}
+
+impl<T: KeyLayoutMapper> IncrementalBms<T> {
+    /// Parses `source` fully with `config`.
+    pub fn new<P: Prompter, R: Rng, M: TokenModifier>(
+        source: impl Into<String>,
+        config: ParseConfig<T, P, R, M>,
+    ) -> Self {
+        let source = source.into();
+        let LexOutput {
+            mut tokens,
+            lex_warnings,
+        } = TokenStream::parse_lex(&source);
+        config.token_modifier.modify(&mut tokens);
+        let tokens = tokens.iter().map(owned).collect();
+        let mut this = Self {
+            source,
+            tokens,
+            index: TokenIndex::default(),
+            lex_warnings,
+            bms: Ok(Bms::default()),
+            parse_warnings: Vec::new(),
+            dangling_notes: 0,
+            _phantom: std::marker::PhantomData,
+        };
+        this.parse_fully(config);
+        this
+    }
+
+    /// Returns the current source text.
+    #[must_use]
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the current tokens.
+    #[must_use]
+    pub fn tokens(&self) -> TokenStream<'_> {
+        self.tokens.iter().map(borrowed).collect()
+    }
+
+    /// Returns the warnings of the lexer on the current source text.
+    #[must_use]
+    pub fn lex_warnings(&self) -> &[LexWarningWithRange] {
+        &self.lex_warnings
+    }
+
+    /// Returns the current model.
+    pub const fn bms(&self) -> &Result<Bms, ParseErrorWithRange> {
+        &self.bms
+    }
+
+    /// Returns the warnings of the parser on the current model.
+    #[must_use]
+    pub fn parse_warnings(&self) -> &[ParseWarningWithRange] {
+        &self.parse_warnings
+    }
+
+    /// Replaces the byte range `range` of the source text with `replacement`, and updates the model. See [the module document](self) for which edits are applied incrementally.
+    ///
+    /// `config` is used for the full parse. Its token modifier is applied to the re-lexed lines only, so it must modify each token independently as [`DefaultTokenRelaxer`](crate::bms::parse::token_processor::DefaultTokenRelaxer) does.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `range` is out of the source text or not on UTF-8 character boundaries.
+    pub fn edit<P: Prompter, R: Rng, M: TokenModifier>(
+        &mut self,
+        range: Range<usize>,
+        replacement: &str,
+        config: ParseConfig<T, P, R, M>,
+    ) -> Reparse {
+        let line_start = self.source[..range.start]
+            .rfind('\n')
+            .map_or(0, |index| index + 1);
+        let old_line_end = self.source[range.end..]
+            .find('\n')
+            .map_or(self.source.len(), |index| range.end + index + 1);
+        self.source.replace_range(range.clone(), replacement);
+        let new_line_end = (old_line_end + replacement.len()).saturating_sub(range.len());
+        let shift = |offset: usize| (offset + new_line_end).saturating_sub(old_line_end);
+
+        // Re-lex the touched lines and splice them into the tokens.
+        let LexOutput {
+            tokens: mut relexed,
+            lex_warnings,
+        } = TokenStream::parse_lex(&self.source[line_start..new_line_end]);
+        config.token_modifier.modify(&mut relexed);
+        let inserted: Vec<OwnedTokenWithRange> = relexed
+            .iter()
+            .map(|token| move_range(owned(token), |offset| offset + line_start))
+            .collect();
+        let first = self
+            .tokens
+            .partition_point(|token| token.start() < line_start);
+        let last = self
+            .tokens
+            .partition_point(|token| token.start() < old_line_end);
+        let removed: Vec<_> = self
+            .tokens
+            .splice(first..last, inserted.iter().cloned())
+            .collect();
+        let moved = self.tokens.split_off(first + inserted.len());
+        self.tokens
+            .extend(moved.into_iter().map(|token| move_range(token, shift)));
+        for token in &removed {
+            self.index.remove(&borrowed(token));
+        }
+        self.index.shift(old_line_end, shift);
+        for token in &inserted {
+            self.index.insert(&borrowed(token));
+        }
+        self.lex_warnings = splice_warnings(
+            std::mem::take(&mut self.lex_warnings),
+            line_start..old_line_end,
+            lex_warnings
+                .into_iter()
+                .map(|warning| move_range(warning, |offset| offset + line_start)),
+            shift,
+        );
+
+        let removed_lines: Vec<_> = removed
+            .iter()
+            .filter_map(|token| raw_command_line(&borrowed(token)))
+            .collect();
+        let inserted_lines: Vec<_> = inserted
+            .iter()
+            .filter_map(|token| raw_command_line(&borrowed(token)))
+            .collect();
+        let affected = self.affected(
+            line_start,
+            changed_tokens(&removed, &inserted)
+                .into_iter()
+                .map(borrowed),
+        );
+        let raw_position = if removed_lines == inserted_lines {
+            Some(None)
+        } else {
+            self.raw_command_position(first, line_start..new_line_end, removed_lines.len())
+                .map(Some)
+        };
+        let Some(((affected, raw_position), bms)) =
+            affected.zip(raw_position).zip(self.bms.as_mut().ok())
+        else {
+            self.parse_fully(config);
+            return Reparse::Full;
+        };
+        shift_randomized(&mut bms.randomized, old_line_end, shift);
+        if let Some(position) = raw_position {
+            bms.repr
+                .raw_command_lines
+                .splice(position..position + removed_lines.len(), inserted_lines);
+        }
+        let case_sensitive_obj_id = self.index.case_sensitive_obj_id;
+        let tokens_at = |starts: &[usize]| -> Vec<_> {
+            starts
+                .iter()
+                .filter_map(|&start| {
+                    let at = self.tokens.partition_point(|token| token.start() < start);
+                    self.tokens.get(at).map(borrowed)
+                })
+                .collect()
+        };
+        let mut parse_warnings = Vec::new();
+        if !affected.notes.is_empty() {
+            let messages = tokens_at(
+                &affected
+                    .notes
+                    .iter()
+                    .filter_map(|key| self.index.messages.get(key))
+                    .flatten()
+                    .copied()
+                    .collect::<Vec<_>>(),
+            );
+            let (sub, warnings) = parse_subset::<T>(&messages, case_sensitive_obj_id);
+            parse_warnings.extend(warnings);
+            for &(track, channel) in &affected.notes {
+                let Some(channel_id) = note_channel(channel) else {
+                    continue;
+                };
+                let removing: Vec<_> = bms
+                    .wav
+                    .notes
+                    .notes_in(ObjTime::start_of(track)..)
+                    .take_while(|(_, note)| note.offset.track() == track)
+                    .filter(|(_, note)| note.channel_id == channel_id)
+                    .map(|(index, _)| index)
+                    .collect();
+                self.dangling_notes += removing.len();
+                for index in removing {
+                    bms.wav.notes.pop_by_idx(index);
+                }
+            }
+            for note in sub.wav.notes.into_all_notes() {
+                if !note.wav_id.is_null() {
+                    bms.wav.notes.push_note(note);
+                }
+            }
+            // Drop the dangling notes once they are the majority, to keep the memory bounded on many edits.
+            if self.dangling_notes * 2 > bms.wav.notes.all_notes_insertion_order().count() {
+                let notes = std::mem::take(&mut bms.wav.notes).into_all_notes();
+                for note in notes {
+                    if !note.wav_id.is_null() {
+                        bms.wav.notes.push_note(note);
+                    }
+                }
+                self.dangling_notes = 0;
+            }
+        }
+        for &key in &affected.definitions {
+            let definitions = tokens_at(
+                self.index
+                    .definitions
+                    .get(&key)
+                    .map_or(&[][..], Vec::as_slice),
+            );
+            let (mut sub, warnings) = parse_subset::<T>(&definitions, case_sensitive_obj_id);
+            parse_warnings.extend(warnings);
+            replace_definition(bms, &mut sub, key);
+        }
+        if affected.metadata_comment {
+            let comments = tokens_at(&self.index.comments);
+            let (sub, warnings) = parse_subset::<T>(&comments, case_sensitive_obj_id);
+            parse_warnings.extend(warnings);
+            bms.metadata.email = sub.metadata.email;
+            bms.metadata.url = sub.metadata.url;
+        }
+        let new_lines = line_start..new_line_end;
+        self.parse_warnings = splice_warnings(
+            std::mem::take(&mut self.parse_warnings),
+            line_start..old_line_end,
+            parse_warnings
+                .into_iter()
+                .filter(|warning| new_lines.contains(&warning.start())),
+            shift,
+        );
+        Reparse::Incremental
+    }
+
+    /// Collects the model entries affected by `changed` tokens, or returns `None` if a full parse is needed. `line_start` is the start of the touched lines.
+    fn affected<'b>(
+        &self,
+        line_start: usize,
+        changed: impl IntoIterator<Item = TokenWithRange<'b>>,
+    ) -> Option<Affected> {
+        let bms = self.bms.as_ref().ok()?;
+        let index = &self.index;
+        if index.depth_at(line_start) > 0 {
+            return None;
+        }
+        let mut affected = Affected::default();
+        for token in changed {
+            match token.content() {
+                Token::Header { name, .. }
+                    if is_control_flow(name) || token.content().is_control_flow_token() =>
+                {
+                    return None;
+                }
+                Token::Header { name, .. } => {
+                    affected
+                        .definitions
+                        .insert(definition_key(name, index.case_sensitive_obj_id)?);
+                }
+                Token::Message { track, channel, .. } => {
+                    note_channel(*channel)?;
+                    affected.notes.insert((*track, *channel));
+                }
+                Token::NotACommand(line) => {
+                    affected.metadata_comment |= line.trim_start().starts_with('%');
+                }
+            }
+        }
+        if index.lnobj > 0 && !affected.notes.is_empty() {
+            return None;
+        }
+        let randomized = |starts: &[usize]| starts.iter().any(|&start| index.depth_at(start) > 0);
+        for key in &affected.notes {
+            if index
+                .messages
+                .get(key)
+                .is_some_and(|starts| randomized(starts))
+            {
+                return None;
+            }
+        }
+        // The definitions must not be duplicated nor randomized, to be resolved without the prompter.
+        for &key in &affected.definitions {
+            let starts = index.definitions.get(&key).map_or(&[][..], Vec::as_slice);
+            if starts.len() > 1 || randomized(starts) || is_referred(bms, index, key) {
+                return None;
+            }
+        }
+        // The comments in the inactive scopes must not be applied.
+        if affected.metadata_comment && !index.scopes.is_empty() {
+            return None;
+        }
+        Some(affected)
+    }
+
+    /// Returns the position of the changed headers in [`BmsSourceRepresentation::raw_command_lines`](crate::bms::model::repr::BmsSourceRepresentation::raw_command_lines), which lists the activated headers, or `None` if it cannot be found without a full parse. `first..after` is the range of the inserted tokens.
+    fn raw_command_position(
+        &self,
+        first: usize,
+        new_lines: Range<usize>,
+        removed_len: usize,
+    ) -> Option<usize> {
+        let raw_len = self.bms.as_ref().ok()?.repr.raw_command_lines.len();
+        let count_headers = |range: &[OwnedTokenWithRange]| {
+            range
+                .iter()
+                .filter(|token| matches!(token.content(), OwnedToken::Header { .. }))
+                .count()
+        };
+        let scopes = &self.index.scopes;
+        if scopes
+            .first()
+            .is_none_or(|&(start, _)| start >= new_lines.start)
+        {
+            Some(count_headers(self.tokens.get(..first)?))
+        } else if scopes
+            .last()
+            .is_none_or(|&(start, _)| start < new_lines.end)
+        {
+            let after = self
+                .tokens
+                .partition_point(|token| token.start() < new_lines.end);
+            raw_len.checked_sub(count_headers(self.tokens.get(after..)?) + removed_len)
+        } else {
+            None
+        }
+    }
+
+    fn parse_fully<P: Prompter, R: Rng, M: TokenModifier>(
+        &mut self,
+        config: ParseConfig<T, P, R, M>,
+    ) {
+        let tokens: Vec<_> = self.tokens.iter().map(borrowed).collect();
+        let ParseOutput {
+            bms,
+            parse_warnings,
+        } = Bms::from_token_stream::<'_, T, _, _, _>(&tokens, config);
+        self.bms = bms;
+        self.parse_warnings = parse_warnings;
+        self.index = TokenIndex::new(&self.tokens);
+        self.dangling_notes = 0;
+    }
+}
+
+/// Parses `tokens` into a model by the default processors, as `#BASE 62` is given if `case_sensitive_obj_id`.
+fn parse_subset<T: KeyLayoutMapper>(
+    tokens: &[TokenWithRange<'_>],
+    case_sensitive_obj_id: bool,
+) -> (Bms, Vec<ParseWarningWithRange>) {
+    let base = SourceRangeMixin::new(
+        Token::Header {
+            name: "BASE".into(),
+            args: "62".into(),
+        },
+        0..0,
+    );
+    let subset: Vec<_> = case_sensitive_obj_id
+        .then_some(&base)
+        .into_iter()
+        .chain(tokens)
+        .collect();
+    let config = default_config_with_rng(RngMock([BigUint::from(1u64)]))
+        .key_mapper::<T>()
+        .prompter(AlwaysUseNewer);
+    let ParseOutput {
+        bms,
+        parse_warnings,
+    } = Bms::from_token_stream::<'_, T, _, _, _>(subset, config);
+    (bms.unwrap_or_default(), parse_warnings)
+}
+
+/// Returns the key of the definition header `name`, or `None` if it is not a definition.
+fn definition_key(name: &str, case_sensitive_obj_id: bool) -> Option<DefinitionKey> {
+    // `#DEFEXRANK` defines the id `00` of `#EXRANKxx`.
+    if name.eq_ignore_ascii_case("DEFEXRANK") {
+        return Some(("EXRANK", ObjId::try_from("00", false).ok()?));
+    }
+    // `#SONGxx` is an alias of `#TEXTxx`.
+    if let Some(id) = name.strip_prefix_ignore_case("SONG") {
+        return Some(("TEXT", ObjId::try_from(id, case_sensitive_obj_id).ok()?));
+    }
+    let (command, id) = split_definition(name)?;
+    let command = match command {
+        "EXBMP" => "BMP",
+        "EXBPM" => "BPM",
+        command => command,
+    };
+    Some((command, ObjId::try_from(id, case_sensitive_obj_id).ok()?))
+}
+
+/// Returns whether a message may refer to the definition, whose value is resolved into the objects or whose absence is warned.
+fn is_referred(bms: &Bms, index: &TokenIndex, (command, id): DefinitionKey) -> bool {
+    match command {
+        "BMP" => bms.bmp.bmp_ids_used.contains(&id),
+        "ARGB" => bms.bmp.argb_ids_used.contains(&id),
+        "BPM" => bms.bpm.bpm_change_ids_used.contains(&id),
+        "STOP" => bms.stop.stop_ids_used.contains(&id),
+        "SCROLL" => bms.scroll.scroll_ids_used.contains(&id),
+        "SPEED" => bms.speed.speed_ids_used.contains(&id),
+        "EXRANK" => bms.judge.exrank_ids_used.contains(&id),
+        "TEXT" => bms.text.text_ids_used.contains(&id),
+        // These ones do not record the used ids.
+        "SWBGA" => index.has_channel(Channel::BgaKeybound),
+        "SEEK" => index.has_channel(Channel::Seek),
+        "CHANGEOPTION" => index.has_channel(Channel::OptionChange),
+        _ => false,
+    }
+}
+
+/// Moves the entry of the definition `key` from `sub` into `bms`, or removes it if `sub` does not have it.
+fn replace_definition(bms: &mut Bms, sub: &mut Bms, (command, id): DefinitionKey) {
+    fn replace<V>(entries: &mut HashMap<ObjId, V>, sub: &mut HashMap<ObjId, V>, id: ObjId) {
+        if let Some(value) = sub.remove(&id) {
+            entries.insert(id, value);
+        } else {
+            entries.remove(&id);
+        }
+    }
+
+    match command {
+        "WAV" => replace(&mut bms.wav.wav_files, &mut sub.wav.wav_files, id),
+        "EXWAV" => replace(&mut bms.wav.exwav_defs, &mut sub.wav.exwav_defs, id),
+        "BMP" => replace(&mut bms.bmp.bmp_files, &mut sub.bmp.bmp_files, id),
+        "BGA" => replace(&mut bms.bmp.bga_defs, &mut sub.bmp.bga_defs, id),
+        "@BGA" => replace(&mut bms.bmp.atbga_defs, &mut sub.bmp.atbga_defs, id),
+        "SWBGA" => replace(&mut bms.bmp.swbga_events, &mut sub.bmp.swbga_events, id),
+        "ARGB" => replace(&mut bms.bmp.argb_defs, &mut sub.bmp.argb_defs, id),
+        "BPM" => replace(&mut bms.bpm.bpm_defs, &mut sub.bpm.bpm_defs, id),
+        "STOP" => replace(&mut bms.stop.stop_defs, &mut sub.stop.stop_defs, id),
+        "SCROLL" => replace(&mut bms.scroll.scroll_defs, &mut sub.scroll.scroll_defs, id),
+        "SPEED" => replace(&mut bms.speed.speed_defs, &mut sub.speed.speed_defs, id),
+        "EXRANK" => replace(&mut bms.judge.exrank_defs, &mut sub.judge.exrank_defs, id),
+        "TEXT" => replace(&mut bms.text.texts, &mut sub.text.texts, id),
+        "SEEK" => replace(&mut bms.video.seek_defs, &mut sub.video.seek_defs, id),
+        "CHANGEOPTION" => replace(
+            &mut bms.option.change_options,
+            &mut sub.option.change_options,
+            id,
+        ),
+        _ => {}
+    }
+}
+
+/// Returns the tokens in `removed` or `inserted` but not in both, comparing the contents.
+fn changed_tokens<'t>(
+    removed: &'t [OwnedTokenWithRange],
+    inserted: &'t [OwnedTokenWithRange],
+) -> Vec<&'t OwnedTokenWithRange> {
+    let mut unmatched: Vec<_> = inserted.iter().collect();
+    let mut changed = Vec::new();
+    for token in removed {
+        if let Some(index) = unmatched
+            .iter()
+            .position(|candidate| candidate.content() == token.content())
+        {
+            unmatched.swap_remove(index);
+        } else {
+            changed.push(token);
+        }
+    }
+    changed.extend(unmatched);
+    changed
+}
+
+/// Formats the header in the same way as [`BmsSourceRepresentation::raw_command_lines`](crate::bms::model::repr::BmsSourceRepresentation::raw_command_lines).
+fn raw_command_line(token: &TokenWithRange<'_>) -> Option<String> {
+    let Token::Header { name, args } = token.content() else {
+        return None;
+    };
+    Some(if args.is_empty() {
+        format!("#{name}")
+    } else {
+        format!("#{name} {args}")
+    })
+}
+
+/// Returns the note channel of the messages whose objects are stored in [`Notes`](crate::bms::model::notes::Notes).
+const fn note_channel(channel: Channel) -> Option<NoteChannelId> {
+    match channel {
+        Channel::Bgm => Some(NoteChannelId::bgm()),
+        Channel::Note { channel_id } => Some(channel_id),
+        _ => None,
+    }
+}
+
+/// Returns how the token changes the depth of the `#RANDOM` and `#SWITCH` scopes.
+fn scope_depth_change(token: &Token<'_>) -> isize {
+    if ["RANDOM", "SETRANDOM", "SWITCH", "SETSWITCH", "IF"]
+        .iter()
+        .any(|command| token.is_header(command))
+    {
+        1
+    } else if ["ENDIF", "ENDRANDOM", "ENDSW", "ENDSWITCH"]
+        .iter()
+        .any(|command| token.is_header(command))
+    {
+        -1
+    } else {
+        0
+    }
+}
+
+fn move_range<C>(item: SourceRangeMixin<C>, f: impl Fn(usize) -> usize) -> SourceRangeMixin<C> {
+    let (content, range) = item.into();
+    SourceRangeMixin::new(content, f(range.start)..f(range.end))
+}
+
+/// Replaces the warnings in the `old_lines` with `inserted`, and shifts the ones after them.
+fn splice_warnings<W>(
+    warnings: Vec<SourceRangeMixin<W>>,
+    old_lines: Range<usize>,
+    inserted: impl IntoIterator<Item = SourceRangeMixin<W>>,
+    shift: impl Fn(usize) -> usize,
+) -> Vec<SourceRangeMixin<W>> {
+    let mut spliced: Vec<_> = warnings
+        .into_iter()
+        .filter(|warning| !old_lines.contains(&warning.start()))
+        .map(|warning| {
+            if warning.start() < old_lines.start {
+                warning
+            } else {
+                move_range(warning, &shift)
+            }
+        })
+        .chain(inserted)
+        .collect();
+    spliced.sort_by_key(SourceRangeMixin::start);
+    spliced
+}
+
+/// Shifts the source positions of the randomized objects after `old_line_end`.
+fn shift_randomized(
+    randomized: &mut [RandomizedObjects],
+    old_line_end: usize,
+    shift: impl Fn(usize) -> usize + Copy,
+) {
+    for objects in randomized {
+        if objects.line_number >= old_line_end {
+            objects.line_number = shift(objects.line_number);
+        }
+        for branch in objects.branches.values_mut() {
+            shift_randomized(&mut branch.sub.randomized, old_line_end, shift);
+        }
+    }
+}
@@ -0,0 +1,293 @@
+//! Detection of the key mode of a chart, to pick the [`KeyLayoutMapper`] to parse it with.
+//!
+//! A wrong [`KeyLayoutMapper`] drops the notes on the lanes it does not know. [`Bms::detect_key_mode`] and [`detect_key_mode_from_tokens`] guess the [`KeyMode`] from the note channels, `#PLAYER` and [`KeyModeHints`] such as the file extension, and [`parse_bms_auto`](crate::bms::parse_bms_auto) parses with the detected mapper.
+//!
+//! The channels decide the mode as below, where `1x` and `2x` stand for the lanes of the player 1 and 2 including the invisible, long and landmine notes:
+//!
+//! | channels | mode | mapper |
+//! |---|---|---|
+//! | `1x` only, without `18` and `19` | [`KeyMode::Beat5K`] | [`KeyLayoutBeat`] |
+//! | `1x` only, with `18` or `19` | [`KeyMode::Beat7K`] | [`KeyLayoutBeat`] |
+//! | `11`-`19` with `17`, and `18` or `19` | [`KeyMode::Pms9K`] | [`KeyLayoutPmsBmeType`] |
+//! | `11`-`15` and `22`-`25` | [`KeyMode::Pms9K`] | [`KeyLayoutPms`] |
+//! | `2x` without `28` and `29` | [`KeyMode::Beat10K`] | [`KeyLayoutBeat`] |
+//! | `2x` with `18`, `19`, `28` or `29` | [`KeyMode::Beat14K`] | [`KeyLayoutBeat`] |
+//!
+//! The `.pms` extension turns the charts only on `1x` into [`KeyMode::Pms9K`], and `#PLAYER 3` turns the charts on `11`-`15` and `22`-`25` into [`KeyMode::Beat10K`]. [`KeyMode::Keyboard24K`] and [`KeyMode::Keyboard48K`] are detected only by the BMSON `mode_hint`, because no BMS channel or mapper is assigned to them, so their confidence is [`KeyModeConfidence::Low`].
+//!
+//! ```
+//! use bms_rs::bms::{
+//!     key_mode::{KeyMode, KeyModeConfidence, KeyModeHints},
+//!     prelude::*,
+//! };
+//!
+//! let source = "#PLAYER 1\n#00111:01\n#00122:01\n#00125:01\n";
+//! let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+//!     .bms
+//!     .unwrap();
+//! let detection = bms.detect_key_mode(&KeyModeHints::default().with_extension("pms"));
+//! assert_eq!(detection.mode, KeyMode::Pms9K);
+//! assert_eq!(detection.layout, KeyLayoutKind::Pms);
+//! assert_eq!(detection.confidence, KeyModeConfidence::High);
+//! ```
+
+use std::{collections::HashSet, ffi::OsStr, ops::RangeInclusive, path::Path};
+
+#[cfg(doc)]
+use crate::bms::command::channel::mapper::{KeyLayoutPms, KeyLayoutPmsBmeType};
+use crate::bms::{
+    command::{
+        PlayerMode,
+        channel::{
+            Channel, NoteChannelId,
+            mapper::{KeyLayout, KeyLayoutBeat, KeyLayoutKind, KeyLayoutMapper},
+        },
+    },
+    lex::{TokenStream, token::Token},
+    model::Bms,
+};
+use crate::chart::types::{Key, PlayerSide};
+
+/// A key mode of charts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyMode {
+    /// 5 keys and a scratch for single play.
+    Beat5K,
+    /// 7 keys and a scratch for single play.
+    Beat7K,
+    /// 5 keys and a scratch on each side for double play.
+    Beat10K,
+    /// 7 keys and a scratch on each side for double play.
+    Beat14K,
+    /// 9 buttons of Pop'n Music.
+    Pms9K,
+    /// 24 keys of a keyboard.
+    Keyboard24K,
+    /// 24 keys of a keyboard on each side for double play.
+    Keyboard48K,
+}
+
+impl KeyMode {
+    /// Returns the mode of the BMSON `mode_hint` ignoring ASCII case, such as `beat-7k` and `popn-9k`.
+    #[must_use]
+    pub fn from_mode_hint(mode_hint: &str) -> Option<Self> {
+        [
+            ("beat-5k", Self::Beat5K),
+            ("beat-7k", Self::Beat7K),
+            ("beat-10k", Self::Beat10K),
+            ("beat-14k", Self::Beat14K),
+            ("popn-5k", Self::Pms9K),
+            ("popn-9k", Self::Pms9K),
+            ("keyboard-24k", Self::Keyboard24K),
+            ("keyboard-24k-double", Self::Keyboard48K),
+        ]
+        .into_iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(mode_hint.trim()))
+        .map(|(_, mode)| mode)
+    }
+
+    /// Returns whether the mode is for double play.
+    #[must_use]
+    pub const fn is_double(self) -> bool {
+        matches!(self, Self::Beat10K | Self::Beat14K | Self::Keyboard48K)
+    }
+
+    /// Returns whether a [`KeyLayoutKind`] supports the mode.
+    #[must_use]
+    pub const fn has_mapper(self) -> bool {
+        !matches!(self, Self::Keyboard24K | Self::Keyboard48K)
+    }
+}
+
+/// How reliable a [`KeyModeDetection`] is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum KeyModeConfidence {
+    /// The chart has no notes, the hints disagree with the channels, or no mapper supports the mode.
+    Low,
+    /// Only the channels decide the mode.
+    Medium,
+    /// The hints agree with the channels, or the BMSON `mode_hint` decides a mode which a mapper supports.
+    High,
+}
+
+/// The information besides the chart for detecting the key mode.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyModeHints<'a> {
+    /// The extension of the chart file without the dot, such as `pms`.
+    pub extension: Option<&'a str>,
+    /// The `mode_hint` of the BMSON chart.
+    pub mode_hint: Option<&'a str>,
+}
+
+impl<'a> KeyModeHints<'a> {
+    /// Creates the hints with the extension of `path`.
+    #[must_use]
+    pub fn from_path(path: &'a Path) -> Self {
+        Self {
+            extension: path.extension().and_then(OsStr::to_str),
+            mode_hint: None,
+        }
+    }
+
+    /// Sets the extension of the chart file.
+    #[must_use]
+    pub const fn with_extension(mut self, extension: &'a str) -> Self {
+        self.extension = Some(extension);
+        self
+    }
+
+    /// Sets the `mode_hint` of the BMSON chart.
+    #[must_use]
+    pub const fn with_mode_hint(mut self, mode_hint: &'a str) -> Self {
+        self.mode_hint = Some(mode_hint);
+        self
+    }
+
+    fn is_pms(&self) -> bool {
+        self.extension
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("pms"))
+    }
+}
+
+/// The result of detecting the key mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct KeyModeDetection {
+    /// The detected key mode.
+    pub mode: KeyMode,
+    /// The recommended key mapper to parse the chart with.
+    pub layout: KeyLayoutKind,
+    /// How reliable the detection is.
+    pub confidence: KeyModeConfidence,
+}
+
+impl Bms {
+    /// Detects the key mode from the channels of the notes, `#PLAYER` and `hints`.
+    ///
+    /// The BMSON charts converted by [`Bms::from_bmson`] have the notes on the beat channels, so [`KeyModeHints::mode_hint`] decides their mode and the recommended mapper is always [`KeyLayoutKind::Beat`].
+    #[must_use]
+    pub fn detect_key_mode(&self, hints: &KeyModeHints<'_>) -> KeyModeDetection {
+        let channels = self.wav.notes.all_notes().map(|note| note.channel_id);
+        detect(channels, self.metadata.player, hints)
+    }
+}
+
+/// Detects the key mode from the note channels and `#PLAYER` in `tokens` and `hints`, before parsing.
+///
+/// All the messages are inspected regardless of `#RANDOM` and `#SWITCH`, so the mode covers every branch.
+#[must_use]
+pub fn detect_key_mode_from_tokens(
+    tokens: &TokenStream<'_>,
+    hints: &KeyModeHints<'_>,
+) -> KeyModeDetection {
+    let mut player = None;
+    let mut channels = vec![];
+    for token in tokens {
+        match token.content() {
+            Token::Header { name, args } if name.eq_ignore_ascii_case("PLAYER") => {
+                player = args.trim().parse().ok().or(player);
+            }
+            Token::Message {
+                channel: Channel::Note { channel_id },
+                message,
+                ..
+            } if message
+                .as_bytes()
+                .chunks_exact(2)
+                .any(|object| object != b"00") =>
+            {
+                channels.push(*channel_id);
+            }
+            _ => {}
+        }
+    }
+    detect(channels.into_iter(), player, hints)
+}
+
+fn detect(
+    channels: impl Iterator<Item = NoteChannelId>,
+    player: Option<PlayerMode>,
+    hints: &KeyModeHints<'_>,
+) -> KeyModeDetection {
+    if let Some(mode) = hints.mode_hint.and_then(KeyMode::from_mode_hint) {
+        return KeyModeDetection {
+            mode,
+            layout: KeyLayoutKind::Beat,
+            confidence: if mode.has_mapper() {
+                KeyModeConfidence::High
+            } else {
+                KeyModeConfidence::Low
+            },
+        };
+    }
+
+    let mut p1 = HashSet::new();
+    let mut p2 = HashSet::new();
+    for map in channels.filter_map(KeyLayoutBeat::from_channel_id) {
+        match map.side() {
+            PlayerSide::Player1 => p1.insert(map.key()),
+            PlayerSide::Player2 => p2.insert(map.key()),
+        };
+    }
+
+    if p1.is_empty() && p2.is_empty() {
+        let (mode, layout) = if hints.is_pms() {
+            (KeyMode::Pms9K, KeyLayoutKind::Pms)
+        } else if player == Some(PlayerMode::Double) {
+            (KeyMode::Beat14K, KeyLayoutKind::Beat)
+        } else {
+            (KeyMode::Beat7K, KeyLayoutKind::Beat)
+        };
+        return KeyModeDetection {
+            mode,
+            layout,
+            confidence: KeyModeConfidence::Low,
+        };
+    }
+
+    let has_7k_keys =
+        |lanes: &HashSet<Key>| lanes.contains(&Key::Key(6)) || lanes.contains(&Key::Key(7));
+    let in_keys = |lanes: &HashSet<Key>, keys: RangeInclusive<u8>| {
+        lanes
+            .iter()
+            .all(|key| matches!(key, Key::Key(key) if keys.contains(key)))
+    };
+    let (mode, layout) = if p2.is_empty() {
+        if p1.contains(&Key::FreeZone) && has_7k_keys(&p1) {
+            (KeyMode::Pms9K, KeyLayoutKind::PmsBmeType)
+        } else if hints.is_pms() {
+            (KeyMode::Pms9K, KeyLayoutKind::Pms)
+        } else if has_7k_keys(&p1) {
+            (KeyMode::Beat7K, KeyLayoutKind::Beat)
+        } else {
+            (KeyMode::Beat5K, KeyLayoutKind::Beat)
+        }
+    } else if in_keys(&p1, 1..=5) && in_keys(&p2, 2..=5) && player != Some(PlayerMode::Double) {
+        (KeyMode::Pms9K, KeyLayoutKind::Pms)
+    } else if has_7k_keys(&p1) || has_7k_keys(&p2) {
+        (KeyMode::Beat14K, KeyLayoutKind::Beat)
+    } else {
+        (KeyMode::Beat10K, KeyLayoutKind::Beat)
+    };
+
+    let conflicts = (hints.is_pms() && mode != KeyMode::Pms9K)
+        || match player {
+            Some(PlayerMode::Single) => mode.is_double(),
+            Some(PlayerMode::Double) => !mode.is_double(),
+            Some(PlayerMode::Two) | None => false,
+        };
+    let confidence = if conflicts {
+        KeyModeConfidence::Low
+    } else if hints.is_pms() || player.is_some() {
+        KeyModeConfidence::High
+    } else {
+        KeyModeConfidence::Medium
+    };
+    KeyModeDetection {
+        mode,
+        layout,
+        confidence,
+    }
+}
@@ -4,6 +4,7 @@
 //! [`crate::bms::parse::ParseOutput`])
 
 pub mod cursor;
+pub mod stream;
 pub mod token;
 
 use std::borrow::Cow;
@@ -83,6 +84,14 @@ impl<'a> IntoIterator for &'a TokenStream<'a> {
     }
 }
 
+impl<'a> FromIterator<TokenWithRange<'a>> for TokenStream<'a> {
+    fn from_iter<I: IntoIterator<Item = TokenWithRange<'a>>>(iter: I) -> Self {
+        Self {
+            tokens: iter.into_iter().collect(),
+        }
+    }
+}
+
 /// A list of tokens reference.
 /// This is a wrapper of [`Vec<&'a TokenWithRange<'a>>`] that provides some additional methods.
 #[derive(Debug, Clone, PartialEq, Eq)]
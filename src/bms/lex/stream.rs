@@ -0,0 +1,160 @@
+//! Streaming lexer over [`BufRead`] for huge or concatenated inputs.
+//!
+//! [`TokenStream::parse_lex`] borrows the whole source text. [`StreamLexer`] instead reads one line at a time, decodes it by [`LineDecoder`] and yields it as [`LexLine`], whose tokens borrow only the line. The ranges of the tokens are the byte offsets in the decoded text, same as the ones by [`TokenStream::parse_lex`] on the whole decoded text.
+//!
+//! The memory is bounded only while the lines are consumed one by one, or when the iteration stops early. The tokens borrow their [`LexLine`], so a full parse such as [`parse_bms_lines`](crate::bms::parse_bms_lines) keeps every line alive until it ends, and needs as much memory as the whole decoded text.
+//!
+//! The input is split at `\n` before decoding, so the decoder must be for an encoding which does not use the byte `0x0A` in multi-byte characters, such as UTF-8 and `Shift_JIS`.
+//!
+//! Stop iterating to exit early, for example after the headers:
+//!
+//! ```
+//! use bms_rs::bms::{lex::stream::StreamLexer, prelude::*};
+//!
+//! let source = "#TITLE Song\n#BPM 150\n#00111:01\n";
+//! let lines: Vec<_> = StreamLexer::new(source.as_bytes())
+//!     .map_while(|line| line.ok())
+//!     .take_while(|line| !line.text().starts_with("#001"))
+//!     .collect();
+//! assert_eq!(lines.len(), 2);
+//! let LexOutput { tokens, .. } = lines[1].lex();
+//! assert_eq!(tokens.iter().next().unwrap().range(), &(12..16));
+//! ```
+//!
+//! Parse the lines by [`parse_bms_lines`](crate::bms::parse_bms_lines), or lex and collect them into [`TokenStream`] to feed [`Bms::from_token_stream`](crate::bms::model::Bms::from_token_stream).
+
+use std::{
+    borrow::Cow,
+    io::{self, BufRead},
+};
+
+use crate::bms::command::mixin::SourceRangeMixin;
+
+use super::{LexOutput, TokenStream};
+
+/// A decoder of the lines read by [`StreamLexer`].
+pub trait LineDecoder {
+    /// Decodes the bytes of `line`, including the line break if any.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`io::Error`] if `line` cannot be decoded.
+    fn decode<'b>(&mut self, line: &'b [u8]) -> io::Result<Cow<'b, str>>;
+}
+
+/// The decoder of UTF-8, which rejects the invalid bytes instead of replacing them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Utf8Decoder;
+
+impl LineDecoder for Utf8Decoder {
+    fn decode<'b>(&mut self, line: &'b [u8]) -> io::Result<Cow<'b, str>> {
+        std::str::from_utf8(line)
+            .map(Cow::Borrowed)
+            .map_err(|error| io::Error::new(io::ErrorKind::InvalidData, error))
+    }
+}
+
+/// A decoded line read by [`StreamLexer`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct LexLine {
+    text: String,
+    offset: usize,
+}
+
+impl LexLine {
+    /// Returns the decoded text of the line, including the line break if any.
+    #[must_use]
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Returns the byte offset of the line in the decoded text.
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Lexes the line into the tokens borrowing it. The ranges of the tokens and the warnings are the ones in the whole decoded text.
+    pub fn lex(&self) -> LexOutput<'_> {
+        lex_at(&self.text, self.offset)
+    }
+}
+
+/// Lexes the line `text` placed at the byte `offset` of the whole text.
+pub(crate) fn lex_at(text: &str, offset: usize) -> LexOutput<'_> {
+    let LexOutput {
+        tokens,
+        lex_warnings,
+    } = TokenStream::parse_lex(text);
+    LexOutput {
+        tokens: tokens
+            .into_iter()
+            .map(|token| shift(token, offset))
+            .collect(),
+        lex_warnings: lex_warnings
+            .into_iter()
+            .map(|warning| shift(warning, offset))
+            .collect(),
+    }
+}
+
+/// An iterator of the decoded lines read from [`BufRead`].
+pub struct StreamLexer<R, D = Utf8Decoder> {
+    reader: R,
+    decoder: D,
+    /// The buffer of the current line.
+    line: Vec<u8>,
+    /// The byte offset of the next line in the decoded text.
+    offset: usize,
+}
+
+impl<R: BufRead> StreamLexer<R> {
+    /// Creates a new lexer reading UTF-8 text from `reader`.
+    pub const fn new(reader: R) -> Self {
+        Self::with_decoder(reader, Utf8Decoder)
+    }
+}
+
+impl<R: BufRead, D: LineDecoder> StreamLexer<R, D> {
+    /// Creates a new lexer reading from `reader` and decoding by `decoder`.
+    pub const fn with_decoder(reader: R, decoder: D) -> Self {
+        Self {
+            reader,
+            decoder,
+            line: Vec::new(),
+            offset: 0,
+        }
+    }
+
+    /// Returns the number of bytes of the decoded text so far.
+    #[must_use]
+    pub const fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+impl<R: BufRead, D: LineDecoder> Iterator for StreamLexer<R, D> {
+    type Item = io::Result<LexLine>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.line.clear();
+        match self.reader.read_until(b'\n', &mut self.line) {
+            Ok(0) => return None,
+            Ok(_) => {}
+            Err(error) => return Some(Err(error)),
+        }
+        let text = match self.decoder.decode(&self.line) {
+            Ok(text) => text.into_owned(),
+            Err(error) => return Some(Err(error)),
+        };
+        let offset = self.offset;
+        self.offset += text.len();
+        Some(Ok(LexLine { text, offset }))
+    }
+}
+
+/// Moves the range of `item` in a line into the one in the whole input.
+fn shift<T>(item: SourceRangeMixin<T>, offset: usize) -> SourceRangeMixin<T> {
+    let (content, range) = item.into();
+    SourceRangeMixin::new(content, range.start + offset..range.end + offset)
+}
@@ -0,0 +1,481 @@
+//! Configurable lints over the parsed [`Bms`] and its tokens.
+//!
+//! [`LintRegistry`] holds the [`LintRule`]s with their [`LintSeverity`]. Each rule has an id such as `total-undefined`, which is used to change its severity, to turn it off with [`LintSeverity::Allow`], and to suppress it in the source text. [`LintRegistry::builtin`] registers the rules in [`builtin`], which cover [`Bms::check_validity`] and [`Bms::check_playing`].
+//!
+//! The lines in the source text suppress the rules by their ids:
+//!
+//! - `%LINT-ALLOW rule-id...` suppresses the rules in the whole file.
+//! - `%LINT-ALLOW-NEXT rule-id...` suppresses the rules on the next command line only.
+//!
+//! The suppressions and the locations of the diagnostics need [`LintContext::with_tokens`] and [`LintContext::with_source_map`] respectively. Without them, the diagnostics are not located and no rule is suppressed.
+//!
+//! Some diagnostics carry a [`Fix`] as [`LintDiagnostic::fix`], which [`apply_fixes`](crate::bms::fix::apply_fixes) applies to the source text. The fixes need the locations, and some of them need [`LintContext::with_source`] too.
+//!
+//! ```
+//! use bms_rs::bms::{
+//!     lint::{LintContext, LintRegistry, LintSeverity},
+//!     prelude::*,
+//!     source_map::SourceMapProcessor,
+//! };
+//!
+//! let source = "%LINT-ALLOW total-undefined\n#BPM 120\n#WAV01 kick.wav\n#00011:0102\n";
+//! let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+//! let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+//!     &tokens,
+//!     default_config().override_token_processor(SourceMapProcessor),
+//! );
+//! let bms = output.bms.unwrap();
+//! let source_map = output.extensions.unwrap();
+//!
+//! let registry = LintRegistry::builtin::<KeyLayoutBeat>()
+//!     .with_severity("note-in-track-zero", LintSeverity::Error);
+//! let ctx = LintContext::new(&bms)
+//!     .with_tokens(&tokens)
+//!     .with_source_map(&source_map);
+//! let output = registry.run(&ctx);
+//! let rules: Vec<_> = output.diagnostics.iter().map(|diagnostic| diagnostic.rule).collect();
+//! assert_eq!(rules, ["missing-definition", "note-in-track-zero", "note-in-track-zero"]);
+//! assert_eq!(output.diagnostics[0].range, Some(53..64));
+//! assert!(output.has_errors());
+//! ```
+
+pub mod builtin;
+
+use std::{
+    any::TypeId,
+    cell::{OnceCell, RefCell},
+    ops::Range,
+    rc::Rc,
+};
+
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{SimpleSource, ToAriadne, build_report_with_help};
+#[cfg(feature = "diagnostics")]
+use ariadne::{Color, Report, ReportKind};
+
+use crate::bms::{
+    command::{channel::mapper::KeyLayoutMapper, mixin::SourceRangeMixin},
+    fix::Fix,
+    lex::{TokenStream, token::Token},
+    model::Bms,
+    parse::{
+        check_playing::PlayingCheckOutput,
+        timing::{TimingCheckConfig, TimingWarning},
+        validity::ValidityCheckOutput,
+    },
+    source_map::SourceMap,
+};
+
+/// The comment prefix to suppress the rules in the whole file.
+const ALLOW_FILE: &str = "%LINT-ALLOW";
+/// The comment prefix to suppress the rules on the next command line.
+const ALLOW_NEXT: &str = "%LINT-ALLOW-NEXT";
+
+/// How a diagnostic of a [`LintRule`] is reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintSeverity {
+    /// The rule is turned off.
+    Allow,
+    /// An informational note, which does not need any fix.
+    Info,
+    /// A problem which should be fixed, but does not reject the chart.
+    Warning,
+    /// A problem which rejects the chart.
+    Error,
+}
+
+/// The results of the checks over the chart with a key layout, computed on the first use.
+#[derive(Default)]
+struct LayoutChecks {
+    validity: OnceCell<Rc<ValidityCheckOutput>>,
+    playing: OnceCell<Rc<PlayingCheckOutput>>,
+    /// The results for each config.
+    timing: RefCell<Vec<(TimingCheckConfig, Rc<[TimingWarning]>)>>,
+}
+
+/// The inputs of the [`LintRule`]s.
+///
+/// The results of [`Bms::check_validity`], [`Bms::check_playing`] and [`Bms::check_timing`] are computed once for each key layout and shared among the rules.
+pub struct LintContext<'a> {
+    bms: &'a Bms,
+    tokens: Option<&'a TokenStream<'a>>,
+    source_map: Option<&'a SourceMap>,
+    source: Option<&'a str>,
+    checks: RefCell<Vec<(TypeId, Rc<LayoutChecks>)>>,
+}
+
+impl<'a> LintContext<'a> {
+    /// Creates a new context over `bms`.
+    #[must_use]
+    pub const fn new(bms: &'a Bms) -> Self {
+        Self {
+            bms,
+            tokens: None,
+            source_map: None,
+            source: None,
+            checks: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Adds the tokens which `bms` was parsed from, for the allow-comments and the rules over the tokens.
+    #[must_use]
+    pub const fn with_tokens(mut self, tokens: &'a TokenStream<'a>) -> Self {
+        self.tokens = Some(tokens);
+        self
+    }
+
+    /// Adds the [`SourceMap`] recorded on parsing `bms`, to locate the diagnostics.
+    #[must_use]
+    pub const fn with_source_map(mut self, source_map: &'a SourceMap) -> Self {
+        self.source_map = Some(source_map);
+        self
+    }
+
+    /// Adds the source text which `bms` was parsed from, for the fixes editing the whole lines.
+    #[must_use]
+    pub const fn with_source(mut self, source: &'a str) -> Self {
+        self.source = Some(source);
+        self
+    }
+
+    /// Returns the chart to lint.
+    #[must_use]
+    pub const fn bms(&self) -> &'a Bms {
+        self.bms
+    }
+
+    /// Returns the tokens if added.
+    #[must_use]
+    pub const fn tokens(&self) -> Option<&'a TokenStream<'a>> {
+        self.tokens
+    }
+
+    /// Returns the source map if added.
+    #[must_use]
+    pub const fn source_map(&self) -> Option<&'a SourceMap> {
+        self.source_map
+    }
+
+    /// Returns the source text if added.
+    #[must_use]
+    pub const fn source(&self) -> Option<&'a str> {
+        self.source
+    }
+
+    /// Returns the result of [`Bms::check_validity`] with the key layout `T`, computed on the first call for `T`.
+    #[must_use]
+    pub fn validity<T: KeyLayoutMapper + 'static>(&self) -> Rc<ValidityCheckOutput> {
+        Rc::clone(
+            self.checks::<T>()
+                .validity
+                .get_or_init(|| Rc::new(self.bms.check_validity::<T>())),
+        )
+    }
+
+    /// Returns the result of [`Bms::check_playing`] with the key layout `T`, computed on the first call for `T`.
+    #[must_use]
+    pub fn playing<T: KeyLayoutMapper + 'static>(&self) -> Rc<PlayingCheckOutput> {
+        Rc::clone(
+            self.checks::<T>()
+                .playing
+                .get_or_init(|| Rc::new(self.bms.check_playing::<T>())),
+        )
+    }
+
+    /// Returns the result of [`Bms::check_timing`] with the key layout `T` and `config`, computed on the first call for them.
+    #[must_use]
+    pub fn timing<T: KeyLayoutMapper + 'static>(
+        &self,
+        config: &TimingCheckConfig,
+    ) -> Rc<[TimingWarning]> {
+        let checks = self.checks::<T>();
+        let mut timing = checks.timing.borrow_mut();
+        if let Some((_, warnings)) = timing.iter().find(|(cached, _)| cached == config) {
+            return Rc::clone(warnings);
+        }
+        let warnings: Rc<[TimingWarning]> = self.bms.check_timing::<T>(config).into();
+        timing.push((*config, Rc::clone(&warnings)));
+        warnings
+    }
+
+    fn checks<T: 'static>(&self) -> Rc<LayoutChecks> {
+        let mut checks = self.checks.borrow_mut();
+        let layout = TypeId::of::<T>();
+        if let Some((_, cached)) = checks.iter().find(|(cached, _)| *cached == layout) {
+            return Rc::clone(cached);
+        }
+        let created = Rc::new(LayoutChecks::default());
+        checks.push((layout, Rc::clone(&created)));
+        created
+    }
+}
+
+/// A problem found by a [`LintRule`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+pub struct LintFinding {
+    /// The description of the problem.
+    pub message: String,
+    /// The byte range in the source text causing the problem, if located.
+    pub range: Option<Range<usize>>,
+    /// The fix of the problem, if any.
+    pub fix: Option<Fix>,
+}
+
+impl LintFinding {
+    /// Creates a new finding without location.
+    pub fn new(message: &impl ToString) -> Self {
+        Self {
+            message: message.to_string(),
+            range: None,
+            fix: None,
+        }
+    }
+
+    /// Sets the location of the finding.
+    pub const fn with_range(mut self, range: Option<Range<usize>>) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Sets the fix of the finding.
+    pub fn with_fix(mut self, fix: Option<Fix>) -> Self {
+        self.fix = fix;
+        self
+    }
+}
+
+/// A rule checking the chart for a kind of problems.
+pub trait LintRule {
+    /// The unique id of the rule in kebab-case, such as `total-undefined`.
+    fn id(&self) -> &'static str;
+
+    /// The one-line description of the rule.
+    fn description(&self) -> &'static str;
+
+    /// The severity used unless configured by [`LintRegistry::with_severity`].
+    fn default_severity(&self) -> LintSeverity;
+
+    /// Checks the chart and returns the problems found.
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding>;
+}
+
+/// A problem reported by [`LintRegistry::run`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LintDiagnostic {
+    /// The id of the rule reporting it.
+    pub rule: &'static str,
+    /// The configured severity of the rule.
+    pub severity: LintSeverity,
+    /// The description of the problem.
+    pub message: String,
+    /// The byte range in the source text causing the problem, if located.
+    pub range: Option<Range<usize>>,
+    /// The fix of the problem, if any.
+    pub fix: Option<Fix>,
+}
+
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for LintDiagnostic {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        let (kind, color) = match self.severity {
+            LintSeverity::Error => (ReportKind::Error, Color::Red),
+            LintSeverity::Warning => (ReportKind::Warning, Color::Yellow),
+            LintSeverity::Info | LintSeverity::Allow => (ReportKind::Advice, Color::Blue),
+        };
+        // Diagnostics without location are anchored at file start.
+        let help = self.fix.as_ref().map(|fix| format!("fix: {}", fix.message));
+        build_report_with_help(
+            src,
+            kind,
+            self.range.clone().unwrap_or(0..0),
+            &format!("lint `{}`", self.rule),
+            &self.message,
+            color,
+            help.as_deref(),
+        )
+    }
+}
+
+/// Output of [`LintRegistry::run`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct LintOutput {
+    /// The diagnostics in the registration order of the rules.
+    pub diagnostics: Vec<LintDiagnostic>,
+}
+
+impl LintOutput {
+    /// Returns whether any diagnostic has [`LintSeverity::Error`].
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.severity == LintSeverity::Error)
+    }
+
+    /// Returns the fixes of the diagnostics, to pass to [`apply_fixes`](crate::bms::fix::apply_fixes).
+    pub fn fixes(&self) -> impl Iterator<Item = &Fix> {
+        self.diagnostics
+            .iter()
+            .filter_map(|diagnostic| diagnostic.fix.as_ref())
+    }
+}
+
+/// A set of [`LintRule`]s with their configured severity.
+#[derive(Default)]
+pub struct LintRegistry {
+    rules: Vec<(Box<dyn LintRule>, LintSeverity)>,
+}
+
+impl LintRegistry {
+    /// Creates a new registry without any rule.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a new registry with all the rules in [`builtin`], using `T` as the key layout of the notes.
+    #[must_use]
+    pub fn builtin<T: KeyLayoutMapper + 'static>() -> Self {
+        builtin::rules::<T>()
+            .into_iter()
+            .fold(Self::new(), Self::with_boxed_rule)
+    }
+
+    /// Registers `rule` with its default severity. The rule with the same id is replaced.
+    #[must_use]
+    pub fn with_rule(self, rule: impl LintRule + 'static) -> Self {
+        self.with_boxed_rule(Box::new(rule))
+    }
+
+    fn with_boxed_rule(mut self, rule: Box<dyn LintRule>) -> Self {
+        let severity = rule.default_severity();
+        if let Some(entry) = self
+            .rules
+            .iter_mut()
+            .find(|(registered, _)| registered.id() == rule.id())
+        {
+            *entry = (rule, severity);
+        } else {
+            self.rules.push((rule, severity));
+        }
+        self
+    }
+
+    /// Configures the severity of the rule `id`. Unknown ids are ignored.
+    #[must_use]
+    pub fn with_severity(mut self, id: &str, severity: LintSeverity) -> Self {
+        self.set_severity(id, severity);
+        self
+    }
+
+    /// Configures the severity of the rule `id`, and returns whether the rule is registered.
+    pub fn set_severity(&mut self, id: &str, severity: LintSeverity) -> bool {
+        self.rules
+            .iter_mut()
+            .find(|(rule, _)| rule.id() == id)
+            .map(|(_, configured)| *configured = severity)
+            .is_some()
+    }
+
+    /// Returns the configured severity of the rule `id`, or `None` if not registered.
+    #[must_use]
+    pub fn severity(&self, id: &str) -> Option<LintSeverity> {
+        self.rules
+            .iter()
+            .find(|(rule, _)| rule.id() == id)
+            .map(|&(_, severity)| severity)
+    }
+
+    /// Returns the registered rules in the registration order.
+    pub fn rules(&self) -> impl Iterator<Item = &dyn LintRule> {
+        self.rules.iter().map(|(rule, _)| rule.as_ref())
+    }
+
+    /// Runs the rules not allowed, and drops the diagnostics suppressed by the allow-comments.
+    pub fn run(&self, ctx: &LintContext<'_>) -> LintOutput {
+        let allows = ctx.tokens().map(Allows::from_tokens).unwrap_or_default();
+        let diagnostics = self
+            .rules
+            .iter()
+            .filter(|&&(ref rule, severity)| {
+                severity != LintSeverity::Allow && !allows.file.contains(&rule.id())
+            })
+            .flat_map(|(rule, severity)| {
+                rule.check(ctx).into_iter().map(
+                    |LintFinding {
+                         message,
+                         range,
+                         fix,
+                     }| LintDiagnostic {
+                        rule: rule.id(),
+                        severity: *severity,
+                        message,
+                        range,
+                        fix,
+                    },
+                )
+            })
+            .filter(|diagnostic| !allows.suppresses(diagnostic))
+            .collect();
+        LintOutput { diagnostics }
+    }
+}
+
+/// The rule ids suppressed by the allow-comments.
+#[derive(Debug, Default)]
+struct Allows<'a> {
+    /// The rules suppressed in the whole file.
+    file: Vec<&'a str>,
+    /// The rules suppressed in the byte ranges of the next command lines.
+    lines: Vec<(Range<usize>, Vec<&'a str>)>,
+}
+
+impl<'a> Allows<'a> {
+    fn from_tokens(tokens: &'a TokenStream<'a>) -> Self {
+        let mut allows = Self::default();
+        let mut pending: Vec<&'a str> = vec![];
+        let mut commands = tokens
+            .iter()
+            .filter(|token| !matches!(token.content(), Token::NotACommand(_)))
+            .map(SourceRangeMixin::start)
+            .peekable();
+        for token in tokens {
+            let Token::NotACommand(line) = token.content() else {
+                if !pending.is_empty() {
+                    // Skip to the start of this line, then the line spans until the next command.
+                    while commands.next_if(|&start| start <= token.start()).is_some() {}
+                    let end = commands.peek().copied().unwrap_or(usize::MAX);
+                    allows
+                        .lines
+                        .push((token.start()..end, std::mem::take(&mut pending)));
+                }
+                continue;
+            };
+            let line = line.trim();
+            let (command, ids) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            if command == ALLOW_NEXT {
+                pending.extend(ids.split_whitespace());
+            } else if command == ALLOW_FILE {
+                allows.file.extend(ids.split_whitespace());
+            }
+        }
+        allows
+    }
+
+    fn suppresses(&self, diagnostic: &LintDiagnostic) -> bool {
+        let Some(range) = &diagnostic.range else {
+            return false;
+        };
+        self.lines
+            .iter()
+            .any(|(line, ids)| line.contains(&range.start) && ids.contains(&diagnostic.rule))
+    }
+}
@@ -0,0 +1,974 @@
+//! The built-in [`LintRule`]s registered by [`LintRegistry::builtin`](super::LintRegistry::builtin).
+//!
+//! | id | default severity | source |
+//! |---|---|---|
+//! | `misspelled-command` | warning | the commands normalized by [`DefaultTokenRelaxer`] |
+//! | `duplicate-definition` | warning | the definitions recorded in [`SourceMap`] more than once |
+//! | `missing-definition` | warning | [`ValidityMissing`] |
+//! | `unused-definition` | warning | [`ValidityUnused`] |
+//! | `note-in-track-zero` | info | [`ValidityInvalid::PlayableNoteInTrackZero`] |
+//! | `overlapping-notes` | warning | the other [`ValidityInvalid`] |
+//! | `total-undefined` | warning | [`PlayingWarning::TotalUndefined`] |
+//! | `total-out-of-range` | warning | [`TotalWarning`] by [`TotalCheckConfig::default`] |
+//! | `bpm-out-of-range` | warning | [`TimingWarningKind::BpmTooHigh`] and [`TimingWarningKind::BpmTooLow`] |
+//! | `stop-too-long` | warning | [`TimingWarningKind::StopTooLong`] |
+//! | `non-positive-scroll` | info | [`TimingWarningKind::NegativeScroll`] and [`TimingWarningKind::ZeroScroll`] |
+//! | `speed-near-zero` | warning | [`TimingWarningKind::SpeedNearZero`] |
+//! | `section-too-short` | warning | [`TimingWarningKind::SectionLenTooShort`] |
+//! | `note-during-stop` | info | [`TimingWarningKind::NoteDuringStop`] |
+//! | `start-bpm-undefined` | warning | [`PlayingWarning::StartBpmUndefined`] |
+//! | `no-displayable-notes` | warning | [`PlayingWarning::NoDisplayableNotes`] |
+//! | `no-playable-notes` | warning | [`PlayingWarning::NoPlayableNotes`] |
+//! | `invalid-value` | error | [`PlayingError::InvalidBpm`], [`PlayingError::InvalidStop`], [`PlayingError::InvalidSpeed`], [`PlayingError::InvalidScroll`] and [`PlayingError::InvalidSeek`] |
+//! | `bpm-undefined` | error | [`PlayingError::BpmUndefined`] |
+//! | `no-notes` | error | [`PlayingError::NoNotes`] |
+//!
+//! These rules attach the fixes:
+//!
+//! - `misspelled-command` replaces the command with the normalized one. The fix needs [`LintContext::with_source`] if the arguments are changed.
+//! - `duplicate-definition` removes the overridden definition lines, because [`AlwaysWarnAndUseNewer`] uses the last one. The fix needs [`LintContext::with_source`].
+//! - `overlapping-notes` removes the visible notes inside the long notes.
+//! - `total-undefined` adds `#TOTAL` recommended by [`TotalFormula::default`].
+//!
+//! The rules over [`TimingWarningKind`] check by [`TimingCheckConfig::default`]. Register them by [`LintRegistry::with_rule`](super::LintRegistry::with_rule) with another config to change the limits.
+//!
+//! [`DefaultTokenRelaxer`]: crate::bms::parse::token_processor::DefaultTokenRelaxer
+//! [`SourceMap`]: crate::bms::source_map::SourceMap
+//! [`TotalWarning`]: crate::bms::parse::total::TotalWarning
+//! [`AlwaysWarnAndUseNewer`]: crate::bms::parse::prompt::AlwaysWarnAndUseNewer
+
+use std::{marker::PhantomData, ops::Range};
+
+use crate::bms::{
+    command::{
+        ObjId,
+        channel::{
+            Channel,
+            mapper::{KeyLayoutBeat, KeyLayoutMapper},
+        },
+        time::ObjTime,
+    },
+    cst::object_times,
+    fix::{Fix, TextEdit, line_range},
+    lex::token::Token,
+    model::Bms,
+    parse::{
+        check_playing::{PlayingError, PlayingWarning},
+        timing::{TimingCheckConfig, TimingWarning, TimingWarningKind},
+        token_processor::DefaultTokenRelaxer,
+        total::{TotalCheckConfig, TotalFormula},
+        validity::{ValidityInvalid, ValidityMissing, ValidityUnused},
+    },
+    process::invalid_values,
+};
+use crate::chart::types::{Key, NoteKind, PlayerSide};
+
+use super::{LintContext, LintFinding, LintRule, LintSeverity};
+
+/// Returns all the built-in rules, using `T` as the key layout of the notes.
+#[must_use]
+pub fn rules<T: KeyLayoutMapper + 'static>() -> Vec<Box<dyn LintRule>> {
+    vec![
+        Box::new(MisspelledCommand),
+        Box::new(DuplicateDefinition),
+        Box::new(MissingDefinition),
+        Box::new(UnusedDefinition),
+        Box::new(NoteInTrackZero::<T>::default()),
+        Box::new(OverlappingNotes::<T>::default()),
+        Box::new(TotalUndefined::<T>::default()),
+        Box::new(TotalOutOfRange::<T>::default()),
+        Box::new(BpmOutOfRange::default()),
+        Box::new(StopTooLong::default()),
+        Box::new(NonPositiveScroll),
+        Box::new(SpeedNearZero::default()),
+        Box::new(SectionTooShort::default()),
+        Box::new(NoteDuringStop::<T>::default()),
+        Box::new(StartBpmUndefined),
+        Box::new(NoDisplayableNotes::<T>::default()),
+        Box::new(NoPlayableNotes::<T>::default()),
+        Box::new(InvalidValue),
+        Box::new(BpmUndefined),
+        Box::new(NoNotes),
+    ]
+}
+
+/// Misspelled commands such as `#RONDAM` and `#END IF`, which the relaxed parsing reads as the correct ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MisspelledCommand;
+
+impl LintRule for MisspelledCommand {
+    fn id(&self) -> &'static str {
+        "misspelled-command"
+    }
+
+    fn description(&self) -> &'static str {
+        "Commands are misspelled, but read as the correct ones by the relaxed parsing."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        let Some(tokens) = ctx.tokens() else {
+            return vec![];
+        };
+        tokens
+            .iter()
+            .filter_map(|token| {
+                let (relaxed, fix) = DefaultTokenRelaxer.relax(token, ctx.source())?;
+                let message = format!("`{}` is misspelled, read as `{relaxed}`", token.content());
+                Some(
+                    LintFinding::new(&message)
+                        .with_range(Some(token.range().clone()))
+                        .with_fix(fix),
+                )
+            })
+            .collect()
+    }
+}
+
+/// Definitions such as `#WAVxx` defined more than once, where the older ones are overridden.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct DuplicateDefinition;
+
+impl LintRule for DuplicateDefinition {
+    fn id(&self) -> &'static str {
+        "duplicate-definition"
+    }
+
+    fn description(&self) -> &'static str {
+        "Definitions are defined more than once."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        let Some(source_map) = ctx.source_map() else {
+            return vec![];
+        };
+        let mut findings: Vec<_> = source_map
+            .duplicated_definitions()
+            .flat_map(|(command, id, ranges)| {
+                ranges
+                    .split_last()
+                    .into_iter()
+                    .flat_map(|(_, older)| older)
+                    .map(move |range| {
+                        let fix = ctx.source().map(|source| {
+                            Fix::new(
+                                &"remove the overridden definition",
+                                vec![TextEdit::delete(line_range(source, range))],
+                            )
+                        });
+                        LintFinding::new(&format!("#{command}{id} is defined again later"))
+                            .with_range(Some(range.clone()))
+                            .with_fix(fix)
+                    })
+            })
+            .collect();
+        findings.sort_by_key(|finding| finding.range.as_ref().map(|range| range.start));
+        findings
+    }
+}
+
+/// Objects referring ids without the definitions, such as `#WAVxx` and `#BMPxx`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MissingDefinition;
+
+impl LintRule for MissingDefinition {
+    fn id(&self) -> &'static str {
+        "missing-definition"
+    }
+
+    fn description(&self) -> &'static str {
+        "Objects refer ids without the definitions."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        // The layout does not matter for the missing definitions.
+        ctx.validity::<KeyLayoutBeat>()
+            .missing
+            .iter()
+            .map(|missing| LintFinding::new(missing).with_range(locate_missing(ctx, missing)))
+            .collect()
+    }
+}
+
+/// Definitions such as `#WAVxx` and `#BPMxx` which no object refers.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct UnusedDefinition;
+
+impl LintRule for UnusedDefinition {
+    fn id(&self) -> &'static str {
+        "unused-definition"
+    }
+
+    fn description(&self) -> &'static str {
+        "Definitions are not referred by any object."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        // The layout does not matter for the unused definitions.
+        ctx.validity::<KeyLayoutBeat>()
+            .unused
+            .iter()
+            .map(|unused| LintFinding::new(unused).with_range(locate_unused(ctx, unused)))
+            .collect()
+    }
+}
+
+/// Playable notes in the track 000, which some players skip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoteInTrackZero<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for NoteInTrackZero<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for NoteInTrackZero<T> {
+    fn id(&self) -> &'static str {
+        "note-in-track-zero"
+    }
+
+    fn description(&self) -> &'static str {
+        "Playable notes are placed in the track 000."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Info
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        ctx.validity::<T>()
+            .invalid
+            .iter()
+            .filter(|invalid| matches!(invalid, ValidityInvalid::PlayableNoteInTrackZero { .. }))
+            .map(|invalid| LintFinding::new(invalid).with_range(locate_invalid::<T>(ctx, invalid)))
+            .collect()
+    }
+}
+
+/// Notes overlapping with other notes in the same lane.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct OverlappingNotes<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for OverlappingNotes<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for OverlappingNotes<T> {
+    fn id(&self) -> &'static str {
+        "overlapping-notes"
+    }
+
+    fn description(&self) -> &'static str {
+        "Notes overlap with other notes in the same lane."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        ctx.validity::<T>()
+            .invalid
+            .iter()
+            .filter(|invalid| !matches!(invalid, ValidityInvalid::PlayableNoteInTrackZero { .. }))
+            .map(|invalid| {
+                let fix = match invalid {
+                    ValidityInvalid::OverlapVisibleSingleWithLong {
+                        side, key, time, ..
+                    } => fix_overlapping_note::<T>(ctx, *side, *key, *time),
+                    _ => None,
+                };
+                LintFinding::new(invalid)
+                    .with_range(locate_invalid::<T>(ctx, invalid))
+                    .with_fix(fix)
+            })
+            .collect()
+    }
+}
+
+/// The `#TOTAL` is not specified. The fix adds the recommended value for the playable notes in the layout `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TotalUndefined<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for TotalUndefined<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for TotalUndefined<T> {
+    fn id(&self) -> &'static str {
+        "total-undefined"
+    }
+
+    fn description(&self) -> &'static str {
+        "The `#TOTAL` is not specified."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_warnings::<T>(ctx, |warning| {
+            matches!(warning, PlayingWarning::TotalUndefined)
+        })
+        .into_iter()
+        .map(|finding| {
+            // The range points the first command, where `#TOTAL` should be added.
+            let fix = finding
+                .range
+                .as_ref()
+                .and_then(|range| fix_total_undefined::<T>(ctx.bms(), range.start));
+            finding.with_fix(fix)
+        })
+        .collect()
+    }
+}
+
+/// The `#TOTAL` is far from the recommended value for the playable notes in the layout `T`.
+///
+/// Register it by [`LintRegistry::with_rule`](super::LintRegistry::with_rule) with another [`TotalCheckConfig`] to change the formula and the ratios.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TotalOutOfRange<T> {
+    config: TotalCheckConfig,
+    _phantom: PhantomData<fn() -> T>,
+}
+
+impl<T> TotalOutOfRange<T> {
+    /// Creates a new rule checking by `config`.
+    #[must_use]
+    pub const fn new(config: TotalCheckConfig) -> Self {
+        Self {
+            config,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<T> Default for TotalOutOfRange<T> {
+    fn default() -> Self {
+        Self::new(TotalCheckConfig::default())
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for TotalOutOfRange<T> {
+    fn id(&self) -> &'static str {
+        "total-out-of-range"
+    }
+
+    fn description(&self) -> &'static str {
+        "The `#TOTAL` is far from the recommended value."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        ctx.bms()
+            .check_total::<T>(&self.config)
+            .into_iter()
+            .map(|warning| {
+                let range = ctx
+                    .source_map()
+                    .and_then(|source_map| source_map.header("TOTAL").last().cloned());
+                LintFinding::new(&warning).with_range(range)
+            })
+            .collect()
+    }
+}
+
+/// BPMs out of the limits in [`TimingCheckConfig`], including the `#BPM` header.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct BpmOutOfRange {
+    config: TimingCheckConfig,
+}
+
+impl BpmOutOfRange {
+    /// Creates a new rule checking by `config`.
+    #[must_use]
+    pub const fn new(config: TimingCheckConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl LintRule for BpmOutOfRange {
+    fn id(&self) -> &'static str {
+        "bpm-out-of-range"
+    }
+
+    fn description(&self) -> &'static str {
+        "BPMs are extremely high or low."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        timing_findings::<KeyLayoutBeat>(ctx, &self.config, |kind| {
+            matches!(
+                kind,
+                TimingWarningKind::BpmTooHigh { .. } | TimingWarningKind::BpmTooLow { .. }
+            )
+        })
+    }
+}
+
+/// STOPs longer than the limit in [`TimingCheckConfig`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct StopTooLong {
+    config: TimingCheckConfig,
+}
+
+impl StopTooLong {
+    /// Creates a new rule checking by `config`.
+    #[must_use]
+    pub const fn new(config: TimingCheckConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl LintRule for StopTooLong {
+    fn id(&self) -> &'static str {
+        "stop-too-long"
+    }
+
+    fn description(&self) -> &'static str {
+        "STOPs last too long."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        timing_findings::<KeyLayoutBeat>(ctx, &self.config, |kind| {
+            matches!(kind, TimingWarningKind::StopTooLong { .. })
+        })
+    }
+}
+
+/// Scrolling factors which are negative or zero, which some players do not support.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NonPositiveScroll;
+
+impl LintRule for NonPositiveScroll {
+    fn id(&self) -> &'static str {
+        "non-positive-scroll"
+    }
+
+    fn description(&self) -> &'static str {
+        "Scrolling factors are negative or zero."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Info
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        // The limits do not matter for the scrolling factors.
+        timing_findings::<KeyLayoutBeat>(ctx, &TimingCheckConfig::default(), |kind| {
+            matches!(
+                kind,
+                TimingWarningKind::NegativeScroll { .. } | TimingWarningKind::ZeroScroll
+            )
+        })
+    }
+}
+
+/// Spacing factors lower than the limit in [`TimingCheckConfig`].
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SpeedNearZero {
+    config: TimingCheckConfig,
+}
+
+impl SpeedNearZero {
+    /// Creates a new rule checking by `config`.
+    #[must_use]
+    pub const fn new(config: TimingCheckConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl LintRule for SpeedNearZero {
+    fn id(&self) -> &'static str {
+        "speed-near-zero"
+    }
+
+    fn description(&self) -> &'static str {
+        "Spacing factors are nearly zero, so the notes overlap."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        timing_findings::<KeyLayoutBeat>(ctx, &self.config, |kind| {
+            matches!(kind, TimingWarningKind::SpeedNearZero { .. })
+        })
+    }
+}
+
+/// Section lengths shorter than the limit in [`TimingCheckConfig`], including zero and negative ones.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SectionTooShort {
+    config: TimingCheckConfig,
+}
+
+impl SectionTooShort {
+    /// Creates a new rule checking by `config`.
+    #[must_use]
+    pub const fn new(config: TimingCheckConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl LintRule for SectionTooShort {
+    fn id(&self) -> &'static str {
+        "section-too-short"
+    }
+
+    fn description(&self) -> &'static str {
+        "Section lengths are nearly zero."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        timing_findings::<KeyLayoutBeat>(ctx, &self.config, |kind| {
+            matches!(kind, TimingWarningKind::SectionLenTooShort { .. })
+        })
+    }
+}
+
+/// Playable notes in the layout `T` placed at the same time as STOPs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoteDuringStop<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for NoteDuringStop<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for NoteDuringStop<T> {
+    fn id(&self) -> &'static str {
+        "note-during-stop"
+    }
+
+    fn description(&self) -> &'static str {
+        "Playable notes are placed during STOPs."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Info
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        // The limits do not matter for the notes.
+        timing_findings::<T>(ctx, &TimingCheckConfig::default(), |kind| {
+            matches!(kind, TimingWarningKind::NoteDuringStop { .. })
+        })
+    }
+}
+
+/// The `#BPM` is not specified, but there are BPM changes.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StartBpmUndefined;
+
+impl LintRule for StartBpmUndefined {
+    fn id(&self) -> &'static str {
+        "start-bpm-undefined"
+    }
+
+    fn description(&self) -> &'static str {
+        "The `#BPM` is not specified, so the first BPM change is used."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_warnings::<KeyLayoutBeat>(ctx, |warning| {
+            matches!(warning, PlayingWarning::StartBpmUndefined)
+        })
+    }
+}
+
+/// There are notes, but none of them is displayable in the layout `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoDisplayableNotes<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for NoDisplayableNotes<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for NoDisplayableNotes<T> {
+    fn id(&self) -> &'static str {
+        "no-displayable-notes"
+    }
+
+    fn description(&self) -> &'static str {
+        "There is no displayable notes."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_warnings::<T>(ctx, |warning| {
+            matches!(warning, PlayingWarning::NoDisplayableNotes)
+        })
+    }
+}
+
+/// There are notes, but none of them is playable in the layout `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoPlayableNotes<T>(PhantomData<fn() -> T>);
+
+impl<T> Default for NoPlayableNotes<T> {
+    fn default() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T: KeyLayoutMapper + 'static> LintRule for NoPlayableNotes<T> {
+    fn id(&self) -> &'static str {
+        "no-playable-notes"
+    }
+
+    fn description(&self) -> &'static str {
+        "There is no playable notes."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Warning
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_warnings::<T>(ctx, |warning| {
+            matches!(warning, PlayingWarning::NoPlayableNotes)
+        })
+    }
+}
+
+/// Values which could not be parsed, such as `#BPM abc` and `#STOP01 x`.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct InvalidValue;
+
+impl LintRule for InvalidValue {
+    fn id(&self) -> &'static str {
+        "invalid-value"
+    }
+
+    fn description(&self) -> &'static str {
+        "A BPM, STOP, SPEED, SCROLL or SEEK value could not be parsed."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Error
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        invalid_values(ctx.bms())
+            .iter()
+            .map(|error| playing_error_finding(ctx, error))
+            .collect()
+    }
+}
+
+/// Neither the `#BPM` nor BPM changes are specified.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BpmUndefined;
+
+impl LintRule for BpmUndefined {
+    fn id(&self) -> &'static str {
+        "bpm-undefined"
+    }
+
+    fn description(&self) -> &'static str {
+        "There is no BPM defined."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Error
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_errors::<KeyLayoutBeat>(ctx, |error| matches!(error, PlayingError::BpmUndefined))
+    }
+}
+
+/// There is no notes at all.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NoNotes;
+
+impl LintRule for NoNotes {
+    fn id(&self) -> &'static str {
+        "no-notes"
+    }
+
+    fn description(&self) -> &'static str {
+        "There is no notes."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Error
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        playing_errors::<KeyLayoutBeat>(ctx, |error| matches!(error, PlayingError::NoNotes))
+    }
+}
+
+fn playing_warnings<T: KeyLayoutMapper + 'static>(
+    ctx: &LintContext<'_>,
+    filter: impl Fn(&PlayingWarning) -> bool,
+) -> Vec<LintFinding> {
+    ctx.playing::<T>()
+        .playing_warnings
+        .iter()
+        .filter(|warning| filter(warning))
+        .map(|warning| {
+            let range = ctx
+                .source_map()
+                .map(|source_map| source_map.locate_playing_warning(warning.clone()).into())
+                .map(|(_, range): (PlayingWarning, Range<usize>)| range);
+            LintFinding::new(&warning).with_range(range)
+        })
+        .collect()
+}
+
+fn playing_errors<T: KeyLayoutMapper + 'static>(
+    ctx: &LintContext<'_>,
+    filter: impl Fn(&PlayingError) -> bool,
+) -> Vec<LintFinding> {
+    ctx.playing::<T>()
+        .playing_errors
+        .iter()
+        .filter(|error| filter(error))
+        .map(|error| playing_error_finding(ctx, error))
+        .collect()
+}
+
+fn playing_error_finding(ctx: &LintContext<'_>, error: &PlayingError) -> LintFinding {
+    let range = ctx
+        .source_map()
+        .map(|source_map| source_map.locate_playing_error(error.clone()).into())
+        .map(|(_, range): (PlayingError, Range<usize>)| range);
+    LintFinding::new(error).with_range(range)
+}
+
+fn timing_findings<T: KeyLayoutMapper + 'static>(
+    ctx: &LintContext<'_>,
+    config: &TimingCheckConfig,
+    filter: impl Fn(&TimingWarningKind) -> bool,
+) -> Vec<LintFinding> {
+    ctx.timing::<T>(config)
+        .iter()
+        .filter(|warning| filter(&warning.kind))
+        .map(|warning| LintFinding::new(warning).with_range(locate_timing(ctx, warning)))
+        .collect()
+}
+
+fn locate_missing(ctx: &LintContext<'_>, missing: &ValidityMissing) -> Option<Range<usize>> {
+    let bms = ctx.bms();
+    match missing {
+        ValidityMissing::WavForNote(id) | ValidityMissing::WavForBgm(id) => {
+            let note = bms.wav.notes.all_notes().find(|note| note.wav_id == *id)?;
+            first(ctx.source_map()?.object(note.offset, note.channel_id))
+        }
+        ValidityMissing::BmpForBga(id) => {
+            let bga = bms.bmp.bga_changes.values().find(|bga| bga.id == *id)?;
+            first(
+                ctx.source_map()?
+                    .object(bga.time, bga.layer.to_channel().into()),
+            )
+        }
+        ValidityMissing::BpmChangeDef(id) => locate_id_usage(ctx, Channel::BpmChange, *id),
+        ValidityMissing::StopDef(id) => locate_id_usage(ctx, Channel::Stop, *id),
+    }
+}
+
+fn locate_unused(ctx: &LintContext<'_>, unused: &ValidityUnused) -> Option<Range<usize>> {
+    let (commands, id): (&[&str], _) = match unused {
+        ValidityUnused::Wav(id) => (&["WAV"], id),
+        ValidityUnused::Bmp(id) => (&["BMP", "EXBMP"], id),
+        ValidityUnused::BpmChangeDef(id) => (&["BPM", "EXBPM"], id),
+        ValidityUnused::StopDef(id) => (&["STOP"], id),
+        ValidityUnused::ScrollDef(id) => (&["SCROLL"], id),
+        ValidityUnused::SpeedDef(id) => (&["SPEED"], id),
+        ValidityUnused::TextDef(id) => (&["TEXT"], id),
+        ValidityUnused::ExRankDef(id) => (&["EXRANK"], id),
+        ValidityUnused::ArgbDef(id) => (&["ARGB"], id),
+    };
+    let source_map = ctx.source_map()?;
+    commands
+        .iter()
+        .find_map(|command| source_map.definition(command, *id).last().cloned())
+}
+
+fn locate_invalid<T: KeyLayoutMapper>(
+    ctx: &LintContext<'_>,
+    invalid: &ValidityInvalid,
+) -> Option<Range<usize>> {
+    let (side, key, time) = match invalid {
+        ValidityInvalid::PlayableNoteInTrackZero { side, key, time }
+        | ValidityInvalid::OverlapVisibleSingleWithSingle { side, key, time }
+        | ValidityInvalid::OverlapVisibleSingleWithLong {
+            side, key, time, ..
+        }
+        | ValidityInvalid::OverlapLandmineWithSingle { side, key, time } => (*side, *key, *time),
+        ValidityInvalid::OverlapsLandmineLongAtStart {
+            side,
+            key,
+            ln_start,
+            ..
+        } => (*side, *key, *ln_start),
+    };
+    locate_note::<T>(ctx, side, key, time)
+}
+
+fn locate_timing(ctx: &LintContext<'_>, warning: &TimingWarning) -> Option<Range<usize>> {
+    let source_map = ctx.source_map()?;
+    let channel = match warning.kind {
+        TimingWarningKind::BpmTooHigh { channel, .. }
+        | TimingWarningKind::BpmTooLow { channel, .. } => match channel {
+            Some(channel) => channel.into(),
+            None => return source_map.header("BPM").last().cloned(),
+        },
+        TimingWarningKind::StopTooLong { .. } => Channel::Stop.into(),
+        TimingWarningKind::NegativeScroll { .. } | TimingWarningKind::ZeroScroll => {
+            Channel::Scroll.into()
+        }
+        TimingWarningKind::SpeedNearZero { .. } => Channel::Speed.into(),
+        TimingWarningKind::SectionLenTooShort { .. } => Channel::SectionLen.into(),
+        TimingWarningKind::NoteDuringStop { channel_id } => channel_id,
+    };
+    first(source_map.object(warning.time, channel))
+}
+
+fn locate_note<T: KeyLayoutMapper>(
+    ctx: &LintContext<'_>,
+    side: PlayerSide,
+    key: Key,
+    time: ObjTime,
+) -> Option<Range<usize>> {
+    let source_map = ctx.source_map()?;
+    ctx.bms()
+        .wav
+        .notes
+        .all_notes()
+        .filter(|note| note.offset == time)
+        .find(|note| {
+            T::from_channel_id(note.channel_id)
+                .is_some_and(|map| map.side() == side && map.key() == key)
+        })
+        .and_then(|note| first(source_map.object(note.offset, note.channel_id)))
+}
+
+/// Finds the first message on `channel` using `id`, because the model drops the ids of the objects.
+fn locate_id_usage(ctx: &LintContext<'_>, channel: Channel, id: ObjId) -> Option<Range<usize>> {
+    ctx.tokens()?
+        .iter()
+        .find(|token| {
+            let Token::Message {
+                channel: used_channel,
+                message,
+                ..
+            } = token.content()
+            else {
+                return false;
+            };
+            *used_channel == channel
+                && message.as_bytes().chunks_exact(2).any(|used| {
+                    std::str::from_utf8(used).is_ok_and(|used| {
+                        ObjId::try_from(used, false) == Ok(id)
+                            || ObjId::try_from(used, true) == Ok(id)
+                    })
+                })
+        })
+        .map(|token| token.range().clone())
+}
+
+fn fix_overlapping_note<T: KeyLayoutMapper>(
+    ctx: &LintContext<'_>,
+    side: PlayerSide,
+    key: Key,
+    time: ObjTime,
+) -> Option<Fix> {
+    let source_map = ctx.source_map()?;
+    let note = ctx
+        .bms()
+        .wav
+        .notes
+        .all_notes()
+        .filter(|note| note.offset == time)
+        .find(|note| {
+            T::from_channel_id(note.channel_id).is_some_and(|map| {
+                map.side() == side && map.key() == key && map.kind() == NoteKind::Visible
+            })
+        })?;
+    let edits: Vec<_> = source_map
+        .object(time, note.channel_id)
+        .iter()
+        .filter_map(|line| object_range(line, time))
+        .map(|range| TextEdit::replace(range, "00"))
+        .collect();
+    (!edits.is_empty()).then(|| Fix::new(&"remove the note", edits))
+}
+
+fn fix_total_undefined<T: KeyLayoutMapper>(bms: &Bms, at: usize) -> Option<Fix> {
+    if bms.playable_note_count::<T>() == 0 {
+        return None;
+    }
+    let total = bms.recommended_total::<T>(TotalFormula::default());
+    let header = format!("#TOTAL {total:.0}");
+    Some(Fix::new(
+        &format!("add `{header}`"),
+        vec![TextEdit::insert(at, format!("{header}\n"))],
+    ))
+}
+
+/// Returns the range of the object at `time` in the message line at `line`, such as `#00111:0001`.
+fn object_range(line: &Range<usize>, time: ObjTime) -> Option<Range<usize>> {
+    const HEAD: usize = "#xxxyy:".len();
+    let pairs = line.len().checked_sub(HEAD)? / 2;
+    object_times(time.track().0, pairs as u64)
+        .position(|object_time| object_time == time)
+        .map(|index| {
+            let start = line.start + HEAD + index * 2;
+            start..start + 2
+        })
+}
+
+fn first(ranges: &[Range<usize>]) -> Option<Range<usize>> {
+    ranges.first().cloned()
+}
@@ -0,0 +1,360 @@
+//! Long note pairing checks, to find the broken pairs of the long notes.
+//!
+//! The model keeps only the objects paired up already, so [`LongNoteProcessor`] replays the pairing from the tokens as an extension token processor. Each lane, which is a pair of [`PlayerSide`] and [`Key`] decoded by the key layout, is paired separately:
+//!
+//! - `#LNTYPE 1` (default): the objects on the long note channels pair up as the start and the end in order, same as [`Bms::process`](crate::chart::process::Process).
+//! - `#LNTYPE 2`: a run of the consecutive objects on the long note channels is a long note, closed by the next `00` object.
+//! - `#LNOBJ wav_id`: an object of `wav_id` on the visible channels ends the long note started by the previous visible object.
+//!
+//! ```
+//! use bms_rs::bms::{long_note::{LongNoteIssue, LongNoteProcessor}, prelude::*};
+//!
+//! let source = "#00151:01000100\n#00251:01\n";
+//! let output = parse_bms_with_extension::<KeyLayoutBeat, _, _, _, _>(
+//!     source,
+//!     default_config().override_token_processor(LongNoteProcessor::<KeyLayoutBeat>::new()),
+//! );
+//! let checked = output.extensions.expect("parse must succeed");
+//!
+//! let [issue] = checked.issues.as_slice() else {
+//!     panic!("expected one issue");
+//! };
+//! assert!(matches!(issue.content(), LongNoteIssue::Unclosed { .. }));
+//! assert_eq!(&source[issue.range().clone()], "#00251:01");
+//! ```
+//!
+//! Only the commands activated by `#RANDOM` and `#SWITCH` scopes are checked, same as the model.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    marker::PhantomData,
+    ops::Range,
+};
+
+use thiserror::Error;
+
+use crate::bms::{
+    command::{
+        LnType, ObjId,
+        channel::{Channel, mapper::KeyLayoutMapper},
+        mixin::SourceRangeMixin,
+        time::ObjTime,
+    },
+    cst::{is_base_62, message_objects},
+    lex::token::{Token, TokenWithRange},
+    parse::{
+        ParseErrorWithRange,
+        prompt::Prompter,
+        token_processor::{ProcessContext, TokenProcessor},
+    },
+};
+use crate::chart::types::{Key, NoteKind, PlayerSide};
+
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{SimpleSource, ToAriadne, build_report};
+#[cfg(feature = "diagnostics")]
+use ariadne::{Color, Report, ReportKind};
+
+/// A broken pair of the long notes.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LongNoteIssue {
+    /// A long note start has no end, such as the odd objects of `#LNTYPE 1` or the run of `#LNTYPE 2` not followed by `00`.
+    #[error("Long note starting at {time:?} is not closed (side={side:?}, key={key:?})")]
+    Unclosed {
+        /// Player side where the long note is placed.
+        side: PlayerSide,
+        /// Key lane where the long note is placed.
+        key: Key,
+        /// Start time of the long note.
+        time: ObjTime,
+    },
+    /// An `#LNOBJ` end has no visible object before it to start from.
+    #[error("Long note end by #LNOBJ at {time:?} has no start (side={side:?}, key={key:?})")]
+    EndWithoutStart {
+        /// Player side where the end is placed.
+        side: PlayerSide,
+        /// Key lane where the end is placed.
+        key: Key,
+        /// Time of the end.
+        time: ObjTime,
+    },
+    /// A long note starts inside another long note in the same lane.
+    #[error(
+        "Long note starting at {time:?} overlaps another long note (side={side:?}, key={key:?}; ln=[{other_start:?}..{other_end:?}])"
+    )]
+    Overlap {
+        /// Player side where the long notes are placed.
+        side: PlayerSide,
+        /// Key lane where the long notes are placed.
+        key: Key,
+        /// Start time of the overlapping long note.
+        time: ObjTime,
+        /// Start time of the overlapped long note.
+        other_start: ObjTime,
+        /// End time of the overlapped long note.
+        other_end: ObjTime,
+    },
+}
+
+impl LongNoteIssue {
+    /// Returns the time where the issue is found.
+    #[must_use]
+    pub const fn time(&self) -> ObjTime {
+        match self {
+            Self::Unclosed { time, .. }
+            | Self::EndWithoutStart { time, .. }
+            | Self::Overlap { time, .. } => *time,
+        }
+    }
+}
+
+/// A broken pair of the long notes with the range of the message line placing the object at [`LongNoteIssue::time`].
+pub type LongNoteIssueWithRange = SourceRangeMixin<LongNoteIssue>;
+
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for LongNoteIssueWithRange {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        build_report(
+            src,
+            ReportKind::Warning,
+            self.range().clone(),
+            "Long note issue",
+            self.content(),
+            Color::Yellow,
+        )
+    }
+}
+
+/// Output of [`LongNoteProcessor`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[must_use]
+pub struct LongNoteCheckOutput {
+    /// The broken pairs, sorted by the time.
+    pub issues: Vec<LongNoteIssueWithRange>,
+}
+
+impl LongNoteCheckOutput {
+    /// Returns whether all the long notes are paired.
+    #[must_use]
+    pub const fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// An extension token processor which checks the long note pairs decoded by the key layout `T`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct LongNoteProcessor<T>(PhantomData<fn() -> T>);
+
+impl<T> LongNoteProcessor<T> {
+    /// Creates a new processor.
+    #[must_use]
+    pub const fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<T> Default for LongNoteProcessor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T: KeyLayoutMapper> TokenProcessor for LongNoteProcessor<T> {
+    type Output = LongNoteCheckOutput;
+
+    fn process<P: Prompter>(
+        &self,
+        ctx: &mut ProcessContext<'_, '_, P>,
+    ) -> Result<Self::Output, ParseErrorWithRange> {
+        let tokens = ctx.take_input();
+        let case_sensitive_obj_id = tokens.iter().any(|token| {
+            matches!(token.content(), Token::Header { name, args } if is_base_62(name, args))
+        });
+        let mut ln_type = LnType::default();
+        let mut ln_obj_ids = HashSet::new();
+        let mut lanes: HashMap<(PlayerSide, Key), Lane<'_>> = HashMap::new();
+        for token in tokens {
+            match token.content() {
+                Token::Header { name, args } if name.eq_ignore_ascii_case("LNTYPE") => {
+                    ln_type = if args == "2" {
+                        LnType::Mgq
+                    } else {
+                        LnType::Rdm
+                    };
+                }
+                Token::Header { name, args } if name.eq_ignore_ascii_case("LNOBJ") => {
+                    if let Ok(id) = ObjId::try_from(args, case_sensitive_obj_id) {
+                        ln_obj_ids.insert(id);
+                    }
+                }
+                Token::Message {
+                    channel: Channel::Note { channel_id },
+                    ..
+                } => {
+                    if let Some(map) = T::from_channel_id(*channel_id) {
+                        lanes.entry((map.side(), map.key())).or_default().record(
+                            token,
+                            map.kind(),
+                            case_sensitive_obj_id,
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let mut issues: Vec<_> = lanes
+            .into_iter()
+            .flat_map(|((side, key), lane)| {
+                lane.check(ln_type, &ln_obj_ids)
+                    .into_iter()
+                    .map(move |(issue, range)| {
+                        SourceRangeMixin::new(issue.into_issue(side, key), range.clone())
+                    })
+            })
+            .collect();
+        issues.sort_by_key(|issue| (issue.content().time(), issue.start()));
+        Ok(LongNoteCheckOutput { issues })
+    }
+}
+
+/// The objects in a lane, keyed by the time.
+#[derive(Debug, Default)]
+struct Lane<'a> {
+    /// The objects on the visible channels.
+    visible: BTreeMap<ObjTime, (Option<ObjId>, &'a Range<usize>)>,
+    /// The objects on the long note channels, `None` for `00`.
+    long: BTreeMap<ObjTime, Option<&'a Range<usize>>>,
+}
+
+/// [`LongNoteIssue`] without the lane.
+enum LaneIssue {
+    Unclosed(ObjTime),
+    EndWithoutStart(ObjTime),
+    Overlap(ObjTime, ObjTime, ObjTime),
+}
+
+impl LaneIssue {
+    const fn into_issue(self, side: PlayerSide, key: Key) -> LongNoteIssue {
+        match self {
+            Self::Unclosed(time) => LongNoteIssue::Unclosed { side, key, time },
+            Self::EndWithoutStart(time) => LongNoteIssue::EndWithoutStart { side, key, time },
+            Self::Overlap(time, other_start, other_end) => LongNoteIssue::Overlap {
+                side,
+                key,
+                time,
+                other_start,
+                other_end,
+            },
+        }
+    }
+}
+
+impl<'a> Lane<'a> {
+    fn record(
+        &mut self,
+        token: &'a TokenWithRange<'_>,
+        kind: NoteKind,
+        case_sensitive_obj_id: bool,
+    ) {
+        let Token::Message { track, message, .. } = token.content() else {
+            return;
+        };
+        let range = token.range();
+        for (time, id) in message_objects(track.0, message) {
+            let is_null = id == b"00";
+            match kind {
+                NoteKind::Visible if !is_null => {
+                    let id = std::str::from_utf8(id)
+                        .ok()
+                        .and_then(|id| ObjId::try_from(id, case_sensitive_obj_id).ok());
+                    self.visible.insert(time, (id, range));
+                }
+                // An object overrides `00` placed at the same time by another line.
+                NoteKind::Long if is_null => {
+                    self.long.entry(time).or_insert(None);
+                }
+                NoteKind::Long => {
+                    self.long.insert(time, Some(range));
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn check(
+        &self,
+        ln_type: LnType,
+        ln_obj_ids: &HashSet<ObjId>,
+    ) -> Vec<(LaneIssue, &'a Range<usize>)> {
+        let mut issues = vec![];
+        let mut intervals = vec![];
+
+        match ln_type {
+            LnType::Rdm => {
+                let objects: Vec<_> = self
+                    .long
+                    .iter()
+                    .filter_map(|(&time, range)| range.map(|range| (time, range)))
+                    .collect();
+                for pair in objects.chunks(2) {
+                    match *pair {
+                        [(start, range), (end, _)] => intervals.push((start, end, range)),
+                        [(start, range)] => issues.push((LaneIssue::Unclosed(start), range)),
+                        _ => {}
+                    }
+                }
+            }
+            LnType::Mgq => {
+                let mut run = None;
+                for (&time, &object) in &self.long {
+                    match (object, run) {
+                        (Some(range), None) => run = Some((time, range)),
+                        (None, Some((start, range))) => {
+                            intervals.push((start, time, range));
+                            run = None;
+                        }
+                        _ => {}
+                    }
+                }
+                if let Some((start, range)) = run {
+                    issues.push((LaneIssue::Unclosed(start), range));
+                }
+            }
+        }
+
+        let mut head = None;
+        for (&time, &(id, range)) in &self.visible {
+            if id.is_some_and(|id| ln_obj_ids.contains(&id)) {
+                if let Some((start, head_range)) = head.take() {
+                    intervals.push((start, time, head_range));
+                } else {
+                    issues.push((LaneIssue::EndWithoutStart(time), range));
+                }
+            } else {
+                head = Some((time, range));
+            }
+        }
+
+        intervals.sort_by_key(|&(start, end, _)| (start, end));
+        let mut latest: Option<(ObjTime, ObjTime)> = None;
+        for (start, end, range) in intervals {
+            match latest {
+                Some((other_start, other_end)) if start <= other_end => {
+                    issues.push((LaneIssue::Overlap(start, other_start, other_end), range));
+                    if other_end < end {
+                        latest = Some((start, end));
+                    }
+                }
+                _ => latest = Some((start, end)),
+            }
+        }
+        issues
+    }
+}
@@ -4,7 +4,7 @@
 //! extended BGA commands like `#BGA`, `#@BGA`, `#SWBGA`, `#ARGB`, and opacity/color changes.
 
 use std::{
-    collections::{BTreeMap, HashMap, btree_map::Entry},
+    collections::{BTreeMap, HashMap, HashSet, btree_map::Entry},
     path::PathBuf,
 };
 
@@ -44,6 +44,10 @@ pub struct BmpObjects {
     pub bga_argb_changes: HashMap<BgaLayer, BTreeMap<ObjTime, BgaArgbObj>>,
     /// BGA keybound events, indexed by time. `#xxxA5:`
     pub bga_keybound_events: BTreeMap<ObjTime, BgaKeyboundObj>,
+    /// Record of used BMP ids from BGA messages, for validity checks.
+    pub bmp_ids_used: HashSet<ObjId>,
+    /// Record of used ARGB ids from `#ARGBxx` messages, for validity checks.
+    pub argb_ids_used: HashSet<ObjId>,
 }
 
 impl BmpObjects {
@@ -1,6 +1,6 @@
 //! This module introduces struct [`JudgeObjects`], which manages internal setting values to score plays.
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 use strict_num_extended::FinF64;
 
@@ -22,6 +22,8 @@ pub struct JudgeObjects {
     pub exrank_defs: HashMap<ObjId, ExRankDef>,
     /// Judge events, indexed by time. `#xxxA0:`
     pub judge_events: BTreeMap<ObjTime, JudgeObj>,
+    /// Record of used EXRANK ids from `#EXRANKxx` messages, for validity checks.
+    pub exrank_ids_used: HashSet<ObjId>,
 }
 
 impl JudgeObjects {
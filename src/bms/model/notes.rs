@@ -351,6 +351,11 @@ impl Notes {
     }
 
     fn remove_index(&mut self, idx: usize, removing: &WavObj) {
+        if let Some(indexes) = self.idx_by_wav_id.get_mut(&removing.wav_id)
+            && let Some(pos) = indexes.iter().position(|id| id.0 == idx)
+        {
+            indexes.swap_remove(pos);
+        }
         let channel_id = removing.channel_id;
         if let Some(indexes) = self.idx_by_channel.get_mut(&channel_id)
             && let Some(pos) = indexes.iter().position(|id| id.0 == idx)
@@ -1,6 +1,6 @@
 //! This module introduces struct [`ScrollObjects`], which manages definitions and events of scroll speed change.
 
-use std::collections::{BTreeMap, HashMap, btree_map::Entry};
+use std::collections::{BTreeMap, HashMap, HashSet, btree_map::Entry};
 
 use strict_num_extended::FinF64;
 
@@ -18,6 +18,8 @@ pub struct ScrollObjects {
     pub scroll_defs: HashMap<ObjId, StringValue<FinF64>>,
     /// The scrolling factors corresponding to the id of the scroll speed change object.
     pub scrolling_factor_changes: BTreeMap<ObjTime, ScrollingFactorObj>,
+    /// Record of used SCROLL ids from `#SCROLLxx` messages, for validity checks.
+    pub scroll_ids_used: HashSet<ObjId>,
 }
 
 impl ScrollObjects {
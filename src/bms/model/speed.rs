@@ -1,6 +1,6 @@
 //! This module introduces struct [`SpeedObjects`], which manages definitions and events of spacing change.
 
-use std::collections::{BTreeMap, HashMap, btree_map::Entry};
+use std::collections::{BTreeMap, HashMap, HashSet, btree_map::Entry};
 
 use strict_num_extended::PositiveF64;
 
@@ -18,6 +18,8 @@ pub struct SpeedObjects {
     pub speed_defs: HashMap<ObjId, StringValue<PositiveF64>>,
     /// The spacing factors corresponding to the id of the spacing change object.
     pub speed_factor_changes: BTreeMap<ObjTime, SpeedObj>,
+    /// Record of used SPEED ids from `#SPEEDxx` messages, for validity checks.
+    pub speed_ids_used: HashSet<ObjId>,
 }
 
 impl SpeedObjects {
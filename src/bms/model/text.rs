@@ -1,6 +1,6 @@
 //! This module introduces struct [`TextObjects`], which manages definitions and events of caption texts.
 
-use std::collections::{BTreeMap, HashMap, btree_map::Entry};
+use std::collections::{BTreeMap, HashMap, HashSet, btree_map::Entry};
 
 use crate::bms::{
     parse::{Result, prompt::ChannelDuplication},
@@ -16,6 +16,8 @@ pub struct TextObjects {
     pub texts: HashMap<ObjId, String>,
     /// Text events, indexed by time. `#xxx99:`
     pub text_events: BTreeMap<ObjTime, TextObj>,
+    /// Record of used TEXT ids from `#TEXTxx` messages, for validity checks.
+    pub text_ids_used: HashSet<ObjId>,
 }
 
 impl TextObjects {
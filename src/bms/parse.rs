@@ -6,11 +6,19 @@
 //! This module also defines enums of errors and warnings on parse process.
 
 pub mod check_playing;
+pub mod outcomes;
+pub mod probability;
 pub mod prompt;
+pub mod timing;
 pub mod token_processor;
+pub mod total;
 pub mod validity;
 
-use std::ops::RangeInclusive;
+use std::{
+    hash::{Hash, Hasher},
+    ops::RangeInclusive,
+    sync::Arc,
+};
 
 use num::BigUint;
 use thiserror::Error;
@@ -28,6 +36,7 @@ use crate::bms::{
         mixin::SourceRangeMixin,
         time::{ObjTime, Track},
     },
+    fix::Fix,
     lex::token::TokenWithRange,
     model::Bms,
     rng::Rng,
@@ -82,6 +91,7 @@ impl ToAriadne for ParseErrorWithRange {
 }
 
 /// A warning occurred when parsing the [`super::lex::TokenStream`].
+#[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ParseWarning {
@@ -91,9 +101,16 @@ pub enum ParseWarning {
     /// The object has required but not defined,
     #[error("undefined object: {0:?}")]
     UndefinedObject(ObjId),
-    /// Has duplicated definition, that `prompt_handler` returned [`prompt::DuplicationWorkaround::WarnAndUseOlder`].
-    #[error("duplicating definition: {0}")]
-    DuplicatingDef(ObjId),
+    /// Has duplicated definition, that `prompt_handler` returned [`prompt::DuplicationWorkaround::WarnAndUseOlder`] or [`prompt::DuplicationWorkaround::WarnAndUseNewer`].
+    #[error("duplicating definition: {id}")]
+    DuplicatingDef {
+        /// The duplicated object id.
+        id: ObjId,
+        /// Whether the newer definition is used, that is, the older one is overridden.
+        newer_used: bool,
+        /// The fix removing the unused definition line. See [`ParseWarning::fix`].
+        fix: Option<Fix>,
+    },
     /// Has duplicated track object, that `prompt_handler` returned [`prompt::DuplicationWorkaround::WarnAndUseOlder`].
     #[error("duplicating track object: {0} {1}")]
     DuplicatingTrackObj(Track, Channel),
@@ -103,9 +120,99 @@ pub enum ParseWarning {
     /// Failed to convert a byte into a base-62 character `0-9A-Za-z`.
     #[error("expected id format is base 62 (`0-9A-Za-z`)")]
     OutOfBase62,
+    /// The command was misspelled, and read as the `relaxed` one by [`DefaultTokenRelaxer`](token_processor::DefaultTokenRelaxer).
+    #[error("misspelled command, read as `{relaxed}`")]
+    MisspelledCommand {
+        /// The command read instead.
+        relaxed: String,
+        /// The fix replacing the command with the relaxed one. See [`ParseWarning::fix`].
+        fix: Option<Fix>,
+    },
+    /// A warning reported by an extension token processor registered to [`ParseConfig`].
+    #[error("extension: {0}")]
+    Extension(ExtensionWarning),
+}
+
+impl ParseWarning {
+    /// Returns the fix of the warning, if any.
+    ///
+    /// The fixes need the source text, so they are attached by the functions parsing it such as [`parse_bms`](crate::bms::parse_bms), and not by [`Bms::from_token_stream`]. The fix of [`ParseWarning::DuplicatingDef`] is attached only if the definitions are out of the control flow scopes, because the other branches may use the one looking unused.
+    #[must_use]
+    pub const fn fix(&self) -> Option<&Fix> {
+        match self {
+            Self::DuplicatingDef { fix, .. } | Self::MisspelledCommand { fix, .. } => fix.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// A warning of an extension token processor, which keeps the typed error of the processor.
+///
+/// Two warnings are equal if their messages are equal, because the type of the error cannot be compared. Deserializing keeps only the message.
+#[derive(Debug, Clone, Error)]
+#[error(transparent)]
+pub struct ExtensionWarning(Arc<dyn std::error::Error + Send + Sync>);
+
+impl ExtensionWarning {
+    /// Creates a new warning from the error of the extension.
+    pub fn new(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        Self(Arc::new(error))
+    }
+
+    /// Returns the error of the extension.
+    #[must_use]
+    pub fn error(&self) -> &(dyn std::error::Error + Send + Sync + 'static) {
+        self.0.as_ref()
+    }
+
+    /// Returns the error of the extension if it is of the type `E`.
+    #[must_use]
+    pub fn downcast_ref<E: std::error::Error + 'static>(&self) -> Option<&E> {
+        self.0.downcast_ref()
+    }
+}
+
+impl PartialEq for ExtensionWarning {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl Eq for ExtensionWarning {}
+
+impl Hash for ExtensionWarning {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.to_string().hash(state);
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ExtensionWarning {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> core::result::Result<S::Ok, S::Error> {
+        serializer.collect_str(&self.0)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ExtensionWarning {
+    fn deserialize<D: serde::Deserializer<'de>>(
+        deserializer: D,
+    ) -> core::result::Result<Self, D::Error> {
+        /// The error which has only the deserialized message.
+        #[derive(Debug, Error)]
+        #[error("{0}")]
+        struct Message(String);
+
+        String::deserialize(deserializer).map(|message| Self::new(Message(message)))
+    }
 }
 
 /// A parse warning with position information.
+///
+/// Some warnings carry a [`Fix`], returned by [`ParseWarning::fix`].
 pub type ParseWarningWithRange = SourceRangeMixin<ParseWarning>;
 
 /// Result type for parse operations with `ParseWarning`.
@@ -119,10 +226,13 @@ impl ToAriadne for ParseWarningWithRange {
     ) -> Report<'a, (String, std::ops::Range<usize>)> {
         let (start, end) = self.as_span();
         let filename = src.name().to_string();
-        Report::build(ReportKind::Warning, (filename.clone(), start..end))
+        let mut report = Report::build(ReportKind::Warning, (filename.clone(), start..end))
             .with_message(format!("parse: {}", self.content()))
-            .with_label(Label::new((filename, start..end)).with_color(Color::Blue))
-            .finish()
+            .with_label(Label::new((filename, start..end)).with_color(Color::Blue));
+        if let Some(fix) = self.content().fix() {
+            report.set_help(format!("fix: {}", fix.message));
+        }
+        report.finish()
     }
 }
 
@@ -137,19 +247,60 @@ pub struct ParseOutput {
     pub parse_warnings: Vec<ParseWarningWithRange>,
 }
 
+/// Output of [`Bms::from_token_stream_with_extension`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct ExtendedParseOutput<E> {
+    /// The output Bms.
+    pub bms: core::result::Result<Bms, ParseErrorWithRange>,
+    /// The output of the extension token processors, or `None` if parsing failed.
+    pub extensions: Option<E>,
+    /// Warnings that occurred during parsing.
+    pub parse_warnings: Vec<ParseWarningWithRange>,
+}
+
+impl<E> From<ExtendedParseOutput<E>> for ParseOutput {
+    fn from(output: ExtendedParseOutput<E>) -> Self {
+        Self {
+            bms: output.bms,
+            parse_warnings: output.parse_warnings,
+        }
+    }
+}
+
 impl Bms {
     /// Parses a token stream into [`Bms`] without AST.
     pub fn from_token_stream<'a, T: KeyLayoutMapper, P: Prompter, R: Rng, M: TokenModifier>(
         token_iter: impl IntoIterator<Item = &'a TokenWithRange<'a>>,
         config: ParseConfig<T, P, R, M>,
     ) -> ParseOutput {
+        Self::from_token_stream_with_extension(token_iter, config).into()
+    }
+
+    /// Parses a token stream into [`Bms`] without AST, and returns also the output of the extension token processor of `config`.
+    pub fn from_token_stream_with_extension<
+        'a,
+        T: KeyLayoutMapper,
+        P: Prompter,
+        R: Rng,
+        M: TokenModifier,
+        X: TokenProcessor,
+    >(
+        token_iter: impl IntoIterator<Item = &'a TokenWithRange<'a>>,
+        config: ParseConfig<T, P, R, M, X>,
+    ) -> ExtendedParseOutput<X::Output> {
         let tokens: Vec<_> = token_iter.into_iter().collect();
         let mut tokens_slice = tokens.as_slice();
         let (proc, prompter) = config.build();
         let mut ctx = ProcessContext::new(&mut tokens_slice, &prompter);
-        let res = proc.process(&mut ctx);
-        ParseOutput {
-            bms: res,
+        let (bms, extensions) = match proc.process(&mut ctx) {
+            Ok((bms, extensions)) => (Ok(bms), Some(extensions)),
+            Err(error) => (Err(error), None),
+        };
+        ExtendedParseOutput {
+            bms,
+            extensions,
             parse_warnings: ctx.into_warnings(),
         }
     }
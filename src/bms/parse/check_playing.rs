@@ -4,6 +4,7 @@ use thiserror::Error;
 
 use crate::bms::command::ObjId;
 use crate::bms::command::channel::mapper::KeyLayoutMapper;
+use crate::bms::command::mixin::SourceRangeMixin;
 
 use crate::bms::model::Bms;
 
@@ -101,13 +102,19 @@ pub enum PlayingError {
     NoNotes,
 }
 
+/// A playing warning with position information, located by [`SourceMap`](crate::bms::source_map::SourceMap).
+pub type PlayingWarningWithRange = SourceRangeMixin<PlayingWarning>;
+
+/// A playing error with position information, located by [`SourceMap`](crate::bms::source_map::SourceMap).
+pub type PlayingErrorWithRange = SourceRangeMixin<PlayingError>;
+
 #[cfg(feature = "diagnostics")]
 impl ToAriadne for PlayingWarning {
     fn to_report<'a>(
         &self,
         src: &SimpleSource<'a>,
     ) -> Report<'a, (String, std::ops::Range<usize>)> {
-        // Playing warnings lack precise source positions; anchor at file start.
+        // Playing warnings lack precise source positions; anchor at file start. Use `PlayingWarningWithRange` to point the lines.
         build_report(
             src,
             ReportKind::Warning,
@@ -125,7 +132,7 @@ impl ToAriadne for PlayingError {
         &self,
         src: &SimpleSource<'a>,
     ) -> Report<'a, (String, std::ops::Range<usize>)> {
-        // Playing errors lack precise source positions; anchor at file start.
+        // Playing errors lack precise source positions; anchor at file start. Use `PlayingErrorWithRange` to point the lines.
         build_report(
             src,
             ReportKind::Error,
@@ -137,6 +144,40 @@ impl ToAriadne for PlayingError {
     }
 }
 
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for PlayingWarningWithRange {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        build_report(
+            src,
+            ReportKind::Warning,
+            self.range().clone(),
+            "Playing warning",
+            self.content(),
+            Color::Yellow,
+        )
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for PlayingErrorWithRange {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        build_report(
+            src,
+            ReportKind::Error,
+            self.range().clone(),
+            "Playing error",
+            self.content(),
+            Color::Red,
+        )
+    }
+}
+
 /// Output of checking for playing warnings and errors.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
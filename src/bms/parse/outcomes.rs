@@ -0,0 +1,170 @@
+//! Exhaustive enumeration of the charts which `#RANDOM` and `#SWITCH` commands can produce.
+//!
+//! [`Bms::enumerate_outcomes`] parses the token stream for every combination of the generated values of the activated `#RANDOM` and `#SWITCH` commands, by a depth-first search over the values. The parser also generates a value for every command in the inactive scopes, which never affects the chart, so such a value is fixed to `1` instead of being enumerated. To tell them apart, the generator of the enumeration generates `0`, out of the range, for a command not visited yet, which the parser rejects only if the command is activated. `#SETRANDOM` and `#SETSWITCH` do not generate values.
+//!
+//! The identical charts are merged into one [`RandomOutcome`], so [`Bms::check_playing`] and [`Bms::check_validity`] can run on every variant.
+
+use std::{cell::RefCell, ops::RangeInclusive, rc::Rc};
+
+use num::{BigRational, BigUint, One, Zero};
+
+use crate::bms::{
+    command::channel::mapper::KeyLayoutMapper,
+    default_config_with_rng,
+    lex::token::TokenWithRange,
+    model::Bms,
+    parse::{ParseError, ParseErrorWithRange, ParseOutput, prompt::Prompter},
+    rng::Rng,
+};
+
+/// A concrete chart produced by the `#RANDOM` and `#SWITCH` commands.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RandomOutcome {
+    /// The parsed chart.
+    pub bms: Bms,
+    /// The combinations of the generated values producing this chart. Each combination lists the values in order the parser generates them, so replaying it by an [`Rng`] reproduces the chart. The values for the commands in the inactive scopes are always `1`.
+    pub choices: Vec<Vec<BigUint>>,
+    /// The probability of this chart, that is the sum of the probabilities of [`Self::choices`]. Each activated `#RANDOM n` and `#SWITCH n` generates the values uniformly.
+    pub probability: BigRational,
+}
+
+/// Output of [`Bms::enumerate_outcomes`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct RandomOutcomes {
+    /// The distinct charts in order of the first combination producing them.
+    pub outcomes: Vec<RandomOutcome>,
+    /// Whether the enumeration stopped at the limit before visiting all the combinations.
+    pub truncated: bool,
+}
+
+/// A value generated for a command, with the maximum if the command is activated.
+#[derive(Debug, Clone)]
+struct Generated {
+    value: BigUint,
+    activated_max: Option<BigUint>,
+}
+
+/// The generator replaying `prefix` and then generating `0` to find the next activated command, recording the generated values and their ranges.
+struct TracingRng {
+    prefix: Vec<BigUint>,
+    trace: Rc<RefCell<Vec<RangeInclusive<BigUint>>>>,
+}
+
+impl Rng for TracingRng {
+    fn generate(&mut self, range: RangeInclusive<BigUint>) -> BigUint {
+        let mut trace = self.trace.borrow_mut();
+        let value = self
+            .prefix
+            .get(trace.len())
+            .cloned()
+            .unwrap_or_else(BigUint::zero);
+        trace.push(range);
+        value
+    }
+}
+
+impl Bms {
+    /// Parses a token stream into every distinct chart which the `#RANDOM` and `#SWITCH` commands can produce. See [the module document](self) for details.
+    ///
+    /// At most `limit` combinations are parsed, and [`RandomOutcomes::truncated`] is set if some are left.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`ParseErrorWithRange`] if parsing fails on any combination.
+    pub fn enumerate_outcomes<'a, T: KeyLayoutMapper, P: Prompter + Clone>(
+        token_iter: impl IntoIterator<Item = &'a TokenWithRange<'a>>,
+        prompter: &P,
+        limit: usize,
+    ) -> Result<RandomOutcomes, ParseErrorWithRange> {
+        let tokens: Vec<_> = token_iter.into_iter().collect();
+        let parse = |generated: &[Generated]| {
+            let trace = Rc::new(RefCell::new(Vec::new()));
+            let rng = TracingRng {
+                prefix: generated.iter().map(|g| g.value.clone()).collect(),
+                trace: Rc::clone(&trace),
+            };
+            let config = default_config_with_rng(rng)
+                .key_mapper::<T>()
+                .prompter(prompter.clone());
+            let ParseOutput { bms, .. } =
+                Self::from_token_stream::<'_, T, _, _, _>(tokens.iter().copied(), config);
+            (bms, trace.take())
+        };
+
+        let mut outcomes: Vec<RandomOutcome> = Vec::new();
+        let mut generated: Vec<Generated> = Vec::new();
+        for _ in 0..limit {
+            // Parse until no command rejects `0`, that is, the rest of the commands are inactive.
+            let bms = loop {
+                let (bms, trace) = parse(&generated);
+                let visited = generated.len();
+                let rejected = match &bms {
+                    Err(error)
+                        if trace.len() > visited
+                            && matches!(
+                                error.content(),
+                                ParseError::RandomGeneratedValueOutOfRange { actual, .. }
+                                    | ParseError::SwitchGeneratedValueOutOfRange { actual, .. }
+                                    if actual.is_zero()
+                            ) =>
+                    {
+                        trace.last().cloned()
+                    }
+                    _ => None,
+                };
+                let inactive_until = trace.len() - usize::from(rejected.is_some());
+                generated.extend((visited..inactive_until).map(|_| Generated {
+                    value: BigUint::one(),
+                    activated_max: None,
+                }));
+                let Some(range) = rejected else {
+                    break bms?;
+                };
+                generated.push(Generated {
+                    value: range.start().clone(),
+                    activated_max: Some(range.end().clone()),
+                });
+            };
+
+            let choice: Vec<BigUint> = generated.iter().map(|g| g.value.clone()).collect();
+            let probability = generated
+                .iter()
+                .filter_map(|g| g.activated_max.as_ref())
+                .fold(BigRational::one(), |probability, max| {
+                    probability / BigRational::from_integer(max.clone().into())
+                });
+            if let Some(outcome) = outcomes.iter_mut().find(|outcome| outcome.bms == bms) {
+                outcome.choices.push(choice);
+                outcome.probability += probability;
+            } else {
+                outcomes.push(RandomOutcome {
+                    bms,
+                    choices: vec![choice],
+                    probability,
+                });
+            }
+
+            // Advance the last activated value which has not reached its maximum, and drop the following ones.
+            let Some(last) = generated
+                .iter()
+                .rposition(|g| g.activated_max.as_ref().is_some_and(|max| &g.value < max))
+            else {
+                return Ok(RandomOutcomes {
+                    outcomes,
+                    truncated: false,
+                });
+            };
+            generated.truncate(last + 1);
+            if let Some(g) = generated.last_mut() {
+                g.value += 1u64;
+            }
+        }
+        Ok(RandomOutcomes {
+            outcomes,
+            truncated: true,
+        })
+    }
+}
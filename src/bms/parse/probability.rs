@@ -0,0 +1,174 @@
+//! Probability-weighted analysis of the charts which `#RANDOM` and `#SWITCH` commands can produce.
+//!
+//! [`Bms::branch_probabilities`] walks the tree of [`Bms::randomized`] and computes the probability that each branch is activated: every branch of `#RANDOM n` and `#SWITCH n` has `1/n`, the branch matching `#SETRANDOM` and `#SETSWITCH` has `1`, and a branch under another one is multiplied by the probability of its parent. The branches of `#ELSE` and `#DEF` cover the values not matched by the other branches, and a `#SWITCH` does not fall through to the next `#CASE`, the same as the parser.
+//!
+//! The notes and definitions in a branch appear with the probability of the branch. If the same one appears in some branches, their probabilities are summed. The ones outside of the random scopes appear with the probability `1`.
+
+use std::collections::{HashMap, HashSet};
+
+use num::{BigInt, BigRational, BigUint, One, Zero};
+
+use crate::bms::{
+    command::{ObjId, channel::mapper::KeyLayoutMapper},
+    cst::split_definition,
+    lex::token::Token,
+    model::{
+        Bms,
+        control_flow::{ControlFlowValue, RandomizedObjects},
+        obj::WavObj,
+    },
+};
+
+/// The probability that a note appears.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NoteProbability {
+    /// The note object.
+    pub note: WavObj,
+    /// The probability that the note appears.
+    pub probability: BigRational,
+}
+
+/// The probability that a definition such as `#WAVxx` appears with the arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct DefinitionProbability {
+    /// The upper-cased command of the definition, such as `WAV` for `#WAVxx`.
+    pub command: String,
+    /// The defined object id.
+    pub id: ObjId,
+    /// The arguments of the definition.
+    pub args: String,
+    /// The probability that the definition appears.
+    pub probability: BigRational,
+}
+
+/// Output of [`Bms::branch_probabilities`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[must_use]
+pub struct BranchProbabilities {
+    /// The notes in time order.
+    pub notes: Vec<NoteProbability>,
+    /// The definitions in order of the command and the id.
+    pub definitions: Vec<DefinitionProbability>,
+    /// The expected number of the playable notes.
+    pub expected_playable_notes: BigRational,
+}
+
+type Definition = (String, ObjId, String);
+
+/// The probabilities summed up over the branches.
+#[derive(Default)]
+struct Accumulator<'a> {
+    notes: HashMap<&'a WavObj, BigRational>,
+    definitions: HashMap<Definition, BigRational>,
+    expected_playable_notes: BigRational,
+}
+
+impl<'a> Accumulator<'a> {
+    fn visit<T: KeyLayoutMapper>(&mut self, objects: &'a RandomizedObjects, reach: &BigRational) {
+        for branch in objects.branches() {
+            let probability = reach * branch_probability(objects.generating(), branch.condition());
+            for note in branch.sub().notes().all_notes() {
+                *self.notes.entry(note).or_insert_with(BigRational::zero) += &probability;
+            }
+            for definition in definitions_of::<T>(branch.sub()) {
+                *self
+                    .definitions
+                    .entry(definition)
+                    .or_insert_with(BigRational::zero) += &probability;
+            }
+            let playables = branch.sub().notes().playables::<T>().count();
+            self.expected_playable_notes +=
+                &probability * BigRational::from_integer(playables.into());
+            for nested in &branch.sub().randomized {
+                self.visit::<T>(nested, &probability);
+            }
+        }
+    }
+}
+
+/// The probability that the branch of `condition` is activated when its random scope is activated.
+fn branch_probability(generating: Option<&ControlFlowValue>, condition: &BigUint) -> BigRational {
+    match generating {
+        Some(ControlFlowValue::GenMax(max))
+            if (BigUint::one()..=max.clone()).contains(condition) =>
+        {
+            BigRational::new(BigInt::one(), max.clone().into())
+        }
+        Some(ControlFlowValue::Set(value)) if value == condition => BigRational::one(),
+        _ => BigRational::zero(),
+    }
+}
+
+/// The definitions in the chart, excluding the ones in its random scopes.
+fn definitions_of<T: KeyLayoutMapper>(bms: &Bms) -> impl Iterator<Item = Definition> {
+    bms.unparse::<T>().into_iter().filter_map(|token| {
+        let Token::Header { name, args } = token else {
+            return None;
+        };
+        let (command, id) = split_definition(&name)?;
+        let id = ObjId::try_from(id, true).ok()?;
+        Some((command.to_string(), id, args.into_owned()))
+    })
+}
+
+impl Bms {
+    /// Computes the probability that each note and definition appears. See [the module document](crate::bms::parse::probability) for details.
+    pub fn branch_probabilities<T: KeyLayoutMapper>(&self) -> BranchProbabilities {
+        let mut accumulator = Accumulator::default();
+        for objects in &self.randomized {
+            accumulator.visit::<T>(objects, &BigRational::one());
+        }
+        let Accumulator {
+            mut notes,
+            mut definitions,
+            mut expected_playable_notes,
+        } = accumulator;
+
+        // The chart contains the activated branches too, so only the rest is outside of the random scopes.
+        let branch_notes: HashSet<_> = notes.keys().copied().collect();
+        for note in self.notes().all_notes() {
+            if !branch_notes.contains(note) {
+                notes.insert(note, BigRational::one());
+            }
+        }
+        let outside_playables = self
+            .notes()
+            .playables::<T>()
+            .filter(|note| !branch_notes.contains(note))
+            .count();
+        expected_playable_notes += BigRational::from_integer(outside_playables.into());
+        let branch_definitions: HashSet<_> = definitions.keys().cloned().collect();
+        for definition in definitions_of::<T>(self) {
+            if !branch_definitions.contains(&definition) {
+                definitions.insert(definition, BigRational::one());
+            }
+        }
+
+        let mut notes: Vec<_> = notes
+            .into_iter()
+            .map(|(note, probability)| NoteProbability {
+                note: note.clone(),
+                probability,
+            })
+            .collect();
+        notes.sort_by(|a, b| a.note.cmp(&b.note));
+        let mut definitions: Vec<_> = definitions
+            .into_iter()
+            .map(|((command, id, args), probability)| DefinitionProbability {
+                command,
+                id,
+                args,
+                probability,
+            })
+            .collect();
+        definitions.sort_by(|a, b| (&a.command, a.id, &a.args).cmp(&(&b.command, b.id, &b.args)));
+        BranchProbabilities {
+            notes,
+            definitions,
+            expected_playable_notes,
+        }
+    }
+}
@@ -366,10 +366,18 @@ impl DuplicationWorkaround {
                 *target = newer;
                 Ok(())
             }
-            Self::WarnAndUseOlder => Err(ParseWarning::DuplicatingDef(id)),
+            Self::WarnAndUseOlder => Err(ParseWarning::DuplicatingDef {
+                id,
+                newer_used: false,
+                fix: None,
+            }),
             Self::WarnAndUseNewer => {
                 *target = newer;
-                Err(ParseWarning::DuplicatingDef(id))
+                Err(ParseWarning::DuplicatingDef {
+                    id,
+                    newer_used: true,
+                    fix: None,
+                })
             }
         }
     }
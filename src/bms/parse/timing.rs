@@ -0,0 +1,281 @@
+//! Semantic checks of the timing objects, such as extreme BPMs and huge STOPs, which break some players.
+//!
+//! The values failing to parse are reported by [`Bms::check_playing`] instead.
+//!
+//! ```
+//! use bms_rs::bms::{
+//!     lint::LintSeverity,
+//!     parse::timing::{TimingCheckConfig, TimingWarningKind},
+//!     prelude::*,
+//! };
+//!
+//! let source = "#BPM 120\n#BPM01 99999\n#SCROLL01 -1\n#00108:0001\n#001SC:01\n";
+//! let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+//!     .bms
+//!     .unwrap();
+//! let warnings = bms.check_timing::<KeyLayoutBeat>(&TimingCheckConfig::default());
+//! assert_eq!(warnings.len(), 2);
+//! assert!(matches!(warnings[0].kind, TimingWarningKind::NegativeScroll { .. }));
+//! assert!(matches!(warnings[1].kind, TimingWarningKind::BpmTooHigh { .. }));
+//! assert_eq!(warnings[1].time, ObjTime::new(1, 1, 2).unwrap());
+//! assert_eq!(warnings[1].severity, LintSeverity::Warning);
+//! ```
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::bms::{
+    command::{
+        channel::{Channel, NoteChannelId, mapper::KeyLayoutMapper},
+        time::ObjTime,
+    },
+    lint::LintSeverity,
+    model::Bms,
+};
+
+/// Configuration of [`Bms::check_timing`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TimingCheckConfig {
+    /// The lowest BPM not reported, defaults to `10.0`.
+    pub min_bpm: f64,
+    /// The highest BPM not reported, defaults to `1000.0`.
+    pub max_bpm: f64,
+    /// The longest STOP in seconds not reported, defaults to `10.0`.
+    pub max_stop_seconds: f64,
+    /// The shortest section length in measures not reported, defaults to `0.01`.
+    pub min_section_len: f64,
+    /// The lowest spacing factor of `#SPEED` not reported, defaults to `0.01`.
+    pub min_speed: f64,
+}
+
+impl Default for TimingCheckConfig {
+    fn default() -> Self {
+        Self {
+            min_bpm: 10.0,
+            max_bpm: 1000.0,
+            max_stop_seconds: 10.0,
+            min_section_len: 0.01,
+            min_speed: 0.01,
+        }
+    }
+}
+
+/// The kind of a [`TimingWarning`].
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TimingWarningKind {
+    /// The BPM is higher than [`TimingCheckConfig::max_bpm`].
+    #[error("BPM {bpm} is too high")]
+    BpmTooHigh {
+        /// The BPM to be.
+        bpm: f64,
+        /// The channel of the BPM change, or `None` for the `#BPM` header.
+        channel: Option<Channel>,
+    },
+    /// The BPM is lower than [`TimingCheckConfig::min_bpm`].
+    #[error("BPM {bpm} is too low")]
+    BpmTooLow {
+        /// The BPM to be.
+        bpm: f64,
+        /// The channel of the BPM change, or `None` for the `#BPM` header.
+        channel: Option<Channel>,
+    },
+    /// The STOP is longer than [`TimingCheckConfig::max_stop_seconds`] at the BPM there.
+    #[error("STOP lasts {seconds:.1} seconds")]
+    StopTooLong {
+        /// The duration of the STOP in seconds.
+        seconds: f64,
+    },
+    /// The scrolling factor is negative, so the notes scroll backwards.
+    #[error("scrolling factor {factor} is negative")]
+    NegativeScroll {
+        /// The scrolling factor to be.
+        factor: f64,
+    },
+    /// The scrolling factor is zero, so the notes freeze.
+    #[error("scrolling factor is zero")]
+    ZeroScroll,
+    /// The spacing factor is lower than [`TimingCheckConfig::min_speed`], so the notes overlap.
+    #[error("spacing factor {factor} is nearly zero")]
+    SpeedNearZero {
+        /// The spacing factor to be.
+        factor: f64,
+    },
+    /// The section length is shorter than [`TimingCheckConfig::min_section_len`], zero or negative.
+    #[error("section length {length} is nearly zero")]
+    SectionLenTooShort {
+        /// The section length in measures.
+        length: f64,
+    },
+    /// A playable note is placed at the same time as a STOP, so the players differ whether it is judged before or after the STOP.
+    #[error("note on channel {channel_id} is placed during a STOP")]
+    NoteDuringStop {
+        /// The channel of the note.
+        channel_id: NoteChannelId,
+    },
+}
+
+impl TimingWarningKind {
+    /// Returns the severity of the kind. The lengths which are zero or negative are errors, and the gimmicks which most players support are informational.
+    #[must_use]
+    pub fn severity(&self) -> LintSeverity {
+        match self {
+            Self::SectionLenTooShort { length } if *length <= 0.0 => LintSeverity::Error,
+            Self::NegativeScroll { .. } | Self::ZeroScroll | Self::NoteDuringStop { .. } => {
+                LintSeverity::Info
+            }
+            _ => LintSeverity::Warning,
+        }
+    }
+}
+
+/// A timing object which breaks some players, found by [`Bms::check_timing`].
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[error("{kind} at {time}")]
+pub struct TimingWarning {
+    /// The time of the object. The `#BPM` header is at the start of the track 000.
+    pub time: ObjTime,
+    /// The severity of the problem.
+    pub severity: LintSeverity,
+    /// The kind of the problem.
+    pub kind: TimingWarningKind,
+}
+
+impl TimingWarning {
+    /// Creates a new warning with the severity of `kind`.
+    #[must_use]
+    pub fn new(time: ObjTime, kind: TimingWarningKind) -> Self {
+        Self {
+            time,
+            severity: kind.severity(),
+            kind,
+        }
+    }
+}
+
+impl Bms {
+    /// Checks the BPMs, STOPs, scrolling factors, spacing factors, section lengths and the notes during STOPs by `config`.
+    ///
+    /// The notes are decoded by `T`. The warnings are sorted by the time.
+    #[must_use]
+    pub fn check_timing<T: KeyLayoutMapper>(
+        &self,
+        config: &TimingCheckConfig,
+    ) -> Vec<TimingWarning> {
+        let bpms = self.bpm_timeline();
+        let initial_bpm = self
+            .bpm
+            .bpm
+            .as_ref()
+            .and_then(|bpm| bpm.value().as_ref().ok())
+            .map(|bpm| bpm.as_f64());
+        let mut warnings = vec![];
+
+        let header = initial_bpm.map(|bpm| (ObjTime::start_of(0.into()), bpm, None));
+        let changes = bpms
+            .iter()
+            .map(|(&time, &(bpm, channel))| (time, bpm, Some(channel)));
+        for (time, bpm, channel) in header.into_iter().chain(changes) {
+            if config.max_bpm < bpm {
+                warnings.push(TimingWarning::new(
+                    time,
+                    TimingWarningKind::BpmTooHigh { bpm, channel },
+                ));
+            } else if bpm < config.min_bpm {
+                warnings.push(TimingWarning::new(
+                    time,
+                    TimingWarningKind::BpmTooLow { bpm, channel },
+                ));
+            }
+        }
+
+        // Without `#BPM`, the first BPM change is used from the start.
+        let initial_bpm = initial_bpm.or_else(|| bpms.values().next().map(|&(bpm, _)| bpm));
+        for stop in self.stop.stops.values() {
+            // The BPM changes are applied before the stops on the same position.
+            let Some(bpm) = bpms
+                .range(..=stop.time)
+                .next_back()
+                .map(|(_, &(bpm, _))| bpm)
+                .or(initial_bpm)
+            else {
+                continue;
+            };
+            // A stop is counted in 1/192 of a 4/4 measure.
+            let seconds = stop.duration.as_f64() * 1.25 / bpm;
+            if config.max_stop_seconds < seconds {
+                warnings.push(TimingWarning::new(
+                    stop.time,
+                    TimingWarningKind::StopTooLong { seconds },
+                ));
+            }
+        }
+
+        for change in self.scroll.scrolling_factor_changes.values() {
+            let factor = change.factor.as_f64();
+            let kind = if factor < 0.0 {
+                TimingWarningKind::NegativeScroll { factor }
+            } else if factor == 0.0 {
+                TimingWarningKind::ZeroScroll
+            } else {
+                continue;
+            };
+            warnings.push(TimingWarning::new(change.time, kind));
+        }
+
+        for change in self.speed.speed_factor_changes.values() {
+            let factor = change.factor.as_f64();
+            if factor < config.min_speed {
+                warnings.push(TimingWarning::new(
+                    change.time,
+                    TimingWarningKind::SpeedNearZero { factor },
+                ));
+            }
+        }
+
+        for change in self.section_len.section_len_changes.values() {
+            let length = change.length.as_f64();
+            if length < config.min_section_len {
+                warnings.push(TimingWarning::new(
+                    ObjTime::start_of(change.track),
+                    TimingWarningKind::SectionLenTooShort { length },
+                ));
+            }
+        }
+
+        for note in self.wav.notes.playables::<T>() {
+            if self.stop.stops.contains_key(&note.offset) {
+                warnings.push(TimingWarning::new(
+                    note.offset,
+                    TimingWarningKind::NoteDuringStop {
+                        channel_id: note.channel_id,
+                    },
+                ));
+            }
+        }
+
+        warnings.sort_by_key(|warning| warning.time);
+        warnings
+    }
+
+    /// Returns the BPM changes by the time with their channels. [`Channel::BpmChange`] wins over [`Channel::BpmChangeU8`] on the same time.
+    fn bpm_timeline(&self) -> BTreeMap<ObjTime, (f64, Channel)> {
+        let mut bpms: BTreeMap<_, _> = self
+            .bpm
+            .bpm_changes_u8
+            .iter()
+            .map(|(&time, &bpm)| (time, (bpm as f64, Channel::BpmChangeU8)))
+            .collect();
+        bpms.extend(
+            self.bpm
+                .bpm_changes
+                .values()
+                .map(|change| (change.time, (change.bpm.as_f64(), Channel::BpmChange))),
+        );
+        bpms
+    }
+}
@@ -8,6 +8,7 @@ use itertools::Itertools;
 
 use crate::bms::lex::TokenStream;
 use crate::bms::{
+    fix::{Fix, TextEdit, line_ending, line_range},
     parse::{ParseError, ParseErrorWithRange, ParseWarningWithRange},
     prelude::*,
 };
@@ -180,6 +181,18 @@ impl<T: TokenProcessor + ?Sized> TokenProcessor for Rc<T> {
     }
 }
 
+/// A processor which does nothing, used when no extension processors are registered to [`ParseConfig`].
+impl TokenProcessor for () {
+    type Output = ();
+
+    fn process<P: Prompter>(
+        &self,
+        _ctx: &mut ProcessContext<'_, '_, P>,
+    ) -> Result<Self::Output, ParseErrorWithRange> {
+        Ok(())
+    }
+}
+
 /// A processor [`SequentialProcessor`] which does `first` then `second`.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct SequentialProcessor<F, S> {
@@ -233,6 +246,53 @@ where
 pub fn full_preset<T: KeyLayoutMapper, R: Rng>(
     rng: Rc<RefCell<R>>,
 ) -> impl TokenProcessor<Output = Bms> {
+    full_preset_with_extension::<T, R, _>(rng, ()).map(|(bms, ())| bms)
+}
+
+/// Returns the processors of the header commands, such as `#TITLE`, `#PLAYLEVEL`, `#RANK` and `#BANNER`, for listing songs.
+///
+/// The returned [`Bms`] has only [`Bms::repr`], [`Bms::bpm`], [`Bms::judge`], [`Bms::metadata`], [`Bms::music_info`], [`Bms::resources`] and [`Bms::sprite`]. The messages are ignored, so the objects in them are empty. The `#RANDOM` and `#SWITCH` scopes are evaluated in the same way as [`full_preset`].
+pub fn header_preset<R: Rng>(rng: Rc<RefCell<R>>) -> impl TokenProcessor<Output = Bms> {
+    let case_sensitive_obj_id = Rc::new(RefCell::new(false));
+    let sub_processor = repr::RepresentationProcessor::new(&case_sensitive_obj_id)
+        .then(bpm::BpmProcessor::new(&case_sensitive_obj_id))
+        .then(judge::JudgeProcessor::new(&case_sensitive_obj_id))
+        .then(metadata::MetadataProcessor)
+        .then(music_info::MusicInfoProcessor)
+        .then(resources::ResourcesProcessor)
+        .then(sprite::SpriteProcessor);
+    let bms_mapper = sub_processor.map(
+        |((((((repr, bpm), judge), metadata), music_info), resources), sprite)| {
+            (
+                Bms {
+                    bpm,
+                    judge,
+                    metadata,
+                    music_info,
+                    repr,
+                    resources,
+                    sprite,
+                    ..Bms::default()
+                },
+                (),
+            )
+        },
+    );
+    random::RandomTokenProcessor::new(rng, Rc::new(bms_mapper)).map(
+        |((mut bms, ()), randomized)| {
+            bms.randomized = randomized;
+            bms
+        },
+    )
+}
+
+/// Returns all of processors this crate provided and the extension processor `extension`.
+///
+/// `extension` reads the same tokens as the provided processors, that is, the commands activated by `#RANDOM` and `#SWITCH` scopes. So it can parse vendor-specific commands into its own structures in the same pass. It also runs on every branch of the random scopes to build [`Bms::randomized`], but only the output for the activated commands is returned.
+pub fn full_preset_with_extension<T: KeyLayoutMapper, R: Rng, X: TokenProcessor>(
+    rng: Rc<RefCell<R>>,
+    extension: X,
+) -> impl TokenProcessor<Output = (Bms, X::Output)> {
     let case_sensitive_obj_id = Rc::new(RefCell::new(false));
     let sub_processor = repr::RepresentationProcessor::new(&case_sensitive_obj_id)
         .then(bmp::BmpProcessor::new(&case_sensitive_obj_id))
@@ -313,63 +373,67 @@ pub fn full_preset<T: KeyLayoutMapper, R: Rng>(
             randomized: Vec::default(),
         },
     );
-    let bms_mapper = Rc::new(bms_mapper);
+    let bms_mapper = Rc::new(bms_mapper.then(extension));
 
-    random::RandomTokenProcessor::new(rng, bms_mapper).map(|(mut bms, randomized)| {
-        bms.randomized = randomized;
-        bms
-    })
+    random::RandomTokenProcessor::new(rng, bms_mapper).map(
+        |((mut bms, extension_output), randomized)| {
+            bms.randomized = randomized;
+            (bms, extension_output)
+        },
+    )
 }
 
 pub(crate) fn relax_tokens_default(tokens: &mut TokenStream<'_>) {
     for twr in &mut tokens.tokens {
-        match twr.content_mut() {
-            Token::Header { name, args } => {
-                let n_ref = name.as_ref();
-                let a_ref = args.as_ref();
-                let mut new_name: Option<String> = None;
-                let mut new_args: Option<String> = None;
-
-                if n_ref.eq_ignore_ascii_case("RONDAM") {
-                    new_name = Some("RANDOM".to_string());
-                } else if n_ref.eq_ignore_ascii_case("END")
-                    && a_ref.trim().eq_ignore_ascii_case("IF")
-                {
-                    new_name = Some("ENDIF".to_string());
-                    new_args = Some(String::new());
-                } else if a_ref.is_empty()
-                    && let Some((kw, rest_trim)) = ["RANDOM", "IF"]
-                        .iter()
-                        .find_map(|kw| n_ref.strip_prefix_ignore_case(kw).map(|r| (*kw, r.trim())))
-                {
-                    let digits = if rest_trim.starts_with('[') && rest_trim.ends_with(']') {
-                        &rest_trim[1..rest_trim.len() - 1]
-                    } else {
-                        rest_trim
-                    };
-                    if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
-                        new_name = Some(kw.to_string());
-                        new_args = Some(digits.to_string());
-                    }
-                }
+        if let Some(relaxed) = relax_token(twr.content()) {
+            *twr.content_mut() = relaxed;
+        }
+    }
+}
 
-                if let Some(nn) = new_name {
-                    *name = nn.into();
-                }
-                if let Some(na) = new_args {
-                    *args = na.into();
+/// Returns the token normalized by [`DefaultTokenRelaxer`], or `None` if it needs no change.
+fn relax_token(token: &Token<'_>) -> Option<Token<'static>> {
+    match token {
+        Token::Header { name, args } => {
+            let n_ref = name.as_ref();
+            let a_ref = args.as_ref();
+            let mut new_name: Option<String> = None;
+            let mut new_args: Option<String> = None;
+
+            if n_ref.eq_ignore_ascii_case("RONDAM") {
+                new_name = Some("RANDOM".to_string());
+            } else if n_ref.eq_ignore_ascii_case("END") && a_ref.trim().eq_ignore_ascii_case("IF") {
+                new_name = Some("ENDIF".to_string());
+                new_args = Some(String::new());
+            } else if a_ref.is_empty()
+                && let Some((kw, rest_trim)) = ["RANDOM", "IF"]
+                    .iter()
+                    .find_map(|kw| n_ref.strip_prefix_ignore_case(kw).map(|r| (*kw, r.trim())))
+            {
+                let digits = if rest_trim.starts_with('[') && rest_trim.ends_with(']') {
+                    &rest_trim[1..rest_trim.len() - 1]
+                } else {
+                    rest_trim
+                };
+                if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                    new_name = Some(kw.to_string());
+                    new_args = Some(digits.to_string());
                 }
             }
-            Token::NotACommand(line) => {
-                if line.trim() == "＃ENDIF" {
-                    *twr.content_mut() = Token::Header {
-                        name: "ENDIF".to_string().into(),
-                        args: String::new().into(),
-                    };
-                }
+
+            if new_name.is_none() && new_args.is_none() {
+                return None;
             }
-            Token::Message { .. } => {}
+            Some(Token::Header {
+                name: new_name.unwrap_or_else(|| n_ref.to_string()).into(),
+                args: new_args.unwrap_or_else(|| a_ref.to_string()).into(),
+            })
         }
+        Token::NotACommand(line) => (line.trim() == "＃ENDIF").then(|| Token::Header {
+            name: "ENDIF".to_string().into(),
+            args: String::new().into(),
+        }),
+        Token::Message { .. } => None,
     }
 }
 
@@ -383,6 +447,19 @@ pub trait TokenModifier {
     /// Implementations should be deterministic and avoid altering source ranges.
     fn modify(&self, tokens: &mut TokenStream<'_>);
 
+    /// Applies [`modify`](Self::modify), and returns the warnings about the modified tokens, which may carry the fixes writing the modifications into `source`.
+    ///
+    /// The default implementation reports nothing. `source` is the whole text the tokens are lexed from, if it is known.
+    fn modify_with_warnings(
+        &self,
+        tokens: &mut TokenStream<'_>,
+        source: Option<&str>,
+    ) -> Vec<ParseWarningWithRange> {
+        let _ = source;
+        self.modify(tokens);
+        Vec::new()
+    }
+
     /// Compose this modifier with another, applying `self` first and `second` after.
     ///
     /// The returned modifier preserves order and uses static dispatch.
@@ -414,6 +491,16 @@ where
         self.first.modify(tokens);
         self.second.modify(tokens);
     }
+
+    fn modify_with_warnings(
+        &self,
+        tokens: &mut TokenStream<'_>,
+        source: Option<&str>,
+    ) -> Vec<ParseWarningWithRange> {
+        let mut warnings = self.first.modify_with_warnings(tokens, source);
+        warnings.extend(self.second.modify_with_warnings(tokens, source));
+        warnings
+    }
 }
 
 /// A no-op token modifier used for strict parsing.
@@ -429,10 +516,64 @@ impl TokenModifier for NoopTokenModifier {
 /// delegating to `rewrite_relaxed_tokens`.
 pub struct DefaultTokenRelaxer;
 
+impl DefaultTokenRelaxer {
+    /// Returns the token normalized from `token`, and the fix writing it into the source text, or `None` if it needs no change.
+    ///
+    /// The fix rewriting the arguments needs `source`, because the range of a header token covers the command name only.
+    #[must_use]
+    pub fn relax(
+        &self,
+        token: &TokenWithRange<'_>,
+        source: Option<&str>,
+    ) -> Option<(Token<'static>, Option<Fix>)> {
+        let relaxed = relax_token(token.content())?;
+        let edit = match (token.content(), &relaxed) {
+            (
+                Token::Header { args, .. },
+                Token::Header {
+                    name,
+                    args: relaxed_args,
+                },
+            ) if args == relaxed_args => {
+                Some(TextEdit::replace(token.range().clone(), format!("#{name}")))
+            }
+            _ => source.map(|source| {
+                let line = line_range(source, token.range());
+                let content = line.start..line.end - line_ending(source, &line);
+                TextEdit::replace(content, relaxed.to_string())
+            }),
+        };
+        let fix = edit.map(|edit| Fix::new(&format!("replace with `{relaxed}`"), vec![edit]));
+        Some((relaxed, fix))
+    }
+}
+
 impl TokenModifier for DefaultTokenRelaxer {
     fn modify(&self, tokens: &mut TokenStream<'_>) {
         relax_tokens_default(tokens);
     }
+
+    /// Reports [`ParseWarning::MisspelledCommand`] for each relaxed token.
+    fn modify_with_warnings(
+        &self,
+        tokens: &mut TokenStream<'_>,
+        source: Option<&str>,
+    ) -> Vec<ParseWarningWithRange> {
+        let mut warnings = Vec::new();
+        for token in &mut tokens.tokens {
+            if let Some((relaxed, fix)) = self.relax(token, source) {
+                warnings.push(
+                    ParseWarning::MisspelledCommand {
+                        relaxed: relaxed.to_string(),
+                        fix,
+                    }
+                    .into_wrapper(token),
+                );
+                *token.content_mut() = relaxed;
+            }
+        }
+        warnings
+    }
 }
 
 fn parse_obj_ids(
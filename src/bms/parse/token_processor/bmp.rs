@@ -419,6 +419,8 @@ impl BmpProcessor {
             | Channel::BgaLayer2) => {
                 let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
                 warnings.extend(w);
+                // Record all the used BMP ids for validity checks, before an undefined one stops the loop.
+                objects.bmp_ids_used.extend(pairs.iter().map(|&(_, id)| id));
                 for (time, obj) in pairs {
                     if !objects.bmp_files.contains_key(&obj) {
                         return Err(ParseWarning::UndefinedObject(obj));
@@ -462,6 +464,10 @@ impl BmpProcessor {
                 use super::parse_obj_ids;
                 let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
                 warnings.extend(w);
+                // Record all the used ARGB ids for validity checks, before an undefined one stops the loop.
+                objects
+                    .argb_ids_used
+                    .extend(pairs.iter().map(|&(_, id)| id));
                 for (time, argb_id) in pairs {
                     let layer = Self::bga_layer(channel)?;
                     let argb = objects
@@ -119,9 +119,11 @@ impl BpmProcessor {
         if channel == Channel::BpmChange {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used BPM change ids for validity checks, before an undefined one stops the loop.
+            objects
+                .bpm_change_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, obj) in pairs {
-                // Record used BPM change id for validity checks
-                objects.bpm_change_ids_used.insert(obj);
                 let string_value = objects
                     .bpm_defs
                     .get(&obj)
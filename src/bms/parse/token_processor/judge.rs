@@ -129,6 +129,10 @@ impl JudgeProcessor {
         if channel == Channel::Judge {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used EXRANK ids for validity checks, before an undefined one stops the loop.
+            objects
+                .exrank_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, judge_id) in pairs {
                 let exrank_def = objects
                     .exrank_defs
@@ -9,7 +9,7 @@
 //! - `#ENDRANDOM` - Closes the random scope.
 //! - `#SWITCH` - Starts a switch scope which can contain only `#CASE` or `#DEF` scopes. The switch scope must close with `#ENDSW`. A random integer from 1 to the integer will be generated when parsing the score. Then if the integer of `#CASE` equals to the random integer, the commands in a case scope will be parsed, otherwise all command in it will be ignored. Any command except `#CASE` and `#DEF` must not be included in the scope, but some players allow it.
 //! - `#SETSWITCH` - Starts a switch scope but the integer will be used as the generated random number. It should be used only for tests.
-//! - `#CASE` - Starts a case scope if the integer equals to the generated random number. If there's no `#SKIP` command in the scope, the command control flow will **fallthrough** to the next `#CASE` or `#DEF`.
+//! - `#CASE` - Starts a case scope if the integer equals to the generated random number. The scope ends at the next `#CASE` or `#DEF` even if there's no `#SKIP` command in it, so the command control flow does **not** fall through, unlike some players.
 //! - `#SKIP` - Escapes the current switch scope. It is often used in the end of every case scope.
 //! - `#DEF` - Starts a case scope if any `#CASE` had not matched to the generated random number. It must be placed in the end of the switch scope, otherwise the following cases are ignored.
 //! - `#ENDSW` - Closes the random scope.
@@ -165,7 +165,7 @@ impl<R, N> RandomTokenProcessor<R, N> {
     }
 }
 
-impl<R: Rng, N: TokenProcessor<Output = Bms> + Clone> RandomTokenProcessor<R, N> {
+impl<R: Rng, E, N: TokenProcessor<Output = (Bms, E)> + Clone> RandomTokenProcessor<R, N> {
     // Helper to process a branch buffer into a Bms
     fn process_branch_buffer(
         &self,
@@ -179,7 +179,7 @@ impl<R: Rng, N: TokenProcessor<Output = Bms> + Clone> RandomTokenProcessor<R, N>
         let mut tokens_slice = tokens_vec.as_slice();
         let mut ctx = ProcessContext::new(&mut tokens_slice, prompter);
 
-        let (bms, nested) = sub_processor.process(&mut ctx)?;
+        let ((bms, _), nested) = sub_processor.process(&mut ctx)?;
 
         let mut final_bms = bms;
         final_bms.randomized.extend(nested);
@@ -891,7 +891,7 @@ impl<R: Rng, N: TokenProcessor<Output = Bms> + Clone> RandomTokenProcessor<R, N>
     }
 }
 
-impl<R: Rng, N: TokenProcessor<Output = Bms> + Clone> TokenProcessor
+impl<R: Rng, E, N: TokenProcessor<Output = (Bms, E)> + Clone> TokenProcessor
     for RandomTokenProcessor<R, N>
 {
     type Output = (N::Output, Vec<RandomizedObjects>);
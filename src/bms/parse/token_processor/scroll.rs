@@ -105,6 +105,10 @@ impl ScrollProcessor {
         if channel == Channel::Scroll {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used SCROLL ids for validity checks, before an undefined one stops the loop.
+            objects
+                .scroll_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, obj) in pairs {
                 let string_value = objects
                     .scroll_defs
@@ -107,6 +107,10 @@ impl SpeedProcessor {
         if channel == Channel::Speed {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used SPEED ids for validity checks, before an undefined one stops the loop.
+            objects
+                .speed_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, obj) in pairs {
                 let string_value = objects
                     .speed_defs
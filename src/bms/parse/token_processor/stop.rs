@@ -150,9 +150,11 @@ impl StopProcessor {
         if channel == Channel::Stop {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used STOP ids for validity checks, before an undefined one stops the loop.
+            objects
+                .stop_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, obj) in pairs {
-                // Record used STOP id for validity checks
-                objects.stop_ids_used.insert(obj);
                 let string_value = objects
                     .stop_defs
                     .get(&obj)
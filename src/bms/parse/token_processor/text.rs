@@ -105,6 +105,10 @@ impl TextProcessor {
         if channel == Channel::Text {
             let (pairs, w) = parse_obj_ids(track, message, &self.case_sensitive_obj_id);
             warnings.extend(w);
+            // Record all the used TEXT ids for validity checks, before an undefined one stops the loop.
+            objects
+                .text_ids_used
+                .extend(pairs.iter().map(|&(_, id)| id));
             for (time, text_id) in pairs {
                 let text = objects
                     .texts
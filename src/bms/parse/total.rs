@@ -0,0 +1,141 @@
+//! Recommended `#TOTAL` values, and the check of the specified one against them.
+//!
+//! ```
+//! use bms_rs::bms::{parse::total::{TotalCheckConfig, TotalFormula, TotalWarning}, prelude::*};
+//!
+//! assert_eq!(TotalFormula::Beatoraja.recommended(1000), 352.0);
+//! assert_eq!(TotalFormula::Iidx.recommended(1000).round(), 461.0);
+//!
+//! let source = "#TOTAL 1000\n#00111:01010101\n";
+//! let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+//!     .bms
+//!     .unwrap();
+//! assert_eq!(bms.playable_note_count::<KeyLayoutBeat>(), 4);
+//! assert!(matches!(
+//!     bms.check_total::<KeyLayoutBeat>(&TotalCheckConfig::default()),
+//!     Some(TotalWarning::TooHigh { .. })
+//! ));
+//! ```
+
+use thiserror::Error;
+
+use crate::bms::{command::channel::mapper::KeyLayoutMapper, model::Bms};
+use crate::chart::types::NoteKind;
+
+/// A well-known formula of the recommended `#TOTAL` from the number of the playable notes `n`.
+#[non_exhaustive]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TotalFormula {
+    /// The default of beatoraja for the charts without `#TOTAL`, `160 + (n + clamp(n - 400, 0, 200)) * 0.16`.
+    #[default]
+    Beatoraja,
+    /// The formula like IIDX, which the LR2 charts are often tuned to, `7.605 * n / (0.01 * n + 6.5)`.
+    Iidx,
+}
+
+impl TotalFormula {
+    /// Returns the recommended `#TOTAL` for `notes` playable notes.
+    #[must_use]
+    pub fn recommended(self, notes: usize) -> f64 {
+        let notes = notes as f64;
+        match self {
+            Self::Beatoraja => 160.0 + (notes + (notes - 400.0).clamp(0.0, 200.0)) * 0.16,
+            Self::Iidx => 7.605 * notes / (0.01 * notes + 6.5),
+        }
+    }
+}
+
+/// Configuration of [`Bms::check_total`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TotalCheckConfig {
+    /// The formula of the recommended `#TOTAL`.
+    pub formula: TotalFormula,
+    /// The lowest ratio of the `#TOTAL` to the recommended one, defaults to `0.5`.
+    pub min_ratio: f64,
+    /// The highest ratio of the `#TOTAL` to the recommended one, defaults to `2.0`.
+    pub max_ratio: f64,
+}
+
+impl Default for TotalCheckConfig {
+    fn default() -> Self {
+        Self {
+            formula: TotalFormula::default(),
+            min_ratio: 0.5,
+            max_ratio: 2.0,
+        }
+    }
+}
+
+/// A `#TOTAL` far from the recommended value.
+#[non_exhaustive]
+#[derive(Debug, Clone, Copy, PartialEq, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TotalWarning {
+    /// The `#TOTAL` is lower than the recommended value by the ratio, so the gauge hardly rises.
+    #[error("#TOTAL {total} is too low, the recommended value is {recommended:.0}")]
+    TooLow {
+        /// The specified `#TOTAL`.
+        total: f64,
+        /// The recommended `#TOTAL`.
+        recommended: f64,
+    },
+    /// The `#TOTAL` is higher than the recommended value by the ratio, so the gauge rises too easily.
+    #[error("#TOTAL {total} is too high, the recommended value is {recommended:.0}")]
+    TooHigh {
+        /// The specified `#TOTAL`.
+        total: f64,
+        /// The recommended `#TOTAL`.
+        recommended: f64,
+    },
+}
+
+impl Bms {
+    /// Counts the playable notes decoded by `T`. A long note is counted once, for the pair of the start and the end objects.
+    #[must_use]
+    pub fn playable_note_count<T: KeyLayoutMapper>(&self) -> usize {
+        let (visible, long) = self
+            .wav
+            .notes
+            .playables::<T>()
+            .filter_map(|note| T::from_channel_id(note.channel_id))
+            .fold((0, 0), |(visible, long), map| {
+                if map.kind() == NoteKind::Long {
+                    (visible, long + 1)
+                } else {
+                    (visible + 1, long)
+                }
+            });
+        visible + long / 2
+    }
+
+    /// Returns the recommended `#TOTAL` by `formula` for the playable notes decoded by `T`.
+    #[must_use]
+    pub fn recommended_total<T: KeyLayoutMapper>(&self, formula: TotalFormula) -> f64 {
+        formula.recommended(self.playable_note_count::<T>())
+    }
+
+    /// Checks whether the `#TOTAL` is within the ratios in `config` of the recommended value.
+    ///
+    /// It returns `None` also if the `#TOTAL` is undefined, which [`Bms::check_playing`] reports, or is not a number.
+    #[must_use]
+    pub fn check_total<T: KeyLayoutMapper>(
+        &self,
+        config: &TotalCheckConfig,
+    ) -> Option<TotalWarning> {
+        let total = self.judge.total.as_ref()?.value().as_ref().ok()?.as_f64();
+        let recommended = self.recommended_total::<T>(config.formula);
+        if recommended <= 0.0 {
+            return None;
+        }
+        let ratio = total / recommended;
+        if ratio < config.min_ratio {
+            Some(TotalWarning::TooLow { total, recommended })
+        } else if config.max_ratio < ratio {
+            Some(TotalWarning::TooHigh { total, recommended })
+        } else {
+            None
+        }
+    }
+}
@@ -10,8 +10,8 @@ use thiserror::Error;
 
 use crate::bms::{
     command::{ObjId, time::ObjTime},
-    model::{Bms, obj::WavObj},
-    prelude::{KeyLayout, KeyLayoutBeat, KeyLayoutMapper},
+    model::{Bms, control_flow::RandomizedObjects, obj::WavObj},
+    prelude::KeyLayoutMapper,
 };
 use crate::chart::types::{Key, NoteKind, PlayerSide};
 
@@ -37,6 +37,40 @@ pub enum ValidityMissing {
     StopDef(ObjId),
 }
 
+/// Unused-related validity entries, for the definitions which no object refers.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Error)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ValidityUnused {
+    /// A `#WAV` definition is not used by any note or BGM.
+    #[error("Unused WAV definition: {0:?}")]
+    Wav(ObjId),
+    /// A `#BMP`/`#EXBMP` definition is not used by any BGA change or `#BGA`/`#@BGA` definition.
+    #[error("Unused BMP definition: {0:?}")]
+    Bmp(ObjId),
+    /// A `#BPMxx` definition is not used by any BPM change.
+    #[error("Unused BPM change definition: {0:?}")]
+    BpmChangeDef(ObjId),
+    /// A `#STOPxx` definition is not used by any STOP event.
+    #[error("Unused STOP definition: {0:?}")]
+    StopDef(ObjId),
+    /// A `#SCROLLxx` definition is not used by any scroll speed change.
+    #[error("Unused SCROLL definition: {0:?}")]
+    ScrollDef(ObjId),
+    /// A `#SPEEDxx` definition is not used by any spacing change.
+    #[error("Unused SPEED definition: {0:?}")]
+    SpeedDef(ObjId),
+    /// A `#TEXTxx` definition is not used by any text event.
+    #[error("Unused TEXT definition: {0:?}")]
+    TextDef(ObjId),
+    /// A `#EXRANKxx` definition is not used by any judge change.
+    #[error("Unused EXRANK definition: {0:?}")]
+    ExRankDef(ObjId),
+    /// A `#ARGBxx` definition is not used by any BGA color change.
+    #[error("Unused ARGB definition: {0:?}")]
+    ArgbDef(ObjId),
+}
+
 /// Invalid-related validity entries.
 #[non_exhaustive]
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
@@ -113,6 +147,8 @@ pub struct ValidityCheckOutput {
     pub missing: Vec<ValidityMissing>,
     /// Invalid-related findings.
     pub invalid: Vec<ValidityInvalid>,
+    /// Unused-related findings, sorted by the kind and id.
+    pub unused: Vec<ValidityUnused>,
 }
 
 impl Bms {
@@ -120,10 +156,47 @@ impl Bms {
     ///
     /// This performs basic referential integrity checks and data invariants that
     /// are required for correct playback, separate from parse-time checks.
-    pub fn check_validity(&self) -> ValidityCheckOutput {
+    /// The notes are decoded into lanes by `T`, and each `(PlayerSide, Key)` pair is checked as a separate lane.
+    pub fn check_validity<T: KeyLayoutMapper>(&self) -> ValidityCheckOutput {
         let missing = self.check_missing();
-        let invalid = self.check_invalid();
-        ValidityCheckOutput { missing, invalid }
+        let invalid = self.check_invalid::<T>();
+        let unused = self.check_unused();
+        ValidityCheckOutput {
+            missing,
+            invalid,
+            unused,
+        }
+    }
+
+    fn check_unused(&self) -> Vec<ValidityUnused> {
+        let mut used = UsedIds::default();
+        used.collect(self);
+        let poor_bmp = ObjId::null();
+
+        let mut unused: Vec<ValidityUnused> = unused_ids(&self.wav.wav_files, &used.wavs)
+            .map(ValidityUnused::Wav)
+            .chain(
+                unused_ids(&self.bmp.bmp_files, &used.bmps)
+                    // `#BMP00` is shown on POOR without any message.
+                    .filter(|&id| id != poor_bmp)
+                    .map(ValidityUnused::Bmp),
+            )
+            .chain(
+                unused_ids(&self.bpm.bpm_defs, &used.bpm_changes).map(ValidityUnused::BpmChangeDef),
+            )
+            .chain(unused_ids(&self.stop.stop_defs, &used.stops).map(ValidityUnused::StopDef))
+            .chain(
+                unused_ids(&self.scroll.scroll_defs, &used.scrolls).map(ValidityUnused::ScrollDef),
+            )
+            .chain(unused_ids(&self.speed.speed_defs, &used.speeds).map(ValidityUnused::SpeedDef))
+            .chain(unused_ids(&self.text.texts, &used.texts).map(ValidityUnused::TextDef))
+            .chain(
+                unused_ids(&self.judge.exrank_defs, &used.exranks).map(ValidityUnused::ExRankDef),
+            )
+            .chain(unused_ids(&self.bmp.argb_defs, &used.argbs).map(ValidityUnused::ArgbDef))
+            .collect();
+        unused.sort_unstable();
+        unused
     }
 
     fn check_missing(&self) -> Vec<ValidityMissing> {
@@ -158,7 +231,7 @@ impl Bms {
         missing
     }
 
-    fn check_invalid(&self) -> Vec<ValidityInvalid> {
+    fn check_invalid<T: KeyLayoutMapper>(&self) -> Vec<ValidityInvalid> {
         let mut invalid = vec![];
 
         // Placement/overlap checks for notes on lanes.
@@ -167,10 +240,10 @@ impl Bms {
         //      - Overlap: visible single within long interval (same lane)
         //      - Overlap: landmine vs single (same time, same lane)
         //      - Overlap: landmine within long interval -> warn once at long start
-        let mut lane_to_notes: HashMap<Key, Vec<&WavObj>> = HashMap::new();
+        let mut lane_to_notes: HashMap<(PlayerSide, Key), Vec<&WavObj>> = HashMap::new();
         for obj in self.wav.notes.all_notes() {
             // Visible note in section 000 (track index 0)
-            let Some(map) = KeyLayoutBeat::from_channel_id(obj.channel_id) else {
+            let Some(map) = T::from_channel_id(obj.channel_id) else {
                 continue;
             };
             if map.kind().is_playable() && obj.offset.track().0 == 0 {
@@ -180,9 +253,12 @@ impl Bms {
                     time: obj.offset,
                 });
             }
-            lane_to_notes.entry(map.key()).or_default().push(obj);
+            lane_to_notes
+                .entry((map.side(), map.key()))
+                .or_default()
+                .push(obj);
         }
-        for (key, objs) in lane_to_notes {
+        for ((side, key), objs) in lane_to_notes {
             if objs.is_empty() {
                 continue;
             }
@@ -194,23 +270,21 @@ impl Bms {
             let long_times: Vec<ObjTime> = lane_objs
                 .iter()
                 .filter_map(|o| {
-                    let map = KeyLayoutBeat::from_channel_id(o.channel_id)?;
+                    let map = T::from_channel_id(o.channel_id)?;
                     (map.kind() == NoteKind::Long).then_some(o.offset)
                 })
                 .collect();
 
             // Overlap single vs single at the same time
             let mut single_offsets = HashSet::new();
-            for (single_obj, map) in lane_objs
+            for (single_obj, _) in lane_objs
                 .iter()
-                .filter_map(|obj| {
-                    KeyLayoutBeat::from_channel_id(obj.channel_id).map(|map| (obj, map))
-                })
+                .filter_map(|obj| T::from_channel_id(obj.channel_id).map(|map| (obj, map)))
                 .filter(|(_, map)| map.kind() == NoteKind::Visible)
             {
                 if !single_offsets.insert(single_obj.offset) {
                     invalid.push(ValidityInvalid::OverlapVisibleSingleWithSingle {
-                        side: map.side(),
+                        side,
                         key,
                         time: single_obj.offset,
                     });
@@ -218,16 +292,14 @@ impl Bms {
             }
 
             // Overlap landmine vs single at the same time
-            for (landmine_obj, map) in lane_objs
+            for (landmine_obj, _) in lane_objs
                 .iter()
-                .filter_map(|obj| {
-                    KeyLayoutBeat::from_channel_id(obj.channel_id).map(|map| (obj, map))
-                })
+                .filter_map(|obj| T::from_channel_id(obj.channel_id).map(|map| (obj, map)))
                 .filter(|(_, map)| map.kind() == NoteKind::Landmine)
             {
                 if single_offsets.contains(&landmine_obj.offset) {
                     invalid.push(ValidityInvalid::OverlapLandmineWithSingle {
-                        side: map.side(),
+                        side,
                         key,
                         time: landmine_obj.offset,
                     });
@@ -271,16 +343,14 @@ impl Bms {
             };
 
             // Overlap single vs long: any visible single inside any LN interval
-            for (single_obj, map) in lane_objs
+            for (single_obj, _) in lane_objs
                 .iter()
-                .filter_map(|obj| {
-                    KeyLayoutBeat::from_channel_id(obj.channel_id).map(|map| (obj, map))
-                })
+                .filter_map(|obj| T::from_channel_id(obj.channel_id).map(|map| (obj, map)))
                 .filter(|(_, map)| map.kind() == NoteKind::Visible)
             {
                 if let Some((start, end)) = time_overlaps_any_ln(single_obj.offset) {
                     invalid.push(ValidityInvalid::OverlapVisibleSingleWithLong {
-                        side: map.side(),
+                        side,
                         key,
                         time: single_obj.offset,
                         ln_start: start,
@@ -292,18 +362,16 @@ impl Bms {
             // Landmine vs long: warn once per LN interval at the long start
             // if any landmine appears inside that interval (including at start).
             let mut warned_ln_intervals: HashSet<(ObjTime, ObjTime)> = HashSet::new();
-            for (landmine_obj, map) in lane_objs
+            for (landmine_obj, _) in lane_objs
                 .iter()
-                .filter_map(|obj| {
-                    KeyLayoutBeat::from_channel_id(obj.channel_id).map(|map| (obj, map))
-                })
+                .filter_map(|obj| T::from_channel_id(obj.channel_id).map(|map| (obj, map)))
                 .filter(|(_, map)| map.kind() == NoteKind::Landmine)
             {
                 if let Some((start, end)) = time_overlaps_any_ln(landmine_obj.offset)
                     && warned_ln_intervals.insert((start, end))
                 {
                     invalid.push(ValidityInvalid::OverlapsLandmineLongAtStart {
-                        side: map.side(),
+                        side,
                         key,
                         ln_start: start,
                         ln_end: end,
@@ -315,11 +383,60 @@ impl Bms {
     }
 }
 
+/// The ids referred by the objects in the chart and in all the branches of its `#RANDOM` and `#SWITCH` scopes, because the objects in the other branches may use the definitions.
+#[derive(Debug, Default)]
+struct UsedIds {
+    wavs: HashSet<ObjId>,
+    bmps: HashSet<ObjId>,
+    bpm_changes: HashSet<ObjId>,
+    stops: HashSet<ObjId>,
+    scrolls: HashSet<ObjId>,
+    speeds: HashSet<ObjId>,
+    texts: HashSet<ObjId>,
+    exranks: HashSet<ObjId>,
+    argbs: HashSet<ObjId>,
+}
+
+impl UsedIds {
+    fn collect(&mut self, bms: &Bms) {
+        self.wavs
+            .extend(bms.wav.notes.all_notes().map(|obj| obj.wav_id));
+        self.bmps.extend(
+            bms.bmp
+                .bmp_ids_used
+                .iter()
+                .copied()
+                .chain(bms.bmp.bga_changes.values().map(|bga| bga.id))
+                .chain(bms.bmp.bga_defs.values().map(|def| def.source_bmp))
+                .chain(bms.bmp.atbga_defs.values().map(|def| def.source_bmp)),
+        );
+        self.bpm_changes.extend(&bms.bpm.bpm_change_ids_used);
+        self.stops.extend(&bms.stop.stop_ids_used);
+        self.scrolls.extend(&bms.scroll.scroll_ids_used);
+        self.speeds.extend(&bms.speed.speed_ids_used);
+        self.texts.extend(&bms.text.text_ids_used);
+        self.exranks.extend(&bms.judge.exrank_ids_used);
+        self.argbs.extend(&bms.bmp.argb_ids_used);
+        for branch in bms.randomized.iter().flat_map(RandomizedObjects::branches) {
+            self.collect(branch.sub());
+        }
+    }
+}
+
+/// Returns the ids in `defs` but not in `used`.
+fn unused_ids<'a, V>(
+    defs: &'a HashMap<ObjId, V>,
+    used: &'a HashSet<ObjId>,
+) -> impl Iterator<Item = ObjId> + 'a {
+    defs.keys().filter(|id| !used.contains(id)).copied()
+}
+
 #[cfg(test)]
 mod tests {
 
     use super::*;
     use crate::bms::{
+        command::channel::mapper::{KeyLayout, KeyLayoutBeat, KeyLayoutPms},
         command::{ObjId, time::ObjTime},
         model::{
             notes::Notes,
@@ -347,7 +464,7 @@ mod tests {
         });
         bms.wav.notes = notes;
         // No WAV defined for id
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.missing.contains(&ValidityMissing::WavForNote(id)));
     }
 
@@ -364,7 +481,7 @@ mod tests {
                 layer: BgaLayer::Base,
             },
         );
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.missing.contains(&ValidityMissing::BmpForBga(id)));
     }
 
@@ -382,7 +499,7 @@ mod tests {
         });
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.invalid.iter().any(|e| matches!(
             e,
             ValidityInvalid::PlayableNoteInTrackZero { time: t0, side: PlayerSide::Player1, key: Key::Key(1) } if *t0 == time
@@ -410,7 +527,7 @@ mod tests {
         });
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.invalid.iter().any(|e| matches!(
             e,
             ValidityInvalid::OverlapVisibleSingleWithSingle { time: t0, side: PlayerSide::Player1, key: Key::Key(1) } if *t0 == time
@@ -450,7 +567,7 @@ mod tests {
         });
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.invalid.iter().any(|e| matches!(
             e,
             ValidityInvalid::OverlapVisibleSingleWithLong { side: PlayerSide::Player1, key: Key::Key(1), time: t0, ln_start: s, ln_end: e } if *t0 == vis_time && *s == ln_start && *e == ln_end
@@ -489,7 +606,7 @@ mod tests {
         });
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.invalid.iter().any(|e| matches!(
             e,
             ValidityInvalid::OverlapsLandmineLongAtStart { side: PlayerSide::Player1, key: Key::Key(1), ln_start: s, ln_end: e } if *s == ln_start && *e == ln_end
@@ -517,7 +634,7 @@ mod tests {
         });
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         assert!(out.invalid.iter().any(|e| matches!(
             e,
             ValidityInvalid::OverlapLandmineWithSingle { time: t0, side: PlayerSide::Player1, key: Key::Key(1) } if *t0 == time
@@ -558,7 +675,7 @@ mod tests {
 
         bms.wav.notes = notes;
 
-        let out = bms.check_validity();
+        let out = bms.check_validity::<KeyLayoutBeat>();
         // This should detect the overlap, but currently fails due to the bug
         assert!(
             out.invalid.iter().any(|e| matches!(
@@ -575,4 +692,49 @@ mod tests {
             out.invalid
         );
     }
+
+    #[test]
+    fn test_double_play_sides_are_separate_lanes() {
+        let mut bms = Bms::default();
+        let time = t(1, 0, 4);
+        let mut notes = Notes::default();
+        for (side, wav_id) in [(PlayerSide::Player1, "01"), (PlayerSide::Player2, "02")] {
+            notes.push_note(WavObj {
+                offset: time,
+                channel_id: KeyLayoutBeat::new(side, NoteKind::Visible, Key::Key(1))
+                    .to_channel_id(),
+                wav_id: ObjId::try_from(wav_id, false).unwrap(),
+            });
+        }
+        bms.wav.notes = notes;
+
+        let out = bms.check_validity::<KeyLayoutBeat>();
+        assert_eq!(out.invalid, vec![]);
+    }
+
+    #[test]
+    fn test_overlap_in_pms_lanes() {
+        let mut bms = Bms::default();
+        let time = t(1, 0, 4);
+        let mut notes = Notes::default();
+        for wav_id in ["01", "02"] {
+            notes.push_note(WavObj {
+                offset: time,
+                channel_id: KeyLayoutPms::new(PlayerSide::Player1, NoteKind::Visible, Key::Key(6))
+                    .to_channel_id(),
+                wav_id: ObjId::try_from(wav_id, false).unwrap(),
+            });
+        }
+        bms.wav.notes = notes;
+
+        let out = bms.check_validity::<KeyLayoutPms>();
+        assert_eq!(
+            out.invalid,
+            vec![ValidityInvalid::OverlapVisibleSingleWithSingle {
+                side: PlayerSide::Player1,
+                key: Key::Key(6),
+                time,
+            }]
+        );
+    }
 }
@@ -9,7 +9,7 @@ pub use crate::diagnostics::{SimpleSource, ToAriadne, emit_bms_warnings};
 
 // Re-export types from bms module
 pub use super::{
-    BmsOutput, BmsWarning, ParseConfig,
+    AutoBmsOutput, BmsHeaderOutput, BmsOutput, BmsWarning, ExtendedBmsOutput, ParseConfig,
     command::{
         JudgeLevel, LnMode, LnType, ObjId, ObjIdManager, PlayerMode, PoorMode, Volume,
         channel::{
@@ -20,8 +20,8 @@ pub use super::{
                 PlayerSideKeyConverter,
             },
             mapper::{
-                KeyLayout, KeyLayoutBeat, KeyLayoutBeatNanasi, KeyLayoutDscOctFp, KeyLayoutMapper,
-                KeyLayoutPms, KeyLayoutPmsBmeType,
+                KeyLayout, KeyLayoutBeat, KeyLayoutBeatNanasi, KeyLayoutDscOctFp, KeyLayoutKind,
+                KeyLayoutMapper, KeyLayoutPms, KeyLayoutPmsBmeType,
             },
             read_channel,
         },
@@ -53,20 +53,26 @@ pub use super::{
         wav::ExWavDef,
     },
     parse::{
-        ParseError, ParseErrorWithRange, ParseOutput, ParseWarning, ParseWarningWithRange,
-        check_playing::{PlayingCheckOutput, PlayingError, PlayingWarning},
+        ExtendedParseOutput, ExtensionWarning, ParseError, ParseErrorWithRange, ParseOutput,
+        ParseWarning, ParseWarningWithRange,
+        check_playing::{
+            PlayingCheckOutput, PlayingError, PlayingErrorWithRange, PlayingWarning,
+            PlayingWarningWithRange,
+        },
+        outcomes::{RandomOutcome, RandomOutcomes},
+        probability::{BranchProbabilities, DefinitionProbability, NoteProbability},
         prompt::{
             AlwaysUseNewer, AlwaysUseOlder, AlwaysWarnAndUseNewer, AlwaysWarnAndUseOlder,
             DefDuplication, DuplicationWorkaround, Prompter,
         },
         token_processor::{
-            DefaultTokenRelaxer, NoopTokenModifier, SequentialTokenModifier, TokenModifier,
-            TokenProcessor,
+            DefaultTokenRelaxer, NoopTokenModifier, ProcessContext, SequentialTokenModifier,
+            TokenModifier, TokenProcessor,
         },
-        validity::{ValidityCheckOutput, ValidityInvalid, ValidityMissing},
+        validity::{ValidityCheckOutput, ValidityInvalid, ValidityMissing, ValidityUnused},
     },
-    parse_bms,
-    rng::{Rng, RngMock},
+    parse_bms, parse_bms_auto, parse_bms_header, parse_bms_lines, parse_bms_with_extension,
+    rng::{BeatorajaRng, Rng, RngMock},
 };
 
 // Re-export chart process trait
@@ -27,6 +27,79 @@ use crate::chart::{Chart, DEFAULT_BPM, DEFAULT_SPEED, MAX_FIN_F64, MAX_NON_NEGAT
 /// Users should use [`Bms::process`](Process) via the [`Process`] trait instead.
 struct BmsProcessor;
 
+/// Returns the errors of all the values which could not be parsed, such as `#BPMxx` and `#STOPxx`.
+///
+/// [`BmsProcessor::parse`] fails with the first one of them.
+pub(crate) fn invalid_values(bms: &Bms) -> Vec<PlayingError> {
+    let mut errors = Vec::new();
+
+    // Validate the initial BPM
+    if let Some(string_value) = &bms.bpm.bpm
+        && let Err(e) = string_value.value()
+    {
+        errors.push(PlayingError::InvalidBpm {
+            raw: string_value.raw().to_string(),
+            error: format!("{e:?}"),
+        });
+    }
+
+    // Validate BPM definitions
+    for string_value in bms.bpm.bpm_defs.values() {
+        if let Err(e) = string_value.value() {
+            errors.push(PlayingError::InvalidBpm {
+                raw: string_value.raw().to_string(),
+                error: format!("{e:?}"),
+            });
+        }
+    }
+
+    // Validate STOP definitions
+    for (obj_id, string_value) in &bms.stop.stop_defs {
+        if let Err(e) = string_value.value() {
+            errors.push(PlayingError::InvalidStop {
+                obj_id: *obj_id,
+                raw: string_value.raw().to_string(),
+                error: format!("{e:?}"),
+            });
+        }
+    }
+
+    // Validate SPEED definitions
+    for (obj_id, string_value) in &bms.speed.speed_defs {
+        if let Err(e) = string_value.value() {
+            errors.push(PlayingError::InvalidSpeed {
+                obj_id: *obj_id,
+                raw: string_value.raw().to_string(),
+                error: format!("{e:?}"),
+            });
+        }
+    }
+
+    // Validate SCROLL definitions
+    for (obj_id, string_value) in &bms.scroll.scroll_defs {
+        if let Err(e) = string_value.value() {
+            errors.push(PlayingError::InvalidScroll {
+                obj_id: *obj_id,
+                raw: string_value.raw().to_string(),
+                error: format!("{e:?}"),
+            });
+        }
+    }
+
+    // Validate SEEK definitions
+    for (obj_id, string_value) in &bms.video.seek_defs {
+        if let Err(e) = string_value.value() {
+            errors.push(PlayingError::InvalidSeek {
+                obj_id: *obj_id,
+                raw: string_value.raw().to_string(),
+                error: format!("{e:?}"),
+            });
+        }
+    }
+
+    errors
+}
+
 /// Convert STOP duration from 192nd-note units to beats (measure units).
 ///
 /// In 4/4 time signature:
@@ -46,65 +119,8 @@ impl BmsProcessor {
     ///
     /// Returns [`PlayingError::InvalidBpm`] if the BPM value could not be parsed.
     pub fn parse<T: KeyLayoutMapper>(bms: &Bms) -> Result<Chart, PlayingError> {
-        // === Validate all StringValue definitions ===
-        let mut errors = Vec::new();
-
-        // Validate BPM definitions
-        for string_value in bms.bpm.bpm_defs.values() {
-            if let Err(e) = string_value.value() {
-                errors.push(PlayingError::InvalidBpm {
-                    raw: string_value.raw().to_string(),
-                    error: format!("{e:?}"),
-                });
-            }
-        }
-
-        // Validate STOP definitions
-        for (obj_id, string_value) in &bms.stop.stop_defs {
-            if let Err(e) = string_value.value() {
-                errors.push(PlayingError::InvalidStop {
-                    obj_id: *obj_id,
-                    raw: string_value.raw().to_string(),
-                    error: format!("{e:?}"),
-                });
-            }
-        }
-
-        // Validate SPEED definitions
-        for (obj_id, string_value) in &bms.speed.speed_defs {
-            if let Err(e) = string_value.value() {
-                errors.push(PlayingError::InvalidSpeed {
-                    obj_id: *obj_id,
-                    raw: string_value.raw().to_string(),
-                    error: format!("{e:?}"),
-                });
-            }
-        }
-
-        // Validate SCROLL definitions
-        for (obj_id, string_value) in &bms.scroll.scroll_defs {
-            if let Err(e) = string_value.value() {
-                errors.push(PlayingError::InvalidScroll {
-                    obj_id: *obj_id,
-                    raw: string_value.raw().to_string(),
-                    error: format!("{e:?}"),
-                });
-            }
-        }
-
-        // Validate SEEK definitions
-        for (obj_id, string_value) in &bms.video.seek_defs {
-            if let Err(e) = string_value.value() {
-                errors.push(PlayingError::InvalidSeek {
-                    obj_id: *obj_id,
-                    raw: string_value.raw().to_string(),
-                    error: format!("{e:?}"),
-                });
-            }
-        }
-
         // If there are errors, return the first one
-        if let Some(err) = errors.into_iter().next() {
+        if let Some(err) = invalid_values(bms).into_iter().next() {
             return Err(err);
         }
 
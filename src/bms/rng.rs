@@ -21,6 +21,10 @@
 //!
 //! A production-ready implementation using the [`rand`] crate for true random number generation:
 //!
+//! ## [`BeatorajaRng`]
+//!
+//! An emulation of the sequence which beatoraja uses for `#RANDOM`, to reproduce the branches of its scores from the seed.
+//!
 //! [`rand`]: https://crates.io/crates/rand
 
 use core::ops::RangeInclusive;
@@ -174,6 +178,13 @@ impl JavaRandom {
         self.next(32)
     }
 
+    /// Java's `nextDouble()` method - returns a value in `[0, 1)`
+    pub fn next_double(&mut self) -> f64 {
+        let high = i64::from(self.next(26)) << 27;
+        let low = i64::from(self.next(27));
+        (high + low) as f64 / (1u64 << 53) as f64
+    }
+
     /// Java's `nextInt(int bound)` method
     ///
     /// # Panics
@@ -251,6 +262,47 @@ impl Rng for JavaRandom {
     }
 }
 
+/// An emulation of the `#RANDOM` sequence of beatoraja.
+///
+/// The BMS decoder of beatoraja, `BMSDecoder` of jbms-parser, maps `#RANDOM n` to `(int) (Math.random() * n) + 1`, and evaluates `#RANDOM` also in the skipped `#IF` scopes, so the inactive ones also generate values as the parser of this crate does. `Math.random()` is `nextDouble()` of a `java.util.Random` which is seeded by the clock, and beatoraja records the chosen values instead of the seed, so pass the seed of the `java.util.Random` to reproduce. `#SWITCH n` is mapped in the same way.
+///
+/// # Examples
+///
+/// ```rust
+/// use bms_rs::bms::rng::{BeatorajaRng, Rng};
+/// use num::BigUint;
+///
+/// let mut rng = BeatorajaRng::new(0);
+/// // `new Random(0).nextDouble()` is 0.730967787376657.
+/// let n = rng.generate(BigUint::from(1u64)..=BigUint::from(10u64));
+/// assert_eq!(n, BigUint::from(8u64));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BeatorajaRng(JavaRandom);
+
+impl BeatorajaRng {
+    /// Create a new [`BeatorajaRng`] with the given seed.
+    #[must_use]
+    pub const fn new(seed: i64) -> Self {
+        Self(JavaRandom::new(seed))
+    }
+}
+
+impl Rng for BeatorajaRng {
+    fn generate(&mut self, range: RangeInclusive<BigUint>) -> BigUint {
+        use num::One;
+
+        let (start, end) = (range.start(), range.end());
+        let width = end - start + BigUint::one();
+        // Java computes the product in `double` and truncates it into `int`.
+        let Some(width) = width.to_i32() else {
+            return start.clone();
+        };
+        let offset = (self.0.next_double() * f64::from(width)) as u32;
+        start + BigUint::from(offset)
+    }
+}
+
 #[cfg(all(test, feature = "rand"))]
 mod tests {
     use super::*;
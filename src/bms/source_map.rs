@@ -0,0 +1,235 @@
+//! Source map from the entries of [`Bms`](crate::bms::model::Bms) back to the byte ranges in the source text.
+//!
+//! The model drops the positions of the tokens, so [`SourceMapProcessor`] records them into [`SourceMap`] as an extension token processor. Register it to [`ParseConfig`](crate::bms::ParseConfig) only when needed:
+//!
+//! ```
+//! use bms_rs::bms::{prelude::*, source_map::SourceMapProcessor};
+//!
+//! let source = "#TITLE Song\n#WAV01 kick.wav\n#00111:0001\n";
+//! let output = parse_bms_with_extension::<KeyLayoutBeat, _, _, _, _>(
+//!     source,
+//!     default_config().override_token_processor(SourceMapProcessor),
+//! );
+//! let source_map = output.extensions.expect("parse must succeed");
+//!
+//! let wav_ranges = source_map.definition("WAV", ObjId::try_from("01", false).unwrap());
+//! assert_eq!(wav_ranges, [12..18]);
+//! assert_eq!(&source[wav_ranges[0].clone()], "#WAV01");
+//!
+//! let time = ObjTime::new(1, 1, 2).unwrap();
+//! let channel: NoteChannelId = "11".parse().unwrap();
+//! assert_eq!(source_map.object(time, channel), [28..39]);
+//! ```
+//!
+//! The ranges are the ones of the tokens, so they point the command names of the header lines and the whole message lines. The ranges are recorded only for the commands activated by `#RANDOM` and `#SWITCH` scopes, same as the model. All the ranges of duplicated entries are kept in the source order.
+
+use std::{collections::HashMap, ops::Range};
+
+use crate::bms::{
+    command::{
+        ObjId,
+        channel::{Channel, NoteChannelId},
+        time::ObjTime,
+    },
+    cst::{DEFINITION_COMMANDS, is_base_62, message_objects, split_definition},
+    lex::token::{Token, TokenWithRange},
+    parse::{
+        ParseErrorWithRange,
+        check_playing::{
+            PlayingError, PlayingErrorWithRange, PlayingWarning, PlayingWarningWithRange,
+        },
+        prompt::Prompter,
+        token_processor::{ProcessContext, TokenProcessor},
+    },
+};
+
+/// A side table from the model entries to the byte ranges of the source lines.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// The header fields keyed by the upper-cased command name, such as `TITLE`.
+    headers: HashMap<String, Vec<Range<usize>>>,
+    /// The definitions keyed by the command in [`DEFINITION_COMMANDS`] and the id.
+    definitions: HashMap<(&'static str, ObjId), Vec<Range<usize>>>,
+    /// The objects keyed by the time and the channel, mapped to the message lines placing them.
+    objects: HashMap<(ObjTime, NoteChannelId), Vec<Range<usize>>>,
+}
+
+impl SourceMap {
+    /// Returns the ranges of the header lines whose command is `name` case-insensitively, such as `TITLE`. The definitions such as `#WAVxx` are not included, use [`Self::definition`] instead.
+    #[must_use]
+    pub fn header(&self, name: &str) -> &[Range<usize>] {
+        self.headers
+            .get(&name.to_ascii_uppercase())
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the ranges of the definition lines of `id` by `command` case-insensitively, such as `WAV` for `#WAVxx`.
+    #[must_use]
+    pub fn definition(&self, command: &str, id: ObjId) -> &[Range<usize>] {
+        DEFINITION_COMMANDS
+            .iter()
+            .find(|known| known.eq_ignore_ascii_case(command))
+            .and_then(|&known| self.definitions.get(&(known, id)))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the definitions defined more than once, as the command such as `WAV`, the id and the ranges in the source order.
+    pub fn duplicated_definitions(
+        &self,
+    ) -> impl Iterator<Item = (&'static str, ObjId, &[Range<usize>])> {
+        self.definitions
+            .iter()
+            .filter(|(_, ranges)| ranges.len() > 1)
+            .map(|(&(command, id), ranges)| (command, id, ranges.as_slice()))
+    }
+
+    /// Returns the ranges of the message lines which placed an object at `time` on `channel`.
+    #[must_use]
+    pub fn object(&self, time: ObjTime, channel: NoteChannelId) -> &[Range<usize>] {
+        self.objects
+            .get(&(time, channel))
+            .map_or(&[], Vec::as_slice)
+    }
+
+    /// Attaches the range of the source lines causing `warning`.
+    ///
+    /// The warnings about the missing commands point to the first command, where the missing one should be added.
+    #[must_use]
+    pub fn locate_playing_warning(&self, warning: PlayingWarning) -> PlayingWarningWithRange {
+        let range = match &warning {
+            PlayingWarning::StartBpmUndefined => {
+                self.first_object_on(&[Channel::BpmChangeU8, Channel::BpmChange])
+            }
+            PlayingWarning::NoDisplayableNotes | PlayingWarning::NoPlayableNotes => {
+                self.first_object()
+            }
+            _ => None,
+        };
+        PlayingWarningWithRange::new(warning, range.unwrap_or_else(|| self.first_command()))
+    }
+
+    /// Attaches the range of the source lines causing `error`.
+    ///
+    /// The errors about the missing commands point to the first command, where the missing one should be added.
+    #[must_use]
+    pub fn locate_playing_error(&self, error: PlayingError) -> PlayingErrorWithRange {
+        let range = match &error {
+            PlayingError::InvalidBpm { .. } => self.header("BPM").last().cloned(),
+            PlayingError::InvalidStop { obj_id, .. } => {
+                self.definition("STOP", *obj_id).last().cloned()
+            }
+            PlayingError::InvalidSpeed { obj_id, .. } => {
+                self.definition("SPEED", *obj_id).last().cloned()
+            }
+            PlayingError::InvalidScroll { obj_id, .. } => {
+                self.definition("SCROLL", *obj_id).last().cloned()
+            }
+            PlayingError::InvalidSeek { obj_id, .. } => {
+                self.definition("SEEK", *obj_id).last().cloned()
+            }
+            _ => None,
+        };
+        PlayingErrorWithRange::new(error, range.unwrap_or_else(|| self.first_command()))
+    }
+
+    fn all_ranges(&self) -> impl Iterator<Item = &Range<usize>> {
+        self.headers
+            .values()
+            .chain(self.definitions.values())
+            .chain(self.objects.values())
+            .flatten()
+    }
+
+    fn first_command(&self) -> Range<usize> {
+        self.all_ranges()
+            .min_by_key(|range| range.start)
+            .cloned()
+            .unwrap_or(0..0)
+    }
+
+    fn first_object(&self) -> Option<Range<usize>> {
+        self.objects
+            .values()
+            .flatten()
+            .min_by_key(|range| range.start)
+            .cloned()
+    }
+
+    fn first_object_on(&self, channels: &[Channel]) -> Option<Range<usize>> {
+        let channels: Vec<NoteChannelId> = channels.iter().copied().map(Into::into).collect();
+        self.objects
+            .iter()
+            .filter(|((_, channel), _)| channels.contains(channel))
+            .flat_map(|(_, ranges)| ranges)
+            .min_by_key(|range| range.start)
+            .cloned()
+    }
+
+    fn record(&mut self, token: &TokenWithRange<'_>, case_sensitive_obj_id: bool) {
+        let range = token.range().clone();
+        match token.content() {
+            Token::Header { name, .. } => {
+                if let Some((command, id)) = split_definition(name)
+                    && let Ok(id) = ObjId::try_from(id, case_sensitive_obj_id)
+                {
+                    self.definitions
+                        .entry((command, id))
+                        .or_default()
+                        .push(range);
+                } else {
+                    self.headers
+                        .entry(name.to_ascii_uppercase())
+                        .or_default()
+                        .push(range);
+                }
+            }
+            Token::Message {
+                track,
+                channel,
+                message,
+            } => {
+                if *channel == Channel::SectionLen {
+                    self.objects
+                        .entry((ObjTime::start_of(*track), (*channel).into()))
+                        .or_default()
+                        .push(range);
+                    return;
+                }
+                let channel = NoteChannelId::from(*channel);
+                for (time, id) in message_objects(track.0, message) {
+                    if id == b"00" {
+                        continue;
+                    }
+                    let ranges = self.objects.entry((time, channel)).or_default();
+                    if ranges.last() != Some(&range) {
+                        ranges.push(range.clone());
+                    }
+                }
+            }
+            Token::NotACommand(_) => {}
+        }
+    }
+}
+
+/// An extension token processor which records [`SourceMap`] of the activated commands.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct SourceMapProcessor;
+
+impl TokenProcessor for SourceMapProcessor {
+    type Output = SourceMap;
+
+    fn process<P: Prompter>(
+        &self,
+        ctx: &mut ProcessContext<'_, '_, P>,
+    ) -> Result<Self::Output, ParseErrorWithRange> {
+        let tokens = ctx.take_input();
+        let case_sensitive_obj_id = tokens.iter().any(|token| {
+            matches!(token.content(), Token::Header { name, args } if is_base_62(name, args))
+        });
+        let mut source_map = SourceMap::default();
+        for token in tokens {
+            source_map.record(token, case_sensitive_obj_id);
+        }
+        Ok(source_map)
+    }
+}
@@ -1649,7 +1649,7 @@ where
 
 /// Calculate the least common multiple (LCM) of a slice of u64 values
 /// Returns 1 if the slice is empty
-fn lcm_slice(denominators: &[u64]) -> u64 {
+pub(crate) fn lcm_slice(denominators: &[u64]) -> u64 {
     denominators.iter().fold(1, |acc, denom| acc.lcm(denom))
 }
 
@@ -29,6 +29,7 @@
 
 pub mod bms_to_bmson;
 pub mod bmson_to_bms;
+pub mod legacy;
 pub mod parse;
 pub mod prelude;
 pub mod process;
@@ -42,7 +43,7 @@ use std::{
 #[cfg(feature = "diagnostics")]
 use ariadne::{Color, Report, ReportKind};
 use chumsky::{prelude::*, span::SimpleSpan};
-use serde::{Deserialize, Deserializer, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::bms::command::LnMode;
 
@@ -50,6 +51,7 @@ use crate::bms::command::LnMode;
 use crate::diagnostics::{ToAriadne, build_report};
 
 use self::{
+    legacy::{LegacyUpgradeOutput, LegacyUpgradeWarning},
     parse::{
         Error as JsonError, Recovered as JsonRecovered, Warning as JsonWarning, parser,
         split_chumsky_errors,
@@ -69,6 +71,7 @@ pub struct Bmson<'a> {
     /// Location of bar lines in pulses. If `None`, then a 4/4 beat is assumed and bar lines will be generates every 4 quarter notes. If `Some(vec![])`, this chart will not have any bar line.
     ///
     /// This format represents an irregular meter by bar lines.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub lines: Option<Vec<BarLine>>,
     /// Events of bpm change. If there are coincident events, the successor is only applied.
     #[serde(default)]
@@ -82,13 +85,13 @@ pub struct Bmson<'a> {
     #[serde(default)]
     pub bga: Bga<'a>,
     /// Beatoraja implementation of scroll events.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub scroll_events: Vec<ScrollEvent>,
     /// Beatoraja implementation of mine channel.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub mine_channels: Vec<MineChannel<'a>>,
     /// Beatoraja implementation of invisible key channel.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub key_channels: Vec<KeyChannel<'a>>,
 }
 
@@ -134,19 +137,19 @@ pub struct BmsonInfo<'a> {
     #[serde(default = "default_percentage")]
     pub total: FinF64,
     /// Background image file name. This should be displayed during the game play.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub back_image: Option<Cow<'a, str>>,
     /// Eyecatch image file name. This should be displayed during the chart is loading.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub eyecatch_image: Option<Cow<'a, str>>,
     /// Title image file name. This should be displayed before the game starts instead of title of the music.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title_image: Option<Cow<'a, str>>,
     /// Banner image file name. This should be displayed in music select or result scene. The aspect ratio of image is usually 15:4.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub banner_image: Option<Cow<'a, str>>,
     /// Preview music file name. This should be played when this chart is selected in a music select scene.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub preview_music: Option<Cow<'a, str>>,
     /// Numbers of pulse per quarter note in 4/4 measure. You must check this because it affects the actual seconds of `PulseNumber`.
     #[serde(
@@ -155,7 +158,7 @@ pub struct BmsonInfo<'a> {
     )]
     pub resolution: NonZeroU64,
     /// Beatoraja implementation of long note type.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "is_default_ln_mode")]
     pub ln_type: LnMode,
 }
 
@@ -262,6 +265,14 @@ where
     deserializer.deserialize_option(ResolutionVisitor)
 }
 
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "signature required by serde"
+)]
+fn is_default_ln_mode(mode: &LnMode) -> bool {
+    *mode == LnMode::default()
+}
+
 /// Event of bar line of the chart.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BarLine {
@@ -296,7 +307,10 @@ pub struct Note {
     /// Position to be placed.
     pub y: PulseNumber,
     /// Lane information. The `Some` number represents the key to play, otherwise it is not playable (BGM) note.
-    #[serde(deserialize_with = "deserialize_x_none_if_zero")]
+    #[serde(
+        serialize_with = "serialize_x_zero_if_none",
+        deserialize_with = "deserialize_x_none_if_zero"
+    )]
     pub x: Option<NonZeroU8>,
     /// Length of pulses of the note. It will be a normal note if zero, otherwise a long note.
     pub l: u64,
@@ -317,11 +331,11 @@ pub struct Note {
     /// (similar to BMS multiplex WAV definitions).
     pub c: bool,
     /// Beatoraja implementation of long note type.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub t: Option<LnMode>,
     /// Beatoraja implementation of long note up flag.
     /// If it is true and configured at the end position of a long note, then this position will become the ending note of the long note.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub up: Option<bool>,
 }
 
@@ -333,6 +347,19 @@ where
     Ok(opt.and_then(NonZeroU8::new))
 }
 
+/// Writes the BGM lane `None` as `0`, because the specification has no `null` lane.
+#[expect(
+    clippy::ref_option,
+    clippy::trivially_copy_pass_by_ref,
+    reason = "the signature is required by serde"
+)]
+fn serialize_x_zero_if_none<S>(x: &Option<NonZeroU8>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u8(x.map_or(0, NonZeroU8::get))
+}
+
 /// BPM change note.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct BpmEvent {
@@ -411,7 +438,10 @@ pub struct ScrollEvent {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct MineEvent {
     /// Lane information. The `Some` number represents the key to play, otherwise it is not playable (BGM) note.
-    #[serde(deserialize_with = "deserialize_x_none_if_zero")]
+    #[serde(
+        serialize_with = "serialize_x_zero_if_none",
+        deserialize_with = "deserialize_x_none_if_zero"
+    )]
     pub x: Option<NonZeroU8>,
     /// Position to be placed.
     pub y: PulseNumber,
@@ -432,7 +462,10 @@ pub struct MineChannel<'a> {
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct KeyEvent {
     /// Lane information. The `Some` number represents the key to play, otherwise it is not playable (BGM) note.
-    #[serde(deserialize_with = "deserialize_x_none_if_zero")]
+    #[serde(
+        serialize_with = "serialize_x_zero_if_none",
+        deserialize_with = "deserialize_x_none_if_zero"
+    )]
     pub x: Option<NonZeroU8>,
     /// Position to be placed.
     pub y: PulseNumber,
@@ -448,6 +481,7 @@ pub struct KeyChannel<'a> {
 }
 
 /// Errors that can occur during BMSON parsing.
+#[non_exhaustive]
 #[derive(Debug)]
 pub enum BmsonParseError<'a> {
     /// JSON parsing warning intentionally emitted by the parser.
@@ -465,6 +499,11 @@ pub enum BmsonParseError<'a> {
         /// The unrecoverable JSON parsing error.
         error: JsonError<'a>,
     },
+    /// A legacy (version 0.21 or unversioned) bmson was upgraded into the current schema with a loss.
+    LegacyUpgrade {
+        /// The warning emitted during upgrading.
+        warning: LegacyUpgradeWarning,
+    },
     /// Deserialization error from serde.
     Deserialize {
         /// The serde deserialization error.
@@ -500,6 +539,14 @@ impl ToAriadne for BmsonParseError<'_> {
             BmsonParseError::JsonWarning { warning } => warning.to_report(src),
             BmsonParseError::JsonRecovered { error } => error.to_report(src),
             BmsonParseError::JsonError { error } => error.to_report(src),
+            BmsonParseError::LegacyUpgrade { warning } => build_report(
+                src,
+                ReportKind::Warning,
+                0..0,
+                "BMSON legacy upgrade warning",
+                warning,
+                Color::Yellow,
+            ),
             BmsonParseError::Deserialize { error } => error.to_report(src),
         }
     }
@@ -522,6 +569,8 @@ pub struct BmsonParseOutput<'a> {
 /// It uses chumsky parser internally to parse JSON, then deserializes the result
 /// using `serde_path_to_error` for detailed error information.
 ///
+/// Legacy bmson files (version 0.21 or unversioned, see [`legacy`]) are detected and upgraded into the current schema before deserialization. Fields which cannot be mapped are reported as [`BmsonParseError::LegacyUpgrade`].
+///
 /// # Returns
 ///
 /// Returns a `BmsonParseOutput` containing the parsed BMSON data (if successful),
@@ -572,6 +621,23 @@ pub fn parse_bmson<'a>(json: &'a str) -> BmsonParseOutput<'a> {
         });
     }
 
+    // Upgrade the legacy schema if detected
+    let json_value = json_value.map(|json_value| {
+        if !legacy::is_legacy(&json_value) {
+            return json_value;
+        }
+        let LegacyUpgradeOutput {
+            value: upgraded,
+            warnings: upgrade_warnings,
+        } = legacy::upgrade(json_value);
+        errors.extend(
+            upgrade_warnings
+                .into_iter()
+                .map(|warning| BmsonParseError::LegacyUpgrade { warning }),
+        );
+        upgraded
+    });
+
     // Try to deserialize the JSON value into Bmson
     let bmson = json_value
         .map(|json_value| serde_path_to_error::deserialize(&json_value))
@@ -583,3 +649,43 @@ pub fn parse_bmson<'a>(json: &'a str) -> BmsonParseOutput<'a> {
 
     BmsonParseOutput { bmson, errors }
 }
+
+impl Bmson<'_> {
+    /// Serializes this chart into a compact JSON string.
+    ///
+    /// Fields are written in the order of the bmson specification, [`PulseNumber`]s are written as integers, and the optional fields which are absent or empty (such as `lines: None` or beatoraja extension channels without any events) are omitted. The output can be read back with [`parse_bmson`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `serde_json` failed to serialize the chart.
+    pub fn to_string(&self) -> serde_json::Result<String> {
+        serde_json::to_string(self)
+    }
+
+    /// Serializes this chart into a pretty-printed JSON string. See also [`Bmson::to_string`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `serde_json` failed to serialize the chart.
+    pub fn to_string_pretty(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    /// Writes this chart as compact JSON into `writer`. See also [`Bmson::to_string`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `serde_json` failed to serialize the chart or writing into `writer` failed.
+    pub fn to_writer<W: std::io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer(writer, self)
+    }
+
+    /// Writes this chart as pretty-printed JSON into `writer`. See also [`Bmson::to_string`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `serde_json` failed to serialize the chart or writing into `writer` failed.
+    pub fn to_writer_pretty<W: std::io::Write>(&self, writer: W) -> serde_json::Result<()> {
+        serde_json::to_writer_pretty(writer, self)
+    }
+}
@@ -125,6 +125,7 @@ impl Bms {
             bms.bpm
                 .bpm_defs
                 .insert(bpm_def_id, StringValue::from_value(bpm));
+            bms.bpm.bpm_change_ids_used.insert(bpm_def_id);
 
             bms.bpm.bpm_changes.insert(time, BpmChangeObj { time, bpm });
         }
@@ -143,6 +144,7 @@ impl Bms {
             bms.stop
                 .stop_defs
                 .insert(stop_def_id, StringValue::from_value(duration));
+            bms.stop.stop_ids_used.insert(stop_def_id);
 
             bms.stop.stops.insert(time, StopObj { time, duration });
         }
@@ -160,6 +162,7 @@ impl Bms {
             bms.scroll
                 .scroll_defs
                 .insert(scroll_def_id, StringValue::from_value(factor));
+            bms.scroll.scroll_ids_used.insert(scroll_def_id);
 
             bms.scroll
                 .scrolling_factor_changes
@@ -0,0 +1,272 @@
+//! Part: Upgrade legacy bmson (version 0.21 and unversioned) into the current schema.
+//!
+//! The legacy schema differs from bmson 1.0 mainly in naming:
+//!
+//! | legacy                | current                 |
+//! | --------------------- | ----------------------- |
+//! | `info.initBPM`        | `info.init_bpm`         |
+//! | `info.judgeRank`      | `info.judge_rank`       |
+//! | `bpmNotes[].v`        | `bpm_events[].bpm`      |
+//! | `stopNotes[].v`       | `stop_events[].duration`|
+//! | `soundChannel`        | `sound_channels`        |
+//! | `bga.bgaHeader[].ID`  | `bga.bga_header[].id`   |
+//! | `bga.bgaNotes`        | `bga.bga_events`        |
+//! | `bga.layerNotes`      | `bga.layer_events`      |
+//! | `bga.poorNotes`       | `bga.poor_events`       |
+//!
+//! The legacy format has a fixed resolution of 240 pulses per quarter note, which is same as [`super::default_resolution`].
+
+use serde_json::{Map, Value};
+use thiserror::Error;
+
+/// Version string assigned to an unversioned legacy chart after upgrading.
+pub const LEGACY_VERSION: &str = "0.21.0";
+
+/// Warnings that occur during upgrading a legacy bmson into the current schema.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[non_exhaustive]
+pub enum LegacyUpgradeWarning {
+    /// The legacy field has no counterpart in the current schema, so it was dropped.
+    #[error("legacy field `{0}` has no counterpart in bmson 1.0 and was dropped")]
+    UnmappedField(String),
+    /// The legacy field had an unexpected type, so it was dropped.
+    #[error("legacy field `{0}` has an unexpected type and was dropped")]
+    UnexpectedType(String),
+}
+
+/// Checks whether the JSON value looks like a legacy bmson.
+///
+/// A value is treated as legacy if its `version` starts with `0.`, or if it is unversioned and uses any of the legacy-only fields (`bpmNotes`, `stopNotes`, `soundChannel` or `info.initBPM`).
+#[must_use]
+pub fn is_legacy(value: &Value) -> bool {
+    let Some(object) = value.as_object() else {
+        return false;
+    };
+    match object.get("version") {
+        Some(Value::String(version)) => version.starts_with("0."),
+        Some(_) => false,
+        None => {
+            ["bpmNotes", "stopNotes", "soundChannel"]
+                .iter()
+                .any(|key| object.contains_key(*key))
+                || object
+                    .get("info")
+                    .and_then(Value::as_object)
+                    .is_some_and(|info| info.contains_key("initBPM"))
+        }
+    }
+}
+
+/// Output of [`upgrade`].
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct LegacyUpgradeOutput {
+    /// The JSON value in the current bmson schema.
+    pub value: Value,
+    /// Warnings that occurred during upgrading.
+    pub warnings: Vec<LegacyUpgradeWarning>,
+}
+
+/// Upgrades a legacy bmson JSON value into the current schema, which can be deserialized into [`super::Bmson`].
+///
+/// The fields already in the current schema are kept as is, so partially migrated files are also accepted.
+pub fn upgrade(legacy: Value) -> LegacyUpgradeOutput {
+    let mut warnings = Vec::new();
+    let Value::Object(legacy) = legacy else {
+        return LegacyUpgradeOutput {
+            value: legacy,
+            warnings,
+        };
+    };
+
+    let mut current = Map::new();
+    current.insert(
+        "version".to_string(),
+        Value::String(LEGACY_VERSION.to_string()),
+    );
+    for (key, value) in legacy {
+        match key.as_str() {
+            "version" | "scroll_events" | "mine_channels" | "key_channels" => {
+                current.insert(key, value);
+            }
+            "info" => {
+                let info = upgrade_object(value, "info", &mut warnings, |field| {
+                    Some(match field {
+                        "initBPM" => "init_bpm",
+                        "judgeRank" => "judge_rank",
+                        "title" | "subtitle" | "artist" | "subartists" | "genre" | "mode_hint"
+                        | "chart_name" | "level" | "init_bpm" | "judge_rank" | "total"
+                        | "back_image" | "eyecatch_image" | "title_image" | "banner_image"
+                        | "preview_music" | "resolution" | "ln_type" => field,
+                        _ => return None,
+                    })
+                });
+                current.insert(key, info);
+            }
+            "lines" => {
+                let lines = upgrade_array(value, "lines", &mut warnings, |field| {
+                    matches!(field, "y" | "k").then_some(field)
+                });
+                current.insert(key, lines);
+            }
+            "bpmNotes" | "bpm_events" => {
+                let events = upgrade_array(value, &key, &mut warnings, |field| match field {
+                    "v" | "bpm" => Some("bpm"),
+                    "y" => Some("y"),
+                    _ => None,
+                });
+                current.insert("bpm_events".to_string(), events);
+            }
+            "stopNotes" | "stop_events" => {
+                let events = upgrade_array(value, &key, &mut warnings, |field| match field {
+                    "v" | "duration" => Some("duration"),
+                    "y" => Some("y"),
+                    _ => None,
+                });
+                current.insert("stop_events".to_string(), events);
+            }
+            "soundChannel" | "sound_channels" => {
+                let channels = upgrade_sound_channels(value, &key, &mut warnings);
+                current.insert("sound_channels".to_string(), channels);
+            }
+            "bga" => {
+                let bga = upgrade_bga(value, &mut warnings);
+                current.insert(key, bga);
+            }
+            _ => warnings.push(LegacyUpgradeWarning::UnmappedField(key)),
+        }
+    }
+
+    LegacyUpgradeOutput {
+        value: Value::Object(current),
+        warnings,
+    }
+}
+
+/// Renames keys of `object` by `rename`, dropping the keys mapped into `None` with warnings.
+fn upgrade_object(
+    object: Value,
+    path: &str,
+    warnings: &mut Vec<LegacyUpgradeWarning>,
+    rename: impl Fn(&str) -> Option<&str>,
+) -> Value {
+    let Value::Object(object) = object else {
+        warnings.push(LegacyUpgradeWarning::UnexpectedType(path.to_string()));
+        return Value::Object(Map::new());
+    };
+    let mut upgraded = Map::new();
+    for (key, value) in object {
+        match rename(&key) {
+            Some(new_key) => {
+                upgraded.insert(new_key.to_string(), value);
+            }
+            None => warnings.push(LegacyUpgradeWarning::UnmappedField(format!("{path}.{key}"))),
+        }
+    }
+    Value::Object(upgraded)
+}
+
+/// Applies [`upgrade_object`] to each element of `array`.
+fn upgrade_array(
+    array: Value,
+    path: &str,
+    warnings: &mut Vec<LegacyUpgradeWarning>,
+    rename: impl Fn(&str) -> Option<&str>,
+) -> Value {
+    let Value::Array(array) = array else {
+        warnings.push(LegacyUpgradeWarning::UnexpectedType(path.to_string()));
+        return Value::Array(vec![]);
+    };
+    Value::Array(
+        array
+            .into_iter()
+            .enumerate()
+            .map(|(i, element)| upgrade_object(element, &format!("{path}[{i}]"), warnings, &rename))
+            .collect(),
+    )
+}
+
+fn upgrade_sound_channels(
+    channels: Value,
+    path: &str,
+    warnings: &mut Vec<LegacyUpgradeWarning>,
+) -> Value {
+    let Value::Array(channels) = channels else {
+        warnings.push(LegacyUpgradeWarning::UnexpectedType(path.to_string()));
+        return Value::Array(vec![]);
+    };
+    Value::Array(
+        channels
+            .into_iter()
+            .enumerate()
+            .map(|(i, channel)| {
+                let channel_path = format!("{path}[{i}]");
+                let Value::Object(channel) = channel else {
+                    warnings.push(LegacyUpgradeWarning::UnexpectedType(channel_path));
+                    return Value::Object(Map::new());
+                };
+                let mut upgraded = Map::new();
+                for (key, value) in channel {
+                    match key.as_str() {
+                        "name" => {
+                            upgraded.insert(key, value);
+                        }
+                        "notes" => {
+                            let notes = upgrade_array(
+                                value,
+                                &format!("{channel_path}.notes"),
+                                warnings,
+                                |field| {
+                                    matches!(field, "x" | "y" | "l" | "c" | "t" | "up")
+                                        .then_some(field)
+                                },
+                            );
+                            upgraded.insert(key, notes);
+                        }
+                        _ => warnings.push(LegacyUpgradeWarning::UnmappedField(format!(
+                            "{channel_path}.{key}"
+                        ))),
+                    }
+                }
+                Value::Object(upgraded)
+            })
+            .collect(),
+    )
+}
+
+fn upgrade_bga(bga: Value, warnings: &mut Vec<LegacyUpgradeWarning>) -> Value {
+    let Value::Object(bga) = bga else {
+        warnings.push(LegacyUpgradeWarning::UnexpectedType("bga".to_string()));
+        return Value::Object(Map::new());
+    };
+    let mut upgraded = Map::new();
+    for (key, value) in bga {
+        let path = format!("bga.{key}");
+        match key.as_str() {
+            "bgaHeader" | "bga_header" => {
+                let headers = upgrade_array(value, &path, warnings, |field| match field {
+                    "ID" | "id" => Some("id"),
+                    "name" => Some("name"),
+                    _ => None,
+                });
+                upgraded.insert("bga_header".to_string(), headers);
+            }
+            "bgaNotes" | "bga_events" | "layerNotes" | "layer_events" | "poorNotes"
+            | "poor_events" => {
+                let new_key = match key.as_str() {
+                    "bgaNotes" | "bga_events" => "bga_events",
+                    "layerNotes" | "layer_events" => "layer_events",
+                    _ => "poor_events",
+                };
+                let events = upgrade_array(value, &path, warnings, |field| match field {
+                    "ID" | "id" => Some("id"),
+                    "y" => Some("y"),
+                    _ => None,
+                });
+                upgraded.insert(new_key.to_string(), events);
+            }
+            _ => warnings.push(LegacyUpgradeWarning::UnmappedField(path)),
+        }
+    }
+    Value::Object(upgraded)
+}
@@ -19,6 +19,8 @@ pub use super::bms_to_bmson::{BmsToBmsonOutput, BmsToBmsonWarning};
 
 pub use super::bmson_to_bms::{BmsonToBmsOutput, BmsonToBmsWarning};
 
+pub use super::legacy::LegacyUpgradeWarning;
+
 // Re-export utility types
 pub use super::pulse::{PulseConverter, PulseNumber};
 pub use strict_num_extended::{FinF64, PositiveF64};
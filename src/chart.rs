@@ -92,6 +92,9 @@ pub(crate) const MAX_FIN_F64: FinF64 = FinF64::new_const(f64::MAX);
 /// Default BPM value
 pub const DEFAULT_BPM: PositiveF64 = PositiveF64::new_const(120.0);
 
+/// The file of `#WAVxx` which the converted notes without sounds refer to. The players play nothing for the file not found.
+pub const SILENT_WAV: &str = "silent.wav";
+
 /// Default speed factor
 pub const DEFAULT_SPEED: PositiveF64 = PositiveF64::ONE;
 
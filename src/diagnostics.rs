@@ -132,16 +132,34 @@ pub fn build_report<'a>(
     title: &str,
     label_message: &impl ToString,
     color: Color,
+) -> Report<'a, (String, std::ops::Range<usize>)> {
+    build_report_with_help(src, kind, range, title, label_message, color, None)
+}
+
+/// Same as [`build_report`], but shows the `help` under the label if any.
+#[cfg(feature = "diagnostics")]
+#[must_use]
+pub fn build_report_with_help<'a>(
+    src: &SimpleSource<'a>,
+    kind: ReportKind<'a>,
+    range: std::ops::Range<usize>,
+    title: &str,
+    label_message: &impl ToString,
+    color: Color,
+    help: Option<&str>,
 ) -> Report<'a, (String, std::ops::Range<usize>)> {
     let filename = src.name().to_string();
-    Report::build(kind, (filename.clone(), range.clone()))
+    let mut report = Report::build(kind, (filename.clone(), range.clone()))
         .with_message(title)
         .with_label(
             Label::new((filename, range))
                 .with_message(label_message.to_string())
                 .with_color(color),
-        )
-        .finish()
+        );
+    if let Some(help) = help {
+        report.set_help(help);
+    }
+    report.finish()
 }
 
 /// Convenience method: batch render `BmsWarning` list.
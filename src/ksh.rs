@@ -0,0 +1,315 @@
+//! The [K-Shoot MANIA](https://www.kshootmania.com/) chart format (`.ksh`) definition.
+//!
+//! A ksh file consists of a header and a body. The header is a list of `key=value` lines, and the body is a sequence of measures separated by `--` lines. Each measure has note lines formed as `BBBB|FF|LL`:
+//!
+//! - `BBBB` are the BT-A..D buttons: `0` is empty, `1` is a chip and `2` is a long note.
+//! - `FF` are the FX-L/R buttons: `0` is empty, `2` is a chip and the other characters are a long note (with an audio effect).
+//! - `LL` are the left/right lasers: `-` is empty, `:` is a connection and `0-9A-Za-o` is a laser position.
+//!
+//! The note lines divide the measure equally. Option lines such as `t=180` (BPM change) or `beat=3/4` (time signature) are applied at the position of the next note line.
+//!
+//! ```text
+//! title=Sample
+//! t=120
+//! --
+//! beat=4/4
+//! 1000|00|--
+//! 0000|00|--
+//! 2000|02|0-
+//! 2000|00|o-
+//! --
+//! ```
+//!
+//! Use [`parse_ksh`] to read the text, and [`crate::bms::model::Bms::from_ksh`] to convert it into the BMS model, which also can be processed into a [`crate::chart::Chart`].
+
+pub mod ksh_to_bms;
+
+use thiserror::Error;
+
+#[cfg(feature = "diagnostics")]
+use ariadne::{Color, Label, Report, ReportKind};
+
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{SimpleSource, ToAriadne};
+
+use crate::bms::command::mixin::SourceRangeMixin;
+
+/// Top-level object for ksh format.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Ksh {
+    /// Header metadata of the chart.
+    pub header: KshHeader,
+    /// Measures of the chart body in order.
+    pub measures: Vec<KshMeasure>,
+}
+
+/// Header metadata of the chart.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KshHeader {
+    /// `title`, the title of the music.
+    pub title: Option<String>,
+    /// `artist`, the composer of the music.
+    pub artist: Option<String>,
+    /// `effect`, the author of the chart.
+    pub effect: Option<String>,
+    /// `jacket`, the jacket image file name.
+    pub jacket: Option<String>,
+    /// `illustrator`, the author of the jacket image.
+    pub illustrator: Option<String>,
+    /// `difficulty`, one of `light`, `challenge`, `extended` or `infinite`.
+    pub difficulty: Option<String>,
+    /// `level`, the difficulty level from 1 to 20.
+    pub level: Option<u8>,
+    /// `t`, the BPM to display. It may be a range such as `120-240`.
+    pub bpm: Option<String>,
+    /// `m`, the music file name. The alternative files may follow after `;`.
+    pub music: Option<String>,
+    /// `o`, the offset in milliseconds from the start of the music to the start of the chart.
+    pub offset: Option<i64>,
+    /// `total`, the gauge total percentage.
+    pub total: Option<u32>,
+    /// `ver`, the version of the format.
+    pub version: Option<String>,
+    /// Other header fields which are not interpreted, in order of appearance.
+    pub others: Vec<(String, String)>,
+}
+
+/// A measure of the chart body.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct KshMeasure {
+    /// Note lines which divide the measure equally.
+    pub lines: Vec<KshLine>,
+    /// Option lines in the measure.
+    pub options: Vec<KshOption>,
+}
+
+/// An option line in the measure, such as `t=180`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KshOption {
+    /// Index of the note line in the measure where this option is applied.
+    pub line_index: usize,
+    /// Key of the option.
+    pub key: String,
+    /// Value of the option.
+    pub value: String,
+}
+
+/// A note line in the measure, formed as `BBBB|FF|LL`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KshLine {
+    /// States of the BT-A..D buttons.
+    pub bt: [KshButton; 4],
+    /// States of the FX-L/R buttons.
+    pub fx: [KshButton; 2],
+    /// States of the left/right lasers.
+    pub lasers: [KshLaser; 2],
+}
+
+/// A state of the button on the note line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KshButton {
+    /// No note is placed.
+    #[default]
+    Empty,
+    /// A chip (single) note.
+    Chip,
+    /// A part of the long note.
+    Long,
+}
+
+/// A state of the laser on the note line.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum KshLaser {
+    /// No laser is placed.
+    #[default]
+    Empty,
+    /// The laser is connecting the previous and next positions.
+    Connection,
+    /// The laser position from 0 (left) to 50 (right).
+    Position(u8),
+}
+
+/// A warning occurred when parsing the ksh format.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Error)]
+#[non_exhaustive]
+pub enum KshWarning {
+    /// The header line is not formed as `key=value`.
+    #[error("invalid header line: {0}")]
+    InvalidHeader(String),
+    /// The header value could not be parsed.
+    #[error("invalid value for header `{key}`: {value}")]
+    InvalidHeaderValue {
+        /// Key of the header.
+        key: String,
+        /// Value of the header.
+        value: String,
+    },
+    /// The body line is neither a note line, an option line nor a comment.
+    #[error("invalid note line: {0}")]
+    InvalidNoteLine(String),
+}
+
+/// A ksh warning with position information.
+pub type KshWarningWithRange = SourceRangeMixin<KshWarning>;
+
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for KshWarningWithRange {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        let (start, end) = self.as_span();
+        let filename = src.name().to_string();
+        Report::build(ReportKind::Warning, (filename.clone(), start..end))
+            .with_message(format!("ksh: {}", self.content()))
+            .with_label(Label::new((filename, start..end)).with_color(Color::Yellow))
+            .finish()
+    }
+}
+
+/// Output of parsing a ksh file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+pub struct KshParseOutput {
+    /// The parsed chart.
+    pub ksh: Ksh,
+    /// Warnings that occurred during parsing.
+    pub warnings: Vec<KshWarningWithRange>,
+}
+
+/// Parse a ksh file from source text.
+///
+/// Parsing never fails, the malformed lines are skipped with warnings.
+pub fn parse_ksh(source: &str) -> KshParseOutput {
+    let mut ksh = Ksh::default();
+    let mut warnings = vec![];
+    let mut in_body = false;
+    let mut measure = KshMeasure::default();
+    let mut has_measure_content = false;
+
+    let mut offset = 0;
+    for raw_line in source.split_inclusive('\n') {
+        let start = offset;
+        offset += raw_line.len();
+        let line = raw_line
+            .trim_end_matches(['\r', '\n'])
+            .trim_start_matches('\u{feff}');
+        let range = start..start + line.len();
+
+        if line.starts_with("//") || line.trim().is_empty() {
+            continue;
+        }
+        if line == "--" {
+            if in_body {
+                ksh.measures.push(std::mem::take(&mut measure));
+                has_measure_content = false;
+            }
+            in_body = true;
+            continue;
+        }
+        if !in_body {
+            if let Err(warning) = parse_header_line(line, &mut ksh.header) {
+                warnings.push(SourceRangeMixin::new(warning, range));
+            }
+            continue;
+        }
+        if line.starts_with('#') {
+            // Audio effect definitions are not used for conversion.
+            continue;
+        }
+        has_measure_content = true;
+        if let Some(note_line) = parse_note_line(line) {
+            measure.lines.push(note_line);
+        } else if let Some((key, value)) = line.split_once('=') {
+            measure.options.push(KshOption {
+                line_index: measure.lines.len(),
+                key: key.trim().to_string(),
+                value: value.trim().to_string(),
+            });
+        } else {
+            warnings.push(SourceRangeMixin::new(
+                KshWarning::InvalidNoteLine(line.to_string()),
+                range,
+            ));
+        }
+    }
+    if has_measure_content {
+        ksh.measures.push(measure);
+    }
+
+    KshParseOutput { ksh, warnings }
+}
+
+fn parse_header_line(line: &str, header: &mut KshHeader) -> Result<(), KshWarning> {
+    let Some((key, value)) = line.split_once('=') else {
+        return Err(KshWarning::InvalidHeader(line.to_string()));
+    };
+    let invalid_value = || KshWarning::InvalidHeaderValue {
+        key: key.to_string(),
+        value: value.to_string(),
+    };
+    let text = Some(value.to_string());
+    match key {
+        "title" => header.title = text,
+        "artist" => header.artist = text,
+        "effect" => header.effect = text,
+        "jacket" => header.jacket = text,
+        "illustrator" => header.illustrator = text,
+        "difficulty" => header.difficulty = text,
+        "level" => header.level = Some(value.trim().parse().map_err(|_| invalid_value())?),
+        "t" => header.bpm = text,
+        "m" => header.music = text,
+        "o" => header.offset = Some(value.trim().parse().map_err(|_| invalid_value())?),
+        "total" => header.total = Some(value.trim().parse().map_err(|_| invalid_value())?),
+        "ver" => header.version = text,
+        _ => header.others.push((key.to_string(), value.to_string())),
+    }
+    Ok(())
+}
+
+fn parse_note_line(line: &str) -> Option<KshLine> {
+    let mut parts = line.split('|');
+    let (Some(bt), Some(fx), Some(lasers)) = (parts.next(), parts.next(), parts.next()) else {
+        return None;
+    };
+    let bt: Vec<_> = bt.chars().collect();
+    let fx: Vec<_> = fx.chars().collect();
+    // Lasers may be followed by a spin notation such as `@(192`.
+    let lasers: Vec<_> = lasers.chars().take(2).collect();
+    let (&[b0, b1, b2, b3], &[f0, f1], &[l0, l1]) =
+        (bt.as_slice(), fx.as_slice(), lasers.as_slice())
+    else {
+        return None;
+    };
+
+    let bt_button = |c: char| match c {
+        '0' => Some(KshButton::Empty),
+        '1' => Some(KshButton::Chip),
+        '2' => Some(KshButton::Long),
+        _ => None,
+    };
+    let fx_button = |c: char| match c {
+        '0' => KshButton::Empty,
+        '2' => KshButton::Chip,
+        _ => KshButton::Long,
+    };
+    let laser = |c: char| match c {
+        '-' => Some(KshLaser::Empty),
+        ':' => Some(KshLaser::Connection),
+        '0'..='9' => Some(KshLaser::Position(c as u8 - b'0')),
+        'A'..='Z' => Some(KshLaser::Position(c as u8 - b'A' + 10)),
+        'a'..='o' => Some(KshLaser::Position(c as u8 - b'a' + 36)),
+        _ => None,
+    };
+
+    Some(KshLine {
+        bt: [
+            bt_button(b0)?,
+            bt_button(b1)?,
+            bt_button(b2)?,
+            bt_button(b3)?,
+        ],
+        fx: [fx_button(f0), fx_button(f1)],
+        lasers: [laser(l0)?, laser(l1)?],
+    })
+}
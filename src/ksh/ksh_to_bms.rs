@@ -0,0 +1,342 @@
+//! Part: Convert `Ksh` to `Bms`.
+//!
+//! The buttons are mapped into the Player 1 lanes of [`KeyLayoutBeat`]:
+//!
+//! | ksh   | BMS    | channel     |
+//! | ----- | ------ | ----------- |
+//! | BT-A  | `Key1` | `11` / `51` |
+//! | BT-B  | `Key2` | `12` / `52` |
+//! | BT-C  | `Key3` | `13` / `53` |
+//! | BT-D  | `Key4` | `14` / `54` |
+//! | FX-L  | `Key5` | `15` / `55` |
+//! | FX-R  | `Key6` | `18` / `58` |
+//!
+//! Long notes are written in `#LNTYPE 1` notation, a pair of start and end objects. The lasers are not representable in BMS, so they are dropped with [`KshToBmsWarning::LasersDropped`].
+//!
+//! The music file `m` is placed as a BGM object of `#WAV01`. If the music offset `o` is positive, an extra lead-in measure is inserted at the track `000` so that the chart starts at `o` milliseconds of the music. The notes have no keysound, so they refer to `#WAV02` defined as [`SILENT_WAV`], a file which is not shipped and thus plays nothing.
+
+use std::{collections::BTreeSet, path::PathBuf};
+
+use strict_num_extended::{FinF64, NonNegativeF64, PositiveF64};
+use thiserror::Error;
+
+use crate::{
+    bms::{command::string_value::StringValue, prelude::*},
+    chart::{Chart, DEFAULT_BPM, process::Process},
+    ksh::{Ksh, KshButton, KshLaser, KshMeasure},
+};
+
+pub use crate::chart::SILENT_WAV;
+
+/// Warnings that occur during conversion from `Ksh` to `Bms`.
+#[derive(Debug, Clone, Error, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum KshToBmsWarning {
+    /// The lasers are not supported in BMS, so they were dropped.
+    #[error("{0} laser segments were dropped")]
+    LasersDropped(usize),
+    /// The initial BPM was missing or invalid and default value was used.
+    #[error("initial BPM was missing or invalid, using default value")]
+    MissingBpm,
+    /// The BPM change `t=` was invalid and ignored.
+    #[error("invalid BPM change: {0}")]
+    InvalidBpm(String),
+    /// The time signature `beat=` was invalid and ignored.
+    #[error("invalid time signature: {0}")]
+    InvalidBeat(String),
+    /// The stop `stop=` was invalid and ignored.
+    #[error("invalid stop: {0}")]
+    InvalidStop(String),
+    /// The option is not supported in BMS, so it was ignored.
+    #[error("unsupported option: {0}")]
+    UnsupportedOption(String),
+    /// The music offset `o` was negative, which cannot be represented, so it was ignored.
+    #[error("negative music offset {0} ms was ignored")]
+    NegativeMusicOffset(i64),
+}
+
+/// Output of the conversion from `Ksh` to `Bms`.
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct KshToBmsOutput {
+    /// The converted `Bms` object.
+    pub bms: Bms,
+    /// Warnings that occurred during the conversion.
+    pub warnings: Vec<KshToBmsWarning>,
+    /// Warnings that affect the playing of the score.
+    pub playing_warnings: Vec<PlayingWarning>,
+    /// Errors that make the score unplayable.
+    pub playing_errors: Vec<PlayingError>,
+}
+
+impl Bms {
+    /// Convert `Ksh` to `Bms`. See [the module document](self) for the conversion rules.
+    pub fn from_ksh(ksh: &Ksh) -> KshToBmsOutput {
+        let mut bms = Self::default();
+        let mut warnings = Vec::new();
+        let mut wav_obj_id_issuer = ObjId::all_values();
+        let music_wav_id = wav_obj_id_issuer.next().unwrap_or_else(ObjId::null);
+        let note_wav_id = wav_obj_id_issuer.next().unwrap_or_else(ObjId::null);
+
+        // Convert header
+        let header = &ksh.header;
+        bms.music_info.title.clone_from(&header.title);
+        bms.music_info.artist.clone_from(&header.artist);
+        bms.music_info.maker.clone_from(&header.effect);
+        bms.sprite.stage_file = header.jacket.as_ref().map(PathBuf::from);
+        bms.metadata.player = Some(PlayerMode::Single);
+        bms.metadata.play_level = header.level;
+        bms.metadata.difficulty =
+            header
+                .difficulty
+                .as_deref()
+                .and_then(|difficulty| match difficulty {
+                    "light" => Some(1),
+                    "challenge" => Some(2),
+                    "extended" => Some(3),
+                    "infinite" => Some(4),
+                    _ => None,
+                });
+        bms.judge.total = header
+            .total
+            .and_then(|total| FinF64::new(total as f64).ok())
+            .map(StringValue::from_value);
+
+        // Initial BPM, the header may have a range such as `120-240` for display
+        let init_bpm = ksh
+            .measures
+            .first()
+            .and_then(|measure| {
+                measure
+                    .options
+                    .iter()
+                    .find(|option| option.line_index == 0 && option.key == "t")
+            })
+            .map(|option| option.value.as_str())
+            .or(header.bpm.as_deref())
+            .and_then(|bpm| bpm.trim().parse().ok())
+            .and_then(|bpm| PositiveF64::new(bpm).ok())
+            .unwrap_or_else(|| {
+                warnings.push(KshToBmsWarning::MissingBpm);
+                DEFAULT_BPM
+            });
+        bms.bpm.bpm = Some(StringValue::from_value(init_bpm));
+
+        // Music and its offset
+        let music_offset = header.offset.unwrap_or(0);
+        let first_track = if music_offset > 0 {
+            if let Ok(length) =
+                FinF64::new(music_offset as f64 * init_bpm.as_f64() / (240.0 * 1000.0))
+            {
+                bms.section_len.section_len_changes.insert(
+                    Track(0),
+                    SectionLenChangeObj {
+                        track: Track(0),
+                        length,
+                    },
+                );
+            }
+            1
+        } else {
+            if music_offset < 0 {
+                warnings.push(KshToBmsWarning::NegativeMusicOffset(music_offset));
+            }
+            0
+        };
+        if let Some(music) = header
+            .music
+            .as_deref()
+            .and_then(|music| music.split(';').next())
+            .filter(|music| !music.is_empty())
+        {
+            bms.wav.wav_files.insert(music_wav_id, PathBuf::from(music));
+            bms.wav
+                .notes
+                .push_bgm::<KeyLayoutBeat>(ObjTime::start_of(Track(0)), music_wav_id);
+        }
+
+        // Options
+        let mut unsupported_options = BTreeSet::new();
+        let mut section_len = FinF64::ONE;
+        for (index, measure) in ksh.measures.iter().enumerate() {
+            let track = Track(first_track + index as u64);
+            for option in &measure.options {
+                let time = time_of_line(track, option.line_index, measure);
+                match option.key.as_str() {
+                    "t" => {
+                        if let Some(bpm) = option
+                            .value
+                            .parse()
+                            .ok()
+                            .and_then(|bpm| PositiveF64::new(bpm).ok())
+                        {
+                            bms.bpm.bpm_changes.insert(time, BpmChangeObj { time, bpm });
+                        } else {
+                            warnings.push(KshToBmsWarning::InvalidBpm(option.value.clone()));
+                        }
+                    }
+                    "beat" => {
+                        if let Some(length) = parse_beat(&option.value) {
+                            section_len = length;
+                        } else {
+                            warnings.push(KshToBmsWarning::InvalidBeat(option.value.clone()));
+                        }
+                    }
+                    "stop" => {
+                        if let Some(duration) = option
+                            .value
+                            .parse()
+                            .ok()
+                            .and_then(|duration| NonNegativeF64::new(duration).ok())
+                        {
+                            bms.stop
+                                .push_stop_ignore_duplicate(StopObj { time, duration });
+                        } else {
+                            warnings.push(KshToBmsWarning::InvalidStop(option.value.clone()));
+                        }
+                    }
+                    key => {
+                        unsupported_options.insert(key.to_string());
+                    }
+                }
+            }
+            if section_len != FinF64::ONE {
+                bms.section_len.section_len_changes.insert(
+                    track,
+                    SectionLenChangeObj {
+                        track,
+                        length: section_len,
+                    },
+                );
+            }
+        }
+        // The initial BPM is already in the header.
+        bms.bpm
+            .bpm_changes
+            .remove(&ObjTime::start_of(Track(first_track)));
+
+        // Buttons
+        let lines = || {
+            ksh.measures
+                .iter()
+                .enumerate()
+                .flat_map(move |(index, measure)| {
+                    let track = Track(first_track + index as u64);
+                    measure
+                        .lines
+                        .iter()
+                        .enumerate()
+                        .map(move |(line_index, line)| {
+                            (time_of_line(track, line_index, measure), line)
+                        })
+                })
+        };
+        let end_time = ObjTime::start_of(Track(first_track + ksh.measures.len() as u64));
+        for lane in 0..6 {
+            let key = Key::Key(lane as u8 + 1);
+            let mut push = |offset, kind| {
+                bms.wav.notes.push_note(WavObj {
+                    offset,
+                    channel_id: KeyLayoutBeat::new(PlayerSide::Player1, kind, key).to_channel_id(),
+                    wav_id: note_wav_id,
+                });
+            };
+            let mut long_start = None;
+            for (time, line) in lines() {
+                let button = line
+                    .bt
+                    .get(lane)
+                    .or_else(|| line.fx.get(lane - 4))
+                    .copied()
+                    .unwrap_or_default();
+                if button == KshButton::Long {
+                    long_start.get_or_insert(time);
+                    continue;
+                }
+                if let Some(start) = long_start.take() {
+                    push(start, NoteKind::Long);
+                    push(time, NoteKind::Long);
+                }
+                if button == KshButton::Chip {
+                    push(time, NoteKind::Visible);
+                }
+            }
+            if let Some(start) = long_start {
+                push(start, NoteKind::Long);
+                push(end_time, NoteKind::Long);
+            }
+        }
+        if bms
+            .wav
+            .notes
+            .all_notes()
+            .any(|obj| obj.wav_id == note_wav_id)
+        {
+            bms.wav
+                .wav_files
+                .insert(note_wav_id, PathBuf::from(SILENT_WAV));
+        }
+
+        // Lasers
+        let mut laser_segments = 0;
+        for side in 0..2 {
+            let mut previous = KshLaser::Empty;
+            for (_, line) in lines() {
+                let current = line.lasers.get(side).copied().unwrap_or_default();
+                if previous == KshLaser::Empty && matches!(current, KshLaser::Position(_)) {
+                    laser_segments += 1;
+                }
+                previous = current;
+            }
+        }
+        if laser_segments > 0 {
+            warnings.push(KshToBmsWarning::LasersDropped(laser_segments));
+        }
+        warnings.extend(
+            unsupported_options
+                .into_iter()
+                .map(KshToBmsWarning::UnsupportedOption),
+        );
+
+        let PlayingCheckOutput {
+            playing_warnings,
+            playing_errors,
+        } = bms.check_playing::<KeyLayoutBeat>();
+
+        KshToBmsOutput {
+            bms,
+            warnings,
+            playing_warnings,
+            playing_errors,
+        }
+    }
+}
+
+impl Process for Ksh {
+    type Error = PlayingError;
+
+    fn process(&self) -> Result<Chart, Self::Error> {
+        Process::<KeyLayoutBeat>::process(&Bms::from_ksh(self).bms)
+    }
+}
+
+/// Calculates the time of the `line_index`-th note line in `measure`.
+fn time_of_line(track: Track, line_index: usize, measure: &KshMeasure) -> ObjTime {
+    let lines = measure.lines.len() as u64;
+    let line_index = line_index as u64;
+    if lines <= line_index {
+        return ObjTime::start_of(Track(track.0 + 1));
+    }
+    ObjTime::new(track.0, line_index, lines).unwrap_or_else(|| ObjTime::start_of(track))
+}
+
+/// Parses the time signature such as `3/4` into the section length.
+fn parse_beat(beat: &str) -> Option<FinF64> {
+    let (numerator, denominator) = beat.split_once('/')?;
+    let numerator: u32 = numerator.trim().parse().ok()?;
+    let denominator: u32 = denominator.trim().parse().ok()?;
+    if numerator == 0 || denominator == 0 {
+        return None;
+    }
+    FinF64::new(numerator as f64 / denominator as f64).ok()
+}
@@ -81,6 +81,7 @@
 //! ## Optional Features
 //!
 //! - `minor-command` feature enables the commands that are almost never used in modern BMS Players.
+//! - `quaver` feature enables the Quaver (`.qua`) format support.
 //!
 //! # About the format
 //!
@@ -142,4 +143,9 @@ pub mod bms;
 pub mod bmson;
 pub mod chart;
 pub mod diagnostics;
+pub mod ksh;
+pub mod package;
+pub mod quaver;
+pub mod resource;
+pub mod timed;
 pub(crate) mod util;
@@ -0,0 +1,427 @@
+//! Analysis of the song folders holding several charts, such as the difficulties of a song.
+//!
+//! [`ChartPackage::load`] parses the charts in a directory, each with the key mapper detected by [`parse_bms_auto`]. [`ChartPackage::groups`] groups them by `#TITLE` and `#ARTIST`, and [`ChartPackage::check`] reports the packaging problems in the groups.
+//!
+//! ```no_run
+//! use bms_rs::package::ChartPackage;
+//!
+//! let package = ChartPackage::load("charts/song").unwrap();
+//! for group in package.groups() {
+//!     println!("{:?} has {} charts", group.title, group.charts.len());
+//! }
+//! for warning in package.check() {
+//!     println!("{warning}");
+//! }
+//! ```
+
+use std::{
+    collections::BTreeMap,
+    ffi::OsStr,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+
+use crate::bms::{
+    AutoBmsOutput,
+    command::{PlayerMode, channel::mapper::KeyLayoutKind},
+    default_config,
+    key_mode::{KeyModeDetection, KeyModeHints},
+    model::Bms,
+    parse::ParseErrorWithRange,
+    parse_bms_auto,
+};
+
+/// The file format of a chart, by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ChartFormat {
+    /// `.bms`, mainly for 5 keys.
+    Bms,
+    /// `.bme`, mainly for 7 keys.
+    Bme,
+    /// `.bml`, mainly for the long notes.
+    Bml,
+    /// `.pms`, for 9 keys of Pop'n Music.
+    Pms,
+    /// `.bmson`.
+    Bmson,
+}
+
+impl ChartFormat {
+    /// Returns the format of the extension of `path` ignoring ASCII case, or `None` if it is not a chart.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let extension = path.extension().and_then(OsStr::to_str)?;
+        [
+            ("bms", Self::Bms),
+            ("bme", Self::Bme),
+            ("bml", Self::Bml),
+            ("pms", Self::Pms),
+            ("bmson", Self::Bmson),
+        ]
+        .into_iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(extension))
+        .map(|(_, format)| format)
+    }
+
+    /// Returns the key layout for the charts in this format with `#PLAYER` of `player`.
+    ///
+    /// `.pms` is read by [`KeyLayoutPms`](crate::bms::command::channel::mapper::KeyLayoutPms) unless it is for double play, and the others by [`KeyLayoutBeat`](crate::bms::command::channel::mapper::KeyLayoutBeat). This does not look at the channels, unlike [`ChartPackage::load`] detecting the layout by [`parse_bms_auto`].
+    #[must_use]
+    pub const fn key_layout(self, player: Option<PlayerMode>) -> KeyLayoutKind {
+        match (self, player) {
+            (Self::Pms, Some(PlayerMode::Double)) => KeyLayoutKind::Beat,
+            (Self::Pms, _) => KeyLayoutKind::Pms,
+            _ => KeyLayoutKind::Beat,
+        }
+    }
+}
+
+/// A chart parsed by [`ChartPackage::load`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PackageChart {
+    /// The path to the chart file.
+    pub path: PathBuf,
+    /// The format of the file.
+    pub format: ChartFormat,
+    /// The key layout which the chart was parsed with.
+    pub layout: KeyLayoutKind,
+    /// The key mode detected from the channels, `#PLAYER` and the extension.
+    pub detection: KeyModeDetection,
+    /// The parsed chart. BMSON charts are converted by [`Bms::from_bmson`].
+    pub bms: Bms,
+}
+
+/// An error on loading a chart in [`ChartPackage::load`]. The chart is skipped.
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum PackageLoadError {
+    /// The file could not be read.
+    #[error("failed to read {}: {message}", path.display())]
+    Io {
+        /// The path to the chart file.
+        path: PathBuf,
+        /// The description of the I/O error.
+        message: String,
+    },
+    /// The BMS chart could not be parsed.
+    #[error("failed to parse {}: {error}", path.display())]
+    Bms {
+        /// The path to the chart file.
+        path: PathBuf,
+        /// The error on parsing.
+        error: ParseErrorWithRange,
+    },
+    /// The BMSON chart could not be parsed, or the `bmson` feature is disabled.
+    #[error("failed to parse {}: {message}", path.display())]
+    Bmson {
+        /// The path to the chart file.
+        path: PathBuf,
+        /// The description of the errors.
+        message: String,
+    },
+}
+
+/// The charts sharing `#TITLE` and `#ARTIST`, returned by [`ChartPackage::groups`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ChartGroup {
+    /// The shared `#TITLE`.
+    pub title: Option<String>,
+    /// The shared `#ARTIST`.
+    pub artist: Option<String>,
+    /// The indices of the charts in [`ChartPackage::charts`].
+    pub charts: Vec<usize>,
+}
+
+/// A packaging problem found by [`ChartPackage::check`].
+#[non_exhaustive]
+#[derive(Debug, Clone, PartialEq, Error)]
+pub enum PackageWarning {
+    /// The charts in a group have different `#BPM`s.
+    #[error("charts of {title:?} have different #BPM: {bpms:?}")]
+    DifferentBpm {
+        /// The `#TITLE` of the group.
+        title: Option<String>,
+        /// The `#BPM` of each chart, or `None` if undefined.
+        bpms: Vec<(PathBuf, Option<f64>)>,
+    },
+    /// Some charts in a group have `#SUBTITLE` while others do not.
+    #[error("charts of {title:?} do not agree whether to have #SUBTITLE: {subtitles:?}")]
+    MismatchedSubtitle {
+        /// The `#TITLE` of the group.
+        title: Option<String>,
+        /// The `#SUBTITLE` of each chart.
+        subtitles: Vec<(PathBuf, Option<String>)>,
+    },
+    /// Several charts in a group have the same `#DIFFICULTY` for the same play style.
+    #[error("charts of {title:?} share #DIFFICULTY {difficulty}: {charts:?}")]
+    DuplicateDifficulty {
+        /// The `#TITLE` of the group.
+        title: Option<String>,
+        /// The shared `#DIFFICULTY`.
+        difficulty: u8,
+        /// The charts having it.
+        charts: Vec<PathBuf>,
+    },
+    /// A resource file referred by charts is not found in the directory.
+    #[error("{} referred by {charts:?} is missing", declared.display())]
+    MissingResource {
+        /// The path written in the charts.
+        declared: PathBuf,
+        /// The charts referring it.
+        charts: Vec<PathBuf>,
+    },
+}
+
+/// The charts in a song folder.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartPackage {
+    /// The directory of the charts.
+    pub dir: PathBuf,
+    /// The charts loaded, sorted by the path.
+    pub charts: Vec<PackageChart>,
+    /// The charts failed to load.
+    pub errors: Vec<PackageLoadError>,
+}
+
+impl ChartPackage {
+    /// Returns the paths to the chart files directly in `dir`, sorted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` could not be read.
+    pub fn discover(dir: impl AsRef<Path>) -> io::Result<Vec<PathBuf>> {
+        let mut paths = vec![];
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() && ChartFormat::from_path(&entry.path()).is_some() {
+                paths.push(entry.path());
+            }
+        }
+        paths.sort_unstable();
+        Ok(paths)
+    }
+
+    /// Loads the charts in `dir`, reading the files as UTF-8 and replacing the invalid sequences.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` could not be read.
+    pub fn load(dir: impl AsRef<Path>) -> io::Result<Self> {
+        Self::load_with(dir, |bytes| String::from_utf8_lossy(bytes).into_owned())
+    }
+
+    /// Loads the charts in `dir`, decoding the files by `decode` such as from `Shift_JIS`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` could not be read.
+    pub fn load_with(
+        dir: impl AsRef<Path>,
+        mut decode: impl FnMut(&[u8]) -> String,
+    ) -> io::Result<Self> {
+        let dir = dir.as_ref();
+        let mut package = Self {
+            dir: dir.to_path_buf(),
+            charts: vec![],
+            errors: vec![],
+        };
+        for path in Self::discover(dir)? {
+            let Some(format) = ChartFormat::from_path(&path) else {
+                continue;
+            };
+            let source = match fs::read(&path) {
+                Ok(bytes) => decode(&bytes),
+                Err(error) => {
+                    package.errors.push(PackageLoadError::Io {
+                        path,
+                        message: error.to_string(),
+                    });
+                    continue;
+                }
+            };
+            package.push_chart(path, format, &source);
+        }
+        Ok(package)
+    }
+
+    /// Groups the charts by `#TITLE` and `#ARTIST`, in the order of the first chart of each group.
+    #[must_use]
+    pub fn groups(&self) -> Vec<ChartGroup> {
+        let mut groups: Vec<ChartGroup> = vec![];
+        for (index, chart) in self.charts.iter().enumerate() {
+            let title = trimmed(chart.bms.music_info.title.as_deref());
+            let artist = trimmed(chart.bms.music_info.artist.as_deref());
+            if let Some(group) = groups
+                .iter_mut()
+                .find(|group| group.title == title && group.artist == artist)
+            {
+                group.charts.push(index);
+            } else {
+                groups.push(ChartGroup {
+                    title,
+                    artist,
+                    charts: vec![index],
+                });
+            }
+        }
+        groups
+    }
+
+    /// Checks the groups for different `#BPM`s, mismatched `#SUBTITLE`s and duplicate `#DIFFICULTY`s, and the charts for the missing resource files.
+    #[must_use]
+    pub fn check(&self) -> Vec<PackageWarning> {
+        let mut warnings = vec![];
+        for group in self.groups() {
+            let members: Vec<_> = group
+                .charts
+                .iter()
+                .filter_map(|&index| self.charts.get(index))
+                .collect();
+
+            let bpms: Vec<_> = members
+                .iter()
+                .map(|chart| {
+                    let bpm = chart
+                        .bms
+                        .bpm
+                        .bpm
+                        .as_ref()
+                        .and_then(|bpm| bpm.value().as_ref().ok())
+                        .map(|bpm| bpm.as_f64());
+                    (chart.path.clone(), bpm)
+                })
+                .collect();
+            if bpms.windows(2).any(|pair| match pair {
+                [(_, a), (_, b)] => a != b,
+                _ => false,
+            }) {
+                warnings.push(PackageWarning::DifferentBpm {
+                    title: group.title.clone(),
+                    bpms,
+                });
+            }
+
+            let subtitles: Vec<_> = members
+                .iter()
+                .map(|chart| {
+                    let subtitle = trimmed(chart.bms.music_info.subtitle.as_deref());
+                    (chart.path.clone(), subtitle)
+                })
+                .collect();
+            let with_subtitle = subtitles
+                .iter()
+                .filter(|(_, subtitle)| subtitle.is_some())
+                .count();
+            if with_subtitle != 0 && with_subtitle != subtitles.len() {
+                warnings.push(PackageWarning::MismatchedSubtitle {
+                    title: group.title.clone(),
+                    subtitles,
+                });
+            }
+
+            let mut slots: BTreeMap<_, Vec<PathBuf>> = BTreeMap::new();
+            for chart in &members {
+                let Some(difficulty) = chart.bms.metadata.difficulty else {
+                    continue;
+                };
+                slots
+                    .entry((difficulty, chart.detection.mode))
+                    .or_default()
+                    .push(chart.path.clone());
+            }
+            warnings.extend(
+                slots
+                    .into_iter()
+                    .filter(|(_, charts)| charts.len() > 1)
+                    .map(
+                        |((difficulty, _), charts)| PackageWarning::DuplicateDifficulty {
+                            title: group.title.clone(),
+                            difficulty,
+                            charts,
+                        },
+                    ),
+            );
+        }
+
+        let mut missing: BTreeMap<PathBuf, Vec<PathBuf>> = BTreeMap::new();
+        for chart in &self.charts {
+            for resource in chart.bms.check_resources(&self.dir).missing() {
+                let charts = missing.entry(resource.declared.clone()).or_default();
+                if !charts.contains(&chart.path) {
+                    charts.push(chart.path.clone());
+                }
+            }
+        }
+        warnings.extend(
+            missing
+                .into_iter()
+                .map(|(declared, charts)| PackageWarning::MissingResource { declared, charts }),
+        );
+        warnings
+    }
+}
+
+impl ChartPackage {
+    /// Parses a BMS chart and adds it, or records the error.
+    fn push_chart(&mut self, path: PathBuf, format: ChartFormat, source: &str) {
+        if format == ChartFormat::Bmson {
+            self.push_bmson(path, source);
+            return;
+        }
+        let hints = KeyModeHints::from_path(&path);
+        let AutoBmsOutput { output, detection } = parse_bms_auto(source, default_config(), &hints);
+        match output.bms {
+            Ok(bms) => self.charts.push(PackageChart {
+                path,
+                format,
+                layout: detection.layout,
+                detection,
+                bms,
+            }),
+            Err(error) => self.errors.push(PackageLoadError::Bms { path, error }),
+        }
+    }
+
+    #[cfg(feature = "bmson")]
+    fn push_bmson(&mut self, path: PathBuf, source: &str) {
+        let output = crate::bmson::parse_bmson(source);
+        let Some(bmson) = output.bmson else {
+            let message = output
+                .errors
+                .iter()
+                .map(|error| format!("{error:?}"))
+                .collect::<Vec<_>>()
+                .join("; ");
+            self.errors.push(PackageLoadError::Bmson { path, message });
+            return;
+        };
+        let mode_hint = bmson.info.mode_hint.to_string();
+        let bms = Bms::from_bmson(bmson).bms;
+        let hints = KeyModeHints::default().with_mode_hint(&mode_hint);
+        self.charts.push(PackageChart {
+            path,
+            format: ChartFormat::Bmson,
+            // The lanes are converted into the channels of the beat layout.
+            layout: KeyLayoutKind::Beat,
+            detection: bms.detect_key_mode(&hints),
+            bms,
+        });
+    }
+
+    #[cfg(not(feature = "bmson"))]
+    fn push_bmson(&mut self, path: PathBuf, _source: &str) {
+        self.errors.push(PackageLoadError::Bmson {
+            path,
+            message: "the `bmson` feature is disabled".into(),
+        });
+    }
+}
+
+fn trimmed(text: Option<&str>) -> Option<String> {
+    text.map(str::trim)
+        .filter(|text| !text.is_empty())
+        .map(str::to_string)
+}
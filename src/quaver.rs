@@ -0,0 +1,321 @@
+//! The [Quaver](https://quavergame.com/) chart format (`.qua`) definition.
+//!
+//! A qua file is a YAML document. Unlike BMS, all the objects are placed by the time in milliseconds from the start of the audio file:
+//!
+//! - [`QuaTimingPoint`]s define the BPM and the time signature from their start time,
+//! - [`QuaSliderVelocity`]s change the scroll speed multiplier from their start time,
+//! - [`QuaHitObject`]s are the notes, which become long notes if they have the end time.
+//!
+//! ```yaml
+//! AudioFile: audio.mp3
+//! Mode: Keys4
+//! Title: Sample
+//! TimingPoints:
+//! - StartTime: 0
+//!   Bpm: 120
+//! SliderVelocities:
+//! - StartTime: 1000
+//!   Multiplier: 1.5
+//! HitObjects:
+//! - StartTime: 1000
+//!   Lane: 1
+//! - StartTime: 1500
+//!   Lane: 2
+//!   EndTime: 2000
+//! ```
+//!
+//! Use [`parse_qua`] to read the text, and [`crate::bms::model::Bms::from_qua`] to convert it into the BMS model, which also can be processed into a [`crate::chart::Chart`]. [`crate::bms::model::Bms::to_qua`] converts it back.
+#![cfg(feature = "quaver")]
+#![cfg_attr(docsrs, doc(cfg(feature = "quaver")))]
+
+pub mod bms_to_qua;
+pub mod qua_to_bms;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[cfg(feature = "diagnostics")]
+use ariadne::{Color, Label, Report, ReportKind};
+
+#[cfg(feature = "diagnostics")]
+use crate::diagnostics::{SimpleSource, ToAriadne};
+
+/// Top-level object for qua format.
+///
+/// The fields which are not used for conversion, such as `EditorLayers` or `SoundEffects`, are ignored.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct Qua {
+    /// The audio file name of the music.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_file: Option<String>,
+    /// The time in milliseconds to start the preview of the music.
+    pub song_preview_time: i64,
+    /// The background image file name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub background_file: Option<String>,
+    /// The map id on the server, `-1` if not uploaded.
+    pub map_id: i64,
+    /// The map set id on the server, `-1` if not uploaded.
+    pub map_set_id: i64,
+    /// The key mode of the chart.
+    pub mode: QuaMode,
+    /// The title of the music.
+    pub title: String,
+    /// The artist of the music.
+    pub artist: String,
+    /// The source of the music, such as the game it is from.
+    pub source: String,
+    /// The tags for searching.
+    pub tags: String,
+    /// The author of the chart.
+    pub creator: String,
+    /// The name of the difficulty, such as `Hard`.
+    pub difficulty_name: String,
+    /// The description of the chart.
+    pub description: String,
+    /// The genre of the music.
+    pub genre: String,
+    /// Whether the slider velocities are not normalized by the BPM.
+    #[serde(rename = "BPMDoesNotAffectScrollVelocity")]
+    pub bpm_does_not_affect_scroll_velocity: bool,
+    /// The scroll speed multiplier before the first slider velocity.
+    pub initial_scroll_velocity: f64,
+    /// Whether the chart has the scratch lane after the keys.
+    pub has_scratch_key: bool,
+    /// The audio samples for keysounds, referred by [`QuaKeySound::sample`] from 1.
+    pub custom_audio_samples: Vec<QuaAudioSample>,
+    /// The timing points in order of the start time.
+    pub timing_points: Vec<QuaTimingPoint>,
+    /// The scroll speed changes in order of the start time.
+    pub slider_velocities: Vec<QuaSliderVelocity>,
+    /// The notes in order of the start time.
+    pub hit_objects: Vec<QuaHitObject>,
+}
+
+impl Default for Qua {
+    fn default() -> Self {
+        Self {
+            audio_file: None,
+            song_preview_time: 0,
+            background_file: None,
+            map_id: -1,
+            map_set_id: -1,
+            mode: QuaMode::default(),
+            title: String::new(),
+            artist: String::new(),
+            source: String::new(),
+            tags: String::new(),
+            creator: String::new(),
+            difficulty_name: String::new(),
+            description: String::new(),
+            genre: String::new(),
+            bpm_does_not_affect_scroll_velocity: false,
+            initial_scroll_velocity: 1.0,
+            has_scratch_key: false,
+            custom_audio_samples: vec![],
+            timing_points: vec![],
+            slider_velocities: vec![],
+            hit_objects: vec![],
+        }
+    }
+}
+
+/// The key mode of the chart.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuaMode {
+    /// 4 keys.
+    #[default]
+    Keys4,
+    /// 7 keys.
+    Keys7,
+}
+
+impl QuaMode {
+    /// Returns the number of the keys, excluding the scratch lane.
+    #[must_use]
+    pub const fn key_count(self) -> u8 {
+        match self {
+            Self::Keys4 => 4,
+            Self::Keys7 => 7,
+        }
+    }
+}
+
+/// An audio sample for keysounds.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct QuaAudioSample {
+    /// The audio file name.
+    pub path: String,
+    /// Whether the sample is played at the normal speed regardless of the playback rate.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub unaffected_by_rate: bool,
+}
+
+/// A timing point, which defines the BPM and the time signature from its start time.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct QuaTimingPoint {
+    /// The start time in milliseconds.
+    pub start_time: f64,
+    /// The BPM from the start time.
+    pub bpm: f64,
+    /// The time signature from the start time.
+    #[serde(skip_serializing_if = "is_default_signature")]
+    pub signature: QuaTimeSignature,
+    /// Whether the bar lines are hidden.
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub hidden: bool,
+}
+
+impl Default for QuaTimingPoint {
+    fn default() -> Self {
+        Self {
+            start_time: 0.0,
+            bpm: 120.0,
+            signature: QuaTimeSignature::default(),
+            hidden: false,
+        }
+    }
+}
+
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "signature required by serde"
+)]
+fn is_default_signature(signature: &QuaTimeSignature) -> bool {
+    *signature == QuaTimeSignature::default()
+}
+
+/// The time signature of the timing point.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum QuaTimeSignature {
+    /// 4/4, four beats in a measure.
+    #[default]
+    Quadruple,
+    /// 3/4, three beats in a measure.
+    Triple,
+}
+
+impl QuaTimeSignature {
+    /// Returns the number of the beats in a measure.
+    #[must_use]
+    pub const fn beats(self) -> u32 {
+        match self {
+            Self::Quadruple => 4,
+            Self::Triple => 3,
+        }
+    }
+}
+
+/// A scroll speed change.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct QuaSliderVelocity {
+    /// The start time in milliseconds.
+    pub start_time: f64,
+    /// The scroll speed multiplier from the start time.
+    pub multiplier: f64,
+}
+
+/// A note of the chart.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct QuaHitObject {
+    /// The time in milliseconds to hit.
+    pub start_time: i64,
+    /// The lane from 1. The scratch lane follows after the keys if [`Qua::has_scratch_key`] is set.
+    pub lane: u8,
+    /// The time in milliseconds to release for the long note, or `0` for the normal note.
+    #[serde(skip_serializing_if = "is_zero")]
+    pub end_time: i64,
+    /// The keysounds played on hit.
+    pub key_sounds: Vec<QuaKeySound>,
+}
+
+impl QuaHitObject {
+    /// Returns whether this is a long note.
+    #[must_use]
+    pub const fn is_long_note(&self) -> bool {
+        self.start_time < self.end_time
+    }
+}
+
+#[expect(
+    clippy::trivially_copy_pass_by_ref,
+    reason = "signature required by serde"
+)]
+const fn is_zero(value: &i64) -> bool {
+    *value == 0
+}
+
+/// A keysound of the note.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase", default)]
+pub struct QuaKeySound {
+    /// The index of [`Qua::custom_audio_samples`] from 1.
+    pub sample: usize,
+    /// The volume in percent.
+    pub volume: u8,
+}
+
+impl Default for QuaKeySound {
+    fn default() -> Self {
+        Self {
+            sample: 0,
+            volume: 100,
+        }
+    }
+}
+
+/// An error occurred when parsing or writing the qua format.
+#[derive(Debug, Error)]
+#[error("qua: {0}")]
+pub struct QuaError(#[from] pub serde_yaml_ng::Error);
+
+#[cfg(feature = "diagnostics")]
+impl ToAriadne for QuaError {
+    fn to_report<'a>(
+        &self,
+        src: &SimpleSource<'a>,
+    ) -> Report<'a, (String, std::ops::Range<usize>)> {
+        let start = self.0.location().map_or(0, |location| location.index());
+        let filename = src.name().to_string();
+        Report::build(ReportKind::Error, (filename.clone(), start..start))
+            .with_message(self.to_string())
+            .with_label(Label::new((filename, start..start)).with_color(Color::Red))
+            .finish()
+    }
+}
+
+/// Parse a qua file from YAML string.
+///
+/// # Errors
+///
+/// Returns [`QuaError`] if the source is not a valid YAML or does not match the qua schema.
+pub fn parse_qua(source: &str) -> Result<Qua, QuaError> {
+    Ok(serde_yaml_ng::from_str(
+        source.trim_start_matches('\u{feff}'),
+    )?)
+}
+
+impl Qua {
+    /// Serializes this chart into a YAML string. The optional fields which are absent or have no effect, such as `EndTime` of normal notes, are omitted. The output can be read back with [`parse_qua`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QuaError`] if `serde_yaml_ng` failed to serialize the chart.
+    pub fn to_string(&self) -> Result<String, QuaError> {
+        Ok(serde_yaml_ng::to_string(self)?)
+    }
+
+    /// Writes this chart as YAML into `writer`. See also [`Qua::to_string`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`QuaError`] if `serde_yaml_ng` failed to serialize the chart or writing into `writer` failed.
+    pub fn to_writer<W: std::io::Write>(&self, writer: W) -> Result<(), QuaError> {
+        Ok(serde_yaml_ng::to_writer(writer, self)?)
+    }
+}
@@ -0,0 +1,238 @@
+//! Part: Convert `Bms` to `Qua`.
+//!
+//! The chart is converted through [`TimedChart`](crate::timed::TimedChart), so the positions are converted into the times in milliseconds as described in [`crate::timed::bms_to_timed`].
+//!
+//! The Player 1 lanes of [`KeyLayoutBeat`] are mapped into the lanes, `Key1` to `Key4` as `Keys4` mode or `Key1` to `Key7` as `Keys7` mode, and `Scratch(1)` into the scratch lane. The other notes, such as the invisible notes, the landmines or the Player 2 lanes, cannot be represented and are dropped.
+//!
+//! A timing point is placed at each BPM change and each change of the section length `#xxx02`. Quaver has only the time signatures of 3 and 4 beats, so the other section lengths are dropped with [`BmsToQuaWarning::SignatureDropped`]. Quaver has no stops, so the scrolling is stopped by the slider velocity `0` while stopping instead. The scrolling factor changes `#SCROLLxx` are converted into the slider velocities.
+//!
+//! The BGM object at the start of the chart is used as the audio file, and the keysounds of the notes are listed in the custom audio samples. The other BGM objects are dropped.
+
+use std::collections::BTreeMap;
+
+use thiserror::Error;
+
+use crate::{
+    bms::prelude::*,
+    quaver::{
+        Qua, QuaAudioSample, QuaHitObject, QuaKeySound, QuaMode, QuaSliderVelocity,
+        QuaTimeSignature, QuaTimingPoint,
+    },
+    timed::{
+        TimedLane, TimedObject,
+        bms_to_timed::{BmsToTimedOutput, BmsToTimedWarning},
+    },
+};
+
+/// Warnings that occur during conversion from `Bms` to `Qua`.
+#[derive(Debug, Clone, Error, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BmsToQuaWarning {
+    /// The notes not representable in Quaver were dropped.
+    #[error("{0} notes not representable in Quaver were dropped")]
+    NotesDropped(usize),
+    /// The BGM objects other than the audio file were dropped.
+    #[error("{0} BGM objects were dropped")]
+    BgmDropped(usize),
+    /// The measures from the time in milliseconds had neither 3 nor 4 beats, so the previous time signature was kept.
+    #[error("time signature at {0} ms is not representable in Quaver")]
+    SignatureDropped(i64),
+    /// A warning from the conversion into the intermediate [`TimedChart`](crate::timed::TimedChart).
+    #[error(transparent)]
+    Timed(#[from] BmsToTimedWarning),
+}
+
+/// Output of the conversion from `Bms` to `Qua`.
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct BmsToQuaOutput {
+    /// The converted `Qua` object.
+    pub qua: Qua,
+    /// Warnings that occurred during the conversion.
+    pub warnings: Vec<BmsToQuaWarning>,
+}
+
+impl Bms {
+    /// Convert `Bms` to `Qua`. See [the module document](crate::quaver::bms_to_qua) for the conversion rules.
+    pub fn to_qua(&self) -> BmsToQuaOutput {
+        let BmsToTimedOutput {
+            timed,
+            warnings: timed_warnings,
+        } = self.to_timed::<KeyLayoutBeat>();
+        let mut warnings: Vec<BmsToQuaWarning> = timed_warnings
+            .into_iter()
+            .map(BmsToQuaWarning::Timed)
+            .collect();
+
+        // Notes
+        let mut dropped_notes = 0;
+        let mut notes: Vec<(&TimedObject, Key)> = Vec::new();
+        let mut bgms = Vec::new();
+        for object in &timed.objects {
+            match (object.lane, object.kind) {
+                (None, _) => bgms.push(object),
+                (
+                    Some(TimedLane {
+                        side: PlayerSide::Player1,
+                        key: key @ (Key::Key(1..=7) | Key::Scratch(1)),
+                    }),
+                    NoteKind::Visible | NoteKind::Long,
+                ) => notes.push((object, key)),
+                _ => dropped_notes += 1,
+            }
+        }
+        if dropped_notes > 0 {
+            warnings.push(BmsToQuaWarning::NotesDropped(dropped_notes));
+        }
+        let mode = if notes.iter().any(|(_, key)| matches!(key, Key::Key(5..=7))) {
+            QuaMode::Keys7
+        } else {
+            QuaMode::Keys4
+        };
+        let has_scratch_key = notes.iter().any(|&(_, key)| key == Key::Scratch(1));
+
+        let mut samples: BTreeMap<usize, usize> = notes
+            .iter()
+            .filter_map(|(object, _)| object.sound)
+            .map(|sound| (sound, 0))
+            .collect();
+        for (index, sample) in samples.values_mut().enumerate() {
+            *sample = index + 1;
+        }
+        let mut hit_objects: Vec<QuaHitObject> = notes
+            .iter()
+            .map(|&(object, key)| {
+                let lane = match key {
+                    Key::Key(lane) => lane,
+                    _ => mode.key_count() + 1,
+                };
+                let end_time = if object.kind == NoteKind::Long {
+                    (object.ms + object.length_ms).round() as i64
+                } else {
+                    0
+                };
+                QuaHitObject {
+                    start_time: object.ms.round() as i64,
+                    lane,
+                    end_time,
+                    key_sounds: object
+                        .sound
+                        .and_then(|sound| samples.get(&sound))
+                        .map(|&sample| QuaKeySound {
+                            sample,
+                            volume: 100,
+                        })
+                        .into_iter()
+                        .collect(),
+                }
+            })
+            .collect();
+        hit_objects.sort_by_key(|hit_object| (hit_object.start_time, hit_object.lane));
+
+        // Audio file
+        let audio_file = bgms
+            .iter()
+            .filter(|object| object.ms.abs() <= f64::EPSILON)
+            .find_map(|object| object.sound.and_then(|sound| timed.sounds.get(sound)));
+        let dropped_bgms = bgms.len() - usize::from(audio_file.is_some());
+        if dropped_bgms > 0 {
+            warnings.push(BmsToQuaWarning::BgmDropped(dropped_bgms));
+        }
+
+        // Timing
+        let mut timing_points = vec![];
+        let mut last_bpm = None;
+        let mut signature = QuaTimeSignature::default();
+        for point in &timed.tempo_map {
+            let mut changed = last_bpm != Some(point.bpm);
+            last_bpm = Some(point.bpm);
+            if let Some(beats) = point.beats_per_measure {
+                let ms = point.ms.round() as i64;
+                match signature_of(beats.as_f64()) {
+                    Some(new) => {
+                        changed |= new != signature;
+                        signature = new;
+                    }
+                    None => warnings.push(BmsToQuaWarning::SignatureDropped(ms)),
+                }
+            }
+            if changed {
+                timing_points.push(QuaTimingPoint {
+                    start_time: point.ms,
+                    bpm: point.bpm.as_f64(),
+                    signature,
+                    ..QuaTimingPoint::default()
+                });
+            }
+        }
+
+        let mut slider_velocities: Vec<QuaSliderVelocity> = timed
+            .scroll_changes
+            .iter()
+            .map(|change| QuaSliderVelocity {
+                start_time: change.ms,
+                multiplier: change.factor.as_f64(),
+            })
+            .collect();
+        for stop in &timed.stops {
+            if stop.duration_ms.as_f64() <= 0.0 {
+                continue;
+            }
+            let resumed = timed
+                .scroll_changes
+                .iter()
+                .rev()
+                .find(|change| change.ms <= stop.ms)
+                .map_or(1.0, |change| change.factor.as_f64());
+            slider_velocities.push(QuaSliderVelocity {
+                start_time: stop.ms,
+                multiplier: 0.0,
+            });
+            slider_velocities.push(QuaSliderVelocity {
+                start_time: stop.ms + stop.duration_ms.as_f64(),
+                multiplier: resumed,
+            });
+        }
+        slider_velocities.sort_by(|a, b| a.start_time.total_cmp(&b.start_time));
+
+        let qua = Qua {
+            audio_file: audio_file.map(|path| path.display().to_string()),
+            background_file: self
+                .sprite
+                .stage_file
+                .as_ref()
+                .or(self.sprite.back_bmp.as_ref())
+                .map(|path| path.display().to_string()),
+            mode,
+            title: self.music_info.title.clone().unwrap_or_default(),
+            artist: self.music_info.artist.clone().unwrap_or_default(),
+            creator: self.music_info.maker.clone().unwrap_or_default(),
+            difficulty_name: self.music_info.subtitle.clone().unwrap_or_default(),
+            genre: self.music_info.genre.clone().unwrap_or_default(),
+            has_scratch_key,
+            custom_audio_samples: samples
+                .keys()
+                .map(|&sound| QuaAudioSample {
+                    path: timed
+                        .sounds
+                        .get(sound)
+                        .map(|path| path.display().to_string())
+                        .unwrap_or_default(),
+                    unaffected_by_rate: false,
+                })
+                .collect(),
+            timing_points,
+            slider_velocities,
+            hit_objects,
+            ..Qua::default()
+        };
+        BmsToQuaOutput { qua, warnings }
+    }
+}
+
+/// Returns the time signature of the measures with `beats` beats, if Quaver has it.
+fn signature_of(beats: f64) -> Option<QuaTimeSignature> {
+    [QuaTimeSignature::Quadruple, QuaTimeSignature::Triple]
+        .into_iter()
+        .find(|signature| (f64::from(signature.beats()) - beats).abs() < 1e-9)
+}
@@ -0,0 +1,258 @@
+//! Part: Convert `Qua` to `Bms`.
+//!
+//! The chart is converted through [`TimedChart`], so the measures and the positions are chosen as described in [`crate::timed::timed_to_bms`]. Each timing point starts a new measure like Quaver draws the bar lines.
+//!
+//! The lanes are mapped into the Player 1 lanes of [`KeyLayoutBeat`], `Key1` to `Key4` (or `Key7`) in order, and the scratch lane into `Scratch(1)`.
+//!
+//! The audio file is placed as a BGM object of `#WAV01` at the start of the audio, and the custom audio samples follow as `#WAV02` and later. The notes without keysounds refer to the next id, which is defined as [`SILENT_WAV`](crate::chart::SILENT_WAV) only if it is referred. The slider velocities are converted into scrolling factor changes `#SCROLLxx`.
+
+use std::path::PathBuf;
+
+use strict_num_extended::{FinF64, PositiveF64};
+use thiserror::Error;
+
+use crate::{
+    bms::prelude::*,
+    chart::{Chart, DEFAULT_BPM, process::Process},
+    quaver::Qua,
+    timed::{
+        TempoPoint, TimedChart, TimedLane, TimedObject, TimedScrollChange,
+        timed_to_bms::{Quantization, TimedToBmsOutput, TimedToBmsWarning},
+    },
+};
+
+/// Warnings that occur during conversion from `Qua` to `Bms`.
+#[derive(Debug, Clone, Error, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum QuaToBmsWarning {
+    /// The timing point at the time had an invalid BPM and was ignored.
+    #[error("timing point at {0} ms has an invalid BPM")]
+    InvalidBpm(i64),
+    /// The slider velocity at the time had an invalid multiplier and was ignored.
+    #[error("slider velocity at {0} ms has an invalid multiplier")]
+    InvalidSliderVelocity(i64),
+    /// The hit object at the time was on the lane out of the key mode, so it was dropped.
+    #[error("hit object at {time} ms is on the invalid lane {lane}")]
+    InvalidLane {
+        /// The start time of the hit object.
+        time: i64,
+        /// The lane of the hit object.
+        lane: u8,
+    },
+    /// The keysound referred an audio sample which does not exist, so it was ignored.
+    #[error("keysound refers to the unknown sample {0}")]
+    UnknownSample(usize),
+    /// A warning from the conversion of the intermediate [`TimedChart`].
+    #[error(transparent)]
+    Timed(#[from] TimedToBmsWarning),
+}
+
+/// Output of the conversion from `Qua` to `Bms`.
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct QuaToBmsOutput {
+    /// The converted `Bms` object.
+    pub bms: Bms,
+    /// Warnings that occurred during the conversion.
+    pub warnings: Vec<QuaToBmsWarning>,
+    /// The largest difference in milliseconds between the original and the placed times.
+    pub max_quantization_error_ms: f64,
+    /// Warnings that affect the playing of the score.
+    pub playing_warnings: Vec<PlayingWarning>,
+    /// Errors that make the score unplayable.
+    pub playing_errors: Vec<PlayingError>,
+}
+
+impl Bms {
+    /// Convert `Qua` to `Bms`. See [the module document](self) for the conversion rules.
+    pub fn from_qua(qua: &Qua) -> QuaToBmsOutput {
+        let mut warnings = Vec::new();
+        let timed = qua_to_timed(qua, &mut warnings);
+        let TimedToBmsOutput {
+            mut bms,
+            warnings: timed_warnings,
+            max_quantization_error_ms,
+            playing_warnings,
+            playing_errors,
+        } = Self::from_timed::<KeyLayoutBeat>(&timed, Quantization::default());
+        warnings.extend(timed_warnings.into_iter().map(QuaToBmsWarning::Timed));
+
+        // Convert header
+        let non_empty = |text: &str| (!text.is_empty()).then(|| text.to_string());
+        bms.music_info.title = non_empty(&qua.title);
+        bms.music_info.subtitle = non_empty(&qua.difficulty_name);
+        bms.music_info.artist = non_empty(&qua.artist);
+        bms.music_info.genre = non_empty(&qua.genre);
+        bms.music_info.maker = non_empty(&qua.creator);
+        bms.sprite.stage_file = qua.background_file.as_ref().map(PathBuf::from);
+        bms.metadata.player = Some(PlayerMode::Single);
+
+        QuaToBmsOutput {
+            bms,
+            warnings,
+            max_quantization_error_ms,
+            playing_warnings,
+            playing_errors,
+        }
+    }
+}
+
+impl Process for Qua {
+    type Error = PlayingError;
+
+    fn process(&self) -> Result<Chart, Self::Error> {
+        Process::<KeyLayoutBeat>::process(&Bms::from_qua(self).bms)
+    }
+}
+
+fn qua_to_timed(qua: &Qua, warnings: &mut Vec<QuaToBmsWarning>) -> TimedChart {
+    let mut timed = TimedChart::default();
+
+    // Timing
+    for point in &qua.timing_points {
+        let bpm = PositiveF64::new(point.bpm)
+            .ok()
+            .filter(|_| point.start_time.is_finite());
+        let Some(bpm) = bpm else {
+            warnings.push(QuaToBmsWarning::InvalidBpm(point.start_time as i64));
+            continue;
+        };
+        timed.tempo_map.push(TempoPoint {
+            ms: point.start_time,
+            bpm,
+            beats_per_measure: PositiveF64::new(point.signature.beats() as f64).ok(),
+        });
+    }
+    timed.tempo_map.sort_by(|a, b| a.ms.total_cmp(&b.ms));
+
+    // Slider velocities
+    let mut slider_velocities = Vec::new();
+    for sv in &qua.slider_velocities {
+        if sv.multiplier.is_finite() && sv.start_time.is_finite() {
+            slider_velocities.push((sv.start_time, sv.multiplier));
+        } else {
+            warnings.push(QuaToBmsWarning::InvalidSliderVelocity(sv.start_time as i64));
+        }
+    }
+    slider_velocities.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+    let origin_ms = qua
+        .hit_objects
+        .iter()
+        .map(|hit_object| hit_object.start_time as f64)
+        .chain(timed.tempo_map.iter().map(|point| point.ms))
+        .chain(slider_velocities.iter().map(|&(time, _)| time))
+        .fold(0.0, f64::min);
+    let mut change_times: Vec<f64> = slider_velocities.iter().map(|&(time, _)| time).collect();
+    if qua.bpm_does_not_affect_scroll_velocity {
+        // Cancel the scroll speed change by BPM which is natural in BMS.
+        change_times.extend(timed.tempo_map.iter().map(|point| point.ms));
+    }
+    change_times.sort_by(f64::total_cmp);
+    let bpm_at = |ms: f64| {
+        timed
+            .tempo_map
+            .iter()
+            .rev()
+            .find(|point| point.ms <= ms)
+            .or_else(|| timed.tempo_map.first())
+            .map_or(DEFAULT_BPM, |point| point.bpm)
+    };
+    let init_bpm = bpm_at(origin_ms).as_f64();
+    let mut current_factor = 1.0;
+    let mut scroll_changes = Vec::new();
+    for ms in std::iter::once(origin_ms).chain(change_times) {
+        let multiplier = slider_velocities
+            .iter()
+            .rev()
+            .find(|&&(time, _)| time <= ms)
+            .map_or(qua.initial_scroll_velocity, |&(_, multiplier)| multiplier);
+        let factor = if qua.bpm_does_not_affect_scroll_velocity {
+            multiplier * init_bpm / bpm_at(ms).as_f64()
+        } else {
+            multiplier
+        };
+        if (factor - current_factor).abs() <= f64::EPSILON {
+            continue;
+        }
+        if let Ok(factor) = FinF64::new(factor) {
+            scroll_changes.push(TimedScrollChange { ms, factor });
+            current_factor = factor.as_f64();
+        }
+    }
+    timed.scroll_changes = scroll_changes;
+
+    // Sounds
+    if let Some(audio_file) = qua.audio_file.as_deref().filter(|file| !file.is_empty()) {
+        timed.sounds.push(PathBuf::from(audio_file));
+        timed.objects.push(TimedObject {
+            ms: 0.0,
+            lane: None,
+            kind: NoteKind::Visible,
+            length_ms: 0.0,
+            sound: Some(0),
+        });
+    }
+    let first_sample = timed.sounds.len();
+    timed.sounds.extend(
+        qua.custom_audio_samples
+            .iter()
+            .map(|sample| PathBuf::from(&sample.path)),
+    );
+
+    // Notes
+    let key_count = qua.mode.key_count();
+    for hit_object in &qua.hit_objects {
+        let key = match hit_object.lane {
+            lane @ 1.. if lane <= key_count => Key::Key(lane),
+            lane if qua.has_scratch_key && lane == key_count + 1 => Key::Scratch(1),
+            lane => {
+                warnings.push(QuaToBmsWarning::InvalidLane {
+                    time: hit_object.start_time,
+                    lane,
+                });
+                continue;
+            }
+        };
+        let mut sounds = hit_object.key_sounds.iter().filter_map(|key_sound| {
+            let sound = key_sound
+                .sample
+                .checked_sub(1)
+                .filter(|&index| index < qua.custom_audio_samples.len())
+                .map(|index| first_sample + index);
+            if sound.is_none() {
+                warnings.push(QuaToBmsWarning::UnknownSample(key_sound.sample));
+            }
+            sound
+        });
+        let ms = hit_object.start_time as f64;
+        let (kind, length_ms) = if hit_object.is_long_note() {
+            (
+                NoteKind::Long,
+                (hit_object.end_time - hit_object.start_time) as f64,
+            )
+        } else {
+            (NoteKind::Visible, 0.0)
+        };
+        timed.objects.push(TimedObject {
+            ms,
+            lane: Some(TimedLane {
+                side: PlayerSide::Player1,
+                key,
+            }),
+            kind,
+            length_ms,
+            sound: sounds.next(),
+        });
+        // Layered keysounds are played as BGM.
+        for sound in sounds {
+            timed.objects.push(TimedObject {
+                ms,
+                lane: None,
+                kind: NoteKind::Visible,
+                length_ms: 0.0,
+                sound: Some(sound),
+            });
+        }
+    }
+    timed
+}
@@ -0,0 +1,480 @@
+//! Verification of the resource files referred by charts, on the disk.
+//!
+//! The charts are often distributed with the files whose names differ from the ones in the charts. [`ResourceResolver`] finds the file for a path in the chart as BMS players do:
+//!
+//! 1. The file with the exact name.
+//! 2. The file whose name equals ignoring ASCII case, for the charts made on case-insensitive file systems.
+//! 3. The file whose stem equals ignoring ASCII case, with another extension of the same kind:
+//!    - sounds: `wav`, `ogg`, `flac` and `mp3`,
+//!    - images: `bmp`, `png`, `jpg` and `jpeg`,
+//!    - movies: `mpg`, `mpeg`, `mp4`, `wmv` and `avi`.
+//!
+//! The directories in the path are also matched ignoring ASCII case, and both `/` and `\` are treated as separators. If several files are found in the first matching step, the path is ambiguous.
+//!
+//! ```no_run
+//! use bms_rs::bms::prelude::*;
+//!
+//! let source = std::fs::read_to_string("charts/song/song.bms").unwrap();
+//! let bms = parse_bms::<KeyLayoutBeat, _, _, _>(&source, default_config())
+//!     .bms
+//!     .unwrap();
+//! let output = bms.check_resources("charts/song");
+//! for resource in output.missing() {
+//!     println!("missing {:?} for {:?}", resource.declared, resource.usage);
+//! }
+//! ```
+
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsString,
+    path::{Component, Path, PathBuf},
+};
+
+use crate::bms::{command::ObjId, model::Bms};
+
+/// The extensions substituted for each other, in the order of preference.
+const SUBSTITUTES: [&[&str]; 3] = [
+    &["wav", "ogg", "flac", "mp3"],
+    &["bmp", "png", "jpg", "jpeg"],
+    &["mpg", "mpeg", "mp4", "wmv", "avi"],
+];
+
+/// Where a resource file is referred in the chart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResourceUsage {
+    /// A sound file by `#WAVxx`.
+    Wav(ObjId),
+    /// A sound file by `#EXWAVxx`.
+    ExWav(ObjId),
+    /// An image or movie file by `#BMPxx`.
+    Bmp(ObjId),
+    /// The image shown on POOR by `#BMP00`.
+    PoorImage,
+    /// A sound file of `sound_channels` in BMSON, by the index.
+    SoundChannel(usize),
+    /// A sound file of `mine_channels` in BMSON, by the index.
+    MineChannel(usize),
+    /// A sound file of `key_channels` in BMSON, by the index.
+    KeyChannel(usize),
+    /// A picture file of `bga_header` in BMSON, by the picture id.
+    Picture(u32),
+    /// The background image by `#BACKBMP`, or `back_image` in BMSON.
+    BackImage,
+    /// The image shown on loading by `#STAGEFILE`, or `eyecatch_image` in BMSON.
+    EyecatchImage,
+    /// The title image by `title_image` in BMSON.
+    TitleImage,
+    /// The banner image by `#BANNER`, or `banner_image` in BMSON.
+    Banner,
+    /// The character file by `#CHARFILE`.
+    CharFile,
+    /// The movie by `#VIDEOFILE`.
+    Video,
+    /// The preview sound by `#PREVIEW`, or `preview_music` in BMSON.
+    PreviewMusic,
+    /// The MIDI file by `#MIDIFILE`.
+    Midi,
+}
+
+/// How a resource file was found by [`ResourceResolver::resolve`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ResourceStatus {
+    /// The file exists with the exact path.
+    Found(PathBuf),
+    /// Only a file with another case or extension exists.
+    Fallback(PathBuf),
+    /// Several files match ignoring case or extension, so which is used depends on the player.
+    Ambiguous(Vec<PathBuf>),
+    /// No file matches.
+    Missing,
+}
+
+/// A resource file referred by the chart, with the result of resolution.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CheckedResource {
+    /// Where the file is referred.
+    pub usage: ResourceUsage,
+    /// The path written in the chart.
+    pub declared: PathBuf,
+    /// The result of resolution.
+    pub status: ResourceStatus,
+}
+
+/// Output of checking the resource files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[must_use]
+pub struct ResourceCheckOutput {
+    /// All the referred files, sorted by [`ResourceUsage`].
+    pub resources: Vec<CheckedResource>,
+}
+
+impl ResourceCheckOutput {
+    /// Returns the resources not found.
+    pub fn missing(&self) -> impl Iterator<Item = &CheckedResource> {
+        self.resources
+            .iter()
+            .filter(|resource| resource.status == ResourceStatus::Missing)
+    }
+
+    /// Returns the resources matching several files.
+    pub fn ambiguous(&self) -> impl Iterator<Item = &CheckedResource> {
+        self.resources
+            .iter()
+            .filter(|resource| matches!(resource.status, ResourceStatus::Ambiguous(_)))
+    }
+
+    /// Returns whether all the resources are found without ambiguity.
+    #[must_use]
+    pub fn is_complete(&self) -> bool {
+        self.resources.iter().all(|resource| {
+            matches!(
+                resource.status,
+                ResourceStatus::Found(_) | ResourceStatus::Fallback(_)
+            )
+        })
+    }
+}
+
+/// A resolver of the paths in a chart against the files in its directory.
+///
+/// The directory listings are cached, so reuse it for the paths of the same chart.
+#[derive(Debug, Clone)]
+pub struct ResourceResolver {
+    root: PathBuf,
+    /// The entries of the directories, or `None` if unreadable.
+    listings: HashMap<PathBuf, Option<Vec<Entry>>>,
+}
+
+/// A directory entry cached by [`ResourceResolver`].
+#[derive(Debug, Clone)]
+struct Entry {
+    name: OsString,
+    is_dir: bool,
+}
+
+impl ResourceResolver {
+    /// Creates a new resolver for the chart in `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            listings: HashMap::new(),
+        }
+    }
+
+    /// Resolves `declared`, the path written in the chart, relative to the chart directory.
+    pub fn resolve(&mut self, declared: &Path) -> ResourceStatus {
+        let declared = declared.to_string_lossy();
+        let mut components: Vec<&str> = declared
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != ".")
+            .collect();
+        let Some(file_name) = components.pop() else {
+            return ResourceStatus::Missing;
+        };
+
+        let mut dirs = vec![self.root.clone()];
+        let mut dirs_exact = true;
+        for component in components {
+            if component == ".." {
+                dirs = dirs.into_iter().map(|dir| dir.join("..")).collect();
+                continue;
+            }
+            let exact: Vec<PathBuf> = dirs
+                .iter()
+                .map(|dir| dir.join(component))
+                .filter(|dir| self.has_entry(dir, true))
+                .collect();
+            if exact.is_empty() {
+                dirs_exact = false;
+                dirs = dirs
+                    .iter()
+                    .flat_map(|dir| self.matching(dir, component, true))
+                    .collect();
+            } else {
+                dirs = exact;
+            }
+        }
+
+        let (file_exact, found) = self.find_file(&dirs, file_name);
+        let exact = dirs_exact && file_exact;
+        match found.as_slice() {
+            [] => ResourceStatus::Missing,
+            [path] if exact => ResourceStatus::Found(path.clone()),
+            [path] => ResourceStatus::Fallback(path.clone()),
+            _ => ResourceStatus::Ambiguous(found),
+        }
+    }
+
+    /// Finds the files for `file_name` in `dirs` by the first matching step, and returns whether it is an exact match.
+    fn find_file(&mut self, dirs: &[PathBuf], file_name: &str) -> (bool, Vec<PathBuf>) {
+        let exact: Vec<PathBuf> = dirs
+            .iter()
+            .map(|dir| dir.join(file_name))
+            .filter(|path| self.has_entry(path, false))
+            .collect();
+        if !exact.is_empty() {
+            return (true, exact);
+        }
+
+        let ignoring_case: Vec<PathBuf> = dirs
+            .iter()
+            .flat_map(|dir| self.matching(dir, file_name, false))
+            .collect();
+        if !ignoring_case.is_empty() {
+            return (false, ignoring_case);
+        }
+
+        let (stem, extension) = file_name.rsplit_once('.').unwrap_or((file_name, ""));
+        let substitutes: Vec<&str> = SUBSTITUTES
+            .iter()
+            .find(|group| {
+                group
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+            })
+            .map_or_else(
+                // Unknown or no extension, so try every kind.
+                || SUBSTITUTES.concat(),
+                |group| group.to_vec(),
+            );
+        let substituted = dirs
+            .iter()
+            .flat_map(|dir| {
+                substitutes
+                    .iter()
+                    .flat_map(|substitute| {
+                        self.matching(dir, &format!("{stem}.{substitute}"), false)
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        (false, substituted)
+    }
+
+    /// Returns the paths of the entries in `dir` whose name equals `name` ignoring ASCII case.
+    fn matching(&mut self, dir: &Path, name: &str, is_dir: bool) -> Vec<PathBuf> {
+        self.listing(dir)
+            .iter()
+            .filter(|entry| {
+                entry.is_dir == is_dir
+                    && entry
+                        .name
+                        .to_str()
+                        .is_some_and(|entry_name| entry_name.eq_ignore_ascii_case(name))
+            })
+            .map(|entry| dir.join(&entry.name))
+            .collect()
+    }
+
+    /// Returns whether the entry `path` exists with the exact name in the listing of its parent.
+    fn has_entry(&mut self, path: &Path, is_dir: bool) -> bool {
+        let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        self.listing(dir)
+            .iter()
+            .any(|entry| entry.is_dir == is_dir && entry.name == name)
+    }
+
+    fn listing(&mut self, dir: &Path) -> &[Entry] {
+        self.listings
+            .entry(dir.to_path_buf())
+            .or_insert_with(|| {
+                let mut entries: Vec<Entry> = std::fs::read_dir(dir)
+                    .ok()?
+                    .filter_map(Result::ok)
+                    .map(|entry| Entry {
+                        name: entry.file_name(),
+                        // Follows the symbolic links as opening the file does.
+                        is_dir: entry.path().is_dir(),
+                    })
+                    .collect();
+                // Sorts to report the ambiguous files in a stable order.
+                entries.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+                Some(entries)
+            })
+            .as_deref()
+            .unwrap_or_default()
+    }
+
+    fn check<'a>(
+        &mut self,
+        declared: impl IntoIterator<Item = (ResourceUsage, &'a Path)>,
+    ) -> ResourceCheckOutput {
+        let mut resources: Vec<CheckedResource> = declared
+            .into_iter()
+            .map(|(usage, path)| CheckedResource {
+                usage,
+                declared: path.to_path_buf(),
+                status: self.resolve(path),
+            })
+            .collect();
+        resources.sort_by_key(|resource| resource.usage);
+        ResourceCheckOutput { resources }
+    }
+}
+
+/// The extensions of the chart files, which are not resources.
+const CHART_EXTENSIONS: [&str; 5] = ["bms", "bme", "bml", "pms", "bmson"];
+
+/// Returns the files in `dir` and its subdirectories which no resource in `checked` refers, sorted by the path.
+///
+/// Pass the outputs of all the charts in `dir`, because the charts of the other difficulties often share the files. The chart files themselves are excluded, but the other files such as `readme.txt` are included.
+///
+/// The paths are compared after removing `.` and `..` components, so `./song` and `song` refer to the same files. The symbolic links to directories are not followed, to avoid looping over a cycle.
+///
+/// # Errors
+///
+/// Returns the error if `dir` or its subdirectories cannot be read.
+pub fn unreferenced_files<'a>(
+    dir: impl AsRef<Path>,
+    checked: impl IntoIterator<Item = &'a ResourceCheckOutput>,
+) -> std::io::Result<Vec<PathBuf>> {
+    let referenced: HashSet<PathBuf> = checked
+        .into_iter()
+        .flat_map(|output| &output.resources)
+        .flat_map(|resource| match &resource.status {
+            ResourceStatus::Found(path) | ResourceStatus::Fallback(path) => {
+                std::slice::from_ref(path)
+            }
+            ResourceStatus::Ambiguous(paths) => paths.as_slice(),
+            ResourceStatus::Missing => &[],
+        })
+        .map(PathBuf::as_path)
+        .map(normalize)
+        .collect();
+
+    let mut unreferenced = vec![];
+    let mut dirs = vec![dir.as_ref().to_path_buf()];
+    while let Some(current) = dirs.pop() {
+        for entry in std::fs::read_dir(&current)? {
+            let entry = entry?;
+            let path = current.join(entry.file_name());
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                dirs.push(path);
+                continue;
+            }
+            if file_type.is_symlink() && path.is_dir() {
+                continue;
+            }
+            let is_chart = path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .is_some_and(|ext| {
+                    CHART_EXTENSIONS
+                        .iter()
+                        .any(|chart| chart.eq_ignore_ascii_case(ext))
+                });
+            if !is_chart && !referenced.contains(&normalize(&path)) {
+                unreferenced.push(path);
+            }
+        }
+    }
+    unreferenced.sort_unstable();
+    Ok(unreferenced)
+}
+
+/// Removes the `.` components, and the `..` components with their parents, without accessing the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir
+                if matches!(
+                    normalized.components().next_back(),
+                    Some(Component::Normal(_))
+                ) =>
+            {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+    normalized
+}
+
+impl Bms {
+    /// Checks the files referred by `#WAVxx`, `#EXWAVxx`, `#BMPxx`, `#BACKBMP`, `#STAGEFILE`, `#BANNER`, `#CHARFILE`, `#VIDEOFILE`, `#PREVIEW` and `#MIDIFILE` in `dir`, the directory of the chart.
+    ///
+    /// `#PATH_WAV` is ignored, because it is only for debugging on the author's environment.
+    pub fn check_resources(&self, dir: impl AsRef<Path>) -> ResourceCheckOutput {
+        let wavs = self
+            .wav
+            .wav_files
+            .iter()
+            .map(|(&id, path)| (ResourceUsage::Wav(id), path.as_path()));
+        let exwavs = self
+            .wav
+            .exwav_defs
+            .iter()
+            .map(|(&id, def)| (ResourceUsage::ExWav(id), def.path.as_path()));
+        let bmps = self
+            .bmp
+            .bmp_files
+            .iter()
+            .map(|(&id, bmp)| (ResourceUsage::Bmp(id), bmp.file.as_path()));
+        let others = [
+            (ResourceUsage::PoorImage, &self.bmp.poor_bmp),
+            (ResourceUsage::BackImage, &self.sprite.back_bmp),
+            (ResourceUsage::EyecatchImage, &self.sprite.stage_file),
+            (ResourceUsage::Banner, &self.sprite.banner),
+            (ResourceUsage::CharFile, &self.sprite.char_file),
+            (ResourceUsage::Video, &self.video.video_file),
+            (ResourceUsage::PreviewMusic, &self.music_info.preview_music),
+            (ResourceUsage::Midi, &self.resources.midi_file),
+        ]
+        .into_iter()
+        .filter_map(|(usage, path)| Some((usage, path.as_deref()?)));
+        ResourceResolver::new(dir.as_ref()).check(wavs.chain(exwavs).chain(bmps).chain(others))
+    }
+}
+
+#[cfg(feature = "bmson")]
+impl crate::bmson::Bmson<'_> {
+    /// Checks the sound files of the channels, the pictures of [`Bga`](crate::bmson::Bga), and the image and preview files of [`BmsonInfo`](crate::bmson::BmsonInfo) in `dir`, the directory of the chart.
+    pub fn check_resources(&self, dir: impl AsRef<Path>) -> ResourceCheckOutput {
+        let sounds = self
+            .sound_channels
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| (ResourceUsage::SoundChannel(index), channel.name.as_ref()));
+        let mines = self
+            .mine_channels
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| (ResourceUsage::MineChannel(index), channel.name.as_ref()));
+        let keys = self
+            .key_channels
+            .iter()
+            .enumerate()
+            .map(|(index, channel)| (ResourceUsage::KeyChannel(index), channel.name.as_ref()));
+        let pictures = self
+            .bga
+            .bga_header
+            .iter()
+            .map(|header| (ResourceUsage::Picture(header.id.0), header.name.as_ref()));
+        let info = &self.info;
+        let images = [
+            (ResourceUsage::BackImage, &info.back_image),
+            (ResourceUsage::EyecatchImage, &info.eyecatch_image),
+            (ResourceUsage::TitleImage, &info.title_image),
+            (ResourceUsage::Banner, &info.banner_image),
+            (ResourceUsage::PreviewMusic, &info.preview_music),
+        ]
+        .into_iter()
+        .filter_map(|(usage, path)| Some((usage, path.as_deref()?)));
+        let declared = sounds
+            .chain(mines)
+            .chain(keys)
+            .chain(pictures)
+            .chain(images)
+            .map(|(usage, path)| (usage, Path::new(path)));
+        ResourceResolver::new(dir.as_ref()).check(declared)
+    }
+}
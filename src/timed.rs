@@ -0,0 +1,197 @@
+//! The time-based intermediate chart, an exchange model for the formats placing objects by milliseconds.
+//!
+//! Many chart formats place the objects by the time in milliseconds, but [`Bms`](crate::bms::model::Bms) places them by the position in measures. [`TimedChart`] is a flat list of the objects with their times, and a tempo map to reconstruct the measures. A converter for a time-based format can target this model, and then [`Bms::from_timed`](crate::bms::model::Bms::from_timed) takes care of choosing the positions and the section lengths.
+//!
+//! # Tempo Map
+//!
+//! Each [`TempoPoint`] changes the BPM from its time. If it has [`TempoPoint::beats_per_measure`], a new measure is started at the point, and the measures have that number of beats until the next point with a time signature. So the measure just before the point may be shortened. Otherwise, the measures continue across the point.
+//!
+//! [`TimedStop`]s stop the scrolling for a while, so the times of the objects after the stop include its duration.
+
+pub mod bms_to_timed;
+pub mod timed_to_bms;
+
+use std::path::PathBuf;
+
+use strict_num_extended::{FinF64, NonNegativeF64, PositiveF64};
+
+use crate::chart::{
+    DEFAULT_BPM,
+    types::{Key, NoteKind, PlayerSide},
+};
+
+/// Top-level object of the time-based intermediate chart.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TimedChart {
+    /// The tempo map in order of the time. The chart must have at least one point, otherwise 120 BPM is assumed.
+    pub tempo_map: Vec<TempoPoint>,
+    /// The stops in order of the time.
+    pub stops: Vec<TimedStop>,
+    /// The scrolling factor changes in order of the time.
+    pub scroll_changes: Vec<TimedScrollChange>,
+    /// The objects of the chart.
+    pub objects: Vec<TimedObject>,
+    /// The sound files, referred by [`TimedObject::sound`].
+    pub sounds: Vec<PathBuf>,
+}
+
+/// A point of the tempo map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TempoPoint {
+    /// The time in milliseconds.
+    pub ms: f64,
+    /// The BPM from this point.
+    pub bpm: PositiveF64,
+    /// The number of beats (quarter notes) in a measure, which starts a new measure at this point if present.
+    pub beats_per_measure: Option<PositiveF64>,
+}
+
+/// A stop of the scrolling.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedStop {
+    /// The time in milliseconds to start the stop.
+    pub ms: f64,
+    /// The duration of the stop in milliseconds.
+    pub duration_ms: NonNegativeF64,
+}
+
+/// A change of the scrolling factor.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedScrollChange {
+    /// The time in milliseconds.
+    pub ms: f64,
+    /// The scrolling factor from this time.
+    pub factor: FinF64,
+}
+
+/// A lane where the object is placed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TimedLane {
+    /// The player side of the lane.
+    pub side: PlayerSide,
+    /// The key of the lane.
+    pub key: Key,
+}
+
+/// An object of the chart, a note or a BGM.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TimedObject {
+    /// The time in milliseconds.
+    pub ms: f64,
+    /// The lane of the note, or `None` for the BGM.
+    pub lane: Option<TimedLane>,
+    /// The kind of the note. It is ignored for the BGM.
+    pub kind: NoteKind,
+    /// The length of the long note in milliseconds, `0` for the other objects.
+    pub length_ms: f64,
+    /// The index of [`TimedChart::sounds`] to play, or `None` for no sound.
+    pub sound: Option<usize>,
+}
+
+/// A piece of [`BeatMap`], where the BPM is constant after the stop.
+#[derive(Debug, Clone, Copy)]
+struct BeatPoint {
+    ms: f64,
+    beat: f64,
+    bpm: PositiveF64,
+    stop_ms: f64,
+}
+
+/// Maps the times in milliseconds into the beats from the origin, and vice versa.
+#[derive(Debug)]
+struct BeatMap {
+    points: Vec<BeatPoint>,
+}
+
+impl BeatMap {
+    /// Creates the map whose beat `0` is at `origin_ms`.
+    fn with_origin(origin_ms: f64, bpm: PositiveF64) -> Self {
+        Self {
+            points: vec![BeatPoint {
+                ms: origin_ms,
+                beat: 0.0,
+                bpm,
+                stop_ms: 0.0,
+            }],
+        }
+    }
+
+    /// Creates the map from the tempo map and the stops, whose beat `0` is at `origin_ms`.
+    fn from_times(origin_ms: f64, tempo_map: &[TempoPoint], stops: &[TimedStop]) -> Self {
+        let init_bpm = tempo_map.first().map_or(DEFAULT_BPM, |point| point.bpm);
+        let mut map = Self::with_origin(origin_ms, init_bpm);
+        // The BPM changes are applied before the stops at the same time.
+        let mut events: Vec<(f64, Option<PositiveF64>, f64)> = tempo_map
+            .iter()
+            .map(|point| (point.ms, Some(point.bpm), 0.0))
+            .chain(
+                stops
+                    .iter()
+                    .map(|stop| (stop.ms, None, stop.duration_ms.as_f64())),
+            )
+            .collect();
+        events.sort_by(|(a_ms, a_bpm, _), (b_ms, b_bpm, _)| {
+            a_ms.total_cmp(b_ms)
+                .then_with(|| a_bpm.is_none().cmp(&b_bpm.is_none()))
+        });
+        for (ms, bpm, stop_ms) in events {
+            let point = map.split_at(map.beat_of(ms));
+            if let Some(bpm) = bpm {
+                point.bpm = bpm;
+            }
+            point.stop_ms += stop_ms;
+        }
+        map
+    }
+
+    /// Gets the last point, splitting it at `beat` if needed. `beat` must not be less than the last point.
+    fn split_at(&mut self, beat: f64) -> &mut BeatPoint {
+        let ms = self.ms_of(beat);
+        let needs_split = self
+            .points
+            .last()
+            .is_none_or(|last| (last.beat - beat).abs() > f64::EPSILON);
+        if needs_split {
+            let bpm = self.points.last().map_or(DEFAULT_BPM, |last| last.bpm);
+            self.points.push(BeatPoint {
+                ms,
+                beat,
+                bpm,
+                stop_ms: 0.0,
+            });
+        }
+        self.points
+            .last_mut()
+            .expect("the map must have at least one point")
+    }
+
+    fn beat_of(&self, ms: f64) -> f64 {
+        let index = self.points.partition_point(|point| point.ms <= ms);
+        let Some(point) = index
+            .checked_sub(1)
+            .and_then(|i| self.points.get(i))
+            .or_else(|| self.points.first())
+        else {
+            return 0.0;
+        };
+        let elapsed = (ms - point.ms - point.stop_ms).max(0.0);
+        point.beat + elapsed * point.bpm.as_f64() / 60_000.0
+    }
+
+    /// Converts the beat into the time in milliseconds. The object at the same beat as a stop is reached before the stop.
+    fn ms_of(&self, beat: f64) -> f64 {
+        let index = self.points.partition_point(|point| point.beat < beat);
+        let Some(point) = index.checked_sub(1).and_then(|i| self.points.get(i)) else {
+            return self.points.first().map_or(0.0, |point| point.ms);
+        };
+        point.ms + point.stop_ms + (beat - point.beat) * 60_000.0 / point.bpm.as_f64()
+    }
+
+    fn bpm_at(&self, beat: f64) -> PositiveF64 {
+        let index = self.points.partition_point(|point| point.beat <= beat);
+        index
+            .checked_sub(1)
+            .and_then(|i| self.points.get(i))
+            .map_or(DEFAULT_BPM, |point| point.bpm)
+    }
+}
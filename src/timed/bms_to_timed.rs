@@ -0,0 +1,260 @@
+//! Part: Convert `Bms` to `TimedChart`.
+//!
+//! The positions are converted into the times in milliseconds with the section lengths, the BPM changes and the stops. The tempo map has a point with the time signature at the start of the chart and of each track whose length differs from the previous one, and a point at each BPM change.
+//!
+//! The long notes in `#LNTYPE 1` notation are paired in order on each lane. The ones in `#LNTYPE 2` notation are dropped with [`BmsToTimedWarning::MgqLongNotesDropped`], because the model does not keep the `00` objects closing them. The objects on the channels not mapped by the key layout are treated as the BGM. The objects of `#WAVxx` defined as [`SILENT_WAV`] have no sound.
+
+use std::collections::{BTreeMap, HashMap};
+
+use strict_num_extended::{NonNegativeF64, PositiveF64};
+use thiserror::Error;
+
+use crate::{
+    bms::prelude::*,
+    chart::{DEFAULT_BPM, SILENT_WAV},
+    timed::{
+        BeatMap, TempoPoint, TimedChart, TimedLane, TimedObject, TimedScrollChange, TimedStop,
+    },
+};
+
+/// Warnings that occur during conversion from `Bms` to `TimedChart`.
+#[derive(Debug, Clone, Error, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum BmsToTimedWarning {
+    /// The initial BPM was missing or invalid and default value was used.
+    #[error("initial BPM was missing or invalid, using default value")]
+    MissingBpm,
+    /// The long note had no end, so it was converted into a normal note.
+    #[error("long note at {0:?} has no end")]
+    UnpairedLongNote(ObjTime),
+    /// The chart was in `#LNTYPE 2`, so the objects on the long note channels were dropped.
+    #[error("long notes of #LNTYPE 2 are not supported, {0} objects were dropped")]
+    MgqLongNotesDropped(usize),
+}
+
+/// Output of the conversion from `Bms` to `TimedChart`.
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct BmsToTimedOutput {
+    /// The converted `TimedChart` object.
+    pub timed: TimedChart,
+    /// Warnings that occurred during the conversion.
+    pub warnings: Vec<BmsToTimedWarning>,
+}
+
+impl Bms {
+    /// Convert `Bms` to `TimedChart`. See [the module document](crate::timed::bms_to_timed) for the conversion rules.
+    pub fn to_timed<T: KeyLayoutMapper>(&self) -> BmsToTimedOutput {
+        let mut warnings = Vec::new();
+        let init_bpm = self
+            .bpm
+            .bpm
+            .as_ref()
+            .and_then(|bpm| bpm.value().as_ref().ok().copied())
+            .unwrap_or_else(|| {
+                warnings.push(BmsToTimedWarning::MissingBpm);
+                DEFAULT_BPM
+            });
+
+        // The BPM changes are applied before the stops on the same position.
+        let section_lens = SectionLens::new(self);
+        let mut beat_map = BeatMap::with_origin(0.0, init_bpm);
+        let mut events: Vec<(ObjTime, Option<PositiveF64>, f64)> = self
+            .bpm
+            .bpm_changes
+            .values()
+            .map(|change| (change.time, Some(change.bpm), 0.0))
+            .chain(
+                self.stop
+                    .stops
+                    .values()
+                    .map(|stop| (stop.time, None, stop.duration.as_f64())),
+            )
+            .collect();
+        events.sort_by_key(|&(time, bpm, _)| (time, bpm.is_none()));
+        for (time, bpm, stop) in events {
+            let point = beat_map.split_at(section_lens.beat_of(time));
+            if let Some(bpm) = bpm {
+                point.bpm = bpm;
+            }
+            // A stop is counted in 1/192 of a 4/4 measure.
+            point.stop_ms += stop * 1250.0 / point.bpm.as_f64();
+        }
+        let ms_of = |time: ObjTime| beat_map.ms_of(section_lens.beat_of(time));
+
+        // Tempo map
+        let mut tempo_events: BTreeMap<ObjTime, (Option<PositiveF64>, Option<PositiveF64>)> =
+            BTreeMap::new();
+        let mut signature_tracks = vec![Track(0)];
+        for &track in self.section_len.section_len_changes.keys() {
+            signature_tracks.extend([track, Track(track.0 + 1)]);
+        }
+        for track in signature_tracks {
+            let previous_len = track
+                .0
+                .checked_sub(1)
+                .map(|previous| section_lens.len_of(Track(previous)));
+            let len = section_lens.len_of(track);
+            if previous_len.is_some_and(|previous| (previous - len).abs() <= f64::EPSILON) {
+                continue;
+            }
+            tempo_events.entry(ObjTime::start_of(track)).or_default().1 =
+                PositiveF64::new(len * 4.0).ok();
+        }
+        for change in self.bpm.bpm_changes.values() {
+            tempo_events.entry(change.time).or_default().0 = Some(change.bpm);
+        }
+        let mut current_bpm = init_bpm;
+        let tempo_map = tempo_events
+            .into_iter()
+            .map(|(time, (bpm, beats_per_measure))| {
+                current_bpm = bpm.unwrap_or(current_bpm);
+                TempoPoint {
+                    ms: ms_of(time),
+                    bpm: current_bpm,
+                    beats_per_measure,
+                }
+            })
+            .collect();
+
+        let stops = self
+            .stop
+            .stops
+            .values()
+            .filter_map(|stop| {
+                let beat = section_lens.beat_of(stop.time);
+                let duration_ms = stop.duration.as_f64() * 1250.0 / beat_map.bpm_at(beat).as_f64();
+                Some(TimedStop {
+                    ms: beat_map.ms_of(beat),
+                    duration_ms: NonNegativeF64::new(duration_ms).ok()?,
+                })
+            })
+            .collect();
+        let scroll_changes = self
+            .scroll
+            .scrolling_factor_changes
+            .values()
+            .map(|change| TimedScrollChange {
+                ms: ms_of(change.time),
+                factor: change.factor,
+            })
+            .collect();
+
+        // Sounds, except the silent one written by the conversions.
+        let mut wav_ids: Vec<ObjId> = self
+            .wav
+            .wav_files
+            .iter()
+            .filter(|(_, path)| path.as_os_str() != SILENT_WAV)
+            .map(|(&wav_id, _)| wav_id)
+            .collect();
+        wav_ids.sort();
+        let sound_indexes: HashMap<ObjId, usize> = wav_ids
+            .iter()
+            .enumerate()
+            .map(|(index, &wav_id)| (wav_id, index))
+            .collect();
+        let sounds = wav_ids
+            .iter()
+            .filter_map(|wav_id| self.wav.wav_files.get(wav_id).cloned())
+            .collect();
+
+        // Objects
+        let mut objects = Vec::new();
+        let mut long_notes: HashMap<TimedLane, Vec<&WavObj>> = HashMap::new();
+        for obj in self.wav.notes.all_notes() {
+            let sound = sound_indexes.get(&obj.wav_id).copied();
+            let Some(map) = T::from_channel_id(obj.channel_id) else {
+                objects.push(TimedObject {
+                    ms: ms_of(obj.offset),
+                    lane: None,
+                    kind: NoteKind::Visible,
+                    length_ms: 0.0,
+                    sound,
+                });
+                continue;
+            };
+            let lane = TimedLane {
+                side: map.side(),
+                key: map.key(),
+            };
+            if map.kind() == NoteKind::Long {
+                long_notes.entry(lane).or_default().push(obj);
+                continue;
+            }
+            objects.push(TimedObject {
+                ms: ms_of(obj.offset),
+                lane: Some(lane),
+                kind: map.kind(),
+                length_ms: 0.0,
+                sound,
+            });
+        }
+        if self.repr.ln_type == LnType::Mgq {
+            let dropped = long_notes.drain().map(|(_, objs)| objs.len()).sum();
+            if dropped > 0 {
+                warnings.push(BmsToTimedWarning::MgqLongNotesDropped(dropped));
+            }
+        }
+        for (lane, mut objs) in long_notes {
+            objs.sort_by_key(|obj| obj.offset);
+            for pair in objs.chunks(2) {
+                let (kind, length_ms) = match pair {
+                    [start, end] => (NoteKind::Long, ms_of(end.offset) - ms_of(start.offset)),
+                    _ => (NoteKind::Visible, 0.0),
+                };
+                let Some(start) = pair.first() else {
+                    continue;
+                };
+                if kind == NoteKind::Visible {
+                    warnings.push(BmsToTimedWarning::UnpairedLongNote(start.offset));
+                }
+                objects.push(TimedObject {
+                    ms: ms_of(start.offset),
+                    lane: Some(lane),
+                    kind,
+                    length_ms,
+                    sound: sound_indexes.get(&start.wav_id).copied(),
+                });
+            }
+        }
+        objects.sort_by(|a, b| a.ms.total_cmp(&b.ms));
+
+        BmsToTimedOutput {
+            timed: TimedChart {
+                tempo_map,
+                stops,
+                scroll_changes,
+                objects,
+                sounds,
+            },
+            warnings,
+        }
+    }
+}
+
+/// Converts the positions into the beats with the section lengths.
+struct SectionLens<'a>(&'a BTreeMap<Track, SectionLenChangeObj>);
+
+impl<'a> SectionLens<'a> {
+    const fn new(bms: &'a Bms) -> Self {
+        Self(&bms.section_len.section_len_changes)
+    }
+
+    fn len_of(&self, track: Track) -> f64 {
+        self.0
+            .get(&track)
+            .map_or(1.0, |change| change.length.as_f64())
+    }
+
+    fn beat_of(&self, time: ObjTime) -> f64 {
+        let track = time.track();
+        let shortened: f64 = self
+            .0
+            .range(..track)
+            .map(|(_, change)| change.length.as_f64() - 1.0)
+            .sum();
+        let fraction = time.numerator() as f64 / time.denominator_u64() as f64;
+        (track.0 as f64 + shortened + fraction * self.len_of(track)) * 4.0
+    }
+}
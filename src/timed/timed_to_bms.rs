@@ -0,0 +1,344 @@
+//! Part: Convert `TimedChart` to `Bms`.
+//!
+//! The measures are built from the tempo map at first. Then each time in milliseconds is placed on the fraction of the measure, which has the smallest denominator within [`Quantization::tolerance_ms`]. If no fraction up to [`Quantization::max_denominator`] is within the tolerance, the nearest one is used. The largest difference between the original and the placed times is reported as [`TimedToBmsOutput::max_quantization_error_ms`].
+//!
+//! The measures started by the tempo points are placed at the start of the tracks exactly, so only the tempo points without time signatures, the stops and the objects may have the quantization error. If the chart has objects before the first tempo point or the time `0`, lead-in measures of 4 beats are inserted with the BPM of the first tempo point.
+//!
+//! The sounds are defined as `#WAV01` and later in order. The objects without a sound refer to the next id, which is defined as [`SILENT_WAV`] only if it is referred. The long notes are written in `#LNTYPE 1` notation.
+
+use std::path::PathBuf;
+
+use strict_num_extended::{FinF64, NonNegativeF64};
+use thiserror::Error;
+
+use crate::{
+    bms::{command::string_value::StringValue, prelude::*},
+    chart::{DEFAULT_BPM, SILENT_WAV},
+    timed::{BeatMap, TempoPoint, TimedChart},
+};
+
+/// Small beats to absorb the floating point error when building the measures.
+const BEAT_EPSILON: f64 = 1e-6;
+
+/// Settings to place the times in milliseconds on the fractions of the measures.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Quantization {
+    /// The acceptable difference in milliseconds from the original time.
+    pub tolerance_ms: f64,
+    /// The largest denominator of the fraction to try.
+    pub max_denominator: u64,
+}
+
+impl Default for Quantization {
+    fn default() -> Self {
+        Self {
+            tolerance_ms: 1.0,
+            max_denominator: 960,
+        }
+    }
+}
+
+/// Warnings that occur during conversion from `TimedChart` to `Bms`.
+#[derive(Debug, Clone, Error, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum TimedToBmsWarning {
+    /// The tempo map had no valid points and default BPM was used.
+    #[error("tempo map has no valid points, using default BPM")]
+    MissingTempo,
+    /// The object referred a sound which does not exist, so it was played without the sound.
+    #[error("object refers to the unknown sound {0}")]
+    UnknownSound(usize),
+}
+
+/// Output of the conversion from `TimedChart` to `Bms`.
+#[derive(Debug, Clone, PartialEq)]
+#[must_use]
+pub struct TimedToBmsOutput {
+    /// The converted `Bms` object.
+    pub bms: Bms,
+    /// Warnings that occurred during the conversion.
+    pub warnings: Vec<TimedToBmsWarning>,
+    /// The largest difference in milliseconds between the original and the placed times.
+    pub max_quantization_error_ms: f64,
+    /// Warnings that affect the playing of the score.
+    pub playing_warnings: Vec<PlayingWarning>,
+    /// Errors that make the score unplayable.
+    pub playing_errors: Vec<PlayingError>,
+}
+
+impl Bms {
+    /// Convert `TimedChart` to `Bms`. See [the module document](crate::timed::timed_to_bms) for the conversion rules.
+    ///
+    /// The converted `Bms` has only the objects and their definitions, so the header such as the title should be filled by the caller.
+    pub fn from_timed<T: KeyLayoutMapper>(
+        timed: &TimedChart,
+        quantization: Quantization,
+    ) -> TimedToBmsOutput {
+        let mut bms = Self::default();
+        let mut warnings = Vec::new();
+
+        let mut tempo_map: Vec<TempoPoint> = timed
+            .tempo_map
+            .iter()
+            .filter(|point| point.ms.is_finite())
+            .copied()
+            .collect();
+        tempo_map.sort_by(|a, b| a.ms.total_cmp(&b.ms));
+        if tempo_map.is_empty() {
+            warnings.push(TimedToBmsWarning::MissingTempo);
+            tempo_map.push(TempoPoint {
+                ms: 0.0,
+                bpm: DEFAULT_BPM,
+                beats_per_measure: None,
+            });
+        }
+        let all_times = || {
+            tempo_map
+                .iter()
+                .map(|point| point.ms)
+                .chain(timed.stops.iter().map(|stop| stop.ms))
+                .chain(timed.scroll_changes.iter().map(|change| change.ms))
+                .chain(timed.objects.iter().map(|object| object.ms))
+                .chain(
+                    timed
+                        .objects
+                        .iter()
+                        .map(|object| object.ms + object.length_ms.max(0.0)),
+                )
+                .filter(|ms| ms.is_finite())
+        };
+        let origin_ms = all_times().fold(0.0, f64::min);
+        let end_ms = all_times().fold(origin_ms, f64::max);
+        let beat_map = BeatMap::from_times(origin_ms, &tempo_map, &timed.stops);
+        let measures = Measures::new(&beat_map, &tempo_map, beat_map.beat_of(end_ms));
+        let mut placer = Placer {
+            beat_map: &beat_map,
+            measures: &measures,
+            quantization,
+            max_error_ms: 0.0,
+        };
+
+        // Timing
+        let init_bpm = beat_map.bpm_at(0.0);
+        bms.bpm.bpm = Some(StringValue::from_value(init_bpm));
+        for (index, measure) in measures.0.iter().enumerate() {
+            let track = Track(index as u64);
+            if (measure.beats - 4.0).abs() > BEAT_EPSILON
+                && let Ok(length) = FinF64::new(measure.beats / 4.0)
+            {
+                bms.section_len
+                    .section_len_changes
+                    .insert(track, SectionLenChangeObj { track, length });
+            }
+        }
+        let mut current_bpm = init_bpm;
+        for point in &tempo_map {
+            if point.bpm == current_bpm {
+                continue;
+            }
+            current_bpm = point.bpm;
+            let time = placer.place(point.ms);
+            bms.bpm.bpm_changes.insert(
+                time,
+                BpmChangeObj {
+                    time,
+                    bpm: point.bpm,
+                },
+            );
+        }
+        for stop in &timed.stops {
+            let bpm = beat_map.bpm_at(beat_map.beat_of(stop.ms));
+            // A stop is counted in 1/192 of a 4/4 measure.
+            let Ok(duration) =
+                NonNegativeF64::new(stop.duration_ms.as_f64() * bpm.as_f64() / 1250.0)
+            else {
+                continue;
+            };
+            let time = placer.place(stop.ms);
+            bms.stop
+                .push_stop_ignore_duplicate(StopObj { time, duration });
+        }
+        for change in &timed.scroll_changes {
+            let time = placer.place(change.ms);
+            bms.scroll.scrolling_factor_changes.insert(
+                time,
+                ScrollingFactorObj {
+                    time,
+                    factor: change.factor,
+                },
+            );
+        }
+
+        // Sounds
+        let mut wav_obj_id_issuer = ObjId::all_values();
+        let sound_wav_ids: Vec<ObjId> = timed
+            .sounds
+            .iter()
+            .map(|path| {
+                let wav_id = wav_obj_id_issuer.next().unwrap_or_else(ObjId::null);
+                bms.wav.wav_files.insert(wav_id, path.clone());
+                wav_id
+            })
+            .collect();
+        let silent_wav_id = wav_obj_id_issuer.next().unwrap_or_else(ObjId::null);
+
+        // Objects
+        for object in &timed.objects {
+            let wav_id = object.sound.map_or(silent_wav_id, |sound| {
+                sound_wav_ids.get(sound).copied().unwrap_or_else(|| {
+                    warnings.push(TimedToBmsWarning::UnknownSound(sound));
+                    silent_wav_id
+                })
+            });
+            let time = placer.place(object.ms);
+            let Some(lane) = object.lane else {
+                bms.wav.notes.push_bgm::<T>(time, wav_id);
+                continue;
+            };
+            let mut push = |offset, kind| {
+                bms.wav.notes.push_note(WavObj {
+                    offset,
+                    channel_id: T::new(lane.side, kind, lane.key).to_channel_id(),
+                    wav_id,
+                });
+            };
+            match object.kind {
+                NoteKind::Long if object.length_ms > 0.0 => {
+                    let end_time = placer.place(object.ms + object.length_ms);
+                    push(time, NoteKind::Long);
+                    push(end_time, NoteKind::Long);
+                }
+                NoteKind::Long => push(time, NoteKind::Visible),
+                kind => push(time, kind),
+            }
+        }
+        if bms
+            .wav
+            .notes
+            .all_notes()
+            .any(|obj| obj.wav_id == silent_wav_id)
+        {
+            bms.wav
+                .wav_files
+                .insert(silent_wav_id, PathBuf::from(SILENT_WAV));
+        }
+
+        let max_quantization_error_ms = placer.max_error_ms;
+        let PlayingCheckOutput {
+            playing_warnings,
+            playing_errors,
+        } = bms.check_playing::<T>();
+
+        TimedToBmsOutput {
+            bms,
+            warnings,
+            max_quantization_error_ms,
+            playing_warnings,
+            playing_errors,
+        }
+    }
+}
+
+/// A measure in beats from the origin.
+#[derive(Debug, Clone, Copy)]
+struct Measure {
+    start_beat: f64,
+    beats: f64,
+}
+
+/// The measures in order, each of them becomes a track.
+#[derive(Debug)]
+struct Measures(Vec<Measure>);
+
+impl Measures {
+    fn new(beat_map: &BeatMap, tempo_map: &[TempoPoint], end_beat: f64) -> Self {
+        // The lead-in measures have 4 beats.
+        let mut signatures: Vec<(f64, f64)> = vec![(0.0, 4.0)];
+        for point in tempo_map {
+            let Some(beats_per_measure) = point.beats_per_measure else {
+                continue;
+            };
+            let beat = beat_map.beat_of(point.ms);
+            if let Some(last) = signatures.last_mut()
+                && beat - last.0 <= BEAT_EPSILON
+            {
+                *last = (last.0, beats_per_measure.as_f64());
+                continue;
+            }
+            signatures.push((beat, beats_per_measure.as_f64()));
+        }
+
+        let mut measures = Vec::new();
+        for (index, &(start_beat, beats)) in signatures.iter().enumerate() {
+            let mut push_measures = |count: u64| {
+                for i in 0..count {
+                    measures.push(Measure {
+                        start_beat: start_beat + i as f64 * beats,
+                        beats,
+                    });
+                }
+            };
+            if let Some(&(next_beat, _)) = signatures.get(index + 1) {
+                let total = next_beat - start_beat;
+                let full = (total / beats + BEAT_EPSILON).floor();
+                push_measures(full as u64);
+                let remainder = total - full * beats;
+                if remainder > BEAT_EPSILON {
+                    measures.push(Measure {
+                        start_beat: start_beat + full * beats,
+                        beats: remainder,
+                    });
+                }
+            } else {
+                push_measures(((end_beat - start_beat) / beats).max(0.0).floor() as u64 + 1);
+            }
+        }
+        Self(measures)
+    }
+}
+
+/// Places the times in milliseconds on the measures, recording the quantization error.
+struct Placer<'a> {
+    beat_map: &'a BeatMap,
+    measures: &'a Measures,
+    quantization: Quantization,
+    max_error_ms: f64,
+}
+
+impl Placer<'_> {
+    fn place(&mut self, ms: f64) -> ObjTime {
+        let beat = self.beat_map.beat_of(ms);
+        let index = self
+            .measures
+            .0
+            .partition_point(|measure| measure.start_beat <= beat + BEAT_EPSILON)
+            .saturating_sub(1);
+        let Some(measure) = self.measures.0.get(index) else {
+            return ObjTime::start_of(Track(0));
+        };
+        let fraction = ((beat - measure.start_beat) / measure.beats).clamp(0.0, 1.0);
+
+        let mut best = (0, 1, f64::INFINITY);
+        for denominator in 1..=self.quantization.max_denominator.max(1) {
+            let numerator = (fraction * denominator as f64).round() as u64;
+            let placed_beat =
+                measure.start_beat + numerator as f64 / denominator as f64 * measure.beats;
+            let error = (self.beat_map.ms_of(placed_beat) - ms).abs();
+            if error < best.2 {
+                best = (numerator, denominator, error);
+            }
+            if error <= self.quantization.tolerance_ms {
+                break;
+            }
+        }
+        let (numerator, denominator, error) = best;
+        self.max_error_ms = self.max_error_ms.max(error);
+
+        let track = index as u64;
+        if numerator >= denominator {
+            return ObjTime::start_of(Track(track + 1));
+        }
+        ObjTime::new(track, numerator, denominator)
+            .unwrap_or_else(|| ObjTime::start_of(Track(track)))
+    }
+}
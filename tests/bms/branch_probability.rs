@@ -0,0 +1,159 @@
+use bms_rs::bms::prelude::*;
+use num::{BigInt, BigRational, BigUint};
+
+fn ratio(numerator: i64, denominator: i64) -> BigRational {
+    BigRational::new(BigInt::from(numerator), BigInt::from(denominator))
+}
+
+fn probabilities(source: &str) -> BranchProbabilities {
+    let BmsOutput { bms, .. } = parse_bms::<KeyLayoutBeat, _, _, _>(
+        source,
+        default_config_with_rng(RngMock([BigUint::from(1u64)])),
+    );
+    bms.expect("must be parsed")
+        .branch_probabilities::<KeyLayoutBeat>()
+}
+
+fn note_probabilities(probabilities: &BranchProbabilities) -> Vec<(String, BigRational)> {
+    probabilities
+        .notes
+        .iter()
+        .map(|note| (note.note.wav_id.to_string(), note.probability.clone()))
+        .collect()
+}
+
+#[test]
+fn test_nested_random_probabilities() {
+    let source = "\
+#WAV11 base.wav
+#00111:11
+#RANDOM 2
+#IF 1
+#WAV22 one.wav
+#00112:22
+#RANDOM 3
+#IF 1
+#00113:33
+#ENDIF
+#ENDRANDOM
+#ENDIF
+#ENDRANDOM
+#SETRANDOM 1
+#IF 1
+#00114:44
+#ENDIF
+#ENDRANDOM
+";
+    let probabilities = probabilities(source);
+    assert_eq!(
+        note_probabilities(&probabilities),
+        vec![
+            ("11".into(), ratio(1, 1)),
+            ("22".into(), ratio(1, 2)),
+            ("33".into(), ratio(1, 6)),
+            ("44".into(), ratio(1, 1)),
+        ]
+    );
+    let definitions: Vec<_> = probabilities
+        .definitions
+        .iter()
+        .map(|definition| {
+            (
+                definition.command.as_str(),
+                definition.id.to_string(),
+                definition.args.as_str(),
+                definition.probability.clone(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        definitions,
+        vec![
+            ("WAV", "11".into(), "base.wav", ratio(1, 1)),
+            ("WAV", "22".into(), "one.wav", ratio(1, 2)),
+        ]
+    );
+    // 2 + 1/2 + 1/6
+    assert_eq!(probabilities.expected_playable_notes, ratio(8, 3));
+}
+
+#[test]
+fn test_switch_probabilities() {
+    let source = "\
+#SWITCH 4
+#CASE 1
+#00111:11
+#CASE 2
+#00112:22
+#SKIP
+#DEF
+#00113:33
+#ENDSW
+";
+    let probabilities = probabilities(source);
+    assert_eq!(
+        note_probabilities(&probabilities),
+        vec![
+            ("11".into(), ratio(1, 4)),
+            ("22".into(), ratio(1, 4)),
+            ("33".into(), ratio(1, 2)),
+        ]
+    );
+}
+
+#[test]
+fn test_switch_case_without_skip_does_not_fall_through() {
+    let source = "\
+#SWITCH 2
+#CASE 1
+#00111:11
+#CASE 2
+#00112:22
+#ENDSW
+";
+    // The parser activates only `#CASE 1` for the generated 1, and the analysis agrees.
+    let BmsOutput { bms, .. } = parse_bms::<KeyLayoutBeat, _, _, _>(
+        source,
+        default_config_with_rng(RngMock([BigUint::from(1u64)])),
+    );
+    let bms = bms.expect("must be parsed");
+    let active: Vec<_> = bms
+        .notes()
+        .all_notes()
+        .map(|note| note.wav_id.to_string())
+        .collect();
+    assert_eq!(active, vec!["11".to_string()]);
+    assert_eq!(
+        note_probabilities(&probabilities(source)),
+        vec![("11".into(), ratio(1, 2)), ("22".into(), ratio(1, 2))]
+    );
+}
+
+#[test]
+fn test_probabilities_without_enumeration() {
+    // 10^6 combinations, which are too many to enumerate.
+    let source = "\
+#RANDOM 1000
+#IF 1
+#00111:11
+#ELSE
+#00112:22
+#ENDIF
+#ENDRANDOM
+#RANDOM 1000
+#IF 2
+#00113:33
+#ENDIF
+#ENDRANDOM
+";
+    let probabilities = probabilities(source);
+    assert_eq!(
+        note_probabilities(&probabilities),
+        vec![
+            ("11".into(), ratio(1, 1000)),
+            ("22".into(), ratio(999, 1000)),
+            ("33".into(), ratio(1, 1000)),
+        ]
+    );
+    assert_eq!(probabilities.expected_playable_notes, ratio(1001, 1000));
+}
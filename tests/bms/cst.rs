@@ -0,0 +1,168 @@
+use bms_rs::bms::{
+    cst::{Cst, CstEditError, CstLine, CstLineKind, LineEnding},
+    prelude::*,
+};
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("denominator must be non-zero")
+}
+
+fn channel(id: &str) -> NoteChannelId {
+    id.parse().expect("channel id must be valid")
+}
+
+fn obj_id(id: &str) -> ObjId {
+    ObjId::try_from(id, false).expect("object id must be valid")
+}
+
+#[test]
+fn test_lossless_round_trip() {
+    for source in [
+        include_str!("files/lilith_mx.bms"),
+        include_str!("files/nc_mx.bme"),
+        include_str!("files/J219_7key.bms"),
+        include_str!("files/dive_withblank.bme"),
+        include_str!("files/bemuse_ext.bms"),
+        "#TITLE  spaced  \r\n\r\n; comment\n#00111:01\n  #WAV01 a.wav",
+        "",
+    ] {
+        let cst = Cst::parse(source);
+        assert_eq!(cst.to_string(), source);
+
+        let expected = TokenStream::parse_lex(source);
+        let actual = cst.lex();
+        assert_eq!(actual.tokens, expected.tokens);
+        assert_eq!(actual.lex_warnings, expected.lex_warnings);
+    }
+}
+
+#[test]
+fn test_line_kinds() {
+    let cst = Cst::parse("#TITLE  Foo Bar \r\n\n#00211:0001\nfree text");
+    let kinds: Vec<_> = cst.lines().iter().map(CstLine::kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            CstLineKind::Header {
+                name: "TITLE",
+                args: "Foo Bar",
+            },
+            CstLineKind::Blank,
+            CstLineKind::Message {
+                track: 2,
+                channel: channel("11"),
+                message: "0001",
+            },
+            CstLineKind::Other,
+        ]
+    );
+    let line_endings: Vec<_> = cst.lines().iter().map(CstLine::line_ending).collect();
+    assert_eq!(
+        line_endings,
+        vec![
+            LineEnding::CrLf,
+            LineEnding::Lf,
+            LineEnding::Lf,
+            LineEnding::None
+        ]
+    );
+    assert_eq!(cst.line_range(2), Some(19..30));
+}
+
+#[test]
+fn test_set_header() {
+    let source = "\
+; header
+#TITLE   old title  \r
+#ARTIST someone\r
+#RANDOM 2\r
+#IF 1\r
+#GENRE inner\r
+#ENDIF\r
+#ENDRANDOM\r
+#00111:01\r
+";
+    let mut cst = Cst::parse(source);
+    assert_eq!(cst.set_header("title", "new title"), 1);
+    assert_eq!(
+        cst.to_string(),
+        source.replace("old title", "new title"),
+        "only the arguments are replaced"
+    );
+
+    // The header in the `#IF` scope is not touched, so a new one is inserted.
+    let index = cst.set_header("GENRE", "outer");
+    assert_eq!(index, 3);
+    assert_eq!(
+        cst.lines().get(index).map(CstLine::text),
+        Some("#GENRE outer")
+    );
+    assert_eq!(
+        cst.lines().get(index).map(CstLine::line_ending),
+        Some(LineEnding::CrLf)
+    );
+
+    assert_eq!(cst.remove_header("ARTIST"), 1);
+    assert_eq!(cst.remove_header("GENRE"), 1);
+    assert_eq!(
+        cst.to_string(),
+        source
+            .replace("old title", "new title")
+            .replace("#ARTIST someone\r\n", "")
+    );
+}
+
+#[test]
+fn test_unclosed_scope_is_closed_at_endrandom() {
+    let source = "#RANDOM 2\n#IF 1\n#GENRE inner\n#ENDRANDOM\n#GENRE outer\n";
+    let mut cst = Cst::parse(source);
+    // The `#IF` without `#ENDIF` does not hide the header after `#ENDRANDOM`.
+    assert_eq!(cst.set_header("GENRE", "new"), 4);
+    assert_eq!(cst.to_string(), source.replace("outer", "new"));
+}
+
+#[test]
+fn test_add_and_remove_object() {
+    let source = "#BPM 120\n#00111:01\n#00311:02";
+    let mut cst = Cst::parse(source);
+
+    assert_eq!(
+        cst.add_object(time(2, 1, 4), channel("12"), obj_id("0A")),
+        Ok(2)
+    );
+    assert_eq!(
+        cst.add_object(time(4, 0, 1), channel("11"), obj_id("0B")),
+        Ok(4)
+    );
+    assert_eq!(
+        cst.add_object(time(0, 1, 7919 * 7907), channel("11"), obj_id("0C")),
+        Err(CstEditError::ResolutionTooHigh {
+            resolution: 7919 * 7907
+        })
+    );
+    assert_eq!(
+        cst.to_string(),
+        "#BPM 120\n#00111:01\n#00212:000A0000\n#00311:02\n#00411:0B"
+    );
+
+    let BmsOutput { bms, .. } = parse_bms(&cst.to_string(), default_config());
+    let bms = bms.expect("must be parsed");
+    assert!(
+        bms.wav
+            .notes
+            .all_notes()
+            .any(|obj| obj.offset == time(2, 1, 4) && obj.wav_id == obj_id("0A"))
+    );
+
+    assert!(cst.remove_object(time(2, 1, 4), channel("12")));
+    assert!(cst.remove_object(time(4, 0, 1), channel("11")));
+    assert!(!cst.remove_object(time(1, 1, 2), channel("11")));
+    assert_eq!(cst.to_string(), source);
+}
+
+#[test]
+fn test_remove_object_keeps_other_objects() {
+    let mut cst = Cst::parse("#00111:01020304\n");
+    assert!(cst.remove_object(time(1, 1, 2), channel("11")));
+    assert_eq!(cst.to_string(), "#00111:01020004\n");
+}
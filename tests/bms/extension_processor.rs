@@ -0,0 +1,123 @@
+use std::collections::BTreeMap;
+
+use bms_rs::bms::prelude::*;
+use thiserror::Error;
+
+/// Parses the in-house `#OURTAG_*` headers and the messages on the channel `ZZ`.
+struct OurTagProcessor;
+
+/// A warning of [`OurTagProcessor`].
+#[derive(Debug, PartialEq, Eq, Error)]
+#[error("#OURTAG_{0} has no value")]
+struct EmptyOurTag(String);
+
+#[derive(Debug, Default, PartialEq, Eq)]
+struct OurTags {
+    headers: BTreeMap<String, String>,
+    messages: Vec<(Track, String)>,
+}
+
+impl TokenProcessor for OurTagProcessor {
+    type Output = OurTags;
+
+    fn process<P: Prompter>(
+        &self,
+        ctx: &mut ProcessContext<'_, '_, P>,
+    ) -> Result<Self::Output, ParseErrorWithRange> {
+        let mut tags = OurTags::default();
+        let our_channel: NoteChannelId = "ZZ".parse().expect("channel id must be valid");
+        ctx.all_tokens(|token, _prompter| {
+            let mut warnings = Vec::new();
+            match token.content() {
+                Token::Header { name, args } => {
+                    let Some(key) = name.strip_prefix("OURTAG_") else {
+                        return Ok(warnings);
+                    };
+                    if args.is_empty() {
+                        warnings.push(
+                            ParseWarning::Extension(ExtensionWarning::new(EmptyOurTag(
+                                key.to_string(),
+                            )))
+                            .into_wrapper(token),
+                        );
+                    } else {
+                        tags.headers.insert(key.to_string(), args.to_string());
+                    }
+                }
+                Token::Message {
+                    track,
+                    channel: Channel::Note { channel_id },
+                    message,
+                } if *channel_id == our_channel => {
+                    tags.messages.push((*track, message.to_string()));
+                }
+                _ => {}
+            }
+            Ok(warnings)
+        })?;
+        Ok(tags)
+    }
+}
+
+#[test]
+fn test_extension_processor() {
+    let source = "\
+#TITLE Extended
+#OURTAG_DIFFICULTY insane
+#OURTAG_EMPTY
+#SETRANDOM 2
+#IF 1
+#OURTAG_BRANCH one
+#ENDIF
+#IF 2
+#OURTAG_BRANCH two
+#ENDIF
+#ENDRANDOM
+#001ZZ:0102
+";
+    let ExtendedBmsOutput {
+        bms,
+        extensions,
+        warnings,
+    } = parse_bms_with_extension(
+        source,
+        default_config().override_token_processor(OurTagProcessor),
+    );
+    let bms = bms.expect("must be parsed");
+    assert_eq!(bms.music_info.title.as_deref(), Some("Extended"));
+
+    let tags = extensions.expect("extension output must exist");
+    // Only the activated branch is seen.
+    assert_eq!(
+        tags.headers,
+        BTreeMap::from([
+            ("BRANCH".to_string(), "two".to_string()),
+            ("DIFFICULTY".to_string(), "insane".to_string()),
+        ])
+    );
+    assert_eq!(tags.messages, vec![(Track(1), "0102".to_string())]);
+    let extension_warnings: Vec<_> = warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            BmsWarning::Parse(parse_warning) => match parse_warning.content() {
+                ParseWarning::Extension(extension) => extension.downcast_ref::<EmptyOurTag>(),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(extension_warnings, [&EmptyOurTag("EMPTY".to_string())]);
+}
+
+#[test]
+fn test_append_token_processor() {
+    let ExtendedBmsOutput { extensions, .. } = parse_bms_with_extension(
+        "#OURTAG_A 1\n",
+        default_config()
+            .override_token_processor(OurTagProcessor)
+            .append_token_processor(OurTagProcessor),
+    );
+    let (first, second) = extensions.expect("extension output must exist");
+    assert_eq!(first, second);
+    assert_eq!(first.headers.get("A").map(String::as_str), Some("1"));
+}
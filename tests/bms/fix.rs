@@ -0,0 +1,267 @@
+use bms_rs::bms::{
+    fix::{Fix, TextEdit, apply_fixes},
+    lint::{LintContext, LintOutput, LintRegistry},
+    parse::total::TotalFormula,
+    prelude::*,
+    source_map::SourceMapProcessor,
+};
+use num::BigUint;
+
+/// The layout which reads only the notes of the player 1 side.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Player1Only(KeyLayoutBeat);
+
+impl KeyLayout for Player1Only {
+    fn new(side: PlayerSide, kind: NoteKind, key: Key) -> Self {
+        Self(KeyLayoutBeat::new(side, kind, key))
+    }
+
+    fn side(&self) -> PlayerSide {
+        self.0.side()
+    }
+
+    fn kind(&self) -> NoteKind {
+        self.0.kind()
+    }
+
+    fn key(&self) -> Key {
+        self.0.key()
+    }
+}
+
+impl KeyLayoutMapper for Player1Only {
+    fn to_channel_id(self) -> NoteChannelId {
+        self.0.to_channel_id()
+    }
+
+    fn from_channel_id(channel_id: NoteChannelId) -> Option<Self> {
+        KeyLayoutBeat::from_channel_id(channel_id)
+            .filter(|beat| beat.side() == PlayerSide::Player1)
+            .map(Self)
+    }
+}
+
+fn lint(source: &str, with_source: bool) -> LintOutput {
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let mut relaxed = tokens.clone();
+    DefaultTokenRelaxer.modify(&mut relaxed);
+    let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+        &relaxed,
+        default_config_with_rng(RngMock([BigUint::from(1u64)]))
+            .override_token_processor(SourceMapProcessor),
+    );
+    let bms = output.bms.expect("must be parsed");
+    let source_map = output.extensions.expect("must be parsed");
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let ctx = if with_source {
+        ctx.with_source(source)
+    } else {
+        ctx
+    };
+    LintRegistry::builtin::<KeyLayoutBeat>().run(&ctx)
+}
+
+#[test]
+fn test_apply_fixes() {
+    let source = "#TITLE Song\n#ARTIST Someone\n";
+    let fixes = [
+        Fix::new(&"rename", vec![TextEdit::replace(7..11, "Music")]),
+        // Conflicts with the first one, so skipped entirely.
+        Fix::new(
+            &"conflicting",
+            vec![
+                TextEdit::insert(0, "#GENRE Pop\n"),
+                TextEdit::replace(9..13, "x"),
+            ],
+        ),
+        Fix::new(&"out of range", vec![TextEdit::delete(20..40)]),
+        Fix::new(&"insert", vec![TextEdit::insert(7, "New ")]),
+        Fix::new(&"remove", vec![TextEdit::delete(12..28)]),
+    ];
+    assert_eq!(apply_fixes(source, &fixes), "#TITLE New Music\n");
+}
+
+#[test]
+fn test_lint_fixes() {
+    let source = "\
+#PLAYER 1
+#BPM 120
+#WAV01 old.wav
+#WAV01 kick.wav
+#WAV02 snare.wav
+#RONDAM 2
+#IF 1
+#00112:01
+#END IF
+#00151:01000100
+#00111:00020000
+";
+    let output = lint(source, true);
+    let fixed = apply_fixes(source, output.fixes());
+    assert_eq!(
+        fixed,
+        "\
+#TOTAL 160
+#PLAYER 1
+#BPM 120
+#WAV01 kick.wav
+#WAV02 snare.wav
+#RANDOM 2
+#IF 1
+#00112:01
+#ENDIF
+#00151:01000100
+#00111:00000000
+"
+    );
+    assert_eq!(lint(&fixed, true).fixes().count(), 0);
+}
+
+#[test]
+fn test_line_fixes_need_source() {
+    let source = "\
+#BPM 120
+#WAV01 old.wav
+#WAV01 kick.wav
+#RONDAM 2
+#IF 1
+#00111:01
+#END IF
+";
+    let output = lint(source, false);
+    let fixes: Vec<_> = output
+        .diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            ["misspelled-command", "duplicate-definition"].contains(&diagnostic.rule)
+        })
+        .map(|diagnostic| (diagnostic.rule, diagnostic.fix.clone()))
+        .collect();
+    assert_eq!(
+        fixes,
+        [
+            (
+                "misspelled-command",
+                Some(Fix::new(
+                    &"replace with `#RANDOM 2`",
+                    vec![TextEdit::replace(40..47, "#RANDOM")]
+                ))
+            ),
+            // The arguments of `#END IF` are out of the token.
+            ("misspelled-command", None),
+            ("duplicate-definition", None),
+        ]
+    );
+}
+
+#[test]
+fn test_total_fix_counts_notes_in_layout() {
+    let source = "#BPM 120\n#WAV01 kick.wav\n#00111:0101\n#00121:01010101\n";
+    let lint_beat = lint(source, true);
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let parsed = Bms::from_token_stream_with_extension(
+        &tokens,
+        default_config()
+            .key_mapper::<Player1Only>()
+            .override_token_processor(SourceMapProcessor),
+    );
+    let bms = parsed.bms.expect("must be parsed");
+    let source_map = parsed.extensions.expect("must be parsed");
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let lint_1p = LintRegistry::builtin::<Player1Only>().run(&ctx);
+
+    let total_fix = |output: &LintOutput| {
+        output
+            .diagnostics
+            .iter()
+            .find(|diagnostic| diagnostic.rule == "total-undefined")
+            .and_then(|diagnostic| diagnostic.fix.clone())
+            .map(|fix| fix.message)
+    };
+    let add_total = |total: f64| Some(format!("add `#TOTAL {total:.0}`"));
+    let total_beat = bms.recommended_total::<KeyLayoutBeat>(TotalFormula::default());
+    let total_1p = bms.recommended_total::<Player1Only>(TotalFormula::default());
+    assert_ne!(add_total(total_beat), add_total(total_1p));
+    assert_eq!(total_fix(&lint_beat), add_total(total_beat));
+    assert_eq!(total_fix(&lint_1p), add_total(total_1p));
+}
+
+fn parse_warning_fixes(warnings: &[BmsWarning]) -> Vec<Fix> {
+    warnings
+        .iter()
+        .filter_map(|warning| match warning {
+            BmsWarning::Parse(warning) => warning.content().fix().cloned(),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_warning_fixes() {
+    let source = "\
+#RONDAM 2
+#IF 1
+#WAV02 inner.wav
+#ENDIF
+#ENDRANDOM
+#WAV01 old.wav
+#BPM 120
+#WAV01 new.wav
+#WAV02 outer.wav
+#00111:01
+";
+    let BmsOutput { warnings, .. } = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config());
+    let fixes = parse_warning_fixes(&warnings);
+    let messages: Vec<_> = fixes.iter().map(|fix| fix.message.as_str()).collect();
+    // `#WAV02` is not fixed, because the other branch may use the one in `#IF`.
+    assert_eq!(
+        messages,
+        ["replace with `#RANDOM 2`", "remove the unused definition"]
+    );
+    assert_eq!(
+        apply_fixes(source, &fixes),
+        source
+            .replace("#RONDAM", "#RANDOM")
+            .replace("#WAV01 old.wav\n", "")
+    );
+}
+
+#[test]
+fn test_parse_warning_fix_keeps_older_definition() {
+    let source = "#WAV01 old.wav\n#WAV01 new.wav\n#00111:01\n";
+    let BmsOutput { warnings, .. } = parse_bms::<KeyLayoutBeat, _, _, _>(
+        source,
+        default_config().prompter(AlwaysWarnAndUseOlder),
+    );
+    assert_eq!(
+        apply_fixes(source, &parse_warning_fixes(&warnings)),
+        "#WAV01 old.wav\n#00111:01\n"
+    );
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn test_parse_warning_fix_is_rendered() {
+    use bms_rs::diagnostics::collect_bms_reports;
+
+    let source = "#RONDAM 2\n#ENDRANDOM\n";
+    let BmsOutput { warnings, .. } = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config());
+    let mut rendered = Vec::new();
+    for report in collect_bms_reports("test.bms", source, &warnings) {
+        report
+            .write(
+                ("test.bms".to_string(), ariadne::Source::from(source)),
+                &mut rendered,
+            )
+            .expect("must be written");
+    }
+    let rendered = String::from_utf8(rendered).expect("must be UTF-8");
+    assert!(
+        rendered.contains("fix: replace with `#RANDOM 2`"),
+        "{rendered}"
+    );
+}
@@ -0,0 +1,185 @@
+use bms_rs::bms::{format::format_bms, prelude::*};
+
+const FILES: &[&str] = &[
+    include_str!("files/lilith_mx.bms"),
+    include_str!("files/nc_mx.bme"),
+    include_str!("files/J219_7key.bms"),
+    include_str!("files/dive_withblank.bme"),
+    include_str!("files/bemuse_ext.bms"),
+];
+
+fn parse(source: &str) -> Bms {
+    parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("parse must succeed")
+}
+
+fn sorted_notes(bms: &Bms) -> Vec<WavObj> {
+    let mut notes: Vec<WavObj> = bms.notes().all_notes().cloned().collect();
+    notes.sort_by_key(|note| (note.offset, note.channel_id, note.wav_id));
+    notes
+}
+
+#[test]
+fn test_format_layout() {
+    let source = "\
+#WAV02 b.wav
+; comment
+#00211:0101
+#BPM 150
+#TITLE  Song
+#WAV01 a.wav
+#00111:00000000
+#00211:00000202
+#00101:0001
+#00102:0.75
+#ARTIST Someone
+";
+    let expected = "\
+; comment
+
+#TITLE Song
+#ARTIST Someone
+
+#BPM 150
+
+#WAV01 a.wav
+#WAV02 b.wav
+
+#00101:0001
+#00102:0.75
+
+#00211:01000102
+#00211:0002
+";
+    assert_eq!(format_bms(source), expected);
+}
+
+#[test]
+fn test_format_base_comes_first() {
+    let source = "\
+#WAVzz z.wav
+#LNOBJ zz
+#TITLE Song
+#BASE 62
+#001A1:zz
+";
+    let expected = "\
+#BASE 62
+#TITLE Song
+
+#LNOBJ zz
+
+#WAVzz z.wav
+
+#001A1:zz
+";
+    let formatted = format_bms(source);
+    assert_eq!(formatted, expected);
+    assert_eq!(
+        sorted_notes(&parse(&formatted)),
+        sorted_notes(&parse(source))
+    );
+}
+
+#[test]
+fn test_format_keeps_control_flow_verbatim() {
+    let source = "\
+#00111:01
+#RANDOM 2
+#IF 1
+  #00111:02
+#ENDIF
+
+#IF 2
+  #00111:03
+#ENDIF
+#ENDRANDOM
+#TITLE x
+";
+    let expected = "\
+#00111:01
+
+#RANDOM 2
+#IF 1
+  #00111:02
+#ENDIF
+
+#IF 2
+  #00111:03
+#ENDIF
+#ENDRANDOM
+
+#TITLE x
+";
+    assert_eq!(format_bms(source), expected);
+}
+
+#[test]
+fn test_format_keeps_overrides_after_control_flow() {
+    let source = "\
+#WAV01 outer.wav
+#00111:01
+#SETRANDOM 1
+#IF 1
+#WAV01 branch.wav
+#BPM 200
+#ENDIF
+#ENDRANDOM
+#BPM 150
+#WAV01 after.wav
+#TITLE x
+";
+    let expected = "\
+#WAV01 outer.wav
+
+#00111:01
+
+#SETRANDOM 1
+#IF 1
+#WAV01 branch.wav
+#BPM 200
+#ENDIF
+#ENDRANDOM
+
+#TITLE x
+
+#BPM 150
+
+#WAV01 after.wav
+";
+    let formatted = format_bms(source);
+    assert_eq!(formatted, expected);
+    let original = parse(source);
+    let reparsed = parse(&formatted);
+    assert_eq!(reparsed.bpm.bpm, original.bpm.bpm);
+    assert_eq!(reparsed.wav.wav_files, original.wav.wav_files);
+    assert_eq!(format_bms(&formatted), formatted);
+}
+
+#[test]
+fn test_format_line_endings() {
+    assert_eq!(format_bms(""), "");
+    assert_eq!(
+        format_bms("#TITLE a\r\n#00111:01"),
+        "#TITLE a\r\n\r\n#00111:01\r\n"
+    );
+}
+
+#[test]
+fn test_format_is_idempotent() {
+    for source in FILES {
+        let formatted = format_bms(source);
+        assert_eq!(format_bms(&formatted), formatted);
+    }
+}
+
+#[test]
+fn test_format_preserves_chart() {
+    for source in FILES {
+        let original = parse(source);
+        let formatted = parse(&format_bms(source));
+        assert_eq!(formatted.music_info, original.music_info);
+        assert_eq!(sorted_notes(&formatted), sorted_notes(&original));
+    }
+}
@@ -0,0 +1,108 @@
+use bms_rs::bms::prelude::*;
+use num::BigUint;
+
+fn config() -> ParseConfig<KeyLayoutBeat, AlwaysWarnAndUseNewer, RngMock<1>, DefaultTokenRelaxer> {
+    default_config_with_rng(RngMock([BigUint::from(1u64)]))
+}
+
+#[test]
+fn test_header_matches_full_parse() {
+    let sources = [
+        include_str!("files/lilith_mx.bms"),
+        include_str!("files/J219_7key.bms"),
+        include_str!("files/nc_mx.bme"),
+        include_str!("files/dive_withblank.bme"),
+    ];
+    for source in sources {
+        let full = parse_bms::<KeyLayoutBeat, _, _, _>(source, config())
+            .bms
+            .expect("must be parsed");
+        let BmsHeaderOutput { bms, .. } = parse_bms_header(source, config());
+        let header = bms.expect("must be parsed");
+
+        assert_eq!(header.music_info, full.music_info);
+        assert_eq!(header.metadata, full.metadata);
+        assert_eq!(header.sprite, full.sprite);
+        assert_eq!(header.resources, full.resources);
+        assert_eq!(header.judge.rank, full.judge.rank);
+        assert_eq!(header.judge.total, full.judge.total);
+        assert_eq!(header.bpm.bpm, full.bpm.bpm);
+        assert_eq!(header.notes().all_notes().count(), 0);
+    }
+}
+
+#[test]
+fn test_header_random() {
+    let source = "\
+#TITLE Song
+#RANDOM 2
+#IF 1
+#SUBTITLE [ONE]
+#ENDIF
+#IF 2
+#SUBTITLE [TWO]
+#ENDIF
+#ENDRANDOM
+#00111:01
+";
+    let BmsHeaderOutput {
+        bms,
+        has_random,
+        warnings,
+    } = parse_bms_header(source, config());
+    assert_eq!(warnings, vec![]);
+    assert!(has_random);
+    let bms = bms.expect("must be parsed");
+    assert_eq!(bms.music_info.title.as_deref(), Some("Song"));
+    assert_eq!(bms.music_info.subtitle.as_deref(), Some("[ONE]"));
+
+    // `#SETRANDOM` does not vary the chart.
+    let fixed = parse_bms_header(
+        "#TITLE Song\n#SETRANDOM 1\n#IF 1\n#ENDIF\n#ENDRANDOM\n",
+        config(),
+    );
+    assert!(!fixed.has_random);
+}
+
+#[test]
+fn test_header_stops_at_first_message() {
+    let source = "\
+#TITLE Song
+#00111:01
+#ARTIST Someone
+#RANDOM 2
+#ENDRANDOM
+";
+    let BmsHeaderOutput {
+        bms, has_random, ..
+    } = parse_bms_header(source, config());
+    let bms = bms.expect("must be parsed");
+    assert_eq!(bms.music_info.title.as_deref(), Some("Song"));
+    // The commands after the message are not read, but `#RANDOM` is still found.
+    assert_eq!(bms.music_info.artist, None);
+    assert!(has_random);
+}
+
+#[test]
+fn test_header_random_after_messages() {
+    let source = "\
+#TITLE Song
+#00111:01
+#00211:01
+  #switch 2
+#CASE 1
+#00311:01
+#ENDSW
+";
+    let BmsHeaderOutput {
+        bms, has_random, ..
+    } = parse_bms_header(source, config());
+    assert_eq!(
+        bms.expect("must be parsed").music_info.title.as_deref(),
+        Some("Song")
+    );
+    assert!(has_random);
+
+    let fixed = parse_bms_header("#TITLE Song\n#00111:01\n#SETSWITCH 1\n#ENDSW\n", config());
+    assert!(!fixed.has_random);
+}
@@ -0,0 +1,161 @@
+use bms_rs::bms::{
+    incremental::{IncrementalBms, Reparse},
+    prelude::*,
+};
+use num::BigUint;
+
+type Config = ParseConfig<KeyLayoutBeat, AlwaysWarnAndUseNewer, RngMock<1>, DefaultTokenRelaxer>;
+
+fn config() -> Config {
+    default_config_with_rng(RngMock([BigUint::from(1u64)]))
+}
+
+/// Splits the notes out of `bms` in time order without the dangling ones, because their storage depends on the edits.
+fn normalized(bms: &Bms) -> (Bms, Vec<WavObj>) {
+    let mut bms = bms.clone();
+    let notes = std::mem::take(&mut bms.wav.notes);
+    let mut notes: Vec<_> = notes
+        .all_notes()
+        .filter(|note| !note.wav_id.is_null())
+        .cloned()
+        .collect();
+    notes.sort_by_key(|note| (note.offset, note.channel_id, note.wav_id));
+    (bms, notes)
+}
+
+/// Applies the edit and checks that the result equals the full parse of the edited source.
+fn edit_and_check(
+    incremental: &mut IncrementalBms<KeyLayoutBeat>,
+    target: &str,
+    replacement: &str,
+) -> Reparse {
+    let start = incremental
+        .source()
+        .find(target)
+        .expect("target must be in the source");
+    let reparse = incremental.edit(start..start + target.len(), replacement, config());
+
+    let full = IncrementalBms::new(incremental.source().to_string(), config());
+    assert_eq!(incremental.tokens(), full.tokens());
+    assert_eq!(incremental.lex_warnings(), full.lex_warnings());
+    assert_eq!(incremental.parse_warnings(), full.parse_warnings());
+    let actual = incremental.bms().as_ref().expect("must be parsed");
+    let expected = full.bms().as_ref().expect("must be parsed");
+    assert_eq!(normalized(actual), normalized(expected));
+    reparse
+}
+
+#[test]
+fn test_incremental_edits_on_chart() {
+    let source = include_str!("files/lilith_mx.bms");
+    let mut incremental = IncrementalBms::new(source, config());
+
+    // Note messages and sound definitions are updated incrementally.
+    let edits = [
+        ("#00511:", "#00512:"),
+        ("#00116:1F", "#00116:1F1F"),
+        // An invalid object id is warned.
+        ("#00116:1F1F", "#00116:1F!!"),
+        ("#WAV01 kick_002.wav", "#WAV01 kick_003.wav"),
+        ("#WAV02 kick_023.wav\n", ""),
+        ("#00512:", "#00111:0101\n#00512:"),
+        ("#PLAYER 1\n", "#PLAYER 1\n%URL https://example.com/\n"),
+        // The unchanged headers on the touched lines.
+        ("#GENRE Hi-Tech Rave\n", "#GENRE Hi-Tech Rave\n\n"),
+    ];
+    for (target, replacement) in edits {
+        assert_eq!(
+            edit_and_check(&mut incremental, target, replacement),
+            Reparse::Incremental,
+            "editing {target:?}"
+        );
+    }
+
+    // Other headers need the full parse.
+    assert_eq!(
+        edit_and_check(&mut incremental, "#TITLE Lilith", "#TITLE Lily"),
+        Reparse::Full
+    );
+}
+
+#[test]
+fn test_incremental_edits_around_random() {
+    let source = "\
+#WAV01 a.wav
+#00111:01
+#RANDOM 2
+#IF 1
+#00112:01
+#ENDIF
+#ENDRANDOM
+#00113:01
+";
+    let mut incremental = IncrementalBms::new(source, config());
+
+    // The positions of the random scopes are shifted.
+    assert_eq!(
+        edit_and_check(&mut incremental, "#00111:01", "#00111:0101"),
+        Reparse::Incremental
+    );
+    assert_eq!(
+        edit_and_check(&mut incremental, "#00113:01", "#00113:0001"),
+        Reparse::Incremental
+    );
+    assert_eq!(
+        edit_and_check(&mut incremental, "#00112:01", "#00112:0101"),
+        Reparse::Full
+    );
+    assert_eq!(
+        edit_and_check(&mut incremental, "#RANDOM 2", "#RANDOM 3"),
+        Reparse::Full
+    );
+    // The same track and channel as the randomized messages.
+    assert_eq!(
+        edit_and_check(&mut incremental, "#00113:0001", "#00112:0001"),
+        Reparse::Full
+    );
+}
+
+#[test]
+fn test_incremental_definitions() {
+    let source = "\
+#WAV01 a.wav
+#BMP01 a.bmp
+#BPM01 150
+#BPM02 180
+#STOP01 48
+#00111:01
+#00111:0001
+#00108:02
+";
+    let mut incremental = IncrementalBms::new(source, config());
+
+    // The definitions not referred by the resolved objects are updated incrementally.
+    let edits = [
+        ("#BMP01 a.bmp", "#BMP01 b.bmp"),
+        ("#BPM01 150", "#BPM01 160"),
+        ("#STOP01 48", "#STOP01 96"),
+        ("#STOP01 96\n", ""),
+        ("#BMP01 b.bmp", "#BMP01 b.bmp\n#EXBMP02 0,0,256,256 c.bmp"),
+        // Another line on the same track and channel.
+        ("#00111:0001", "#00111:0100"),
+    ];
+    for (target, replacement) in edits {
+        assert_eq!(
+            edit_and_check(&mut incremental, target, replacement),
+            Reparse::Incremental,
+            "editing {target:?}"
+        );
+    }
+
+    // The BPM change holds the value of `#BPM02`.
+    assert_eq!(
+        edit_and_check(&mut incremental, "#BPM02 180", "#BPM02 200"),
+        Reparse::Full
+    );
+    // The duplicated definitions are resolved by the prompter.
+    assert_eq!(
+        edit_and_check(&mut incremental, "#BPM01 160", "#BPM01 160\n#EXBPM01 170"),
+        Reparse::Full
+    );
+}
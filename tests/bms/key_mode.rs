@@ -0,0 +1,193 @@
+use bms_rs::bms::{
+    key_mode::{
+        KeyMode, KeyModeConfidence, KeyModeDetection, KeyModeHints, detect_key_mode_from_tokens,
+    },
+    prelude::*,
+};
+
+fn detect(source: &str, hints: KeyModeHints<'_>) -> KeyModeDetection {
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    detect_key_mode_from_tokens(&tokens, &hints)
+}
+
+const fn detection(
+    mode: KeyMode,
+    layout: KeyLayoutKind,
+    confidence: KeyModeConfidence,
+) -> KeyModeDetection {
+    KeyModeDetection {
+        mode,
+        layout,
+        confidence,
+    }
+}
+
+#[test]
+fn test_beat_modes() {
+    let hints = KeyModeHints::default();
+    assert_eq!(
+        detect("#00111:01\n#00116:01\n", hints),
+        detection(
+            KeyMode::Beat5K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Medium
+        )
+    );
+    assert_eq!(
+        detect("#PLAYER 1\n#00111:01\n#00159:0101\n", hints),
+        detection(
+            KeyMode::Beat7K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::High
+        )
+    );
+    assert_eq!(
+        detect("#PLAYER 3\n#00111:01\n#00226:01\n", hints),
+        detection(
+            KeyMode::Beat10K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::High
+        )
+    );
+    assert_eq!(
+        detect("#00111:01\n#00228:01\n", hints),
+        detection(
+            KeyMode::Beat14K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Medium
+        )
+    );
+    // The objects `00` place no notes.
+    assert_eq!(
+        detect("#00111:01\n#00118:0000\n", hints),
+        detection(
+            KeyMode::Beat5K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Medium
+        )
+    );
+}
+
+#[test]
+fn test_pms_modes() {
+    let pms = KeyModeHints::default().with_extension("PMS");
+    assert_eq!(
+        detect("#00111:01\n#00115:01\n", pms),
+        detection(KeyMode::Pms9K, KeyLayoutKind::Pms, KeyModeConfidence::High)
+    );
+    assert_eq!(
+        detect("#00111:01\n#00122:01\n#00125:01\n", KeyModeHints::default()),
+        detection(
+            KeyMode::Pms9K,
+            KeyLayoutKind::Pms,
+            KeyModeConfidence::Medium
+        )
+    );
+    assert_eq!(
+        detect("#00111:01\n#00117:01\n#00119:01\n", KeyModeHints::default()),
+        detection(
+            KeyMode::Pms9K,
+            KeyLayoutKind::PmsBmeType,
+            KeyModeConfidence::Medium
+        )
+    );
+    // `#PLAYER 3` turns the lanes of PMS into 10K.
+    assert_eq!(
+        detect("#PLAYER 3\n#00111:01\n#00122:01\n", KeyModeHints::default()),
+        detection(
+            KeyMode::Beat10K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::High
+        )
+    );
+}
+
+#[test]
+fn test_low_confidence() {
+    assert_eq!(
+        detect("#PLAYER 3\n#BPM 120\n", KeyModeHints::default()),
+        detection(
+            KeyMode::Beat14K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Low
+        )
+    );
+    assert_eq!(
+        detect("#PLAYER 1\n#00111:01\n#00228:01\n", KeyModeHints::default()),
+        detection(
+            KeyMode::Beat14K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Low
+        )
+    );
+    assert_eq!(
+        detect(
+            "#00111:01\n#00228:01\n",
+            KeyModeHints::default().with_extension("pms")
+        ),
+        detection(
+            KeyMode::Beat14K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Low
+        )
+    );
+}
+
+#[test]
+fn test_mode_hint() {
+    assert_eq!(
+        KeyMode::from_mode_hint("Keyboard-24K"),
+        Some(KeyMode::Keyboard24K)
+    );
+    assert_eq!(KeyMode::from_mode_hint("popn-5k"), Some(KeyMode::Pms9K));
+    assert_eq!(
+        KeyMode::from_mode_hint("keyboard-24k-double"),
+        Some(KeyMode::Keyboard48K)
+    );
+    assert!(KeyMode::Keyboard48K.is_double());
+    assert_eq!(KeyMode::from_mode_hint("generic-nkeys"), None);
+    assert_eq!(
+        detect(
+            "#00111:01\n",
+            KeyModeHints::default().with_mode_hint("keyboard-24k")
+        ),
+        detection(
+            KeyMode::Keyboard24K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::Low
+        )
+    );
+    assert_eq!(
+        detect(
+            "#00111:01\n",
+            KeyModeHints::default().with_mode_hint("beat-14k")
+        ),
+        detection(
+            KeyMode::Beat14K,
+            KeyLayoutKind::Beat,
+            KeyModeConfidence::High
+        )
+    );
+    assert_eq!(
+        KeyModeHints::from_path(std::path::Path::new("song/9key.pms")).extension,
+        Some("pms")
+    );
+}
+
+#[test]
+fn test_parse_bms_auto() {
+    let source = "#PLAYER 1\n#00111:01\n#00122:01\n#00125:01\n";
+    let hints = KeyModeHints::default().with_extension("pms");
+    let AutoBmsOutput { output, detection } = parse_bms_auto(source, default_config(), &hints);
+    assert_eq!(detection.layout, KeyLayoutKind::Pms);
+    let bms = output.bms.expect("must be parsed");
+    assert_eq!(bms.detect_key_mode(&hints), detection);
+
+    let keys: Vec<_> = bms
+        .notes()
+        .all_notes()
+        .filter_map(|note| KeyLayoutPms::from_channel_id(note.channel_id))
+        .map(|map| map.key())
+        .collect();
+    assert_eq!(keys, [Key::Key(1), Key::Key(6), Key::Key(9)]);
+}
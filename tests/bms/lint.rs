@@ -0,0 +1,256 @@
+use bms_rs::bms::{
+    lint::{LintContext, LintFinding, LintRegistry, LintRule, LintSeverity},
+    parse::timing::TimingCheckConfig,
+    prelude::*,
+    source_map::{SourceMap, SourceMapProcessor},
+};
+
+fn parse(tokens: &TokenStream<'_>) -> (Bms, SourceMap) {
+    let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+        tokens,
+        default_config().override_token_processor(SourceMapProcessor),
+    );
+    (
+        output.bms.expect("must be parsed"),
+        output.extensions.expect("must be parsed"),
+    )
+}
+
+fn rules_of(source: &str, registry: &LintRegistry) -> Vec<(&'static str, LintSeverity)> {
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let (bms, source_map) = parse(&tokens);
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    registry
+        .run(&ctx)
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| (diagnostic.rule, diagnostic.severity))
+        .collect()
+}
+
+#[test]
+fn test_builtin_covers_playing_checks() {
+    let registry = LintRegistry::builtin::<KeyLayoutBeat>();
+    assert_eq!(
+        rules_of("#TITLE Empty\n", &registry),
+        [
+            ("total-undefined", LintSeverity::Warning),
+            ("bpm-undefined", LintSeverity::Error),
+            ("no-notes", LintSeverity::Error),
+        ]
+    );
+
+    let source = "\
+#BPM fast
+#TOTAL 200
+#WAV01 a.wav
+#STOP01 long
+#SPEED01 x
+#SCROLL01 y
+#SEEK01 z
+#00111:01
+";
+    let invalid: Vec<_> = rules_of(source, &registry)
+        .into_iter()
+        .filter(|&(rule, _)| rule != "unused-definition")
+        .collect();
+    assert_eq!(invalid, [("invalid-value", LintSeverity::Error); 5]);
+}
+
+#[test]
+fn test_configure_severity() {
+    let mut registry = LintRegistry::builtin::<KeyLayoutBeat>()
+        .with_severity("total-undefined", LintSeverity::Error)
+        .with_severity("no-notes", LintSeverity::Allow);
+    assert!(!registry.set_severity("unknown-rule", LintSeverity::Error));
+    assert!(registry.set_severity("bpm-undefined", LintSeverity::Info));
+    assert_eq!(registry.severity("unknown-rule"), None);
+    assert_eq!(
+        registry.severity("overlapping-notes"),
+        Some(LintSeverity::Warning)
+    );
+    assert_eq!(
+        rules_of("#TITLE Empty\n", &registry),
+        [
+            ("total-undefined", LintSeverity::Error),
+            ("bpm-undefined", LintSeverity::Info),
+        ]
+    );
+}
+
+#[test]
+fn test_allow_comments() {
+    let source = "\
+%LINT-ALLOW total-undefined
+#BPM 120
+#WAV01 kick.wav
+%LINT-ALLOW-NEXT missing-definition
+#00111:0102
+
+#00211:03
+";
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let (bms, source_map) = parse(&tokens);
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let output = LintRegistry::builtin::<KeyLayoutBeat>().run(&ctx);
+
+    let [diagnostic] = output.diagnostics.as_slice() else {
+        panic!("expected one diagnostic: {output:?}");
+    };
+    assert_eq!(diagnostic.rule, "missing-definition");
+    let range = diagnostic.range.clone().expect("must be located");
+    assert_eq!(&source[range], "#00211:03");
+
+    // Without the tokens, the allow-comments are not applied.
+    let unsuppressed = LintContext::new(&bms).with_source_map(&source_map);
+    let rules: Vec<_> = LintRegistry::builtin::<KeyLayoutBeat>()
+        .run(&unsuppressed)
+        .diagnostics
+        .into_iter()
+        .map(|found| found.rule)
+        .collect();
+    assert_eq!(
+        rules,
+        [
+            "missing-definition",
+            "missing-definition",
+            "total-undefined"
+        ]
+    );
+}
+
+#[test]
+fn test_locate_builtin() {
+    let source = "\
+#TITLE Overlap
+#BPM 120
+#TOTAL 200
+#WAV01 kick.wav
+#00108:01
+#00111:01
+#00111:01
+";
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let (bms, source_map) = parse(&tokens);
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let located: Vec<_> = LintRegistry::builtin::<KeyLayoutBeat>()
+        .run(&ctx)
+        .diagnostics
+        .into_iter()
+        .map(|diagnostic| {
+            let range = diagnostic.range.expect("must be located");
+            (diagnostic.rule, &source[range])
+        })
+        .collect();
+    assert_eq!(
+        located,
+        [
+            ("missing-definition", "#00108:01"),
+            ("overlapping-notes", "#00111:01"),
+        ]
+    );
+}
+
+/// A house rule requiring `#ARTIST`, reading the tokens.
+struct RequireArtist;
+
+impl LintRule for RequireArtist {
+    fn id(&self) -> &'static str {
+        "require-artist"
+    }
+
+    fn description(&self) -> &'static str {
+        "The `#ARTIST` is required."
+    }
+
+    fn default_severity(&self) -> LintSeverity {
+        LintSeverity::Error
+    }
+
+    fn check(&self, ctx: &LintContext<'_>) -> Vec<LintFinding> {
+        let has_artist = ctx.tokens().is_some_and(|tokens| {
+            tokens.iter().any(|token| {
+                matches!(token.content(), Token::Header { name, .. } if name.eq_ignore_ascii_case("ARTIST"))
+            })
+        });
+        if has_artist || ctx.bms().music_info.artist.is_some() {
+            return vec![];
+        }
+        vec![LintFinding::new(&"The `#ARTIST` is not specified.").with_range(Some(0..0))]
+    }
+}
+
+#[test]
+fn test_custom_rule() {
+    let registry = LintRegistry::new().with_rule(RequireArtist);
+    assert_eq!(
+        registry.rules().map(LintRule::id).collect::<Vec<_>>(),
+        ["require-artist"]
+    );
+    assert_eq!(
+        rules_of("#TITLE Song\n", &registry),
+        [("require-artist", LintSeverity::Error)]
+    );
+    assert_eq!(rules_of("#ARTIST Someone\n", &registry), []);
+    assert_eq!(
+        rules_of("%LINT-ALLOW require-artist\n#TITLE Song\n", &registry),
+        []
+    );
+}
+
+#[cfg(feature = "diagnostics")]
+#[test]
+fn test_lint_report() {
+    let source = "#TITLE Empty\n";
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let (bms, source_map) = parse(&tokens);
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let output = LintRegistry::builtin::<KeyLayoutBeat>().run(&ctx);
+    let simple = SimpleSource::new("empty.bms", source);
+    let mut rendered = vec![];
+    for diagnostic in &output.diagnostics {
+        diagnostic
+            .to_report(&simple)
+            .write(
+                ("empty.bms".to_string(), ariadne::Source::from(source)),
+                &mut rendered,
+            )
+            .expect("must be written");
+    }
+    let rendered = String::from_utf8(rendered).expect("must be UTF-8");
+    assert!(rendered.contains("lint `bpm-undefined`"));
+    assert!(rendered.contains("There is no bpm defined."));
+}
+
+#[test]
+fn test_checks_are_shared_for_each_layout() {
+    let LexOutput { tokens, .. } = TokenStream::parse_lex("#BPM 120\n#WAV01 a.wav\n#00111:01\n");
+    let (bms, _) = parse(&tokens);
+    let ctx = LintContext::new(&bms);
+    assert!(std::rc::Rc::ptr_eq(
+        &ctx.validity::<KeyLayoutBeat>(),
+        &ctx.validity::<KeyLayoutBeat>()
+    ));
+    assert!(std::rc::Rc::ptr_eq(
+        &ctx.playing::<KeyLayoutBeat>(),
+        &ctx.playing::<KeyLayoutBeat>()
+    ));
+    let config = TimingCheckConfig::default();
+    assert!(std::rc::Rc::ptr_eq(
+        &ctx.timing::<KeyLayoutBeat>(&config),
+        &ctx.timing::<KeyLayoutBeat>(&config)
+    ));
+    // Another layout has its own results.
+    assert_eq!(
+        *ctx.validity::<KeyLayoutPms>(),
+        bms.check_validity::<KeyLayoutPms>()
+    );
+}
@@ -0,0 +1,173 @@
+use bms_rs::bms::{
+    long_note::{LongNoteCheckOutput, LongNoteIssue, LongNoteProcessor},
+    prelude::*,
+};
+use num::BigUint;
+
+fn check(source: &str) -> LongNoteCheckOutput {
+    parse_bms_with_extension::<KeyLayoutBeat, _, _, _, _>(
+        source,
+        default_config().override_token_processor(LongNoteProcessor::<KeyLayoutBeat>::new()),
+    )
+    .extensions
+    .expect("must be parsed")
+}
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("must be valid time")
+}
+
+fn located<'a>(source: &'a str, checked: &LongNoteCheckOutput) -> Vec<(LongNoteIssue, &'a str)> {
+    checked
+        .issues
+        .iter()
+        .map(|issue| (issue.content().clone(), &source[issue.range().clone()]))
+        .collect()
+}
+
+#[test]
+fn test_paired_long_notes() {
+    let rdm = "\
+#LNOBJ ZZ
+#00151:01000100
+#00161:0101
+#00112:01000100
+#00212:ZZ
+";
+    assert!(check(rdm).is_valid());
+
+    let mgq = "\
+#LNTYPE 2
+#00151:01010000
+#00152:00000101
+#00252:00
+";
+    assert!(check(mgq).is_valid());
+}
+
+#[test]
+fn test_odd_long_notes() {
+    let source = "\
+#00151:01000100
+#00251:01
+#00161:01
+";
+    let checked = check(source);
+    assert_eq!(
+        located(source, &checked),
+        [
+            (
+                LongNoteIssue::Unclosed {
+                    side: PlayerSide::Player2,
+                    key: Key::Key(1),
+                    time: time(1, 0, 1),
+                },
+                "#00161:01"
+            ),
+            (
+                LongNoteIssue::Unclosed {
+                    side: PlayerSide::Player1,
+                    key: Key::Key(1),
+                    time: time(2, 0, 1),
+                },
+                "#00251:01"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_ln_obj_without_start() {
+    let source = "\
+#LNOBJ ZZ
+#00111:ZZ000100
+#00211:ZZZZ
+";
+    let checked = check(source);
+    assert_eq!(
+        located(source, &checked),
+        [
+            (
+                LongNoteIssue::EndWithoutStart {
+                    side: PlayerSide::Player1,
+                    key: Key::Key(1),
+                    time: time(1, 0, 1),
+                },
+                "#00111:ZZ000100"
+            ),
+            (
+                LongNoteIssue::EndWithoutStart {
+                    side: PlayerSide::Player1,
+                    key: Key::Key(1),
+                    time: time(2, 1, 2),
+                },
+                "#00211:ZZZZ"
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_overlapping_long_notes() {
+    let source = "\
+#LNOBJ ZZ
+#00151:01000100
+#00111:000100ZZ
+";
+    let checked = check(source);
+    assert_eq!(
+        located(source, &checked),
+        [(
+            LongNoteIssue::Overlap {
+                side: PlayerSide::Player1,
+                key: Key::Key(1),
+                time: time(1, 1, 4),
+                other_start: time(1, 0, 1),
+                other_end: time(1, 1, 2),
+            },
+            "#00111:000100ZZ"
+        )]
+    );
+}
+
+#[test]
+fn test_unclosed_mgq_run() {
+    let source = "\
+#LNTYPE 2
+#00151:01010000
+#00251:00000101
+";
+    let checked = check(source);
+    assert_eq!(
+        located(source, &checked),
+        [(
+            LongNoteIssue::Unclosed {
+                side: PlayerSide::Player1,
+                key: Key::Key(1),
+                time: time(2, 1, 2),
+            },
+            "#00251:00000101"
+        )]
+    );
+
+    // The objects of `#LNTYPE 2` pair up in order without it.
+    assert!(check(&source.replace("#LNTYPE 2\n", "")).is_valid());
+}
+
+#[test]
+fn test_inactive_branch_is_not_checked() {
+    let source = "\
+#00151:0101
+#RANDOM 2
+#IF 2
+#00251:01
+#ENDIF
+#ENDRANDOM
+";
+    let output = parse_bms_with_extension::<KeyLayoutBeat, _, _, _, _>(
+        source,
+        default_config_with_rng(RngMock([BigUint::from(1u64)]))
+            .override_token_processor(LongNoteProcessor::<KeyLayoutBeat>::new()),
+    );
+    assert!(output.extensions.expect("must be parsed").is_valid());
+}
@@ -1,20 +1,37 @@
 //! Tests for `bms_rs::bms`.
 
 mod base_62;
+mod branch_probability;
 mod comment;
 mod control_flow_model;
+mod cst;
 mod cursor_with_edges;
 mod diagnostics_test;
+mod extension_processor;
 mod extra_channel;
 mod files;
+mod fix;
+mod format;
+mod header_only;
+mod incremental;
+mod key_mode;
+mod lint;
+mod long_note;
 mod nested_random;
 mod nested_switch;
 mod parse_extended_tokens;
 mod playing_conditions;
 mod prelude_test;
 mod prompt_handlers;
+mod random_outcomes;
+mod rng_compat;
+mod source_map;
+mod stream_lex;
+mod timing;
+mod total;
 mod unparse_merge;
 mod unparse_roundtrip;
+mod unused_definitions;
 
 use bms_rs::bms::prelude::*;
 use pretty_assertions::assert_eq;
@@ -222,7 +222,7 @@ fn test_always_warn_and_use_older() {
     assert!(
         parse_warnings
             .iter()
-            .any(|w: &_| matches!(w.content(), ParseWarning::DuplicatingDef(_)))
+            .any(|w: &_| matches!(w.content(), ParseWarning::DuplicatingDef { .. }))
     );
 
     // Check that older values are used for all scope_defines conflicts
@@ -311,7 +311,7 @@ fn test_always_warn_and_use_newer() {
     assert!(
         parse_warnings
             .iter()
-            .any(|w: &_| matches!(w.content(), ParseWarning::DuplicatingDef(_)))
+            .any(|w: &_| matches!(w.content(), ParseWarning::DuplicatingDef { .. }))
     );
 
     // Check that newer values are used for all scope_defines conflicts
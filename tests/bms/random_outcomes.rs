@@ -0,0 +1,190 @@
+use bms_rs::bms::prelude::*;
+use num::BigUint;
+
+fn enumerate(source: &str, limit: usize) -> RandomOutcomes {
+    let LexOutput {
+        tokens,
+        lex_warnings,
+    } = TokenStream::parse_lex(source);
+    assert_eq!(lex_warnings, vec![]);
+    Bms::enumerate_outcomes::<KeyLayoutBeat, _>(&tokens, &AlwaysUseNewer, limit)
+        .expect("must be parsed")
+}
+
+fn values(values: &[u64]) -> Vec<BigUint> {
+    values.iter().copied().map(BigUint::from).collect()
+}
+
+fn wav_ids(bms: &Bms) -> Vec<String> {
+    let mut ids: Vec<String> = bms
+        .notes()
+        .all_notes()
+        .map(|note| note.wav_id.to_string())
+        .collect();
+    ids.sort();
+    ids
+}
+
+#[test]
+fn test_nested_random_outcomes() {
+    let source = "\
+#00111:11
+#RANDOM 2
+#IF 1
+#00112:22
+#RANDOM 3
+#IF 1
+#00113:33
+#ENDIF
+#IF 2
+#00113:44
+#ENDIF
+#ENDRANDOM
+#ENDIF
+#IF 2
+#00112:55
+#ENDIF
+#ENDRANDOM
+";
+    let RandomOutcomes {
+        outcomes,
+        truncated,
+    } = enumerate(source, 100);
+    assert!(!truncated);
+
+    let summary: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| (wav_ids(&outcome.bms), outcome.choices.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (
+                vec!["11".into(), "22".into(), "33".into()],
+                vec![values(&[1, 1])]
+            ),
+            (
+                vec!["11".into(), "22".into(), "44".into()],
+                vec![values(&[1, 2])]
+            ),
+            // `#RANDOM 3` generating 3 activates no branch.
+            (vec!["11".into(), "22".into()], vec![values(&[1, 3])]),
+            // The inner `#RANDOM` is not activated, so its value is fixed.
+            (vec!["11".into(), "55".into()], vec![values(&[2, 1])]),
+        ]
+    );
+
+    for outcome in &outcomes {
+        let mut rng_values = outcome
+            .choices
+            .first()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter();
+        let replayed = parse_bms::<KeyLayoutBeat, _, _, _>(
+            source,
+            default_config_with_rng(ReplayRng(&mut rng_values)),
+        );
+        assert_eq!(replayed.bms.as_ref().ok(), Some(&outcome.bms));
+    }
+}
+
+struct ReplayRng<'a, I>(&'a mut I);
+
+impl<I: Iterator<Item = BigUint>> Rng for ReplayRng<'_, I> {
+    fn generate(&mut self, range: std::ops::RangeInclusive<BigUint>) -> BigUint {
+        self.0.next().unwrap_or_else(|| range.start().clone())
+    }
+}
+
+#[test]
+fn test_switch_and_set_random_outcomes() {
+    let source = "\
+#SETRANDOM 2
+#IF 1
+#00111:11
+#ENDIF
+#IF 2
+#00111:22
+#ENDIF
+#ENDRANDOM
+#SWITCH 3
+#CASE 1
+#00112:33
+#SKIP
+#DEF
+#00112:44
+#SKIP
+#ENDSW
+";
+    let RandomOutcomes {
+        outcomes,
+        truncated,
+    } = enumerate(source, 100);
+    assert!(!truncated);
+    let summary: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| (wav_ids(&outcome.bms), outcome.choices.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            (vec!["22".into(), "33".into()], vec![values(&[1])]),
+            (
+                vec!["22".into(), "44".into()],
+                vec![values(&[2]), values(&[3])]
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_outcomes_are_deduplicated_and_capped() {
+    let source = "\
+#00111:11
+#RANDOM 4
+#IF 1
+#00112:22
+#ENDIF
+#ENDRANDOM
+#RANDOM 4
+#ENDRANDOM
+";
+    let RandomOutcomes {
+        outcomes,
+        truncated,
+    } = enumerate(source, 100);
+    assert!(!truncated);
+    assert_eq!(outcomes.len(), 2);
+    let choice_counts: Vec<_> = outcomes
+        .iter()
+        .map(|outcome| outcome.choices.len())
+        .collect();
+    assert_eq!(choice_counts, [4, 12]);
+
+    let capped = enumerate(source, 5);
+    assert!(capped.truncated);
+    assert_eq!(capped.outcomes.len(), 2);
+    assert_eq!(
+        capped
+            .outcomes
+            .iter()
+            .map(|outcome| outcome.choices.len())
+            .sum::<usize>(),
+        5
+    );
+
+    // Every variant can be checked.
+    for outcome in &outcomes {
+        let PlayingCheckOutput { playing_errors, .. } =
+            outcome.bms.check_playing::<KeyLayoutBeat>();
+        assert!(playing_errors.contains(&PlayingError::BpmUndefined));
+        assert!(
+            outcome
+                .bms
+                .check_validity::<KeyLayoutBeat>()
+                .invalid
+                .is_empty()
+        );
+    }
+}
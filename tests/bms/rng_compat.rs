@@ -0,0 +1,118 @@
+//! The vectors come from the published reference of the generator, not from this crate: the outputs of `new java.util.Random(0)` and `new java.util.Random(42)` on any Java SE, whose algorithm is fixed by the API specification of `java.util.Random`.
+//!
+//! The `#RANDOM` values are derived from them by the mapping of beatoraja documented on [`BeatorajaRng`].
+
+use bms_rs::bms::{prelude::*, rng::JavaRandom};
+use num::BigUint;
+
+/// The maximums of the `#RANDOM` commands generated in order by the vectors below.
+const MAXES: [u64; 5] = [2, 3, 4, 5, 10];
+
+/// The first outputs of `new java.util.Random(0).nextDouble()`.
+const JAVA_RANDOM_0_DOUBLES: [f64; 5] = [
+    0.730_967_787_376_657,
+    0.240_536_415_671_485_87,
+    0.637_417_425_350_108_3,
+    0.550_437_005_117_633_9,
+    0.597_545_277_797_201_8,
+];
+
+/// Generates a value for each of [`MAXES`] in order.
+fn sequence(mut rng: impl Rng) -> Vec<u64> {
+    MAXES
+        .iter()
+        .map(|&max| {
+            let value = rng.generate(BigUint::from(1u64)..=BigUint::from(max));
+            u64::try_from(value).expect("value must fit in u64")
+        })
+        .collect()
+}
+
+#[test]
+fn test_java_random_reference_outputs() {
+    let mut rng = JavaRandom::new(0);
+    for expected in JAVA_RANDOM_0_DOUBLES {
+        assert!((rng.next_double() - expected).abs() < f64::EPSILON);
+    }
+    assert_eq!(JavaRandom::new(0).next_int(), -1_155_484_576);
+    assert_eq!(JavaRandom::new(42).next_int(), -1_170_105_035);
+}
+
+#[test]
+fn test_beatoraja_rng_vectors() {
+    // `(int) (nextDouble() * n) + 1` over `JAVA_RANDOM_0_DOUBLES`.
+    assert_eq!(sequence(BeatorajaRng::new(0)), [2, 1, 3, 3, 6]);
+}
+
+fn wav_ids(source: &str, rng: Box<dyn Rng>) -> Vec<String> {
+    let BmsOutput { bms, .. } =
+        parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config_with_rng(rng));
+    bms.expect("must be parsed")
+        .notes()
+        .all_notes()
+        .map(|note| note.wav_id.to_string())
+        .collect()
+}
+
+#[test]
+fn test_emulated_branches() {
+    let source = "\
+#RANDOM 2
+#IF 1
+#00111:11
+#ENDIF
+#IF 2
+#00111:22
+#ENDIF
+#ENDRANDOM
+#RANDOM 3
+#IF 1
+#00112:33
+#ENDIF
+#IF 2
+#00112:44
+#ENDIF
+#IF 3
+#00112:55
+#ENDIF
+#ENDRANDOM
+";
+    // beatoraja generates 2 and 1.
+    assert_eq!(
+        wav_ids(source, Box::new(BeatorajaRng::new(0))),
+        ["22", "33"]
+    );
+}
+
+#[test]
+fn test_emulated_nested_and_inactive_branches() {
+    // The `#RANDOM` in the inactive scope also generates a value, so the following ones generate the next values of the sequences.
+    let source = "\
+#RANDOM 2
+#IF 1
+#00111:11
+#RANDOM 3
+#IF 1
+#00112:22
+#ENDIF
+#ENDRANDOM
+#ENDIF
+#IF 2
+#00111:33
+#ENDIF
+#ENDRANDOM
+#RANDOM 4
+#IF 1
+#00113:44
+#ENDIF
+#IF 3
+#00113:55
+#ENDIF
+#ENDRANDOM
+";
+    // beatoraja generates 2, 1 and 3, so the nested `#RANDOM 3` is inactive but generates 1.
+    assert_eq!(
+        wav_ids(source, Box::new(BeatorajaRng::new(0))),
+        ["33", "55"]
+    );
+}
@@ -0,0 +1,155 @@
+use std::ops::Range;
+
+use bms_rs::bms::{
+    prelude::*,
+    source_map::{SourceMap, SourceMapProcessor},
+};
+
+fn parse_with_source_map(source: &str) -> (Bms, SourceMap) {
+    let ExtendedBmsOutput {
+        bms, extensions, ..
+    } = parse_bms_with_extension::<KeyLayoutBeat, _, _, _, _>(
+        source,
+        default_config().override_token_processor(SourceMapProcessor),
+    );
+    (
+        bms.expect("must be parsed"),
+        extensions.expect("source map must be recorded"),
+    )
+}
+
+/// Returns the lines where the ranges start.
+fn texts<'a>(source: &'a str, ranges: &[Range<usize>]) -> Vec<&'a str> {
+    ranges
+        .iter()
+        .filter_map(|range| source.get(range.start..)?.lines().next())
+        .collect()
+}
+
+fn obj_id(id: &str) -> ObjId {
+    ObjId::try_from(id, false).expect("object id must be valid")
+}
+
+#[test]
+fn test_source_map_entries() {
+    let source = "\
+#TITLE Song
+#title Again
+#WAV1A kick.wav
+#wav1a snare.wav
+#BPM 150
+#BPM01 200
+#00111:001A
+#00111:1A00
+#00202:0.5
+#00203:0000
+";
+    let (bms, source_map) = parse_with_source_map(source);
+
+    assert_eq!(
+        texts(source, source_map.header("Title")),
+        ["#TITLE Song", "#title Again"]
+    );
+    assert_eq!(texts(source, source_map.header("BPM")), ["#BPM 150"]);
+    assert_eq!(
+        texts(source, source_map.definition("wav", obj_id("1A"))),
+        ["#WAV1A kick.wav", "#wav1a snare.wav"]
+    );
+    assert_eq!(
+        texts(source, source_map.definition("BPM", obj_id("01"))),
+        ["#BPM01 200"]
+    );
+    assert!(source_map.definition("WAV", obj_id("1B")).is_empty());
+
+    // Every note in the model can be traced back to its message line.
+    for note in bms.notes().all_notes() {
+        assert!(!source_map.object(note.offset, note.channel_id).is_empty());
+    }
+    let channel: NoteChannelId = "11".parse().expect("channel id must be valid");
+    let half = ObjTime::new(1, 1, 2).expect("denominator must be non-zero");
+    assert_eq!(
+        texts(source, source_map.object(half, channel)),
+        ["#00111:001A"]
+    );
+    let start = ObjTime::new(1, 0, 1).expect("denominator must be non-zero");
+    assert_eq!(
+        texts(source, source_map.object(start, channel)),
+        ["#00111:1A00"]
+    );
+
+    let section_len: NoteChannelId = "02".parse().expect("channel id must be valid");
+    let track_start = ObjTime::new(2, 0, 1).expect("denominator must be non-zero");
+    assert_eq!(
+        texts(source, source_map.object(track_start, section_len)),
+        ["#00202:0.5"]
+    );
+    let bpm_channel: NoteChannelId = "03".parse().expect("channel id must be valid");
+    assert!(source_map.object(track_start, bpm_channel).is_empty());
+}
+
+#[test]
+fn test_source_map_follows_activated_branch() {
+    let source = "\
+#SETRANDOM 2
+#IF 1
+#WAV01 one.wav
+#ENDIF
+#IF 2
+#WAV01 two.wav
+#ENDIF
+#ENDRANDOM
+";
+    let (_, source_map) = parse_with_source_map(source);
+    assert_eq!(
+        texts(source, source_map.definition("WAV", obj_id("01"))),
+        ["#WAV01 two.wav"]
+    );
+}
+
+#[test]
+fn test_source_map_case_sensitive_ids() {
+    let source = "#BASE 62\n#WAVaa lower.wav\n#WAVAA upper.wav\n";
+    let (_, source_map) = parse_with_source_map(source);
+    let lower = ObjId::try_from("aa", true).expect("object id must be valid");
+    assert_eq!(
+        texts(source, source_map.definition("WAV", lower)),
+        ["#WAVaa lower.wav"]
+    );
+}
+
+#[test]
+fn test_locate_playing_diagnostics() {
+    let source = "\
+#TITLE Song
+#STOP01 abc
+#BPM01 150
+#00108:01
+#00211:01
+";
+    let (bms, source_map) = parse_with_source_map(source);
+    let PlayingCheckOutput {
+        playing_warnings, ..
+    } = bms.check_playing::<KeyLayoutBeat>();
+
+    let located: Vec<_> = playing_warnings
+        .into_iter()
+        .map(|warning| source_map.locate_playing_warning(warning))
+        .collect();
+    let start_bpm = located
+        .iter()
+        .find(|warning| *warning.content() == PlayingWarning::StartBpmUndefined)
+        .expect("start bpm must be undefined");
+    assert_eq!(texts(source, &[start_bpm.range().clone()]), ["#00108:01"]);
+    let total = located
+        .iter()
+        .find(|warning| *warning.content() == PlayingWarning::TotalUndefined)
+        .expect("total must be undefined");
+    assert_eq!(texts(source, &[total.range().clone()]), ["#TITLE Song"]);
+
+    let error = source_map.locate_playing_error(PlayingError::InvalidStop {
+        obj_id: obj_id("01"),
+        raw: "abc".into(),
+        error: "invalid float literal".into(),
+    });
+    assert_eq!(texts(source, &[error.range().clone()]), ["#STOP01 abc"]);
+}
@@ -0,0 +1,162 @@
+use std::{
+    borrow::Cow,
+    io::{BufReader, Read},
+};
+
+use bms_rs::bms::{
+    lex::{
+        LexWarningWithRange,
+        stream::{LexLine, LineDecoder, StreamLexer},
+    },
+    prelude::*,
+};
+
+/// A reader counting the bytes read from the inner reader.
+struct CountingReader<'a> {
+    inner: &'a [u8],
+    read: usize,
+}
+
+impl Read for CountingReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.read += read;
+        Ok(read)
+    }
+}
+
+/// The decoder of `Shift_JIS`.
+struct ShiftJis;
+
+impl LineDecoder for ShiftJis {
+    fn decode<'b>(&mut self, line: &'b [u8]) -> std::io::Result<Cow<'b, str>> {
+        let (text, had_errors) = encoding_rs::SHIFT_JIS.decode_without_bom_handling(line);
+        if had_errors {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "invalid Shift_JIS",
+            ));
+        }
+        Ok(text)
+    }
+}
+
+fn lex_lines(lines: &[LexLine]) -> (TokenStream<'_>, Vec<LexWarningWithRange>) {
+    let mut warnings = Vec::new();
+    let tokens = lines
+        .iter()
+        .flat_map(|line| {
+            let LexOutput {
+                tokens,
+                lex_warnings,
+            } = line.lex();
+            warnings.extend(lex_warnings);
+            tokens
+        })
+        .collect();
+    (tokens, warnings)
+}
+
+#[test]
+fn test_stream_lex_matches_parse_lex() {
+    let source = include_str!("files/lilith_mx.bms");
+    let LexOutput {
+        tokens,
+        lex_warnings,
+    } = TokenStream::parse_lex(source);
+
+    let mut lexer = StreamLexer::new(BufReader::with_capacity(64, source.as_bytes()));
+    let lines: Vec<_> = lexer
+        .by_ref()
+        .collect::<std::io::Result<_>>()
+        .expect("reading from bytes must succeed");
+    let (streamed, streamed_warnings) = lex_lines(&lines);
+    assert_eq!(streamed, tokens);
+    assert_eq!(streamed_warnings, lex_warnings);
+    assert_eq!(lexer.offset(), source.len());
+
+    let config = || default_config().prompter(AlwaysUseNewer);
+    let expected = parse_bms::<KeyLayoutBeat, _, _, _>(source, config());
+    let parsed = parse_bms_lines::<KeyLayoutBeat, _, _, _>(&lines, config());
+    assert_eq!(parsed, expected);
+}
+
+#[test]
+fn test_stream_lex_ranges_and_warnings() {
+    let source = "#TITLE Song\r\n\n  comment line\n#0A111:01\n#00111:0101\n";
+    let lines: Vec<_> = StreamLexer::new(source.as_bytes())
+        .map(|line| line.expect("reading from bytes must succeed"))
+        .collect();
+    let (tokens, warnings) = lex_lines(&lines);
+    let texts: Vec<_> = tokens
+        .iter()
+        .map(|token| {
+            (
+                token.content().to_string(),
+                source.get(token.range().clone()),
+            )
+        })
+        .collect();
+    assert_eq!(
+        texts,
+        [
+            ("#TITLE Song".to_string(), Some("#TITLE")),
+            ("comment line".to_string(), Some("comment")),
+            ("#00111:0101".to_string(), Some("#00111:0101")),
+        ]
+    );
+    let warnings: Vec<_> = warnings.iter().map(SourceRangeMixin::start).collect();
+    // The warning points to the end of the invalid message.
+    let invalid = source.find("#0A111:01").map(|start| start + 9);
+    assert_eq!(warnings, Vec::from_iter(invalid));
+}
+
+#[test]
+fn test_stream_lex_decodes_shift_jis() {
+    let text = "#TITLE 曲名\n日本語のコメント\n#ARTIST 作曲者\n";
+    let (bytes, _, _) = encoding_rs::SHIFT_JIS.encode(text);
+    let lines: Vec<_> = StreamLexer::with_decoder(bytes.as_ref(), ShiftJis)
+        .collect::<std::io::Result<_>>()
+        .expect("the input must be Shift_JIS");
+    let (tokens, _) = lex_lines(&lines);
+    // The ranges are the ones in the decoded text.
+    let LexOutput {
+        tokens: expected, ..
+    } = TokenStream::parse_lex(text);
+    assert_eq!(tokens, expected);
+
+    let BmsOutput { bms, .. } = parse_bms_lines::<KeyLayoutBeat, _, _, _>(
+        &lines,
+        default_config().prompter(AlwaysUseNewer),
+    );
+    let bms = bms.expect("must be parsed");
+    assert_eq!(bms.music_info.title.as_deref(), Some("曲名"));
+    assert_eq!(bms.music_info.artist.as_deref(), Some("作曲者"));
+
+    // UTF-8 is the default, which rejects the Shift_JIS bytes instead of replacing them.
+    let error = StreamLexer::new(bytes.as_ref())
+        .find_map(Result::err)
+        .expect("the input must not be UTF-8");
+    assert_eq!(error.kind(), std::io::ErrorKind::InvalidData);
+}
+
+#[test]
+fn test_stream_lex_stops_early() {
+    let header = "#TITLE Song\n#ARTIST Someone\n";
+    let source = format!("{header}{}", "#00111:01010101\n".repeat(10_000));
+    let mut reader = CountingReader {
+        inner: source.as_bytes(),
+        read: 0,
+    };
+    let headers: Vec<_> = StreamLexer::new(BufReader::with_capacity(256, &mut reader))
+        .map_while(Result::ok)
+        .take_while(|line| {
+            line.lex()
+                .tokens
+                .iter()
+                .all(|token| matches!(token.content(), Token::Header { .. }))
+        })
+        .collect();
+    assert_eq!(headers.len(), 2);
+    assert!(reader.read <= 256, "read {} bytes", reader.read);
+}
@@ -0,0 +1,177 @@
+use bms_rs::bms::{
+    lint::builtin::StopTooLong,
+    lint::{LintContext, LintRegistry, LintSeverity},
+    parse::timing::{TimingCheckConfig, TimingWarning, TimingWarningKind},
+    prelude::*,
+    source_map::SourceMapProcessor,
+};
+
+fn parse(source: &str) -> Bms {
+    parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("must be parsed")
+}
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("must be valid")
+}
+
+fn kinds(bms: &Bms, config: &TimingCheckConfig) -> Vec<(ObjTime, TimingWarningKind)> {
+    bms.check_timing::<KeyLayoutBeat>(config)
+        .into_iter()
+        .map(|warning| (warning.time, warning.kind))
+        .collect()
+}
+
+#[test]
+fn test_bpm_limits() {
+    let bms = parse("#BPM 5\n#BPM01 2000\n#BPM02 150\n#00108:0102\n#00203:FF\n");
+    assert_eq!(
+        kinds(&bms, &TimingCheckConfig::default()),
+        [
+            (
+                time(0, 0, 1),
+                TimingWarningKind::BpmTooLow {
+                    bpm: 5.0,
+                    channel: None
+                }
+            ),
+            (
+                time(1, 0, 1),
+                TimingWarningKind::BpmTooHigh {
+                    bpm: 2000.0,
+                    channel: Some(Channel::BpmChange)
+                }
+            ),
+        ]
+    );
+
+    let loose = TimingCheckConfig {
+        min_bpm: 1.0,
+        max_bpm: 200.0,
+        ..TimingCheckConfig::default()
+    };
+    assert_eq!(
+        kinds(&bms, &loose),
+        [
+            (
+                time(1, 0, 1),
+                TimingWarningKind::BpmTooHigh {
+                    bpm: 2000.0,
+                    channel: Some(Channel::BpmChange)
+                }
+            ),
+            (
+                time(2, 0, 1),
+                TimingWarningKind::BpmTooHigh {
+                    bpm: 255.0,
+                    channel: Some(Channel::BpmChangeU8)
+                }
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_stop_seconds_follow_bpm() {
+    // 1920 units are 10 measures, which is 20 seconds at BPM 120 and 10 seconds at BPM 240.
+    let bms = parse("#BPM 120\n#BPM01 240\n#STOP01 1920\n#00109:01\n#00208:01\n#00209:01\n");
+    let warnings = bms.check_timing::<KeyLayoutBeat>(&TimingCheckConfig::default());
+    assert_eq!(
+        warnings,
+        [TimingWarning::new(
+            time(1, 0, 1),
+            TimingWarningKind::StopTooLong { seconds: 20.0 }
+        )]
+    );
+    assert_eq!(
+        warnings.first().map(|warning| warning.severity),
+        Some(LintSeverity::Warning)
+    );
+}
+
+#[test]
+fn test_scroll_speed_and_section_len() {
+    let bms = parse(
+        "#BPM 120\n#SCROLL01 -0.5\n#SCROLL02 0\n#SPEED01 0.001\n#001SC:0102\n#002SP:01\n#00302:0.001\n#00402:0\n",
+    );
+    let warnings = bms.check_timing::<KeyLayoutBeat>(&TimingCheckConfig::default());
+    let severities: Vec<_> = warnings
+        .iter()
+        .map(|warning| (warning.kind, warning.severity))
+        .collect();
+    assert_eq!(
+        severities,
+        [
+            (
+                TimingWarningKind::NegativeScroll { factor: -0.5 },
+                LintSeverity::Info
+            ),
+            (TimingWarningKind::ZeroScroll, LintSeverity::Info),
+            (
+                TimingWarningKind::SpeedNearZero { factor: 0.001 },
+                LintSeverity::Warning
+            ),
+            (
+                TimingWarningKind::SectionLenTooShort { length: 0.001 },
+                LintSeverity::Warning
+            ),
+            (
+                TimingWarningKind::SectionLenTooShort { length: 0.0 },
+                LintSeverity::Error
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_note_during_stop() {
+    let bms = parse("#BPM 120\n#STOP01 48\n#WAV01 a.wav\n#00109:0001\n#00111:0101\n#00101:01\n");
+    assert_eq!(
+        kinds(&bms, &TimingCheckConfig::default()),
+        [(
+            time(1, 1, 2),
+            TimingWarningKind::NoteDuringStop {
+                channel_id: "11".parse().expect("must be valid")
+            }
+        )]
+    );
+}
+
+#[test]
+fn test_timing_lints() {
+    let source = "#BPM 120\n#TOTAL 160\n#STOP01 960\n#WAV01 kick.wav\n#00109:01\n#00111:01\n";
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+        &tokens,
+        default_config().override_token_processor(SourceMapProcessor),
+    );
+    let bms = output.bms.expect("must be parsed");
+    let source_map = output.extensions.expect("must be parsed");
+    let ctx = LintContext::new(&bms).with_source_map(&source_map);
+
+    let rules = |registry: LintRegistry| -> Vec<_> {
+        registry
+            .run(&ctx)
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.range))
+            .collect()
+    };
+    assert_eq!(
+        rules(LintRegistry::builtin::<KeyLayoutBeat>()),
+        [("note-during-stop", Some(58..67))]
+    );
+
+    let strict = StopTooLong::new(TimingCheckConfig {
+        max_stop_seconds: 5.0,
+        ..TimingCheckConfig::default()
+    });
+    assert_eq!(
+        rules(LintRegistry::builtin::<KeyLayoutBeat>().with_rule(strict)),
+        [
+            ("stop-too-long", Some(48..57)),
+            ("note-during-stop", Some(58..67)),
+        ]
+    );
+}
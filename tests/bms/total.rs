@@ -0,0 +1,110 @@
+use bms_rs::bms::{
+    lint::builtin::TotalOutOfRange,
+    lint::{LintContext, LintRegistry},
+    parse::total::{TotalCheckConfig, TotalFormula, TotalWarning},
+    prelude::*,
+    source_map::SourceMapProcessor,
+};
+
+fn parse(source: &str) -> Bms {
+    parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("must be parsed")
+}
+
+#[test]
+fn test_formulas() {
+    let beatoraja: Vec<_> = [0, 400, 500, 700, 1000]
+        .into_iter()
+        .map(|notes| TotalFormula::Beatoraja.recommended(notes).round())
+        .collect();
+    assert_eq!(beatoraja, [160.0, 224.0, 256.0, 304.0, 352.0]);
+
+    let iidx: Vec<_> = [0, 500, 1000, 2000]
+        .into_iter()
+        .map(|notes| TotalFormula::Iidx.recommended(notes).round())
+        .collect();
+    assert_eq!(iidx, [0.0, 331.0, 461.0, 574.0]);
+}
+
+#[test]
+fn test_playable_note_count() {
+    // 3 visible notes, 2 long notes, an invisible note and a landmine.
+    let bms = parse("#00111:01010001\n#00152:01010101\n#00131:01\n#001D1:01\n");
+    assert_eq!(bms.playable_note_count::<KeyLayoutBeat>(), 5);
+    let recommended = bms.recommended_total::<KeyLayoutBeat>(TotalFormula::Beatoraja);
+    assert!((recommended - TotalFormula::Beatoraja.recommended(5)).abs() < f64::EPSILON);
+}
+
+#[test]
+fn test_check_total() {
+    let notes = "#00111:01010101\n";
+    let config = TotalCheckConfig::default();
+    let recommended = TotalFormula::Beatoraja.recommended(4);
+
+    assert_eq!(
+        parse(notes).check_total::<KeyLayoutBeat>(&config),
+        None,
+        "undefined #TOTAL is reported by check_playing"
+    );
+    assert_eq!(
+        parse(&format!("#TOTAL 160\n{notes}")).check_total::<KeyLayoutBeat>(&config),
+        None
+    );
+    assert_eq!(
+        parse(&format!("#TOTAL 50\n{notes}")).check_total::<KeyLayoutBeat>(&config),
+        Some(TotalWarning::TooLow {
+            total: 50.0,
+            recommended
+        })
+    );
+    assert_eq!(
+        parse(&format!("#TOTAL 400\n{notes}")).check_total::<KeyLayoutBeat>(&config),
+        Some(TotalWarning::TooHigh {
+            total: 400.0,
+            recommended
+        })
+    );
+
+    let strict = TotalCheckConfig {
+        min_ratio: 0.9,
+        max_ratio: 1.1,
+        ..config
+    };
+    assert!(matches!(
+        parse(&format!("#TOTAL 200\n{notes}")).check_total::<KeyLayoutBeat>(&strict),
+        Some(TotalWarning::TooHigh { .. })
+    ));
+}
+
+#[test]
+fn test_total_out_of_range_lint() {
+    let source = "#BPM 120\n#TOTAL 200\n#WAV01 kick.wav\n#00111:01010101\n";
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(source);
+    let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+        &tokens,
+        default_config().override_token_processor(SourceMapProcessor),
+    );
+    let bms = output.bms.expect("must be parsed");
+    let source_map = output.extensions.expect("must be parsed");
+    let ctx = LintContext::new(&bms).with_source_map(&source_map);
+
+    let rules = |registry: LintRegistry| -> Vec<_> {
+        registry
+            .run(&ctx)
+            .diagnostics
+            .into_iter()
+            .map(|diagnostic| (diagnostic.rule, diagnostic.range))
+            .collect()
+    };
+    assert_eq!(rules(LintRegistry::builtin::<KeyLayoutBeat>()), []);
+
+    let strict = TotalOutOfRange::<KeyLayoutBeat>::new(TotalCheckConfig {
+        formula: TotalFormula::Iidx,
+        ..TotalCheckConfig::default()
+    });
+    assert_eq!(
+        rules(LintRegistry::builtin::<KeyLayoutBeat>().with_rule(strict)),
+        [("total-out-of-range", Some(9..15))]
+    );
+}
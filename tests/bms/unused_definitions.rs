@@ -0,0 +1,158 @@
+use bms_rs::bms::{
+    lint::{LintContext, LintRegistry},
+    prelude::*,
+    source_map::SourceMapProcessor,
+};
+use num::BigUint;
+
+const SOURCE: &str = "\
+#BPM 120
+#WAV01 kick.wav
+#WAV02 unused.wav
+#BMP00 poor.bmp
+#BMP01 back.bmp
+#BMP02 unused.bmp
+#BPM01 180
+#BPM02 240
+#STOP01 96
+#STOP02 48
+#SCROLL01 0.5
+#SCROLL02 2
+#SPEED01 1.5
+#SPEED02 3
+#TEXT01 hello
+#TEXT02 unused
+#EXRANK01 50
+#EXRANK02 100
+#ARGB01 255,0,0,0
+#ARGB02 255,255,255,255
+#00111:01
+#00104:01
+#00108:01
+#00109:01
+#001SC:01
+#001SP:01
+#00199:01
+#001A0:01
+#001A1:01
+";
+
+#[test]
+fn test_unused_definitions() {
+    let bms = parse_bms::<KeyLayoutBeat, _, _, _>(SOURCE, default_config())
+        .bms
+        .expect("must be parsed");
+    let id = ObjId::try_from("02", false).expect("must be valid id");
+    assert_eq!(
+        bms.check_validity::<KeyLayoutBeat>().unused,
+        [
+            ValidityUnused::Wav(id),
+            ValidityUnused::Bmp(id),
+            ValidityUnused::BpmChangeDef(id),
+            ValidityUnused::StopDef(id),
+            ValidityUnused::ScrollDef(id),
+            ValidityUnused::SpeedDef(id),
+            ValidityUnused::TextDef(id),
+            ValidityUnused::ExRankDef(id),
+            ValidityUnused::ArgbDef(id),
+        ]
+    );
+}
+
+#[test]
+fn test_bga_definition_uses_bmp() {
+    let source = "\
+#BMP01 source.bmp
+#BMP02 unused.bmp
+#@BGA03 01 0 0 64 64 0 0
+#00111:00
+";
+    let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("must be parsed");
+    let id = ObjId::try_from("02", false).expect("must be valid id");
+    assert_eq!(
+        bms.check_validity::<KeyLayoutBeat>().unused,
+        [ValidityUnused::Bmp(id)]
+    );
+}
+
+#[test]
+fn test_unused_definition_lint() {
+    let LexOutput { tokens, .. } = TokenStream::parse_lex(SOURCE);
+    let output = Bms::from_token_stream_with_extension::<'_, KeyLayoutBeat, _, _, _, _>(
+        &tokens,
+        default_config().override_token_processor(SourceMapProcessor),
+    );
+    let bms = output.bms.expect("must be parsed");
+    let source_map = output.extensions.expect("must be parsed");
+    let ctx = LintContext::new(&bms)
+        .with_tokens(&tokens)
+        .with_source_map(&source_map);
+    let located: Vec<_> = LintRegistry::builtin::<KeyLayoutBeat>()
+        .run(&ctx)
+        .diagnostics
+        .into_iter()
+        .filter(|diagnostic| diagnostic.rule == "unused-definition")
+        .map(|diagnostic| &SOURCE[diagnostic.range.expect("must be located")])
+        .collect();
+    assert_eq!(
+        located,
+        [
+            "#WAV02",
+            "#BMP02",
+            "#BPM02",
+            "#STOP02",
+            "#SCROLL02",
+            "#SPEED02",
+            "#TEXT02",
+            "#EXRANK02",
+            "#ARGB02"
+        ]
+    );
+}
+
+#[test]
+fn test_definitions_used_in_random_branches() {
+    let source = "\
+#WAV01 one.wav
+#WAV02 two.wav
+#BPM01 180
+#RANDOM 2
+#IF 1
+#00111:01
+#ENDIF
+#IF 2
+#00111:02
+#00108:01
+#ENDIF
+#ENDRANDOM
+";
+    for value in [1u64, 2] {
+        let bms = parse_bms::<KeyLayoutBeat, _, _, _>(
+            source,
+            default_config_with_rng(RngMock([BigUint::from(value)])),
+        )
+        .bms
+        .expect("must be parsed");
+        assert_eq!(
+            bms.check_validity::<KeyLayoutBeat>().unused,
+            [],
+            "activating #IF {value}"
+        );
+    }
+}
+
+#[test]
+fn test_ids_after_undefined_one_are_used() {
+    let source = "\
+#BPM01 180
+#STOP01 48
+#00108:0201
+#00109:0201
+";
+    let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("must be parsed");
+    assert_eq!(bms.check_validity::<KeyLayoutBeat>().unused, []);
+}
@@ -2,6 +2,10 @@
 
 use std::num::NonZeroU64;
 
+use bms_rs::bms::{
+    key_mode::{KeyMode, KeyModeConfidence, KeyModeHints},
+    model::Bms,
+};
 use bms_rs::bmson::{
     BgaEvent, BgaHeader, BgaId, BmsonParseError, BpmEvent, parse_bmson, pulse::PulseNumber,
 };
@@ -59,6 +63,12 @@ fn test_bmson100_bemusic_story_48key() {
             }
         ]
     );
+    // Key mode
+    let detection = Bms::from_bmson(bmson.clone())
+        .bms
+        .detect_key_mode(&KeyModeHints::default().with_mode_hint(&bmson.info.mode_hint));
+    assert_eq!(detection.mode, KeyMode::Keyboard48K);
+    assert_eq!(detection.confidence, KeyModeConfidence::Low);
 }
 
 #[test]
@@ -475,6 +485,8 @@ fn test_parse_bmson_totally_broken_json() {
             BmsonParseError::JsonRecovered { .. } => println!("saw JsonRecovered"),
             BmsonParseError::JsonWarning { .. } => println!("saw JsonWarning"),
             BmsonParseError::Deserialize { .. } => println!("saw Deserialize"),
+            BmsonParseError::LegacyUpgrade { .. } => println!("saw LegacyUpgrade"),
+            _ => println!("saw another error"),
         }
     }
     let has_fatal = output
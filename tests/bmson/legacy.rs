@@ -0,0 +1,132 @@
+#![cfg(feature = "bmson")]
+
+use std::num::NonZeroU8;
+
+use bms_rs::bmson::{
+    BgaEvent, BgaHeader, BgaId, BmsonParseError, BpmEvent, StopEvent,
+    legacy::{LEGACY_VERSION, LegacyUpgradeWarning},
+    parse_bmson,
+    pulse::PulseNumber,
+};
+use strict_num_extended::{FinF64, PositiveF64};
+
+const LEGACY_JSON: &str = r#"{
+    "info": {
+        "title": "Legacy Song",
+        "artist": "Old Artist",
+        "genre": "Retro",
+        "judgeRank": 100,
+        "total": 200,
+        "initBPM": 150,
+        "level": 7,
+        "mood": "happy"
+    },
+    "lines": [{ "y": 0 }, { "y": 960 }],
+    "bpmNotes": [{ "y": 960, "v": 180 }],
+    "stopNotes": [{ "y": 1440, "v": 240 }],
+    "soundChannel": [
+        {
+            "name": "kick.wav",
+            "notes": [
+                { "x": 1, "y": 960, "l": 0, "c": false },
+                { "x": 0, "y": 1200, "l": 0, "c": true }
+            ]
+        }
+    ],
+    "bga": {
+        "bgaHeader": [{ "ID": 1, "name": "bg.png" }],
+        "bgaNotes": [{ "y": 0, "id": 1 }],
+        "layerNotes": [],
+        "poorNotes": []
+    }
+}"#;
+
+#[test]
+fn test_legacy_upgrade() {
+    let output = parse_bmson(LEGACY_JSON);
+    let bmson = output
+        .bmson
+        .unwrap_or_else(|| panic!("Failed to parse BMSON: {:?}", output.errors));
+
+    assert_eq!(bmson.version, LEGACY_VERSION);
+    assert_eq!(bmson.info.title, "Legacy Song");
+    assert_eq!(bmson.info.init_bpm, PositiveF64::new(150.0).unwrap());
+    assert_eq!(bmson.info.judge_rank, FinF64::new(100.0).unwrap());
+    assert_eq!(bmson.info.total, FinF64::new(200.0).unwrap());
+    assert_eq!(bmson.info.level, 7);
+    assert_eq!(bmson.info.resolution.get(), 240);
+    assert_eq!(
+        bmson.bpm_events,
+        vec![BpmEvent {
+            y: PulseNumber(960),
+            bpm: PositiveF64::new(180.0).unwrap(),
+        }]
+    );
+    assert_eq!(
+        bmson.stop_events,
+        vec![StopEvent {
+            y: PulseNumber(1440),
+            duration: 240,
+        }]
+    );
+    let [channel] = bmson.sound_channels.as_slice() else {
+        panic!("expected one sound channel: {:?}", bmson.sound_channels);
+    };
+    assert_eq!(channel.name, "kick.wav");
+    let lanes: Vec<_> = channel.notes.iter().map(|note| note.x).collect();
+    assert_eq!(lanes, vec![NonZeroU8::new(1), None]);
+    assert_eq!(
+        bmson.bga.bga_header,
+        vec![BgaHeader {
+            id: BgaId(1),
+            name: "bg.png".into(),
+        }]
+    );
+    assert_eq!(
+        bmson.bga.bga_events,
+        vec![BgaEvent {
+            y: PulseNumber(0),
+            id: BgaId(1),
+        }]
+    );
+
+    let warnings: Vec<_> = output
+        .errors
+        .iter()
+        .map(|e| match e {
+            BmsonParseError::LegacyUpgrade { warning } => warning.clone(),
+            other => panic!("unexpected error: {other:?}"),
+        })
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![LegacyUpgradeWarning::UnmappedField("info.mood".to_string())]
+    );
+}
+
+#[test]
+fn test_legacy_with_version() {
+    let json = r#"{
+        "version": "0.21",
+        "info": { "title": "T", "artist": "A", "genre": "G", "initBPM": 120, "level": 1 },
+        "soundChannel": []
+    }"#;
+    let output = parse_bmson(json);
+    let bmson = output
+        .bmson
+        .unwrap_or_else(|| panic!("Failed to parse BMSON: {:?}", output.errors));
+    assert_eq!(bmson.version, "0.21");
+    assert!(output.errors.is_empty());
+}
+
+#[test]
+fn test_current_format_is_not_upgraded() {
+    let output = parse_bmson(include_str!("files/lostokens.bmson"));
+    assert!(output.bmson.is_some());
+    assert!(
+        !output
+            .errors
+            .iter()
+            .any(|e| matches!(e, BmsonParseError::LegacyUpgrade { .. }))
+    );
+}
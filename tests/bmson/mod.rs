@@ -2,3 +2,5 @@
 
 mod convert_to_bms;
 mod files;
+mod legacy;
+mod write;
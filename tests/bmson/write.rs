@@ -0,0 +1,123 @@
+#![cfg(feature = "bmson")]
+
+use bms_rs::bmson::{Bmson, parse_bmson};
+
+fn parse(data: &str) -> Bmson<'_> {
+    let output = parse_bmson(data);
+    output
+        .bmson
+        .unwrap_or_else(|| panic!("Failed to parse BMSON: {:?}", output.errors))
+}
+
+fn assert_roundtrip(data: &str) {
+    let bmson = parse(data);
+
+    let pretty = bmson.to_string_pretty().expect("serialize pretty");
+    assert_eq!(parse(&pretty), bmson);
+
+    let compact = bmson.to_string().expect("serialize compact");
+    assert_eq!(parse(&compact), bmson);
+
+    let mut buf = Vec::new();
+    bmson.to_writer(&mut buf).expect("write compact");
+    assert_eq!(String::from_utf8(buf).expect("utf-8 output"), compact);
+}
+
+#[test]
+fn test_roundtrip_lostokens() {
+    assert_roundtrip(include_str!("files/lostokens.bmson"));
+}
+
+#[test]
+fn test_roundtrip_bemusic_story_48key() {
+    assert_roundtrip(include_str!("files/bemusicstory_483_48K_ANOTHER.bmson"));
+}
+
+#[test]
+fn test_write_field_order_and_omission() {
+    let json = r#"{
+        "version": "1.0.0",
+        "info": {
+            "title": "Test Song",
+            "artist": "Test Artist",
+            "genre": "Test Genre",
+            "level": 5,
+            "init_bpm": 120.0,
+            "resolution": 240
+        },
+        "sound_channels": [
+            { "name": "a.wav", "notes": [{ "x": 1, "y": 960, "l": 0, "c": false }] }
+        ]
+    }"#;
+    let bmson = parse(json);
+    let compact = bmson.to_string().expect("serialize compact");
+
+    let keys = [
+        "\"version\"",
+        "\"info\"",
+        "\"bpm_events\"",
+        "\"stop_events\"",
+        "\"sound_channels\"",
+        "\"bga\"",
+    ];
+    let positions: Vec<_> = keys
+        .iter()
+        .map(|key| {
+            compact
+                .find(key)
+                .unwrap_or_else(|| panic!("{key} is missing in {compact}"))
+        })
+        .collect();
+    assert!(positions.is_sorted(), "unexpected field order: {compact}");
+
+    assert!(compact.contains(r#""y":960"#));
+    for omitted in [
+        "\"lines\"",
+        "\"scroll_events\"",
+        "\"mine_channels\"",
+        "\"key_channels\"",
+        "\"back_image\"",
+        "\"ln_type\"",
+        "\"up\"",
+    ] {
+        assert!(!compact.contains(omitted), "{omitted} found in {compact}");
+    }
+}
+
+#[test]
+fn test_write_bgm_lane_as_zero() {
+    let json = r#"{
+        "version": "1.0.0",
+        "info": {
+            "title": "Test Song",
+            "artist": "Test Artist",
+            "genre": "Test Genre",
+            "level": 5,
+            "init_bpm": 120.0
+        },
+        "sound_channels": [
+            { "name": "a.wav", "notes": [{ "x": 0, "y": 0, "l": 0, "c": false }, { "x": 0, "y": 240, "l": 0, "c": false }] }
+        ],
+        "mine_channels": [
+            { "name": "b.wav", "notes": [{ "x": 0, "y": 0, "damage": 1.0 }] }
+        ],
+        "key_channels": [
+            { "name": "c.wav", "notes": [{ "x": 0, "y": 0 }] }
+        ]
+    }"#;
+    let compact = parse(json).to_string().expect("serialize compact");
+    assert!(!compact.contains("null"), "null found in {compact}");
+    assert!(
+        compact
+            .contains(r#""notes":[{"y":0,"x":0,"l":0,"c":false},{"y":240,"x":0,"l":0,"c":false}]"#),
+        "unexpected notes in {compact}"
+    );
+    assert!(
+        compact.contains(r#""notes":[{"x":0,"y":0,"damage":1.0}]"#),
+        "unexpected mine in {compact}"
+    );
+    assert!(
+        compact.contains(r#""notes":[{"x":0,"y":0}]"#),
+        "unexpected key in {compact}"
+    );
+}
@@ -0,0 +1,132 @@
+use bms_rs::{
+    bms::prelude::*,
+    chart::process::Process,
+    ksh::{
+        ksh_to_bms::{KshToBmsWarning, SILENT_WAV},
+        parse_ksh,
+    },
+};
+use strict_num_extended::{FinF64, NonNegativeF64, PositiveF64};
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("denominator must be non-zero")
+}
+
+#[test]
+fn test_convert_sample() {
+    let ksh = parse_ksh(include_str!("files/sample.ksh")).ksh;
+    let output = Bms::from_ksh(&ksh);
+    assert_eq!(
+        output.warnings,
+        vec![
+            KshToBmsWarning::LasersDropped(2),
+            KshToBmsWarning::UnsupportedOption("zoom_top".to_string()),
+        ]
+    );
+    assert_eq!(output.playing_errors, vec![]);
+
+    let bms = output.bms;
+    let validity = bms.check_validity::<KeyLayoutBeat>();
+    assert_eq!(validity.missing, vec![]);
+    assert_eq!(validity.invalid, vec![]);
+    assert_eq!(validity.unused, vec![]);
+    assert_eq!(
+        bms.wav
+            .wav_files
+            .get(&ObjId::try_from("02", false).unwrap()),
+        Some(&std::path::PathBuf::from(SILENT_WAV))
+    );
+    assert_eq!(bms.music_info.title.as_deref(), Some("Sample Song"));
+    assert_eq!(bms.music_info.maker.as_deref(), Some("Charter"));
+    assert_eq!(bms.metadata.play_level, Some(12));
+    assert_eq!(bms.metadata.difficulty, Some(2));
+    assert_eq!(
+        bms.bpm.bpm.as_ref().map(|bpm| bpm.value().clone()),
+        Some(Ok(PositiveF64::new(120.0).unwrap()))
+    );
+
+    // Lead-in measure for the music offset 500 ms at 120 BPM.
+    let section_lens: Vec<_> = bms
+        .section_len
+        .section_len_changes
+        .values()
+        .map(|change| (change.track, change.length))
+        .collect();
+    assert_eq!(
+        section_lens,
+        vec![
+            (Track(0), FinF64::new(0.25).unwrap()),
+            (Track(3), FinF64::new(0.75).unwrap()),
+        ]
+    );
+    let music_id = ObjId::try_from("01", false).unwrap();
+    assert_eq!(
+        bms.wav.wav_files.get(&music_id),
+        Some(&std::path::PathBuf::from("music.ogg"))
+    );
+
+    let bpm_changes: Vec<_> = bms
+        .bpm
+        .bpm_changes
+        .values()
+        .map(|change| (change.time, change.bpm))
+        .collect();
+    assert_eq!(
+        bpm_changes,
+        vec![(time(2, 0, 1), PositiveF64::new(240.0).unwrap())]
+    );
+    let stops: Vec<_> = bms
+        .stop
+        .stops
+        .values()
+        .map(|stop| (stop.time, stop.duration))
+        .collect();
+    assert_eq!(
+        stops,
+        vec![(time(3, 0, 1), NonNegativeF64::new(96.0).unwrap())]
+    );
+
+    let mut notes: Vec<_> = bms
+        .notes()
+        .all_notes()
+        .filter_map(|obj| {
+            let layout = KeyLayoutBeat::from_channel_id(obj.channel_id)?;
+            Some((obj.offset, layout.key(), layout.kind()))
+        })
+        .collect();
+    notes.sort_by_key(|(offset, key, _)| (*offset, key.key_number()));
+    assert_eq!(
+        notes,
+        vec![
+            (time(1, 0, 4), Key::Key(1), NoteKind::Visible),
+            (time(1, 1, 4), Key::Key(2), NoteKind::Visible),
+            (time(1, 2, 4), Key::Key(3), NoteKind::Long),
+            (time(1, 2, 4), Key::Key(5), NoteKind::Long),
+            (time(2, 0, 1), Key::Key(3), NoteKind::Long),
+            (time(2, 0, 1), Key::Key(4), NoteKind::Visible),
+            (time(2, 0, 1), Key::Key(5), NoteKind::Long),
+            (time(2, 0, 1), Key::Key(6), NoteKind::Visible),
+        ]
+    );
+}
+
+#[test]
+fn test_process_into_chart() {
+    let ksh = parse_ksh(include_str!("files/sample.ksh")).ksh;
+    let chart = ksh.process().expect("chart must be processed");
+    assert_eq!(chart.init_bpm(), &PositiveF64::new(120.0).unwrap());
+}
+
+#[test]
+fn test_unclosed_long_note() {
+    let source = "t=150\n--\n2000|00|--\n2000|00|--\n--\n";
+    let output = Bms::from_ksh(&parse_ksh(source).ksh);
+    assert_eq!(output.warnings, vec![]);
+    let long_times: Vec<_> = output
+        .bms
+        .notes()
+        .all_notes()
+        .map(|obj| obj.offset)
+        .collect();
+    assert_eq!(long_times, vec![time(0, 0, 1), time(1, 0, 1)]);
+}
@@ -0,0 +1,93 @@
+use bms_rs::ksh::{KshButton, KshLaser, KshWarning, parse_ksh};
+
+#[test]
+fn test_parse_sample() {
+    let source = include_str!("files/sample.ksh");
+    let output = parse_ksh(source);
+    assert_eq!(output.warnings, vec![]);
+
+    let header = &output.ksh.header;
+    assert_eq!(header.title.as_deref(), Some("Sample Song"));
+    assert_eq!(header.artist.as_deref(), Some("Composer"));
+    assert_eq!(header.effect.as_deref(), Some("Charter"));
+    assert_eq!(header.difficulty.as_deref(), Some("challenge"));
+    assert_eq!(header.level, Some(12));
+    assert_eq!(header.bpm.as_deref(), Some("120"));
+    assert_eq!(header.music.as_deref(), Some("music.ogg;music_f.ogg"));
+    assert_eq!(header.offset, Some(500));
+    assert_eq!(header.total, Some(200));
+    assert_eq!(header.version.as_deref(), Some("167"));
+
+    let measures = &output.ksh.measures;
+    assert_eq!(
+        measures
+            .iter()
+            .map(|measure| measure.lines.len())
+            .collect::<Vec<_>>(),
+        vec![4, 2, 3]
+    );
+    let first_line = measures
+        .first()
+        .and_then(|measure| measure.lines.first())
+        .expect("first line must exist");
+    assert_eq!(
+        first_line.bt,
+        [
+            KshButton::Chip,
+            KshButton::Empty,
+            KshButton::Empty,
+            KshButton::Empty
+        ]
+    );
+    let last_line = measures
+        .first()
+        .and_then(|measure| measure.lines.last())
+        .expect("last line must exist");
+    assert_eq!(last_line.fx, [KshButton::Long, KshButton::Empty]);
+    assert_eq!(last_line.lasers, [KshLaser::Position(50), KshLaser::Empty]);
+
+    let options: Vec<_> = measures
+        .iter()
+        .flat_map(|measure| &measure.options)
+        .map(|option| {
+            (
+                option.line_index,
+                option.key.as_str(),
+                option.value.as_str(),
+            )
+        })
+        .collect();
+    assert_eq!(
+        options,
+        vec![
+            (0, "beat", "4/4"),
+            (0, "t", "120"),
+            (0, "t", "240"),
+            (0, "zoom_top", "100"),
+            (0, "beat", "3/4"),
+            (0, "stop", "96"),
+        ]
+    );
+}
+
+#[test]
+fn test_parse_invalid_lines() {
+    let source = "title=Broken\nno header value\n--\n1000|00|--\n10|00\n--\n";
+    let output = parse_ksh(source);
+    let warnings: Vec<_> = output
+        .warnings
+        .iter()
+        .map(|warning| (warning.content().clone(), warning.range().clone()))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (
+                KshWarning::InvalidHeader("no header value".to_string()),
+                13..28
+            ),
+            (KshWarning::InvalidNoteLine("10|00".to_string()), 43..48),
+        ]
+    );
+    assert_eq!(output.ksh.measures.len(), 1);
+}
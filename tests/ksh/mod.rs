@@ -0,0 +1,4 @@
+//! Tests for `bms_rs::ksh`.
+
+mod convert_to_bms;
+mod files;
@@ -3,3 +3,8 @@
 pub mod bms;
 pub mod bmson;
 pub mod chart;
+pub mod ksh;
+pub mod package;
+pub mod quaver;
+pub mod resource;
+pub mod timed;
@@ -0,0 +1,184 @@
+//! Tests for `bms_rs::package`.
+
+use std::{fs, path::PathBuf};
+
+use bms_rs::{
+    bms::{key_mode::KeyMode, prelude::*},
+    package::{ChartFormat, ChartPackage, PackageWarning},
+};
+
+/// A temporary song folder removed on drop.
+struct SongDir(PathBuf);
+
+impl SongDir {
+    fn new(name: &str, files: &[(&str, &str)]) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("bms-rs-package-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).expect("must create dir");
+        for (file, content) in files {
+            fs::write(root.join(file), content).expect("must write file");
+        }
+        Self(root)
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+impl Drop for SongDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+#[test]
+fn test_discover_and_layouts() {
+    let dir = SongDir::new(
+        "discover",
+        &[
+            ("7key.BME", "#PLAYER 1\n#TITLE Song\n#00111:01\n#00119:01\n"),
+            ("9key.pms", "#PLAYER 1\n#TITLE Song\n#00122:01\n"),
+            ("readme.txt", "not a chart"),
+            ("kick.wav", ""),
+        ],
+    );
+    assert_eq!(
+        ChartPackage::discover(&dir.0).expect("must read dir"),
+        [dir.path("7key.BME"), dir.path("9key.pms")]
+    );
+
+    let package = ChartPackage::load(&dir.0).expect("must read dir");
+    assert_eq!(package.errors, []);
+    let layouts: Vec<_> = package
+        .charts
+        .iter()
+        .map(|chart| (chart.format, chart.layout))
+        .collect();
+    assert_eq!(
+        layouts,
+        [
+            (ChartFormat::Bme, KeyLayoutKind::Beat),
+            (ChartFormat::Pms, KeyLayoutKind::Pms)
+        ]
+    );
+    assert_eq!(
+        ChartFormat::Pms.key_layout(Some(PlayerMode::Double)),
+        KeyLayoutKind::Beat
+    );
+    let modes: Vec<_> = package
+        .charts
+        .iter()
+        .map(|chart| (chart.detection.mode, chart.detection.layout))
+        .collect();
+    assert_eq!(
+        modes,
+        [
+            (KeyMode::Beat7K, KeyLayoutKind::Beat),
+            (KeyMode::Pms9K, KeyLayoutKind::Pms)
+        ]
+    );
+}
+
+#[test]
+fn test_groups() {
+    let dir = SongDir::new(
+        "groups",
+        &[
+            ("a.bms", "#TITLE Song\n#ARTIST Alice\n"),
+            ("b.bms", "#TITLE Song \n#ARTIST Alice\n"),
+            ("c.bms", "#TITLE Song\n#ARTIST Bob\n"),
+        ],
+    );
+    let package = ChartPackage::load(&dir.0).expect("must read dir");
+    let groups: Vec<_> = package
+        .groups()
+        .into_iter()
+        .map(|group| (group.title, group.artist, group.charts))
+        .collect();
+    assert_eq!(
+        groups,
+        [
+            (Some("Song".into()), Some("Alice".into()), vec![0, 1]),
+            (Some("Song".into()), Some("Bob".into()), vec![2]),
+        ]
+    );
+}
+
+#[test]
+fn test_check() {
+    let dir = SongDir::new(
+        "check",
+        &[
+            (
+                "normal.bms",
+                "#TITLE Song\n#BPM 150\n#DIFFICULTY 2\n#WAV01 kick.wav\n#WAV02 snare.wav\n",
+            ),
+            (
+                "hyper.bms",
+                "#TITLE Song\n#SUBTITLE [HYPER]\n#BPM 150\n#DIFFICULTY 3\n#WAV01 kick.wav\n",
+            ),
+            (
+                "another.bms",
+                "#TITLE Song\n#SUBTITLE [ANOTHER]\n#BPM 160\n#DIFFICULTY 3\n#WAV01 kick.ogg\n",
+            ),
+            ("kick.wav", ""),
+        ],
+    );
+    let package = ChartPackage::load(&dir.0).expect("must read dir");
+    let title = Some("Song".to_string());
+    assert_eq!(
+        package.check(),
+        [
+            PackageWarning::DifferentBpm {
+                title: title.clone(),
+                bpms: vec![
+                    (dir.path("another.bms"), Some(160.0)),
+                    (dir.path("hyper.bms"), Some(150.0)),
+                    (dir.path("normal.bms"), Some(150.0)),
+                ],
+            },
+            PackageWarning::MismatchedSubtitle {
+                title: title.clone(),
+                subtitles: vec![
+                    (dir.path("another.bms"), Some("[ANOTHER]".into())),
+                    (dir.path("hyper.bms"), Some("[HYPER]".into())),
+                    (dir.path("normal.bms"), None),
+                ],
+            },
+            PackageWarning::DuplicateDifficulty {
+                title,
+                difficulty: 3,
+                charts: vec![dir.path("another.bms"), dir.path("hyper.bms")],
+            },
+            PackageWarning::MissingResource {
+                declared: "snare.wav".into(),
+                charts: vec![dir.path("normal.bms")],
+            },
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "bmson")]
+fn test_load_errors_and_bmson() {
+    let dir = SongDir::new(
+        "bmson",
+        &[
+            (
+                "chart.bmson",
+                r#"{"version":"1.0.0","info":{"title":"Song","artist":"Alice","genre":"","level":1,"init_bpm":150,"resolution":240},"sound_channels":[]}"#,
+            ),
+            ("broken.bmson", "{"),
+        ],
+    );
+    let package = ChartPackage::load(&dir.0).expect("must read dir");
+    assert_eq!(package.errors.len(), 1);
+    let [chart] = package.charts.as_slice() else {
+        panic!("expected one chart, got {:?}", package.charts);
+    };
+    assert_eq!(chart.format, ChartFormat::Bmson);
+    assert_eq!(chart.detection.mode, KeyMode::Beat7K);
+    assert_eq!(chart.bms.music_info.title.as_deref(), Some("Song"));
+}
@@ -0,0 +1,274 @@
+#![cfg(feature = "quaver")]
+
+use bms_rs::{
+    bms::prelude::*,
+    quaver::{
+        Qua, QuaTimeSignature, QuaTimingPoint, bms_to_qua::BmsToQuaWarning, parse_qua,
+        qua_to_bms::QuaToBmsWarning,
+    },
+};
+use strict_num_extended::{FinF64, PositiveF64};
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("denominator must be non-zero")
+}
+
+fn sample() -> Qua {
+    parse_qua(include_str!("files/sample.qua")).expect("sample must be parsed")
+}
+
+#[test]
+fn test_convert_to_bms() {
+    let output = Bms::from_qua(&sample());
+    assert_eq!(output.warnings, vec![]);
+    assert_eq!(output.playing_errors, vec![]);
+    let bms = output.bms;
+    assert_eq!(bms.check_validity::<KeyLayoutBeat>().missing, vec![]);
+
+    assert_eq!(bms.music_info.title.as_deref(), Some("Sample Song"));
+    assert_eq!(bms.music_info.subtitle.as_deref(), Some("Hard"));
+    assert_eq!(bms.music_info.maker.as_deref(), Some("Charter"));
+    let bpm_changes: Vec<_> = bms
+        .bpm
+        .bpm_changes
+        .values()
+        .map(|change| (change.time, change.bpm))
+        .collect();
+    assert_eq!(
+        bpm_changes,
+        vec![(time(2, 0, 1), PositiveF64::new(180.0).unwrap())]
+    );
+    // The 3/4 measures after the second timing point.
+    let section_lens: Vec<_> = bms
+        .section_len
+        .section_len_changes
+        .values()
+        .map(|change| (change.track, change.length))
+        .collect();
+    assert_eq!(
+        section_lens,
+        vec![
+            (Track(2), FinF64::new(0.75).unwrap()),
+            (Track(3), FinF64::new(0.75).unwrap()),
+        ]
+    );
+    let scrolls: Vec<_> = bms
+        .scroll
+        .scrolling_factor_changes
+        .values()
+        .map(|change| (change.time, change.factor))
+        .collect();
+    assert_eq!(scrolls, vec![(time(1, 0, 1), FinF64::new(1.5).unwrap())]);
+
+    let hit_id = ObjId::try_from("02", false).unwrap();
+    let clap_id = ObjId::try_from("03", false).unwrap();
+    let silent_id = ObjId::try_from("04", false).unwrap();
+    assert_eq!(
+        bms.wav.wav_files.get(&hit_id),
+        Some(&std::path::PathBuf::from("hit.wav"))
+    );
+    let mut notes: Vec<_> = bms
+        .notes()
+        .all_notes()
+        .map(|obj| {
+            let key = KeyLayoutBeat::from_channel_id(obj.channel_id)
+                .map(|layout| (layout.key(), layout.kind()));
+            (obj.offset, key, obj.wav_id)
+        })
+        .collect();
+    notes.sort_by_key(|(offset, key, _)| (*offset, key.and_then(|(key, _)| key.key_number())));
+    assert_eq!(
+        notes,
+        vec![
+            (time(0, 0, 1), None, ObjId::try_from("01", false).unwrap()),
+            (
+                time(0, 1, 4),
+                Some((Key::Key(1), NoteKind::Visible)),
+                hit_id
+            ),
+            (
+                time(0, 1, 2),
+                Some((Key::Key(2), NoteKind::Long)),
+                silent_id
+            ),
+            (
+                time(1, 0, 1),
+                Some((Key::Key(2), NoteKind::Long)),
+                silent_id
+            ),
+            (time(1, 1, 2), None, clap_id),
+            (
+                time(1, 1, 2),
+                Some((Key::Key(4), NoteKind::Visible)),
+                hit_id
+            ),
+            (
+                time(2, 0, 1),
+                Some((Key::Key(3), NoteKind::Visible)),
+                silent_id
+            ),
+            (
+                time(2, 1, 2),
+                Some((Key::Key(1), NoteKind::Visible)),
+                silent_id
+            ),
+            (
+                time(3, 1, 3),
+                Some((Key::Key(2), NoteKind::Visible)),
+                silent_id
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_lead_in_and_invalid_objects() {
+    let qua = Qua {
+        timing_points: vec![QuaTimingPoint {
+            start_time: 250.0,
+            bpm: 120.0,
+            ..QuaTimingPoint::default()
+        }],
+        hit_objects: vec![
+            bms_rs::quaver::QuaHitObject {
+                start_time: 250,
+                lane: 1,
+                ..Default::default()
+            },
+            bms_rs::quaver::QuaHitObject {
+                start_time: 250,
+                lane: 5,
+                ..Default::default()
+            },
+        ],
+        ..Qua::default()
+    };
+    let output = Bms::from_qua(&qua);
+    assert_eq!(
+        output.warnings,
+        vec![QuaToBmsWarning::InvalidLane { time: 250, lane: 5 }]
+    );
+    let section_lens: Vec<_> = output
+        .bms
+        .section_len
+        .section_len_changes
+        .values()
+        .map(|change| (change.track, change.length))
+        .collect();
+    assert_eq!(section_lens, vec![(Track(0), FinF64::new(0.125).unwrap())]);
+    let offsets: Vec<_> = output
+        .bms
+        .notes()
+        .all_notes()
+        .map(|obj| obj.offset)
+        .collect();
+    assert_eq!(offsets, vec![time(1, 0, 1)]);
+}
+
+#[test]
+fn test_round_trip_through_bms() {
+    let qua = sample();
+    let output = Bms::from_qua(&qua).bms.to_qua();
+    assert_eq!(output.warnings, vec![BmsToQuaWarning::BgmDropped(1)]);
+    let exported = output.qua;
+
+    assert_eq!(exported.audio_file.as_deref(), Some("audio.mp3"));
+    assert_eq!(exported.title, qua.title);
+    assert_eq!(exported.difficulty_name, qua.difficulty_name);
+    assert_eq!(exported.creator, qua.creator);
+    let timing_points: Vec<_> = exported
+        .timing_points
+        .iter()
+        .map(|point| {
+            (
+                point.start_time.round() as i64,
+                point.bpm.round() as i64,
+                point.signature,
+            )
+        })
+        .collect();
+    assert_eq!(
+        timing_points,
+        vec![
+            (0, 120, QuaTimeSignature::Quadruple),
+            (4000, 180, QuaTimeSignature::Triple),
+            // The tracks after the last 3/4 track of the notes are 4/4 in BMS.
+            (6000, 180, QuaTimeSignature::Quadruple)
+        ]
+    );
+    let slider_velocities: Vec<_> = exported
+        .slider_velocities
+        .iter()
+        .map(|sv| (sv.start_time.round() as i64, sv.multiplier))
+        .collect();
+    assert_eq!(slider_velocities.len(), 1);
+    assert!(
+        slider_velocities
+            .first()
+            .is_some_and(|&(time, multiplier)| time == 2000 && (multiplier - 1.5).abs() < 1e-9)
+    );
+
+    let hit_objects = |chart: &Qua| {
+        chart
+            .hit_objects
+            .iter()
+            .map(|hit_object| (hit_object.start_time, hit_object.lane, hit_object.end_time))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(hit_objects(&exported), hit_objects(&qua));
+    assert_eq!(
+        exported
+            .custom_audio_samples
+            .iter()
+            .map(|sample| sample.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["hit.wav"]
+    );
+}
+
+#[test]
+fn test_export_stop_as_slider_velocity() {
+    let source = "#BPM 120\n#STOP01 96\n#00109:01\n#00211:01\n";
+    let BmsOutput { bms, .. } = parse_bms(source, default_config());
+    let bms = bms.expect("must be parsed");
+    let qua = bms.to_qua().qua;
+    // Stop of 96/192 measure at 120 BPM lasts 1000 ms from 2000 ms.
+    let slider_velocities: Vec<_> = qua
+        .slider_velocities
+        .iter()
+        .map(|sv| (sv.start_time.round() as i64, sv.multiplier.round() as i64))
+        .collect();
+    assert_eq!(slider_velocities, vec![(2000, 0), (3000, 1)]);
+    let starts: Vec<_> = qua
+        .hit_objects
+        .iter()
+        .map(|hit_object| hit_object.start_time)
+        .collect();
+    assert_eq!(starts, vec![5000]);
+}
+
+#[test]
+fn test_export_section_lengths() {
+    let source = "#BPM 120\n#00102:0.75\n#00202:0.5\n#00311:01\n";
+    let BmsOutput { bms, .. } = parse_bms(source, default_config());
+    let output = bms.expect("must be parsed").to_qua();
+    // 4/4 for 2000 ms, 3/4 for 1500 ms, then 2/4 kept as 3/4 for 1000 ms.
+    assert_eq!(
+        output.warnings,
+        vec![BmsToQuaWarning::SignatureDropped(3500)]
+    );
+    let timing_points: Vec<_> = output
+        .qua
+        .timing_points
+        .iter()
+        .map(|point| (point.start_time.round() as i64, point.signature))
+        .collect();
+    assert_eq!(
+        timing_points,
+        vec![
+            (0, QuaTimeSignature::Quadruple),
+            (2000, QuaTimeSignature::Triple),
+            (4500, QuaTimeSignature::Quadruple)
+        ]
+    );
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "quaver")]
+
+use bms_rs::quaver::{QuaHitObject, QuaMode, QuaTimeSignature, parse_qua};
+
+#[test]
+fn test_parse_sample() {
+    let qua = parse_qua(include_str!("files/sample.qua")).expect("sample must be parsed");
+    assert_eq!(qua.audio_file.as_deref(), Some("audio.mp3"));
+    assert_eq!(qua.mode, QuaMode::Keys4);
+    assert_eq!(qua.title, "Sample Song");
+    assert_eq!(qua.difficulty_name, "Hard");
+    assert_eq!(
+        qua.custom_audio_samples
+            .iter()
+            .map(|sample| sample.path.as_str())
+            .collect::<Vec<_>>(),
+        vec!["hit.wav", "clap.wav"]
+    );
+    let [first, second] = qua.timing_points.as_slice() else {
+        panic!("expected 2 timing points: {:?}", qua.timing_points);
+    };
+    assert_eq!(first.signature, QuaTimeSignature::Quadruple);
+    assert_eq!(second.signature, QuaTimeSignature::Triple);
+    assert_eq!(qua.hit_objects.len(), 6);
+    assert!(
+        qua.hit_objects
+            .get(1)
+            .is_some_and(QuaHitObject::is_long_note)
+    );
+}
+
+#[test]
+fn test_write_round_trip() {
+    let qua = parse_qua(include_str!("files/sample.qua")).expect("sample must be parsed");
+    let written = qua.to_string().expect("must be serialized");
+    assert!(!written.contains("EndTime: 0"));
+    assert_eq!(parse_qua(&written).expect("must be parsed again"), qua);
+}
+
+#[test]
+fn test_parse_error() {
+    let error = parse_qua("TimingPoints: 1").expect_err("must be an error");
+    assert!(error.to_string().starts_with("qua: "));
+}
@@ -0,0 +1,4 @@
+//! Tests for `bms_rs::quaver`.
+
+mod convert;
+mod files;
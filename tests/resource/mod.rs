@@ -0,0 +1,280 @@
+//! Tests for `bms_rs::resource`.
+
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use bms_rs::{
+    bms::prelude::*,
+    resource::{ResourceResolver, ResourceStatus, ResourceUsage, unreferenced_files},
+};
+
+/// A temporary chart directory removed on drop.
+struct ChartDir(PathBuf);
+
+impl ChartDir {
+    fn new(name: &str, files: &[&str]) -> Self {
+        let root =
+            std::env::temp_dir().join(format!("bms-rs-resource-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        for file in files {
+            let path = root.join(file);
+            fs::create_dir_all(path.parent().expect("must have parent")).expect("must create dirs");
+            fs::write(path, b"").expect("must write file");
+        }
+        Self(root)
+    }
+
+    fn path(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+}
+
+impl Drop for ChartDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn id(id: &str) -> ObjId {
+    ObjId::try_from(id, false).expect("must be valid id")
+}
+
+#[test]
+fn test_resolve_fallbacks() {
+    let dir = ChartDir::new(
+        "resolve",
+        &[
+            "hat.wav",
+            "kick.ogg",
+            "snare.wav",
+            "amb.ogg",
+            "amb.flac",
+            "Sub/bg.png",
+            "stage.BMP",
+            "movie.mp4",
+        ],
+    );
+    let mut resolver = ResourceResolver::new(&dir.0);
+    let cases = [
+        ("hat.wav", ResourceStatus::Found(dir.path("hat.wav"))),
+        ("kick.wav", ResourceStatus::Fallback(dir.path("kick.ogg"))),
+        ("Snare.WAV", ResourceStatus::Fallback(dir.path("snare.wav"))),
+        (
+            "amb.wav",
+            ResourceStatus::Ambiguous(vec![dir.path("amb.ogg"), dir.path("amb.flac")]),
+        ),
+        (
+            "sub\\bg.bmp",
+            ResourceStatus::Fallback(dir.path("Sub/bg.png")),
+        ),
+        (
+            "./stage.bmp",
+            ResourceStatus::Fallback(dir.path("stage.BMP")),
+        ),
+        ("movie.mpg", ResourceStatus::Fallback(dir.path("movie.mp4"))),
+        ("movie", ResourceStatus::Fallback(dir.path("movie.mp4"))),
+        ("missing.wav", ResourceStatus::Missing),
+        ("hat.png", ResourceStatus::Missing),
+    ];
+    for (declared, expected) in cases {
+        assert_eq!(
+            resolver.resolve(Path::new(declared)),
+            expected,
+            "{declared}"
+        );
+    }
+}
+
+#[test]
+fn test_resolve_prefers_exact_name() {
+    let dir = ChartDir::new("exact", &["kick.wav", "kick.ogg", "KICK.WAV"]);
+    let mut resolver = ResourceResolver::new(&dir.0);
+    assert_eq!(
+        resolver.resolve(Path::new("kick.wav")),
+        ResourceStatus::Found(dir.path("kick.wav"))
+    );
+    assert_eq!(
+        resolver.resolve(Path::new("Kick.wav")),
+        ResourceStatus::Ambiguous(vec![dir.path("KICK.WAV"), dir.path("kick.wav")])
+    );
+}
+
+#[test]
+fn test_bms_resources() {
+    let dir = ChartDir::new(
+        "bms",
+        &["kick.ogg", "bga/Back.png", "stage.jpg", "preview.ogg"],
+    );
+    let source = "\
+#WAV01 kick.wav
+#WAV02 snare.wav
+#BMP01 bga\\back.bmp
+#STAGEFILE stage.jpg
+#BANNER banner.png
+#PREVIEW preview.ogg
+#00111:0102
+";
+    let bms = parse_bms::<KeyLayoutBeat, _, _, _>(source, default_config())
+        .bms
+        .expect("must be parsed");
+    let output = bms.check_resources(&dir.0);
+
+    let statuses: Vec<_> = output
+        .resources
+        .iter()
+        .map(|resource| (resource.usage, resource.status.clone()))
+        .collect();
+    assert_eq!(
+        statuses,
+        [
+            (
+                ResourceUsage::Wav(id("01")),
+                ResourceStatus::Fallback(dir.path("kick.ogg"))
+            ),
+            (ResourceUsage::Wav(id("02")), ResourceStatus::Missing),
+            (
+                ResourceUsage::Bmp(id("01")),
+                ResourceStatus::Fallback(dir.path("bga/Back.png"))
+            ),
+            (
+                ResourceUsage::EyecatchImage,
+                ResourceStatus::Found(dir.path("stage.jpg"))
+            ),
+            (ResourceUsage::Banner, ResourceStatus::Missing),
+            (
+                ResourceUsage::PreviewMusic,
+                ResourceStatus::Found(dir.path("preview.ogg"))
+            ),
+        ]
+    );
+    let missing: Vec<_> = output
+        .missing()
+        .map(|resource| resource.declared.as_path())
+        .collect();
+    assert_eq!(missing, [Path::new("snare.wav"), Path::new("banner.png")]);
+    assert_eq!(output.ambiguous().count(), 0);
+    assert!(!output.is_complete());
+}
+
+#[cfg(feature = "bmson")]
+#[test]
+fn test_bmson_resources() {
+    let dir = ChartDir::new(
+        "bmson",
+        &["kick.ogg", "eyecatch.png", "banner.jpg", "banner.png"],
+    );
+    let mut bmson = bms_rs::bmson::parse_bmson(include_str!("../bmson/files/lostokens.bmson"))
+        .bmson
+        .expect("must be parsed");
+    bmson.info.back_image = None;
+    bmson.info.eyecatch_image = Some("eyecatch.png".into());
+    bmson.info.title_image = Some("title.png".into());
+    bmson.info.banner_image = Some("banner.bmp".into());
+    bmson.info.preview_music = None;
+    bmson.sound_channels.truncate(1);
+    if let Some(channel) = bmson.sound_channels.first_mut() {
+        channel.name = "kick.wav".into();
+    }
+    bmson.mine_channels.clear();
+    bmson.key_channels.clear();
+    bmson.bga.bga_header.clear();
+
+    let output = bmson.check_resources(&dir.0);
+    let usages: Vec<_> = output
+        .resources
+        .iter()
+        .map(|resource| resource.usage)
+        .collect();
+    assert_eq!(
+        usages,
+        [
+            ResourceUsage::SoundChannel(0),
+            ResourceUsage::EyecatchImage,
+            ResourceUsage::TitleImage,
+            ResourceUsage::Banner
+        ]
+    );
+    assert_eq!(
+        output
+            .missing()
+            .map(|resource| resource.usage)
+            .collect::<Vec<_>>(),
+        [ResourceUsage::TitleImage]
+    );
+    assert_eq!(
+        output
+            .ambiguous()
+            .map(|resource| resource.usage)
+            .collect::<Vec<_>>(),
+        [ResourceUsage::Banner]
+    );
+}
+
+#[test]
+fn test_unreferenced_files() {
+    let dir = ChartDir::new(
+        "unreferenced",
+        &[
+            "normal.bms",
+            "hyper.BME",
+            "kick.ogg",
+            "snare.wav",
+            "unused.wav",
+            "bga/back.png",
+            "bga/old.png",
+            "poor.bmp",
+        ],
+    );
+    let normal = parse_bms::<KeyLayoutBeat, _, _, _>(
+        "#WAV01 kick.wav\n#BMP00 poor.bmp\n#BMP01 bga/back.bmp\n",
+        default_config(),
+    )
+    .bms
+    .expect("must be parsed");
+    let hyper = parse_bms::<KeyLayoutBeat, _, _, _>(
+        "#WAV01 kick.wav\n#WAV02 snare.wav\n",
+        default_config(),
+    )
+    .bms
+    .expect("must be parsed");
+    let normal = normal.check_resources(&dir.0);
+    let hyper = hyper.check_resources(&dir.0);
+
+    let all = unreferenced_files(&dir.0, [&normal, &hyper]).expect("must be read");
+    assert_eq!(all, [dir.path("bga/old.png"), dir.path("unused.wav")]);
+    let normal_only = unreferenced_files(&dir.0, [&normal]).expect("must be read");
+    assert_eq!(
+        normal_only,
+        [
+            dir.path("bga/old.png"),
+            dir.path("snare.wav"),
+            dir.path("unused.wav")
+        ]
+    );
+}
+
+#[test]
+fn test_unreferenced_files_with_relative_dir() {
+    let dir = ChartDir::new("unreferenced-relative", &["kick.wav", "bga/back.png"]);
+    let bms = parse_bms::<KeyLayoutBeat, _, _, _>(
+        "#WAV01 ./kick.wav\n#BMP01 bga/../bga/back.png\n",
+        default_config(),
+    )
+    .bms
+    .expect("must be parsed");
+    // The chart is checked in `dir/.`, but the files are listed in `dir`.
+    let output = bms.check_resources(dir.0.join("."));
+    let unreferenced = unreferenced_files(&dir.0, [&output]).expect("must be read");
+    assert_eq!(unreferenced, Vec::<PathBuf>::new());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_unreferenced_files_skips_symlink_cycle() {
+    let dir = ChartDir::new("unreferenced-symlink", &["sub/unused.wav"]);
+    std::os::unix::fs::symlink(&dir.0, dir.path("sub/loop")).expect("must create symlink");
+    let unreferenced = unreferenced_files(&dir.0, std::iter::empty()).expect("must be read");
+    assert_eq!(unreferenced, [dir.path("sub/unused.wav")]);
+}
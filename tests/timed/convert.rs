@@ -0,0 +1,255 @@
+use std::path::PathBuf;
+
+use bms_rs::{
+    bms::prelude::*,
+    chart::SILENT_WAV,
+    timed::{
+        TempoPoint, TimedChart, TimedLane, TimedObject, TimedStop,
+        bms_to_timed::BmsToTimedWarning,
+        timed_to_bms::{Quantization, TimedToBmsWarning},
+    },
+};
+use strict_num_extended::{FinF64, NonNegativeF64, PositiveF64};
+
+fn time(track: u64, numerator: u64, denominator: u64) -> ObjTime {
+    ObjTime::new(track, numerator, denominator).expect("denominator must be non-zero")
+}
+
+fn note(ms: f64, key: u8, length_ms: f64) -> TimedObject {
+    TimedObject {
+        ms,
+        lane: Some(TimedLane {
+            side: PlayerSide::Player1,
+            key: Key::Key(key),
+        }),
+        kind: if length_ms > 0.0 {
+            NoteKind::Long
+        } else {
+            NoteKind::Visible
+        },
+        length_ms,
+        sound: None,
+    }
+}
+
+fn tempo(ms: f64, bpm: f64, beats_per_measure: Option<f64>) -> TempoPoint {
+    TempoPoint {
+        ms,
+        bpm: PositiveF64::new(bpm).expect("BPM must be positive"),
+        beats_per_measure: beats_per_measure
+            .map(|beats| PositiveF64::new(beats).expect("beats must be positive")),
+    }
+}
+
+fn notes(bms: &Bms) -> Vec<(ObjTime, Key, NoteKind)> {
+    bms.wav
+        .notes
+        .all_notes()
+        .filter_map(|obj| {
+            let map = obj.channel_id.try_into_map::<KeyLayoutBeat>()?;
+            Some((obj.offset, map.key(), map.kind()))
+        })
+        .collect()
+}
+
+#[test]
+fn test_convert_to_bms() {
+    let timed = TimedChart {
+        tempo_map: vec![
+            tempo(0.0, 120.0, Some(4.0)),
+            // Mid-measure BPM change.
+            tempo(1000.0, 240.0, None),
+            // 3/4 measures from the beat 8.
+            tempo(2500.0, 240.0, Some(3.0)),
+        ],
+        stops: vec![TimedStop {
+            ms: 2750.0,
+            duration_ms: NonNegativeF64::new(500.0).unwrap(),
+        }],
+        objects: vec![
+            // Before the first tempo point, so a lead-in measure of 1 beat is inserted.
+            note(-500.0, 1, 0.0),
+            TimedObject {
+                ms: 0.0,
+                lane: None,
+                kind: NoteKind::Visible,
+                length_ms: 0.0,
+                sound: Some(0),
+            },
+            note(0.0, 2, 1000.0),
+            note(333.0, 3, 0.0),
+            // After the stop.
+            note(3500.0, 4, 0.0),
+        ],
+        sounds: vec![PathBuf::from("bgm.ogg")],
+        ..TimedChart::default()
+    };
+    let output = Bms::from_timed::<KeyLayoutBeat>(&timed, Quantization::default());
+    assert_eq!(output.warnings, vec![]);
+    assert!(output.max_quantization_error_ms < 1.0);
+    let bms = output.bms;
+    assert_eq!(bms.check_validity::<KeyLayoutBeat>().missing, vec![]);
+    assert_eq!(
+        bms.wav
+            .wav_files
+            .get(&ObjId::try_from("02", false).unwrap()),
+        Some(&PathBuf::from(SILENT_WAV))
+    );
+
+    let section_lens: Vec<_> = bms
+        .section_len
+        .section_len_changes
+        .values()
+        .map(|change| (change.track, change.length))
+        .collect();
+    assert_eq!(
+        section_lens.first(),
+        Some(&(Track(0), FinF64::new(0.25).unwrap()))
+    );
+    assert!(section_lens.contains(&(Track(3), FinF64::new(0.75).unwrap())));
+    assert!(!section_lens.iter().any(|&(track, _)| track == Track(1)));
+    let bpm_changes: Vec<_> = bms
+        .bpm
+        .bpm_changes
+        .values()
+        .map(|change| (change.time, change.bpm))
+        .collect();
+    assert_eq!(
+        bpm_changes,
+        vec![(time(1, 1, 2), PositiveF64::new(240.0).unwrap())]
+    );
+    // 500 ms at 240 BPM is 96/192 of a measure.
+    let stops: Vec<_> = bms
+        .stop
+        .stops
+        .values()
+        .map(|stop| (stop.time, stop.duration))
+        .collect();
+    assert_eq!(
+        stops,
+        vec![(time(3, 1, 3), NonNegativeF64::new(96.0).unwrap())]
+    );
+
+    assert_eq!(
+        notes(&bms),
+        vec![
+            (time(0, 0, 1), Key::Key(1), NoteKind::Visible),
+            (time(1, 0, 1), Key::Key(2), NoteKind::Long),
+            (time(1, 1, 6), Key::Key(3), NoteKind::Visible),
+            (time(1, 1, 2), Key::Key(2), NoteKind::Long),
+            (time(3, 2, 3), Key::Key(4), NoteKind::Visible),
+        ]
+    );
+    let bgm_id = ObjId::try_from("01", false).unwrap();
+    assert_eq!(
+        bms.wav.wav_files.get(&bgm_id),
+        Some(&PathBuf::from("bgm.ogg"))
+    );
+    assert!(
+        bms.wav
+            .notes
+            .all_notes()
+            .any(|obj| obj.offset == time(1, 0, 1) && obj.wav_id == bgm_id)
+    );
+}
+
+#[test]
+fn test_missing_tempo_and_unknown_sound() {
+    let timed = TimedChart {
+        objects: vec![TimedObject {
+            sound: Some(3),
+            ..note(500.0, 1, 0.0)
+        }],
+        ..TimedChart::default()
+    };
+    let output = Bms::from_timed::<KeyLayoutBeat>(&timed, Quantization::default());
+    assert_eq!(
+        output.warnings,
+        vec![
+            TimedToBmsWarning::MissingTempo,
+            TimedToBmsWarning::UnknownSound(3),
+        ]
+    );
+    // 500 ms at the default 120 BPM is a beat.
+    assert_eq!(
+        notes(&output.bms),
+        vec![(time(0, 1, 4), Key::Key(1), NoteKind::Visible)]
+    );
+}
+
+#[test]
+fn test_round_trip_through_timed() {
+    let source = "\
+#BPM 150
+#LNTYPE 1
+#WAV01 a.wav
+#WAV02 b.wav
+#STOP01 48
+#00102:0.75
+#00103:C8
+#00111:01020000
+#00151:01000100
+#00209:0001
+#00212:0101
+#00301:01
+";
+    let BmsOutput { bms, .. } = parse_bms(source, default_config());
+    let bms = bms.expect("must be parsed");
+    let timed_output = bms.to_timed::<KeyLayoutBeat>();
+    assert_eq!(timed_output.warnings, vec![]);
+    // 4 beats at 150 BPM.
+    assert!(
+        timed_output
+            .timed
+            .objects
+            .iter()
+            .any(|object| object.lane.is_some() && (object.ms - 1600.0).abs() < 1e-9)
+    );
+
+    let output = Bms::from_timed::<KeyLayoutBeat>(&timed_output.timed, Quantization::default());
+    assert_eq!(output.warnings, vec![]);
+    assert!(output.max_quantization_error_ms < 1e-6);
+    let converted = output.bms;
+    assert_eq!(
+        converted.section_len.section_len_changes,
+        bms.section_len.section_len_changes
+    );
+    assert_eq!(converted.bpm.bpm_changes, bms.bpm.bpm_changes);
+    assert_eq!(converted.stop.stops, bms.stop.stops);
+    let objs = |chart: &Bms| {
+        let mut objs: Vec<_> = chart
+            .wav
+            .notes
+            .all_notes()
+            .map(|obj| (obj.offset, obj.channel_id, obj.wav_id))
+            .collect();
+        objs.sort();
+        objs
+    };
+    assert_eq!(objs(&converted), objs(&bms));
+}
+
+#[test]
+fn test_mgq_long_notes_are_dropped() {
+    let source = "\
+#BPM 120
+#LNTYPE 2
+#WAV01 a.wav
+#00111:01
+#00151:01010100
+";
+    let BmsOutput { bms, .. } = parse_bms(source, default_config());
+    let output = bms.expect("must be parsed").to_timed::<KeyLayoutBeat>();
+    // The run of 3 objects is one long note closed by `00`, which the model does not keep.
+    assert_eq!(
+        output.warnings,
+        vec![BmsToTimedWarning::MgqLongNotesDropped(3)]
+    );
+    let lanes: Vec<_> = output
+        .timed
+        .objects
+        .iter()
+        .map(|object| (object.ms, object.kind))
+        .collect();
+    assert_eq!(lanes, vec![(2000.0, NoteKind::Visible)]);
+}
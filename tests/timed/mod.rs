@@ -0,0 +1,3 @@
+//! Tests for `bms_rs::timed`.
+
+mod convert;